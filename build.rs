@@ -0,0 +1,37 @@
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    // Embed `git describe` so run manifests can name the exact source revision
+    let describe = Command::new("git")
+        .args(["describe", "--always", "--dirty", "--tags"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=MQ_BENCH_GIT_DESCRIBE={}", describe);
+    // Re-describe when HEAD moves: a branch switch, a commit on the current branch
+    // (its loose ref), or a `git gc` that packs refs. Missing paths are skipped since
+    // cargo treats them as always stale.
+    // `--dirty` also needs a rerun on unstaged edits: watch the sources that make up
+    // the binary. Edits elsewhere (docs, tests) leave the embedded mark stale until
+    // the next rebuild.
+    let mut watched = vec![
+        ".git/HEAD".to_string(),
+        ".git/index".to_string(),
+        ".git/packed-refs".to_string(),
+        "src".to_string(),
+        "Cargo.toml".to_string(),
+    ];
+    if let Ok(head) = std::fs::read_to_string(".git/HEAD")
+        && let Some(r) = head.trim().strip_prefix("ref: ")
+    {
+        watched.push(format!(".git/{}", r));
+    }
+    for path in watched.iter().filter(|p| Path::new(p).exists()) {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+}
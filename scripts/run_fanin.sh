@@ -0,0 +1,94 @@
+#!/usr/bin/env bash
+set -euo pipefail
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+source "${SCRIPT_DIR}/lib.sh"
+
+# Fan-in scenario: PUBLISHERS keys (mt-pub) → one wildcard subscriber with per-source accounting.
+# Usage: scripts/run_fanin.sh [RUN_ID]
+# Env:
+#   ENGINE=zenoh (default) | mqtt | redis | nats   (connect settings as for run_fanout.sh)
+#   PUBLISHERS=100   Number of sources, one key each
+#   RATE=10          Rate per source (msg/s)
+#   POPULARITY=uniform  Rate skew across sources (uniform | zipf:S | hot:KEYS_PCT:TRAFFIC_PCT)
+
+RUN_ID=${1:-${RUN_ID:-run_$(date +%Y%m%d_%H%M%S)}}
+
+def PUBLISHERS "${PUBLISHERS:-100}"
+def PAYLOAD    "${PAYLOAD:-1024}"
+def RATE       "${RATE:-10}"
+def POPULARITY "${POPULARITY:-uniform}"
+def DURATION   "${DURATION:-30}"
+def SNAPSHOT   "${SNAPSHOT:-1}"
+def SHARE_TRANSPORT "${SHARE_TRANSPORT:-true}"
+ENGINE="${ENGINE:-zenoh}"
+
+def TOPIC_PREFIX "${TOPIC_PREFIX:-bench/fanin}"
+def ENDPOINT_SUB "${ENDPOINT_SUB:-tcp/127.0.0.1:7447}"
+def ENDPOINT_PUB "${ENDPOINT_PUB:-tcp/127.0.0.1:7447}"
+def ZENOH_MODE "${ZENOH_MODE:-}"
+def SUB_STARTUP_DELAY "${SUB_STARTUP_DELAY:-2}"
+
+ART_DIR="artifacts/${RUN_ID}/fanin"
+BIN="./target/release/mq-bench"
+
+echo "[run_fanin] ${RUN_ID} (ENGINE=${ENGINE}) | sources=${PUBLISHERS} rate_per_source=${RATE} popularity=${POPULARITY} dur=${DURATION}s"
+mkdir -p "${ART_DIR}"
+
+build_release_if_needed "${BIN}"
+
+SUB_CSV="${ART_DIR}/sub.csv"
+PUB_CSV="${ART_DIR}/mt_pub.csv"
+
+echo "Starting wildcard subscriber → ${TOPIC_PREFIX}/**"
+CONNECT_SUB_ARGS=()
+make_connect_args sub CONNECT_SUB_ARGS
+CMD_SUB=(
+  "${BIN}" --run-id "${RUN_ID}" --instance fanin --snapshot-interval "${SNAPSHOT}" sub
+  "${CONNECT_SUB_ARGS[@]}"
+  --expr "${TOPIC_PREFIX}/**"
+  --per-source
+  --csv "${SUB_CSV}"
+)
+print_cmd "${CMD_SUB[@]}" && echo "       1>$(printf %q "${ART_DIR}/sub.log") 2>&1 &"
+"${CMD_SUB[@]}" >"${ART_DIR}/sub.log" 2>&1 &
+SUB_PID=$!
+trap 'kill -INT ${SUB_PID} >/dev/null 2>&1 || true' EXIT
+
+echo "Waiting ${SUB_STARTUP_DELAY}s for the subscriber..."
+sleep "${SUB_STARTUP_DELAY}"
+
+SHARE_FLAG=()
+if [[ "${SHARE_TRANSPORT}" == "true" ]]; then SHARE_FLAG=(--share-transport); fi
+CONNECT_PUB_ARGS=()
+make_connect_args pub CONNECT_PUB_ARGS
+CMD_MTPUB=(
+  "${BIN}" --run-id "${RUN_ID}" --instance fanin --snapshot-interval "${SNAPSHOT}" mt-pub
+  "${CONNECT_PUB_ARGS[@]}"
+  --topic-prefix "${TOPIC_PREFIX}"
+  --tenants 1 --regions 1 --services 1 --shards "${PUBLISHERS}"
+  --publishers "${PUBLISHERS}"
+  --payload "${PAYLOAD}"
+  --rate "${RATE}"
+  --popularity "${POPULARITY}"
+  --duration "${DURATION}"
+  "${SHARE_FLAG[@]}"
+  --csv "${PUB_CSV}"
+)
+print_cmd "${CMD_MTPUB[@]}" && echo "       1>$(printf %q "${ART_DIR}/mt_pub.log") 2>&1 &"
+"${CMD_MTPUB[@]}" >"${ART_DIR}/mt_pub.log" 2>&1 &
+PUB_PID=$!
+
+echo "[watch] printing status every ${SNAPSHOT}s..."
+watch_until_pub_exits ${PUB_PID} "${SUB_CSV}" "${PUB_CSV}"
+wait ${PUB_PID} || true
+
+# Let in-flight messages land, then stop the subscriber so it writes per-source results
+sleep 2
+kill -INT ${SUB_PID} >/dev/null 2>&1 || true
+wait ${SUB_PID} || true
+trap - EXIT
+
+echo -e "\n=== Summary (${RUN_ID}) ==="
+summarize_common "${SUB_CSV}" "${PUB_CSV}"
+echo "Per-source results: artifacts/${RUN_ID}/sub-fanin/per_source.csv and fan_in.json"
+echo "Fan-in run complete. Artifacts at ${ART_DIR}"
@@ -0,0 +1,424 @@
+//! `mq-bench find-max`: search for the highest offered rate that still meets a latency SLO.
+//!
+//! Each probe runs an in-process subscriber plus publishers (`run_publisher`, or
+//! `run_multi_topic` for the `mt-pub` driver) at one total rate. After a settle period
+//! the probe measures a window of 1 s intervals: it passes when the delivery ratio over
+//! the window is at least the SLO's and no interval p99 exceeds the SLO's. The search
+//! doubles the rate until a probe fails (or the ceiling is reached), then bisects
+//! between the highest pass and the lowest failure until they are within `precision`.
+//! Every probe uses its own keys under `topic_prefix/probe<n>` and waits for its
+//! receivers to shut down before the next one starts.
+
+use crate::crash::CrashConfig;
+use crate::metrics::stats::Stats;
+use crate::rate::ArrivalConfig;
+use crate::roles::multi_topic::{
+    KeyMappingMode, MultiTopicConfig, MultiTopicSubConfig, run_multi_topic, run_multi_topic_sub,
+};
+use crate::roles::publisher::{PublisherConfig, run_publisher};
+use crate::roles::subscriber::{SubscriberConfig, run_subscriber};
+use crate::transport::{ConnectOptions, Engine};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+use tracing::info;
+
+/// Time for the subscriber to declare before publishers start
+const SUB_READY: Duration = Duration::from_millis(500);
+/// Time after publishers stop for in-flight messages to arrive
+const DRAIN: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Driver {
+    Pub,
+    MtPub,
+}
+
+pub fn parse_driver(s: &str) -> Option<Driver> {
+    match s.to_lowercase().as_str() {
+        "pub" => Some(Driver::Pub),
+        "mt-pub" | "mtpub" => Some(Driver::MtPub),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Slo {
+    /// Minimum received/sent over the measurement window
+    pub min_delivery: f64,
+    /// Maximum p99 latency of any 1 s interval in the window
+    pub max_p99_ns: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct FindMaxConfig {
+    pub engine: Engine,
+    pub connect: ConnectOptions,
+    pub topic_prefix: String,
+    pub driver: Driver,
+    pub publishers: u32,
+    /// Shards (keys) of the mt-pub driver; the pub driver uses one key `<probe prefix>/<i>` per publisher
+    pub topics: u32,
+    pub payload_size: usize,
+    pub settle_secs: u64,
+    pub window_secs: u64,
+    pub slo: Slo,
+    /// First total rate probed (msg/s)
+    pub start_rate: f64,
+    /// Highest total rate probed (msg/s)
+    pub max_rate: f64,
+    /// Stop bisecting once (fail - pass) / pass is at most this
+    pub precision: f64,
+}
+
+/// One probe: offered load and what the window measured
+#[derive(Debug, Clone, Serialize)]
+pub struct Probe {
+    pub rate: f64,
+    pub sent: u64,
+    pub received: u64,
+    pub delivery: f64,
+    /// Received msg/s over the window
+    pub throughput: f64,
+    /// Worst interval p50/p99 in the window
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FindMaxResult {
+    /// Highest passing probe
+    pub knee: Option<Probe>,
+    /// Every probe, by rate
+    pub curve: Vec<Probe>,
+}
+
+/// Exponential then bisecting search over rate, fed one pass/fail at a time
+#[derive(Debug, Clone)]
+pub struct RateSearch {
+    start: f64,
+    max: f64,
+    precision: f64,
+    pass: Option<f64>,
+    fail: Option<f64>,
+}
+
+impl RateSearch {
+    pub fn new(start: f64, max: f64, precision: f64) -> Self {
+        Self {
+            start: start.max(1.0).min(max),
+            max,
+            precision,
+            pass: None,
+            fail: None,
+        }
+    }
+
+    /// Next rate to probe, or None when the search is done
+    pub fn next_rate(&self) -> Option<f64> {
+        match (self.pass, self.fail) {
+            (None, None) => Some(self.start),
+            (Some(p), None) => (p < self.max).then(|| (p * 2.0).min(self.max)),
+            (None, Some(f)) => (f >= 2.0).then(|| (f / 2.0).round()),
+            (Some(p), Some(f)) => {
+                let mid = ((p + f) / 2.0).round();
+                ((f - p) / p > self.precision && mid > p && mid < f).then_some(mid)
+            }
+        }
+    }
+
+    pub fn record(&mut self, rate: f64, passed: bool) {
+        if passed {
+            self.pass = Some(self.pass.map_or(rate, |p| p.max(rate)));
+        } else {
+            self.fail = Some(self.fail.map_or(rate, |f| f.min(rate)));
+        }
+    }
+}
+
+pub async fn run_find_max(config: &FindMaxConfig) -> Result<FindMaxResult> {
+    let mut search = RateSearch::new(config.start_rate, config.max_rate, config.precision);
+    let mut curve = Vec::new();
+    while let Some(rate) = search.next_rate() {
+        let probe = run_probe(config, curve.len(), rate).await?;
+        info!(
+            rate = probe.rate,
+            delivery = format!("{:.4}", probe.delivery),
+            p99_ms = format!("{:.3}", probe.p99_ms),
+            passed = probe.passed,
+            "[find-max] probe"
+        );
+        search.record(rate, probe.passed);
+        curve.push(probe);
+    }
+    curve.sort_by(|a, b| a.rate.total_cmp(&b.rate));
+    let knee = curve
+        .iter()
+        .filter(|p| p.passed)
+        .max_by(|a, b| a.rate.total_cmp(&b.rate))
+        .cloned();
+    Ok(FindMaxResult { knee, curve })
+}
+
+/// Keys of probe `index`, so a late message from an earlier probe never lands in this one
+fn probe_prefix(config: &FindMaxConfig, index: usize) -> String {
+    format!("{}/probe{}", config.topic_prefix, index)
+}
+
+async fn run_probe(config: &FindMaxConfig, index: usize, rate: f64) -> Result<Probe> {
+    let prefix = probe_prefix(config, index);
+    let sub_stats = Arc::new(Stats::new());
+    let pub_stats = Arc::new(Stats::new());
+    let run_secs = config.settle_secs + config.window_secs;
+    let publishers = config.publishers.max(1);
+    let per_pub = rate / publishers as f64;
+
+    // Receivers subscribe per key: a single subscriber drops repeated sequence numbers
+    // as duplicates, and every publisher numbers its messages from 0
+    let mut subs = JoinSet::new();
+    match config.driver {
+        Driver::Pub => {
+            for i in 0..publishers {
+                subs.spawn(run_subscriber(SubscriberConfig {
+                    engine: config.engine.clone(),
+                    connect: config.connect.clone(),
+                    key_expr: pub_key(&prefix, i),
+                    output_file: None,
+                    snapshot_interval_secs: 1,
+                    shared_stats: Some(sub_stats.clone()),
+                    disable_internal_snapshot: true,
+                    test_stop_after_secs: Some(run_secs + 2),
+                    crash_config: CrashConfig::default(),
+                    per_source_dir: None,
+                }));
+            }
+        }
+        Driver::MtPub => {
+            subs.spawn(run_multi_topic_sub(MultiTopicSubConfig {
+                engine: config.engine.clone(),
+                connect: config.connect.clone(),
+                topic_prefix: prefix.clone(),
+                tenants: 1,
+                regions: 1,
+                services: 1,
+                shards: config.topics.max(1),
+                subscribers: -1,
+                mapping: KeyMappingMode::MDim,
+                key_latency_file: None,
+                churn: None,
+                duration_secs: run_secs + 2,
+                snapshot_interval_secs: 1,
+                share_transport: true,
+                ramp_up_secs: 0.0,
+                shared_stats: Some(sub_stats.clone()),
+                disable_internal_snapshot: true,
+                crash_config: CrashConfig::default(),
+                crash_per_topic: false,
+                crash_stagger_secs: 0.0,
+            }));
+        }
+    }
+    sleep(SUB_READY).await;
+
+    let mut pubs = JoinSet::new();
+    match config.driver {
+        Driver::Pub => {
+            for i in 0..publishers {
+                pubs.spawn(run_publisher(PublisherConfig {
+                    engine: config.engine.clone(),
+                    connect: config.connect.clone(),
+                    key_expr: pub_key(&prefix, i),
+                    payload_size: config.payload_size,
+                    rate: Some(per_pub),
+                    arrival: ArrivalConfig::default(),
+                    duration_secs: Some(run_secs),
+                    output_file: None,
+                    snapshot_interval_secs: 1,
+                    shared_stats: Some(pub_stats.clone()),
+                    disable_internal_snapshot: true,
+                    crash_config: CrashConfig::default(),
+                    closed_loop: None,
+                }));
+            }
+        }
+        Driver::MtPub => {
+            pubs.spawn(run_multi_topic(MultiTopicConfig {
+                engine: config.engine.clone(),
+                connect: config.connect.clone(),
+                topic_prefix: prefix.clone(),
+                tenants: 1,
+                regions: 1,
+                services: 1,
+                shards: config.topics.max(1),
+                publishers: publishers as i64,
+                mapping: KeyMappingMode::MDim,
+                payload_size: config.payload_size,
+                rate_per_pub: Some(per_pub),
+                arrival: ArrivalConfig::default(),
+                popularity: Default::default(),
+                key_rates_file: None,
+                duration_secs: run_secs,
+                snapshot_interval_secs: 1,
+                share_transport: true,
+                ramp_up_secs: 0.0,
+                shared_stats: Some(pub_stats.clone()),
+                disable_internal_snapshot: true,
+                crash_config: CrashConfig::default(),
+                crash_per_topic: false,
+                crash_stagger_secs: 0.0,
+            }));
+        }
+    }
+
+    sleep(Duration::from_secs(config.settle_secs)).await;
+    // Start the window: snapshots reset the interval histogram and counts
+    let _ = sub_stats.snapshot().await;
+    let sent_before = pub_stats.counters().sent_count;
+    let mut received = 0u64;
+    let (mut p50_ns, mut p99_ns) = (0u64, 0u64);
+    let mut window = Duration::ZERO;
+    for _ in 0..config.window_secs {
+        sleep(Duration::from_secs(1)).await;
+        let snap = sub_stats.snapshot().await;
+        received += snap.interval_received_count;
+        window += snap.interval_duration;
+        p50_ns = p50_ns.max(snap.interval_latency_ns_p50);
+        p99_ns = p99_ns.max(snap.interval_latency_ns_p99);
+    }
+    while let Some(res) = pubs.join_next().await {
+        res.context("publisher task")??;
+    }
+    let sent = pub_stats.counters().sent_count - sent_before;
+    // Messages sent in the window may still be in flight when publishers stop
+    sleep(DRAIN).await;
+    let tail = sub_stats.snapshot().await;
+    received += tail.interval_received_count;
+    p99_ns = p99_ns.max(tail.interval_latency_ns_p99);
+    // Receivers stop on their own timers; wait for them so their subscriptions and
+    // transports shut down before the next probe starts
+    while let Some(res) = subs.join_next().await {
+        res.context("subscriber task")??;
+    }
+
+    let delivery = if sent > 0 {
+        received as f64 / sent as f64
+    } else {
+        0.0
+    };
+    let secs = window.as_secs_f64();
+    Ok(Probe {
+        rate,
+        sent,
+        received,
+        delivery,
+        throughput: if secs > 0.0 {
+            received as f64 / secs
+        } else {
+            0.0
+        },
+        p50_ms: p50_ns as f64 / 1e6,
+        p99_ms: p99_ns as f64 / 1e6,
+        passed: sent > 0 && delivery >= config.slo.min_delivery && p99_ns <= config.slo.max_p99_ns,
+    })
+}
+
+/// One key per publisher with the pub driver, so each has its own sequence space
+fn pub_key(prefix: &str, i: u32) -> String {
+    format!("{}/{}", prefix, i)
+}
+
+/// Knee line plus the rate/latency curve as a markdown table
+pub fn render_markdown(result: &FindMaxResult) -> String {
+    let mut out = String::new();
+    match &result.knee {
+        Some(k) => {
+            let _ = writeln!(
+                out,
+                "Max sustainable rate: {:.0} msg/s (delivered {:.0} msg/s, p99 {:.3} ms)\n",
+                k.rate, k.throughput, k.p99_ms
+            );
+        }
+        None => {
+            let _ = writeln!(out, "No probed rate met the SLO\n");
+        }
+    }
+    let _ = writeln!(
+        out,
+        "| rate | sent | received | delivery | throughput | p50 ms | p99 ms | pass |\n|---|---|---|---|---|---|---|---|"
+    );
+    for p in &result.curve {
+        let _ = writeln!(
+            out,
+            "| {:.0} | {} | {} | {:.4} | {:.1} | {:.3} | {:.3} | {} |",
+            p.rate,
+            p.sent,
+            p.received,
+            p.delivery,
+            p.throughput,
+            p.p50_ms,
+            p.p99_ms,
+            if p.passed { "yes" } else { "no" }
+        );
+    }
+    out
+}
+
+/// Write `curve.csv` (one row per probe) and `knee.json` into `dir`
+pub fn write_results(result: &FindMaxResult, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut w = csv::Writer::from_path(dir.join("curve.csv"))?;
+    for p in &result.curve {
+        w.serialize(p)?;
+    }
+    w.flush()?;
+    std::fs::write(
+        dir.join("knee.json"),
+        serde_json::to_vec_pretty(&result.knee)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_knee(knee: f64, start: f64, max: f64) -> (Option<f64>, Vec<f64>) {
+        let mut s = RateSearch::new(start, max, 0.05);
+        let mut probed = Vec::new();
+        while let Some(r) = s.next_rate() {
+            assert!(probed.len() < 50, "search does not converge");
+            probed.push(r);
+            s.record(r, r <= knee);
+        }
+        (s.pass, probed)
+    }
+
+    #[test]
+    fn search_brackets_then_bisects_to_precision() {
+        let (pass, probed) = search_knee(37_000.0, 1000.0, 1e6);
+        assert_eq!(
+            &probed[..7],
+            &[1000.0, 2000.0, 4000.0, 8000.0, 16000.0, 32000.0, 64000.0]
+        );
+        let pass = pass.unwrap();
+        assert!((37_000.0 / 1.05..=37_000.0).contains(&pass), "{}", pass);
+
+        // The ceiling passes: stop there
+        assert_eq!(
+            search_knee(1e9, 1000.0, 5000.0),
+            (Some(5000.0), vec![1000.0, 2000.0, 4000.0, 5000.0])
+        );
+
+        // Even the start fails: halve down
+        let (pass, probed) = search_knee(300.0, 1000.0, 1e6);
+        assert_eq!(&probed[..3], &[1000.0, 500.0, 250.0]);
+        assert!(pass.unwrap() >= 300.0 / 1.05);
+    }
+}
@@ -1,14 +1,27 @@
 //! mq-bench library crate exposing modules for reuse and testing.
 
 pub mod crash;
+pub mod find_max;
 pub mod logging;
+pub mod manifest;
 pub mod metrics;
+#[cfg(feature = "otel")]
+pub mod otel;
 pub mod output;
 pub mod payload;
 pub mod rate;
+pub mod report;
+#[cfg(feature = "sqlite")]
+pub mod results_db;
+pub mod rng;
 pub mod roles;
+pub mod scenario;
+pub mod session;
 pub mod time_sync;
 pub mod transport;
+pub mod trials;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod wire;
 
 // Optional re-exports for convenience in downstream code/tests
@@ -8,3 +8,67 @@ pub fn init(level: &str) -> Result<()> {
         .init();
     Ok(())
 }
+
+/// Like `init`, but append log lines to `path` (keeps the terminal free for the dashboard).
+pub fn init_file(level: &str, path: &std::path::Path) -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(level)
+        .with_ansi(false)
+        .with_writer(std::sync::Mutex::new(open_log(path)?))
+        .init();
+    Ok(())
+}
+
+fn open_log(path: &std::path::Path) -> Result<std::fs::File> {
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?)
+}
+
+/// Same console output as `init`, plus mq-bench's own spans exported over OTLP.
+/// Also installs the OTLP metric/trace providers; keep the guard until exit.
+#[cfg(feature = "otel")]
+pub fn init_with_otel(
+    level: &str,
+    cfg: &crate::otel::OtelConfig,
+) -> Result<crate::otel::OtelGuard> {
+    init_with_otel_to(level, cfg, None)
+}
+
+/// `init_with_otel` with console output redirected to `log_file` when given.
+#[cfg(feature = "otel")]
+pub fn init_with_otel_to(
+    level: &str,
+    cfg: &crate::otel::OtelConfig,
+    log_file: Option<&std::path::Path>,
+) -> Result<crate::otel::OtelGuard> {
+    use tracing_subscriber::filter::{EnvFilter, filter_fn};
+    use tracing_subscriber::prelude::*;
+
+    let (guard, tracer) = crate::otel::init(cfg)?;
+    // Only bridge our own spans; engine client crates can be very chatty
+    let otel_layer = tracing_opentelemetry::layer()
+        .with_tracer(tracer)
+        .with_filter(filter_fn(|meta| meta.target().starts_with("mq_bench")));
+    let fmt_layer = match log_file {
+        Some(path) => tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(tracing_subscriber::fmt::writer::BoxMakeWriter::new(
+                std::sync::Mutex::new(open_log(path)?),
+            ))
+            .boxed(),
+        None => tracing_subscriber::fmt::layer().with_ansi(false).boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(level))
+        .with(fmt_layer)
+        .with(otel_layer)
+        .try_init()?;
+    Ok(guard)
+}
@@ -1,33 +1,67 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use futures::future::join_all;
 use mq_bench::crash::CrashConfig;
-use mq_bench::metrics::stats::Stats;
-use mq_bench::output::OutputWriter;
+use mq_bench::find_max::{Driver, FindMaxConfig, Slo, parse_driver, run_find_max};
+use mq_bench::metrics::stats::MeasurementWindow;
+#[cfg(feature = "otel")]
+use mq_bench::otel::{OtelConfig, OtlpProtocol, parse_otlp_protocol};
+use mq_bench::output::{OutputFormat, parse_output_format};
+use mq_bench::rate::{
+    ArrivalConfig, ArrivalModel, RateProfile, parse_arrival_model, parse_rate_profile,
+};
+use mq_bench::report::compare::{
+    CompareConfig, REGRESSION_EXIT_CODE, SignificanceTest, compare, metric_by_name,
+    parse_significance_test, render_markdown,
+};
+use mq_bench::report::{ReportConfig, load_result_set, run_report};
+#[cfg(feature = "sqlite")]
+use mq_bench::results_db::{QueryFilter, QueryView, ResultsDb, parse_query_view};
+use mq_bench::roles::conn_storm::{ConnStormConfig, run_conn_storm};
+use mq_bench::roles::multi_query::{MultiQueryConfig, run_multi_query};
 use mq_bench::roles::multi_topic::{
-    KeyMappingMode, MultiTopicConfig, MultiTopicSubConfig, run_multi_topic, run_multi_topic_sub,
+    ChurnConfig, KeyMappingMode, MultiTopicConfig, MultiTopicSubConfig, Popularity,
+    parse_popularity, run_multi_topic, run_multi_topic_sub,
+};
+use mq_bench::roles::publisher::{ClosedLoopConfig, PublisherConfig, run_publisher};
+use mq_bench::roles::queryable::{
+    ChunkCount, QueryableConfig, ReplyMode, ServiceTime, parse_chunk_count, parse_reply_mode,
+    parse_service_time, run_queryable,
 };
-use mq_bench::roles::publisher::{PublisherConfig, run_publisher};
-use mq_bench::roles::queryable::{QueryableConfig, run_queryable};
 use mq_bench::roles::reliable_publisher::{ReliablePublisherConfig, run_reliable_publisher};
+use mq_bench::roles::replay::{ReplayConfig, load_trace, parse_topic_map, run_replay};
 use mq_bench::roles::requester::{RequesterConfig, run_requester};
 use mq_bench::roles::subscriber::{SubscriberConfig, run_subscriber};
-use mq_bench::transport::Engine;
-use mq_bench::transport::config::{parse_connect_kv, parse_engine};
-use std::sync::Arc;
+use mq_bench::scenario::{
+    ScenarioSpec, render_markdown as render_scenario_markdown, run_scenario,
+    write_results as write_scenario_results,
+};
+use mq_bench::session::{RoleSession, SessionOptions};
+use mq_bench::transport::config::{
+    parse_connect_kv, parse_engine, redact_argv, redact_connect_kv, redact_connect_params,
+};
+use mq_bench::transport::{ConnectOptions, Engine};
+use mq_bench::trials::{TrialsConfig, parse_matrix_arg, run_trials};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
 #[command(name = "mq-bench")]
 #[command(about = "Zenoh cluster stress testing harness")]
 struct Cli {
-    /// Run ID for tagging outputs
+    /// Run ID for tagging outputs (default: local start time, e.g. 20250101-120000)
     #[arg(long, default_value = "")]
     run_id: String,
 
-    /// Output directory for artifacts
+    /// Output directory for artifacts; each role writes to <out_dir>/<run_id>/<role>-<instance>/
     #[arg(long, default_value = "./artifacts")]
     out_dir: String,
 
+    /// Instance name distinguishing processes of the same role in one run (default: process id)
+    #[arg(long)]
+    instance: Option<String>,
+
     /// Log level
     #[arg(long, default_value = "info")]
     log_level: String,
@@ -36,11 +70,62 @@ struct Cli {
     #[arg(long, default_value = "1")]
     snapshot_interval: u64,
 
+    /// Serve live Prometheus metrics on ADDR (e.g. 0.0.0.0:9464) at /metrics
+    #[arg(long)]
+    metrics_listen: Option<SocketAddr>,
+
+    /// Exclude samples from the first SECS (by message wall-clock time) from reported stats
+    #[arg(long, default_value = "0")]
+    warmup_secs: f64,
+
+    /// Exclude samples from the last SECS before the role stops from reported stats
+    #[arg(long, default_value = "0")]
+    cooldown_secs: f64,
+
+    /// Warm-up start as UNIX seconds, shared across hosts (default: first message time)
+    #[arg(long)]
+    window_anchor: Option<f64>,
+
+    /// Live terminal dashboard instead of stdout snapshots (logs go to the role's artifact dir)
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+
+    /// Snapshot output format (csv|jsonl)
+    #[arg(long, default_value = "csv", value_parser = output_format_arg)]
+    output_format: OutputFormat,
+
+    /// Also record runs, snapshots and summaries in this SQLite results store
+    #[cfg(feature = "sqlite")]
+    #[arg(long)]
+    results_db: Option<PathBuf>,
+
+    /// Export metrics and sampled message traces to this OTLP collector URL
+    #[cfg(feature = "otel")]
+    #[arg(long)]
+    otel_endpoint: Option<String>,
+
+    /// OTLP transport (grpc|http)
+    #[cfg(feature = "otel")]
+    #[arg(long, default_value = "grpc", value_parser = otlp_protocol_arg)]
+    otel_protocol: OtlpProtocol,
+
+    /// Trace every Nth published message (0 = metrics only)
+    #[cfg(feature = "otel")]
+    #[arg(long, default_value = "1000")]
+    otel_sample_every: u64,
+
+    /// OTLP metrics export interval in seconds
+    #[cfg(feature = "otel")]
+    #[arg(long, default_value = "10")]
+    otel_metrics_interval: u64,
+
     #[command(subcommand)]
     command: Commands,
 }
 
-#[derive(Subcommand)]
+#[derive(Subcommand, Serialize)]
+#[serde(tag = "role", rename_all = "kebab-case")]
 enum Commands {
     /// Publisher role
     Pub {
@@ -76,6 +161,32 @@ enum Commands {
         #[arg(long, alias = "qps", allow_hyphen_values = true)]
         rate: Option<i32>,
 
+        /// Arrival model around the mean rate: constant|poisson|onoff:ON_MS:OFF_MS|burst:N
+        #[arg(long, default_value = "constant", value_parser = arrival_model_arg)]
+        arrival: ArrivalModel,
+
+        /// Seed for the arrival model (default: from the clock)
+        #[arg(long)]
+        arrival_seed: Option<u64>,
+
+        /// Time-varying rate per sender, replacing the fixed rate:
+        /// ramp:FROM..TO@DUR | steps:R1,R2,...@DUR | sine:mean=M,amp=A,period=DUR
+        #[arg(long, value_parser = rate_profile_arg)]
+        rate_profile: Option<RateProfile>,
+
+        /// Closed loop: at most W messages per publisher published but not yet seen back
+        #[arg(long, value_name = "W")]
+        window: Option<usize>,
+
+        /// Closed loop: key whose messages acknowledge ours (default: the publish key, via
+        /// our own subscription; set when an echo republishes elsewhere)
+        #[arg(long, requires = "window")]
+        ack_key: Option<String>,
+
+        /// Closed loop: an unacknowledged message frees its slot after this many ms (counted as a timeout)
+        #[arg(long, default_value = "5000")]
+        ack_timeout_ms: u64,
+
         /// Duration in seconds
         #[arg(long, default_value = "60")]
         duration: u32,
@@ -165,6 +276,24 @@ enum Commands {
         #[arg(long, alias = "qps", allow_hyphen_values = true)]
         rate: Option<i32>,
 
+        /// Arrival model around the mean rate: constant|poisson|onoff:ON_MS:OFF_MS|burst:N
+        #[arg(long, default_value = "constant", value_parser = arrival_model_arg)]
+        arrival: ArrivalModel,
+
+        /// Seed for the arrival model (default: from the clock)
+        #[arg(long)]
+        arrival_seed: Option<u64>,
+
+        /// Time-varying rate per sender, replacing the fixed rate:
+        /// ramp:FROM..TO@DUR | steps:R1,R2,...@DUR | sine:mean=M,amp=A,period=DUR
+        #[arg(long, value_parser = rate_profile_arg)]
+        rate_profile: Option<RateProfile>,
+
+        /// Key popularity for paced runs: uniform | zipf:S | hot:KEYS_PCT:TRAFFIC_PCT
+        /// (per-key rates are written to key_rates.csv in the run directory)
+        #[arg(long, default_value = "uniform", value_parser = popularity_arg)]
+        popularity: Popularity,
+
         /// Duration in seconds
         #[arg(long, default_value = "60")]
         duration: u32,
@@ -254,6 +383,15 @@ enum Commands {
         #[arg(long, default_value = "hash")]
         mapping: String,
 
+        /// Record per-key latency percentiles to key_latency.csv in the run directory
+        #[arg(long, default_value = "false")]
+        key_latency: bool,
+
+        /// Re-create this percent of the subscriptions every second (needs
+        /// --share-transport); each re-subscription is logged to churn.csv
+        #[arg(long)]
+        churn_pct: Option<f64>,
+
         /// Duration in seconds
         #[arg(long, default_value = "60")]
         duration: u32,
@@ -306,6 +444,224 @@ enum Commands {
         #[arg(long, default_value = "0")]
         crash_stagger_secs: f64,
     },
+    /// Multi-key requester (single process, many logical requesters)
+    #[command(name = "mt-req")]
+    MtReq {
+        /// Messaging engine (zenoh|mqtt|redis|nats)
+        #[arg(long, default_value = "zenoh")]
+        engine: String,
+
+        /// Engine connect options as KEY=VALUE (repeatable)
+        #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        connect: Vec<String>,
+
+        /// Back-compat: Zenoh endpoints (maps to connect endpoint=...)
+        #[arg(long)]
+        endpoint: Vec<String>,
+
+        /// Serve prefix template ({t} tenant, {r} region, {n} namespace, {id}); served as PREFIX/** unless equal to --key-template
+        #[arg(long, default_value = "bench/qry/t{t}/r{r}/ns{n}")]
+        serve_prefix_template: String,
+
+        /// Request key template ({t}, {r}, {n}, {id})
+        #[arg(long, default_value = "bench/qry/t{t}/r{r}/ns{n}/item/{id}")]
+        key_template: String,
+
+        /// Dimensions: tenants, regions, namespaces, ids
+        #[arg(long, default_value = "2")]
+        tenants: u32,
+        #[arg(long, default_value = "2")]
+        regions: u32,
+        #[arg(long, default_value = "2")]
+        namespaces: u32,
+        #[arg(long, default_value = "5")]
+        ids: u32,
+
+        /// Number of logical requesters, one key each (<= T*R*N*ID). Negative => use total_keys
+        #[arg(long, default_value = "-1", allow_hyphen_values = true)]
+        requesters: i64,
+
+        /// Queries per second per requester. If omitted or <= 0, runs at max speed (no delay)
+        #[arg(long, alias = "rate", allow_hyphen_values = true)]
+        qps: Option<i32>,
+
+        /// Arrival model around the mean rate: constant|poisson|onoff:ON_MS:OFF_MS|burst:N
+        #[arg(long, default_value = "constant", value_parser = arrival_model_arg)]
+        arrival: ArrivalModel,
+
+        /// Seed for the arrival model (default: from the clock)
+        #[arg(long)]
+        arrival_seed: Option<u64>,
+
+        /// In-flight concurrency per requester
+        #[arg(long, default_value = "4")]
+        concurrency: u32,
+
+        /// Timeout per query (ms)
+        #[arg(long, default_value = "5000")]
+        timeout: u64,
+
+        /// Request body size in bytes (0: empty; otherwise at least 24)
+        #[arg(long, default_value = "0")]
+        request_size: u32,
+
+        /// Also register queryables on every serve prefix in this process
+        #[arg(long, default_value = "false")]
+        serve: bool,
+
+        /// Connect options for the in-process queryables as KEY=VALUE (repeatable; default: --connect)
+        #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        qry_connect: Vec<String>,
+
+        /// Reply size in bytes
+        #[arg(long, default_value = "1024")]
+        reply_size: u32,
+
+        /// Processing delay (ms)
+        #[arg(long, default_value = "0")]
+        proc_delay: u64,
+
+        /// Reply content: fixed|echo|transform (as for qry)
+        #[arg(long, default_value = "fixed", value_parser = reply_mode_arg)]
+        reply_mode: ReplyMode,
+
+        /// Service-time model replacing --proc-delay (as for qry)
+        #[arg(long, value_parser = service_time_arg)]
+        service_time: Option<ServiceTime>,
+
+        /// Stream each reply as K (or MIN..MAX) chunks (as for qry)
+        #[arg(long, value_parser = chunk_count_arg)]
+        chunks: Option<ChunkCount>,
+
+        /// Seed for service-time and chunk-count sampling (default: from the clock)
+        #[arg(long)]
+        service_seed: Option<u64>,
+
+        /// Burn CPU for the service time instead of sleeping
+        #[arg(long)]
+        cpu_bound: bool,
+
+        /// Serve queries from one pool of N workers for all prefixes (0: a task per query)
+        #[arg(long, default_value = "0")]
+        workers: usize,
+
+        /// Duration in seconds
+        #[arg(long, default_value = "60")]
+        duration: u32,
+
+        /// Share a single transport across all requesters (and one across the in-process queryables) (default: false)
+        #[arg(long, default_value = "false")]
+        share_transport: bool,
+
+        /// Total ramp-up time in seconds to spread requester starts (default: 0 = no delay)
+        #[arg(long, default_value = "0")]
+        ramp_up_secs: f64,
+
+        /// Optional CSV output file path (stdout if omitted)
+        #[arg(long)]
+        csv: Option<String>,
+
+        /// Enable connection retry with exponential backoff
+        #[arg(long, default_value = "false")]
+        enable_retry: bool,
+
+        /// Maximum number of connection retry attempts
+        #[arg(long, default_value = "3")]
+        retry_count: u32,
+
+        /// Initial delay between retries in milliseconds
+        #[arg(long, default_value = "1000")]
+        retry_delay: u64,
+    },
+    /// Multi-prefix queryable (single process, many templated prefixes)
+    #[command(name = "mt-qry")]
+    MtQry {
+        /// Messaging engine (zenoh|mqtt|redis|nats)
+        #[arg(long, default_value = "zenoh")]
+        engine: String,
+
+        /// Engine connect options as KEY=VALUE (repeatable)
+        #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        connect: Vec<String>,
+
+        /// Back-compat: Zenoh endpoints (maps to connect endpoint=...)
+        #[arg(long)]
+        endpoint: Vec<String>,
+
+        /// Serve prefix template ({t} tenant, {r} region, {n} namespace, {id}); served as PREFIX/** unless equal to --key-template
+        #[arg(long, default_value = "bench/qry/t{t}/r{r}/ns{n}")]
+        serve_prefix_template: String,
+
+        /// Request key template ({t}, {r}, {n}, {id})
+        #[arg(long, default_value = "bench/qry/t{t}/r{r}/ns{n}/item/{id}")]
+        key_template: String,
+
+        /// Dimensions: tenants, regions, namespaces, ids
+        #[arg(long, default_value = "2")]
+        tenants: u32,
+        #[arg(long, default_value = "2")]
+        regions: u32,
+        #[arg(long, default_value = "2")]
+        namespaces: u32,
+        #[arg(long, default_value = "5")]
+        ids: u32,
+
+        /// Reply size in bytes
+        #[arg(long, default_value = "1024")]
+        reply_size: u32,
+
+        /// Processing delay (ms)
+        #[arg(long, default_value = "0")]
+        proc_delay: u64,
+
+        /// Reply content: fixed|echo|transform (as for qry)
+        #[arg(long, default_value = "fixed", value_parser = reply_mode_arg)]
+        reply_mode: ReplyMode,
+
+        /// Service-time model replacing --proc-delay (as for qry)
+        #[arg(long, value_parser = service_time_arg)]
+        service_time: Option<ServiceTime>,
+
+        /// Stream each reply as K (or MIN..MAX) chunks (as for qry)
+        #[arg(long, value_parser = chunk_count_arg)]
+        chunks: Option<ChunkCount>,
+
+        /// Seed for service-time and chunk-count sampling (default: from the clock)
+        #[arg(long)]
+        service_seed: Option<u64>,
+
+        /// Burn CPU for the service time instead of sleeping
+        #[arg(long)]
+        cpu_bound: bool,
+
+        /// Serve queries from one pool of N workers for all prefixes (0: a task per query)
+        #[arg(long, default_value = "0")]
+        workers: usize,
+
+        /// Duration in seconds
+        #[arg(long, default_value = "60")]
+        duration: u32,
+
+        /// Share a single transport across all queryables (default: false)
+        #[arg(long, default_value = "false")]
+        share_transport: bool,
+
+        /// Optional CSV output file path (stdout if omitted)
+        #[arg(long)]
+        csv: Option<String>,
+
+        /// Enable connection retry with exponential backoff
+        #[arg(long, default_value = "false")]
+        enable_retry: bool,
+
+        /// Maximum number of connection retry attempts
+        #[arg(long, default_value = "3")]
+        retry_count: u32,
+
+        /// Initial delay between retries in milliseconds
+        #[arg(long, default_value = "1000")]
+        retry_delay: u64,
+    },
     /// Subscriber role
     Sub {
         /// Messaging engine (zenoh|mqtt|redis|nats)
@@ -328,6 +684,11 @@ enum Commands {
         #[arg(long, default_value = "1")]
         subscribers: u32,
 
+        /// Fan-in: account per source (mt-pub key) and write per_source.csv and
+        /// fan_in.json to the run directory
+        #[arg(long, default_value = "false")]
+        per_source: bool,
+
         /// QoS level (0,1,2). Mapped per engine; for zenoh: 0=best effort, 1/2=reliable
         #[arg(long, default_value_t = 0u8)]
         qos: u8,
@@ -382,10 +743,31 @@ enum Commands {
         #[arg(long, required = true)]
         key_expr: String,
 
+        /// Request body size in bytes (0: empty; otherwise at least 24, starting with the payload header)
+        #[arg(long, default_value = "0")]
+        request_size: u32,
+
+        /// Read each query's replies to the end of the stream (see qry --chunks); writes stream.json with first/last-chunk latency and chunk loss
+        #[arg(long)]
+        stream: bool,
+
         /// Queries per second. If omitted or <= 0, runs at max speed (no delay)
         #[arg(long, alias = "rate", allow_hyphen_values = true)]
         qps: Option<i32>,
 
+        /// Arrival model around the mean rate: constant|poisson|onoff:ON_MS:OFF_MS|burst:N
+        #[arg(long, default_value = "constant", value_parser = arrival_model_arg)]
+        arrival: ArrivalModel,
+
+        /// Seed for the arrival model (default: from the clock)
+        #[arg(long)]
+        arrival_seed: Option<u64>,
+
+        /// Time-varying rate per sender, replacing the fixed rate:
+        /// ramp:FROM..TO@DUR | steps:R1,R2,...@DUR | sine:mean=M,amp=A,period=DUR
+        #[arg(long, value_parser = rate_profile_arg)]
+        rate_profile: Option<RateProfile>,
+
         /// In-flight concurrency
         #[arg(long, default_value = "10")]
         concurrency: u32,
@@ -436,10 +818,34 @@ enum Commands {
         #[arg(long, default_value = "1024")]
         reply_size: u32,
 
+        /// Reply content: fixed (reply-size bytes), echo (the request) or transform (reply-size bytes carrying the request's header)
+        #[arg(long, default_value = "fixed", value_parser = reply_mode_arg)]
+        reply_mode: ReplyMode,
+
         /// Processing delay (ms)
         #[arg(long, default_value = "0")]
         proc_delay: u64,
 
+        /// Service-time model replacing --proc-delay: fixed:MS | exp:MEAN_MS | lognormal:MEDIAN_MS:SIGMA | bimodal:FAST_MS:SLOW_MS:P_SLOW
+        #[arg(long, value_parser = service_time_arg)]
+        service_time: Option<ServiceTime>,
+
+        /// Stream each reply as K chunks (or MIN..MAX, uniform per query), each after its own service time, then end the query
+        #[arg(long, value_parser = chunk_count_arg)]
+        chunks: Option<ChunkCount>,
+
+        /// Seed for service-time and chunk-count sampling (default: from the clock)
+        #[arg(long)]
+        service_seed: Option<u64>,
+
+        /// Burn CPU for the service time instead of sleeping
+        #[arg(long)]
+        cpu_bound: bool,
+
+        /// Serve queries from a pool of N workers (0: a task per query); writes queue.csv and queue.json
+        #[arg(long, default_value = "0")]
+        workers: usize,
+
         /// QoS level (0,1,2). Mapped per engine; for zenoh: 0=best effort, 1/2=reliable
         #[arg(long, default_value_t = 0u8)]
         qos: u8,
@@ -514,96 +920,638 @@ enum Commands {
         #[arg(long, default_value = "10")]
         ack_timeout: u64,
     },
-}
+    /// Replay a recorded message trace (CSV/JSONL timestamp,topic,size) with its original timing
+    Replay {
+        /// Messaging engine (zenoh|mqtt|redis|nats)
+        #[arg(long, default_value = "zenoh")]
+        engine: String,
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+        /// Engine connect options as KEY=VALUE (repeatable)
+        #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        connect: Vec<String>,
 
-    // Initialize logging
-    mq_bench::logging::init(&cli.log_level)?;
+        /// Back-compat: Zenoh endpoints (maps to connect endpoint=...)
+        #[arg(long)]
+        endpoint: Vec<String>,
 
-    println!(
-        "mq-bench starting with run_id: {}",
-        if cli.run_id.is_empty() {
-            "auto"
-        } else {
-            &cli.run_id
-        }
-    );
+        /// Trace file: CSV with a timestamp,topic,size header, or .jsonl/.ndjson
+        #[arg(long)]
+        trace: PathBuf,
 
-    // Capture snapshot interval once (u64 is Copy)
-    let snapshot_interval_secs = cli.snapshot_interval;
+        /// Prefix the recorded topics are published under (empty: as recorded)
+        #[arg(long, default_value = "bench/replay")]
+        topic_prefix: String,
 
-    match cli.command {
-        Commands::Pub {
-            engine,
-            connect,
-            endpoint,
-            topic_prefix,
-            topics,
-            publishers,
-            payload,
-            rate,
-            duration,
-            qos,
-            csv,
-            share_transport: _,
-            enable_retry,
-            retry_count,
-            retry_delay,
-            mttf,
-            mttr,
-            crash_count,
-            crash_seed,
-        } => {
-            // Parse engine and connect opts (support legacy --endpoint)
-            let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
-            let mut conn = parse_connect_kv(&connect);
-            if conn.params.is_empty() {
-                if let Some(ep) = endpoint.first() {
-                    conn.params.insert("endpoint".into(), ep.clone());
-                }
-            }
-            // Wire retry options
-            conn.retry_enabled = enable_retry;
-            conn.retry_count = retry_count;
-            conn.retry_delay_ms = retry_delay;
-            conn.retry_max_delay_ms = 30000; // 30s max delay
-            // Inject QoS into connect params if not already provided
-            conn.params
-                .entry("qos".into())
-                .or_insert_with(|| qos.to_string());
-            let mut handles = Vec::new();
-            // Externalize snapshotting always (single or multiple)
-            let shared_stats: Option<Arc<Stats>> = Some(Arc::new(Stats::new()));
-            let mut agg_output = if let Some(ref path) = csv {
-                // Single aggregate file
-                Some(OutputWriter::new_csv(path.clone()).await?)
-            } else {
-                Some(OutputWriter::new_stdout())
-            };
-            // snapshot_interval_secs from CLI
-            let agg_stats_clone = shared_stats.clone();
-            let agg_handle = if let Some(stats) = agg_stats_clone.clone() {
-                // Spawn aggregate snapshotter to write combined stats
-                let mut out = agg_output.take();
-                Some(tokio::spawn(async move {
-                    let mut t = tokio::time::interval(std::time::Duration::from_secs(
-                        snapshot_interval_secs,
-                    ));
-                    loop {
-                        t.tick().await;
-                        let snap = stats.snapshot().await;
-                        if let Some(ref mut o) = out {
-                            let _ = o.write_snapshot(&snap).await;
-                        }
-                    }
-                }))
-            } else {
-                None
-            };
-            for i in 0..publishers {
+        /// Rewrite recorded topics starting with FROM to start with TO (repeatable, first match wins)
+        #[arg(long = "map", value_name = "FROM=TO", value_parser = topic_map_arg)]
+        topic_map: Vec<(String, String)>,
+
+        /// Replay speed factor (2 = twice as fast, 0.5 = half speed)
+        #[arg(long, default_value = "1")]
+        speed: f64,
+
+        /// Passes over the trace (0 = repeat until --duration)
+        #[arg(long, default_value = "1")]
+        loops: u32,
+
+        /// Stop after this many seconds (default: when the last pass ends)
+        #[arg(long)]
+        duration: Option<u64>,
+
+        /// QoS level (0,1,2). Mapped per engine; for zenoh: 0=best effort, 1/2=reliable
+        #[arg(long, default_value_t = 0u8)]
+        qos: u8,
+
+        /// Optional CSV output file path (stdout if omitted)
+        #[arg(long)]
+        csv: Option<String>,
+
+        /// Enable connection retry with exponential backoff
+        #[arg(long, default_value = "false")]
+        enable_retry: bool,
+
+        /// Maximum number of connection retry attempts
+        #[arg(long, default_value = "3")]
+        retry_count: u32,
+
+        /// Initial delay between retries in milliseconds
+        #[arg(long, default_value = "1000")]
+        retry_delay: u64,
+    },
+    /// Open many connections at a target rate to measure broker connect capacity
+    #[command(name = "conn-storm")]
+    ConnStorm {
+        /// Messaging engine (zenoh|mqtt|redis|nats)
+        #[arg(long, default_value = "zenoh")]
+        engine: String,
+
+        /// Engine connect options as KEY=VALUE (repeatable)
+        #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        connect: Vec<String>,
+
+        /// Back-compat: Zenoh endpoints (maps to connect endpoint=...)
+        #[arg(long)]
+        endpoint: Vec<String>,
+
+        /// Number of connections to open
+        #[arg(long, default_value = "1000")]
+        connections: u64,
+
+        /// Connect attempts started per second (omit: as fast as --concurrency allows)
+        #[arg(long)]
+        rate: Option<f64>,
+
+        /// Connect attempts in flight at once
+        #[arg(long, default_value = "64")]
+        concurrency: usize,
+
+        /// Subscribe to PREFIX/<i> on each connection once it is up
+        #[arg(long, value_name = "PREFIX")]
+        subscribe: Option<String>,
+
+        /// Seconds to hold all connections open after the last attempt
+        #[arg(long, default_value = "10")]
+        hold_secs: u64,
+
+        /// Per-attempt connect (and subscribe) timeout in milliseconds
+        #[arg(long, default_value = "10000")]
+        connect_timeout_ms: u64,
+
+        /// Latency counts as degraded when a block's median exceeds this multiple of the first block's
+        #[arg(long, default_value = "2")]
+        degrade_factor: f64,
+
+        /// Optional CSV output file path (stdout if omitted)
+        #[arg(long)]
+        csv: Option<String>,
+    },
+    /// Run a YAML scenario of pub/sub and req/reply workloads concurrently, with stats per workload class
+    Scenario {
+        /// Scenario file (workloads with class, role, key, engine and load)
+        #[arg(long)]
+        file: String,
+
+        /// Override the scenario's duration_secs
+        #[arg(long)]
+        duration: Option<u64>,
+
+        /// Run every class alone first and report mixed/isolated latency ratios
+        #[arg(long)]
+        isolate: bool,
+    },
+    /// Summarize run artifacts into summary.csv, SVG charts and report.md
+    Report {
+        /// Artifacts root to scan (new per-role layout and legacy orchestrator dirs)
+        #[arg(long, default_value = "./artifacts")]
+        artifacts: String,
+
+        /// Output directory (default: <artifacts>/report)
+        #[arg(long)]
+        out: Option<String>,
+    },
+    /// Compare two result sets; exits with code 3 when a regression exceeds the threshold
+    Compare {
+        /// Baseline result set (artifacts directory, summary.csv, .json/.jsonl rows, or role summary.json documents)
+        #[arg(long)]
+        baseline: String,
+
+        /// Candidate result set (same formats as --baseline)
+        #[arg(long)]
+        candidate: String,
+
+        /// Metrics to compare (sub_tps, pub_tps, p50_ms, p95_ms, p99_ms, max_cpu_perc, max_mem_perc)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "sub_tps,p50_ms,p95_ms,p99_ms"
+        )]
+        metrics: Vec<String>,
+
+        /// Regression threshold: percent change in the worse direction
+        #[arg(long, default_value = "5")]
+        threshold_pct: f64,
+
+        /// Confidence level for intervals and significance (alpha = 1 - confidence)
+        #[arg(long, default_value = "0.95")]
+        confidence: f64,
+
+        /// Significance test gating regressions when runs are repeated (mann-whitney|bootstrap)
+        #[arg(long, default_value = "mann-whitney", value_parser = significance_test_arg)]
+        test: SignificanceTest,
+
+        /// Bootstrap resamples
+        #[arg(long, default_value = "2000")]
+        bootstrap_iters: usize,
+
+        /// RNG seed for the bootstrap
+        #[arg(long, default_value = "1")]
+        seed: u64,
+
+        /// Also write the comparison as JSON to this path
+        #[arg(long)]
+        json: Option<String>,
+    },
+
+    /// Search for the highest total rate that meets a delivery and p99 SLO (in-process pub + sub)
+    #[command(name = "find-max")]
+    FindMax {
+        /// Messaging engine (zenoh|mqtt|redis|nats)
+        #[arg(long, default_value = "zenoh")]
+        engine: String,
+
+        /// Engine connect options as KEY=VALUE (repeatable)
+        #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+        connect: Vec<String>,
+
+        /// Back-compat: Zenoh endpoints (maps to connect endpoint=...)
+        #[arg(long)]
+        endpoint: Vec<String>,
+
+        /// Topic prefix
+        #[arg(long, default_value = "bench/findmax")]
+        topic_prefix: String,
+
+        /// Publisher role driving the load (pub|mt-pub)
+        #[arg(long, default_value = "pub", value_parser = driver_arg)]
+        driver: Driver,
+
+        /// Number of publishers sharing the offered rate
+        #[arg(long, default_value = "1")]
+        publishers: u32,
+
+        /// mt-pub driver: number of keys (shards of one tenant/region/service); pub uses one key per publisher
+        #[arg(long, default_value = "1")]
+        topics: u32,
+
+        /// Payload size in bytes
+        #[arg(long, default_value = "1024")]
+        payload: u32,
+
+        /// QoS level (0,1,2). Mapped per engine; for zenoh: 0=best effort, 1/2=reliable
+        #[arg(long, default_value_t = 0u8)]
+        qos: u8,
+
+        /// Minimum delivery ratio (received/sent) over the window
+        #[arg(long, default_value = "0.999")]
+        min_delivery: f64,
+
+        /// Maximum p99 latency of any 1 s interval in the window (ms)
+        #[arg(long, default_value = "10")]
+        max_p99_ms: f64,
+
+        /// First total rate probed (msg/s)
+        #[arg(long, default_value = "1000")]
+        start_rate: f64,
+
+        /// Highest total rate probed (msg/s)
+        #[arg(long, default_value = "1000000")]
+        max_rate: f64,
+
+        /// Stop once the pass/fail bracket is within this fraction of the pass rate
+        #[arg(long, default_value = "0.05")]
+        precision: f64,
+
+        /// Seconds each probe runs before measuring
+        #[arg(long, default_value = "2")]
+        settle_secs: u64,
+
+        /// Seconds each probe measures (the stable window)
+        #[arg(long, default_value = "5")]
+        window_secs: u64,
+    },
+
+    /// Repeat a role configuration and report mean, median, stddev and 95% CI across trials
+    Trials {
+        /// Trials per variant
+        #[arg(long, default_value = "5")]
+        repeat: u32,
+
+        /// Pause between trials in seconds
+        #[arg(long, default_value = "0")]
+        cooldown_secs: f64,
+
+        /// Vary a role flag: KEY=V1,V2 (repeatable; every combination is one variant)
+        #[arg(long)]
+        matrix: Vec<String>,
+
+        /// Randomize the order of all (variant, trial) runs
+        #[arg(long)]
+        shuffle: bool,
+
+        /// Seed for --shuffle (default: from the clock, printed for reuse)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Companion role started before each trial and stopped after it, e.g. "sub --engine zenoh --expr bench/**" (repeatable; split on whitespace)
+        #[arg(long = "with")]
+        companions: Vec<String>,
+
+        /// Seconds to wait after starting companions
+        #[arg(long, default_value = "1")]
+        settle_secs: f64,
+
+        /// Main role command, e.g. `pub --engine zenoh --duration 10`
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Query a SQLite results store written with --results-db
+    #[cfg(feature = "sqlite")]
+    Query {
+        /// Results database
+        #[arg(long)]
+        db: PathBuf,
+
+        /// Canned view (runs|best-engine|p99-vs-rate)
+        #[arg(default_value = "runs", value_parser = query_view_arg)]
+        view: QueryView,
+
+        /// Only rows for this engine
+        #[arg(long)]
+        engine: Option<String>,
+
+        /// Only rows for this payload size
+        #[arg(long)]
+        payload: Option<u64>,
+
+        /// Run this SQL instead of a view (tables: runs, snapshots, summaries; view: run_results)
+        #[arg(long)]
+        sql: Option<String>,
+
+        /// Print CSV instead of a Markdown table
+        #[arg(long)]
+        csv: bool,
+    },
+}
+
+fn output_format_arg(s: &str) -> Result<OutputFormat, String> {
+    parse_output_format(s).ok_or_else(|| format!("unknown output format '{}' (csv|jsonl)", s))
+}
+
+fn arrival_model_arg(s: &str) -> Result<ArrivalModel, String> {
+    parse_arrival_model(s).ok_or_else(|| {
+        format!(
+            "unknown arrival model '{}' (constant|poisson|onoff:ON_MS:OFF_MS|burst:N)",
+            s
+        )
+    })
+}
+
+fn rate_profile_arg(s: &str) -> Result<RateProfile, String> {
+    parse_rate_profile(s).ok_or_else(|| {
+        format!(
+            "invalid rate profile '{}' (ramp:FROM..TO@DUR|steps:R1,R2@DUR|sine:mean=M,amp=A,period=DUR)",
+            s
+        )
+    })
+}
+
+fn popularity_arg(s: &str) -> Result<Popularity, String> {
+    parse_popularity(s).ok_or_else(|| {
+        format!(
+            "invalid popularity '{}' (uniform|zipf:S|hot:KEYS_PCT:TRAFFIC_PCT)",
+            s
+        )
+    })
+}
+
+fn reply_mode_arg(s: &str) -> Result<ReplyMode, String> {
+    parse_reply_mode(s).ok_or_else(|| format!("unknown reply mode '{}' (fixed|echo|transform)", s))
+}
+
+fn service_time_arg(s: &str) -> Result<ServiceTime, String> {
+    parse_service_time(s).ok_or_else(|| {
+        format!(
+            "invalid service time '{}' (fixed:MS|exp:MEAN_MS|lognormal:MEDIAN_MS:SIGMA|bimodal:FAST_MS:SLOW_MS:P_SLOW)",
+            s
+        )
+    })
+}
+
+fn chunk_count_arg(s: &str) -> Result<ChunkCount, String> {
+    parse_chunk_count(s).ok_or_else(|| format!("invalid chunk count '{}' (K|MIN..MAX, K >= 1)", s))
+}
+
+fn topic_map_arg(s: &str) -> Result<(String, String), String> {
+    parse_topic_map(s).ok_or_else(|| format!("invalid topic map '{}' (FROM=TO)", s))
+}
+
+fn driver_arg(s: &str) -> Result<Driver, String> {
+    parse_driver(s).ok_or_else(|| format!("unknown driver '{}' (pub|mt-pub)", s))
+}
+
+fn significance_test_arg(s: &str) -> Result<SignificanceTest, String> {
+    parse_significance_test(s)
+        .ok_or_else(|| format!("unknown test '{}' (mann-whitney|bootstrap)", s))
+}
+
+#[cfg(feature = "sqlite")]
+fn query_view_arg(s: &str) -> Result<QueryView, String> {
+    parse_query_view(s)
+        .ok_or_else(|| format!("unknown view '{}' (runs|best-engine|p99-vs-rate)", s))
+}
+
+#[cfg(feature = "otel")]
+fn otlp_protocol_arg(s: &str) -> Result<OtlpProtocol, String> {
+    parse_otlp_protocol(s).ok_or_else(|| format!("unknown OTLP protocol '{}' (grpc|http)", s))
+}
+
+/// Global flags every trial process inherits (run id and output dir are set per trial)
+fn trial_forward_args(cli: &Cli) -> Vec<String> {
+    let mut args = Vec::new();
+    args.extend([
+        "--log-level".to_string(),
+        cli.log_level.clone(),
+        "--snapshot-interval".to_string(),
+        cli.snapshot_interval.to_string(),
+        "--output-format".to_string(),
+        cli.output_format.extension().to_string(),
+        "--warmup-secs".to_string(),
+        cli.warmup_secs.to_string(),
+        "--cooldown-secs".to_string(),
+        cli.cooldown_secs.to_string(),
+    ]);
+    #[cfg(feature = "sqlite")]
+    if let Some(db) = &cli.results_db {
+        args.push("--results-db".to_string());
+        args.push(db.to_string_lossy().into_owned());
+    }
+    args
+}
+
+/// Per-invocation settings shared by every role's output session
+struct SessionContext {
+    run_id: String,
+    /// `<out_dir>/<run_id>`; each role adds `<role>-<instance>`
+    run_dir: PathBuf,
+    instance: String,
+    argv: Vec<String>,
+    metrics_listen: Option<SocketAddr>,
+    format: OutputFormat,
+    snapshot_interval_secs: u64,
+    window: MeasurementWindow,
+    /// Resolved subcommand arguments (tagged with the role name)
+    config: serde_json::Value,
+    #[cfg(feature = "tui")]
+    tui: bool,
+    #[cfg(feature = "sqlite")]
+    results_db: Option<PathBuf>,
+    /// Global flags passed on to trial processes
+    trial_args: Vec<String>,
+}
+
+/// `<out_dir>/<run_id>/<role>-<instance>`
+fn role_dir(ctx: &SessionContext) -> PathBuf {
+    let role = ctx.config["role"].as_str().unwrap_or_default();
+    ctx.run_dir.join(format!("{}-{}", role, ctx.instance))
+}
+
+fn init_logging(level: &str, log_file: Option<&Path>) -> Result<()> {
+    match log_file {
+        Some(path) => mq_bench::logging::init_file(level, path),
+        None => mq_bench::logging::init(level),
+    }
+}
+
+/// Serialize the parsed subcommand for the run summary, redacting connect secrets.
+fn command_config(command: &Commands) -> serde_json::Value {
+    let mut value = serde_json::to_value(command).unwrap_or_default();
+    if let Some(obj) = value.as_object_mut()
+        && let Some(kv) = obj.get("connect").and_then(|v| v.as_array())
+    {
+        let kv: Vec<String> = kv
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect();
+        obj.insert("connect".into(), serde_json::json!(redact_connect_kv(&kv)));
+    }
+    value
+}
+
+/// Topic/key the role works on, for metric labels (first of the role's key arguments)
+fn config_topic(config: &serde_json::Value) -> String {
+    ["topic_prefix", "expr", "key_expr", "topic", "serve_prefix"]
+        .iter()
+        .find_map(|k| match config.get(*k)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Array(a) => Some(
+                a.iter()
+                    .filter_map(|v| v.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Open the role's artifact directory and snapshot writers; `finish` writes the summary.
+async fn start_session(
+    ctx: &SessionContext,
+    engine: &Engine,
+    conn: &ConnectOptions,
+    output_path: Option<&str>,
+) -> Result<RoleSession> {
+    let role = ctx.config["role"].as_str().unwrap_or_default().to_string();
+    RoleSession::start(SessionOptions {
+        run_id: ctx.run_id.clone(),
+        run_dir: Some(role_dir(ctx)),
+        instance: ctx.instance.clone(),
+        argv: ctx.argv.clone(),
+        role,
+        engine: format!("{:?}", engine).to_lowercase(),
+        connect: redact_connect_params(&conn.params),
+        config: ctx.config.clone(),
+        output_path: output_path.map(str::to_string),
+        format: ctx.format,
+        snapshot_interval_secs: ctx.snapshot_interval_secs,
+        metrics_listen: ctx.metrics_listen,
+        topic: config_topic(&ctx.config),
+        window: ctx.window,
+        #[cfg(feature = "tui")]
+        tui: ctx.tui,
+        #[cfg(feature = "sqlite")]
+        results_db: ctx.results_db.clone(),
+    })
+    .await
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let run_id = if cli.run_id.is_empty() {
+        chrono::Local::now().format("%Y%m%d-%H%M%S").to_string()
+    } else {
+        cli.run_id.clone()
+    };
+    let ctx = SessionContext {
+        run_dir: Path::new(&cli.out_dir).join(&run_id),
+        run_id,
+        instance: cli
+            .instance
+            .clone()
+            .unwrap_or_else(|| std::process::id().to_string()),
+        argv: redact_argv(&std::env::args().collect::<Vec<_>>()),
+        format: cli.output_format,
+        metrics_listen: cli.metrics_listen,
+        snapshot_interval_secs: cli.snapshot_interval,
+        window: MeasurementWindow {
+            warmup: std::time::Duration::from_secs_f64(cli.warmup_secs.max(0.0)),
+            cooldown: std::time::Duration::from_secs_f64(cli.cooldown_secs.max(0.0)),
+            anchor_ns: cli.window_anchor.map(|s| (s * 1e9) as u64),
+        },
+        config: command_config(&cli.command),
+        #[cfg(feature = "tui")]
+        tui: cli.tui,
+        #[cfg(feature = "sqlite")]
+        results_db: cli.results_db.clone(),
+        trial_args: trial_forward_args(&cli),
+    };
+
+    // The dashboard owns the terminal, so logs go to the role's artifact directory
+    #[cfg(feature = "tui")]
+    let log_file = cli.tui.then(|| role_dir(&ctx).join("mq-bench.log"));
+    #[cfg(not(feature = "tui"))]
+    let log_file: Option<PathBuf> = None;
+
+    // Initialize logging (plus OTLP export when a collector is given)
+    #[cfg(feature = "otel")]
+    let otel_guard = match &cli.otel_endpoint {
+        Some(endpoint) => {
+            let cfg = OtelConfig {
+                endpoint: endpoint.clone(),
+                protocol: cli.otel_protocol,
+                sample_every: cli.otel_sample_every,
+                metrics_interval: std::time::Duration::from_secs(cli.otel_metrics_interval.max(1)),
+                resource: vec![
+                    ("mq_bench.run_id".into(), ctx.run_id.clone()),
+                    (
+                        "mq_bench.role".into(),
+                        ctx.config["role"].as_str().unwrap_or_default().into(),
+                    ),
+                    ("mq_bench.instance".into(), ctx.instance.clone()),
+                ],
+            };
+            Some(mq_bench::logging::init_with_otel_to(
+                &cli.log_level,
+                &cfg,
+                log_file.as_deref(),
+            )?)
+        }
+        None => {
+            init_logging(&cli.log_level, log_file.as_deref())?;
+            None
+        }
+    };
+    #[cfg(not(feature = "otel"))]
+    init_logging(&cli.log_level, log_file.as_deref())?;
+
+    println!("mq-bench starting with run_id: {}", ctx.run_id);
+
+    let result = run_command(cli.command, &ctx).await;
+
+    #[cfg(feature = "otel")]
+    if let Some(guard) = otel_guard
+        && let Err(e) = guard.shutdown().await
+    {
+        tracing::warn!(error = %e, "OTLP flush on exit failed");
+    }
+    result
+}
+
+async fn run_command(command: Commands, ctx: &SessionContext) -> Result<()> {
+    let snapshot_interval_secs = ctx.snapshot_interval_secs;
+    match command {
+        Commands::Pub {
+            engine,
+            connect,
+            endpoint,
+            topic_prefix,
+            topics,
+            publishers,
+            payload,
+            rate,
+            arrival,
+            arrival_seed,
+            rate_profile,
+            window,
+            ack_key,
+            ack_timeout_ms,
+            duration,
+            qos,
+            csv,
+            share_transport: _,
+            enable_retry,
+            retry_count,
+            retry_delay,
+            mttf,
+            mttr,
+            crash_count,
+            crash_seed,
+        } => {
+            // Parse engine and connect opts (support legacy --endpoint)
+            let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
+            let mut conn = parse_connect_kv(&connect);
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
+            }
+            // Wire retry options
+            conn.retry_enabled = enable_retry;
+            conn.retry_count = retry_count;
+            conn.retry_delay_ms = retry_delay;
+            conn.retry_max_delay_ms = 30000; // 30s max delay
+            // Inject QoS into connect params if not already provided
+            conn.params
+                .entry("qos".into())
+                .or_insert_with(|| qos.to_string());
+            let mut handles = Vec::new();
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
+            for i in 0..publishers {
                 let key_expr = if topics > 1 {
                     format!("{}/{}", topic_prefix, (i % topics))
                 } else {
@@ -624,12 +1572,23 @@ async fn main() -> Result<()> {
                         Some(v) if v > 0 => Some(v as f64),
                         _ => None,
                     },
+                    arrival: ArrivalConfig {
+                        model: arrival,
+                        seed: arrival_seed,
+                        profile: rate_profile.clone(),
+                    }
+                    .for_stream(i as u64),
                     duration_secs: Some(duration as u64),
                     output_file: None,
-                    snapshot_interval_secs: snapshot_interval_secs,
+                    snapshot_interval_secs,
                     shared_stats: shared_stats.clone(),
                     disable_internal_snapshot: true,
                     crash_config: crash_cfg,
+                    closed_loop: window.map(|w| ClosedLoopConfig {
+                        window: w,
+                        ack_key: ack_key.clone(),
+                        ack_timeout: std::time::Duration::from_millis(ack_timeout_ms),
+                    }),
                 };
                 handles.push(tokio::spawn(async move {
                     let _ = run_publisher(cfg).await;
@@ -637,16 +1596,8 @@ async fn main() -> Result<()> {
             }
             // Wait for all publishers to finish
             let _ = join_all(handles).await;
-            // Write final snapshot once more and cleanup
-            if let Some(stats) = shared_stats {
-                if let Some(mut out) = agg_output {
-                    let snap = stats.snapshot().await;
-                    let _ = out.write_snapshot(&snap).await;
-                }
-            }
-            if let Some(h) = agg_handle {
-                h.abort();
-            }
+            // Final snapshot and summary
+            session.finish().await?;
             Ok(())
         }
         Commands::MtPub {
@@ -662,6 +1613,10 @@ async fn main() -> Result<()> {
             mapping,
             payload,
             rate,
+            arrival,
+            arrival_seed,
+            rate_profile,
+            popularity,
             duration,
             share_transport,
             ramp_up_secs,
@@ -678,10 +1633,10 @@ async fn main() -> Result<()> {
         } => {
             let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
             let mut conn = parse_connect_kv(&connect);
-            if conn.params.is_empty() {
-                if let Some(ep) = endpoint.first() {
-                    conn.params.insert("endpoint".into(), ep.clone());
-                }
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
             }
             // Wire retry options
             conn.retry_enabled = enable_retry;
@@ -700,31 +1655,9 @@ async fn main() -> Result<()> {
                 _ => KeyMappingMode::Hash,
             };
 
-            // Aggregate CSV via shared stats (like pub/sub)
-            let shared_stats: Option<Arc<Stats>> = Some(Arc::new(Stats::new()));
-            let mut agg_output = if let Some(ref path) = csv {
-                Some(OutputWriter::new_csv(path.clone()).await?)
-            } else {
-                Some(OutputWriter::new_stdout())
-            };
-            let agg_stats_clone = shared_stats.clone();
-            let agg_handle = if let Some(stats) = agg_stats_clone.clone() {
-                let mut out = agg_output.take();
-                Some(tokio::spawn(async move {
-                    let mut t = tokio::time::interval(std::time::Duration::from_secs(
-                        snapshot_interval_secs,
-                    ));
-                    loop {
-                        t.tick().await;
-                        let snap = stats.snapshot().await;
-                        if let Some(ref mut o) = out {
-                            let _ = o.write_snapshot(&snap).await;
-                        }
-                    }
-                }))
-            } else {
-                None
-            };
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
 
             let cfg = MultiTopicConfig {
                 engine: engine.clone(),
@@ -741,6 +1674,14 @@ async fn main() -> Result<()> {
                     Some(v) if v > 0 => Some(v as f64),
                     _ => None,
                 },
+                arrival: ArrivalConfig {
+                    model: arrival,
+                    seed: arrival_seed,
+                    profile: rate_profile.clone(),
+                },
+                popularity,
+                key_rates_file: (popularity != Popularity::Uniform)
+                    .then(|| role_dir(ctx).join("key_rates.csv")),
                 duration_secs: duration as u64,
                 snapshot_interval_secs,
                 share_transport,
@@ -752,15 +1693,8 @@ async fn main() -> Result<()> {
                 crash_stagger_secs,
             };
             run_multi_topic(cfg).await?;
-            if let Some(stats) = shared_stats {
-                if let Some(mut out) = agg_output {
-                    let snap = stats.snapshot().await;
-                    let _ = out.write_snapshot(&snap).await;
-                }
-            }
-            if let Some(h) = agg_handle {
-                h.abort();
-            }
+            // Final snapshot and summary
+            session.finish().await?;
             Ok(())
         }
         Commands::MtSub {
@@ -774,6 +1708,8 @@ async fn main() -> Result<()> {
             shards,
             subscribers,
             mapping,
+            key_latency,
+            churn_pct,
             duration,
             share_transport,
             ramp_up_secs,
@@ -790,10 +1726,10 @@ async fn main() -> Result<()> {
         } => {
             let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
             let mut conn = parse_connect_kv(&connect);
-            if conn.params.is_empty() {
-                if let Some(ep) = endpoint.first() {
-                    conn.params.insert("endpoint".into(), ep.clone());
-                }
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
             }
             // Wire retry options
             conn.retry_enabled = enable_retry;
@@ -812,31 +1748,9 @@ async fn main() -> Result<()> {
                 _ => KeyMappingMode::Hash,
             };
 
-            // Aggregate CSV via shared stats
-            let shared_stats: Option<Arc<Stats>> = Some(Arc::new(Stats::new()));
-            let mut agg_output = if let Some(ref path) = csv {
-                Some(OutputWriter::new_csv(path.clone()).await?)
-            } else {
-                Some(OutputWriter::new_stdout())
-            };
-            let agg_stats_clone = shared_stats.clone();
-            let agg_handle = if let Some(stats) = agg_stats_clone.clone() {
-                let mut out = agg_output.take();
-                Some(tokio::spawn(async move {
-                    let mut t = tokio::time::interval(std::time::Duration::from_secs(
-                        snapshot_interval_secs,
-                    ));
-                    loop {
-                        t.tick().await;
-                        let snap = stats.snapshot().await;
-                        if let Some(ref mut o) = out {
-                            let _ = o.write_snapshot(&snap).await;
-                        }
-                    }
-                }))
-            } else {
-                None
-            };
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
 
             let cfg = MultiTopicSubConfig {
                 engine: engine.clone(),
@@ -848,6 +1762,11 @@ async fn main() -> Result<()> {
                 shards,
                 subscribers,
                 mapping,
+                key_latency_file: key_latency.then(|| role_dir(ctx).join("key_latency.csv")),
+                churn: churn_pct.map(|pct_per_sec| ChurnConfig {
+                    pct_per_sec,
+                    events_file: Some(role_dir(ctx).join("churn.csv")),
+                }),
                 duration_secs: duration as u64,
                 snapshot_interval_secs,
                 share_transport,
@@ -859,15 +1778,194 @@ async fn main() -> Result<()> {
                 crash_stagger_secs,
             };
             run_multi_topic_sub(cfg).await?;
-            if let Some(stats) = shared_stats {
-                if let Some(mut out) = agg_output {
-                    let snap = stats.snapshot().await;
-                    let _ = out.write_snapshot(&snap).await;
-                }
+            // Final snapshot and summary
+            session.finish().await?;
+            Ok(())
+        }
+        Commands::MtReq {
+            engine,
+            connect,
+            endpoint,
+            serve_prefix_template,
+            key_template,
+            tenants,
+            regions,
+            namespaces,
+            ids,
+            requesters,
+            qps,
+            arrival,
+            arrival_seed,
+            concurrency,
+            timeout,
+            request_size,
+            serve,
+            qry_connect,
+            reply_size,
+            proc_delay,
+            reply_mode,
+            service_time,
+            chunks,
+            service_seed,
+            cpu_bound,
+            workers,
+            duration,
+            share_transport,
+            ramp_up_secs,
+            csv,
+            enable_retry,
+            retry_count,
+            retry_delay,
+        } => {
+            let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
+            let mut conn = parse_connect_kv(&connect);
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
             }
-            if let Some(h) = agg_handle {
-                h.abort();
+            // Wire retry options
+            conn.retry_enabled = enable_retry;
+            conn.retry_count = retry_count;
+            conn.retry_delay_ms = retry_delay;
+            conn.retry_max_delay_ms = 30000;
+            let qry_conn = if qry_connect.is_empty() {
+                conn.clone()
+            } else {
+                ConnectOptions {
+                    params: parse_connect_kv(&qry_connect).params,
+                    ..conn.clone()
+                }
+            };
+
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
+
+            let cfg = MultiQueryConfig {
+                engine: engine.clone(),
+                req_connect: conn,
+                qry_connect: qry_conn,
+                serve_prefix_template,
+                key_template,
+                tenants,
+                regions,
+                namespaces,
+                ids,
+                requesters,
+                qps_per_req: match qps {
+                    Some(v) if v > 0 => Some(v as u32),
+                    _ => None,
+                },
+                arrival: ArrivalConfig {
+                    model: arrival,
+                    seed: arrival_seed,
+                    profile: None,
+                },
+                concurrency,
+                timeout_ms: timeout,
+                request_size: request_size as usize,
+                serve,
+                reply_size: reply_size as usize,
+                reply_mode,
+                service_time: service_time.unwrap_or(ServiceTime::Fixed {
+                    ms: proc_delay as f64,
+                }),
+                chunks,
+                service_seed,
+                cpu_bound,
+                workers,
+                duration_secs: duration as u64,
+                snapshot_interval_secs,
+                share_transport,
+                ramp_up_secs,
+                shared_stats: shared_stats.clone(),
+                disable_internal_snapshot: true,
+            };
+            run_multi_query(cfg).await?;
+            // Final snapshot and summary
+            session.finish().await?;
+            Ok(())
+        }
+        Commands::MtQry {
+            engine,
+            connect,
+            endpoint,
+            serve_prefix_template,
+            key_template,
+            tenants,
+            regions,
+            namespaces,
+            ids,
+            reply_size,
+            proc_delay,
+            reply_mode,
+            service_time,
+            chunks,
+            service_seed,
+            cpu_bound,
+            workers,
+            duration,
+            share_transport,
+            csv,
+            enable_retry,
+            retry_count,
+            retry_delay,
+        } => {
+            let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
+            let mut conn = parse_connect_kv(&connect);
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
             }
+            // Wire retry options
+            conn.retry_enabled = enable_retry;
+            conn.retry_count = retry_count;
+            conn.retry_delay_ms = retry_delay;
+            conn.retry_max_delay_ms = 30000;
+
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
+
+            // Queryables only: no requesters
+            let cfg = MultiQueryConfig {
+                engine: engine.clone(),
+                req_connect: conn.clone(),
+                qry_connect: conn,
+                serve_prefix_template,
+                key_template,
+                tenants,
+                regions,
+                namespaces,
+                ids,
+                requesters: 0,
+                qps_per_req: None,
+                arrival: ArrivalConfig::default(),
+                concurrency: 1,
+                timeout_ms: 0,
+                request_size: 0,
+                serve: true,
+                reply_size: reply_size as usize,
+                reply_mode,
+                service_time: service_time.unwrap_or(ServiceTime::Fixed {
+                    ms: proc_delay as f64,
+                }),
+                chunks,
+                service_seed,
+                cpu_bound,
+                workers,
+                duration_secs: duration as u64,
+                snapshot_interval_secs,
+                share_transport,
+                ramp_up_secs: 0.0,
+                shared_stats: shared_stats.clone(),
+                disable_internal_snapshot: true,
+            };
+            run_multi_query(cfg).await?;
+            // Final snapshot and summary
+            session.finish().await?;
             Ok(())
         }
         Commands::Sub {
@@ -876,6 +1974,7 @@ async fn main() -> Result<()> {
             endpoint,
             expr,
             subscribers,
+            per_source,
             qos,
             csv,
             enable_retry,
@@ -888,10 +1987,10 @@ async fn main() -> Result<()> {
         } => {
             let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
             let mut conn = parse_connect_kv(&connect);
-            if conn.params.is_empty() {
-                if let Some(ep) = endpoint.first() {
-                    conn.params.insert("endpoint".into(), ep.clone());
-                }
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
             }
             // Wire retry options
             conn.retry_enabled = enable_retry;
@@ -903,33 +2002,10 @@ async fn main() -> Result<()> {
                 .entry("qos".into())
                 .or_insert_with(|| qos.to_string());
             let mut handles = Vec::new();
-            // Externalize snapshotting always
-            let shared_stats: Option<Arc<Stats>> = Some(Arc::new(Stats::new()));
-            let mut agg_output = if let Some(ref path) = csv {
-                Some(OutputWriter::new_csv(path.clone()).await?)
-            } else {
-                Some(OutputWriter::new_stdout())
-            };
-            // snapshot_interval_secs from CLI
-            let agg_stats_clone = shared_stats.clone();
-            let agg_handle = if let Some(stats) = agg_stats_clone.clone() {
-                let mut out = agg_output.take();
-                Some(tokio::spawn(async move {
-                    let mut t = tokio::time::interval(std::time::Duration::from_secs(
-                        snapshot_interval_secs,
-                    ));
-                    loop {
-                        t.tick().await;
-                        let snap = stats.snapshot().await;
-                        if let Some(ref mut o) = out {
-                            let _ = o.write_snapshot(&snap).await;
-                        }
-                    }
-                }))
-            } else {
-                None
-            };
-            for _i in 0..subscribers {
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
+            for i in 0..subscribers {
                 let crash_cfg = mq_bench::CrashConfig {
                     mttf_secs: mttf,
                     mttr_secs: mttr,
@@ -941,27 +2017,24 @@ async fn main() -> Result<()> {
                     connect: conn.clone(),
                     key_expr: expr.clone(),
                     output_file: None,
-                    snapshot_interval_secs: snapshot_interval_secs,
+                    snapshot_interval_secs,
                     shared_stats: shared_stats.clone(),
                     disable_internal_snapshot: true,
                     test_stop_after_secs: None,
                     crash_config: crash_cfg,
+                    // One set of per-source files per subscriber
+                    per_source_dir: per_source.then(|| match subscribers {
+                        1 => role_dir(ctx),
+                        _ => role_dir(ctx).join(format!("sub{}", i)),
+                    }),
                 };
                 handles.push(tokio::spawn(async move {
                     let _ = run_subscriber(cfg).await;
                 }));
             }
             let _ = join_all(handles).await;
-            // Final snapshot and cleanup
-            if let Some(stats) = shared_stats {
-                if let Some(mut out) = agg_output {
-                    let snap = stats.snapshot().await;
-                    let _ = out.write_snapshot(&snap).await;
-                }
-            }
-            if let Some(h) = agg_handle {
-                h.abort();
-            }
+            // Final snapshot and summary
+            session.finish().await?;
             Ok(())
         }
         Commands::Req {
@@ -969,7 +2042,12 @@ async fn main() -> Result<()> {
             connect,
             endpoint,
             key_expr,
+            request_size,
+            stream,
             qps,
+            arrival,
+            arrival_seed,
+            rate_profile,
             concurrency,
             timeout,
             duration,
@@ -980,51 +2058,35 @@ async fn main() -> Result<()> {
         } => {
             let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
             let mut conn = parse_connect_kv(&connect);
-            if conn.params.is_empty() {
-                if let Some(ep) = endpoint.first() {
-                    conn.params.insert("endpoint".into(), ep.clone());
-                }
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
             }
             // Wire retry options
             conn.retry_enabled = enable_retry;
             conn.retry_count = retry_count;
             conn.retry_delay_ms = retry_delay;
             conn.retry_max_delay_ms = 30000;
-            // Externalize snapshotting even for single requester
-            let shared_stats: Option<Arc<Stats>> = Some(Arc::new(Stats::new()));
-            let mut agg_output = if let Some(ref path) = csv {
-                Some(OutputWriter::new_csv(path.clone()).await?)
-            } else {
-                Some(OutputWriter::new_stdout())
-            };
-            // snapshot_interval_secs from CLI
-            let agg_stats_clone = shared_stats.clone();
-            let agg_handle: Option<tokio::task::JoinHandle<()>> =
-                if let Some(stats) = agg_stats_clone.clone() {
-                    let mut out = agg_output.take();
-                    Some(tokio::spawn(async move {
-                        let mut t = tokio::time::interval(std::time::Duration::from_secs(
-                            snapshot_interval_secs,
-                        ));
-                        loop {
-                            t.tick().await;
-                            let snap = stats.snapshot().await;
-                            if let Some(ref mut o) = out {
-                                let _ = o.write_snapshot(&snap).await;
-                            }
-                        }
-                    }))
-                } else {
-                    None
-                };
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
             let config = RequesterConfig {
                 engine: engine.clone(),
                 connect: conn,
                 key_expr,
+                request_size: request_size as usize,
+                stream,
+                stream_dir: Some(role_dir(ctx)),
                 qps: match qps {
                     Some(v) if v > 0 => Some(v as u32),
                     _ => None,
                 },
+                arrival: ArrivalConfig {
+                    model: arrival,
+                    seed: arrival_seed,
+                    profile: rate_profile.clone(),
+                },
                 concurrency,
                 timeout_ms: timeout,
                 duration_secs: duration as u64,
@@ -1034,16 +2096,8 @@ async fn main() -> Result<()> {
                 disable_internal_snapshot: true,
             };
             run_requester(config).await?;
-            // Final snapshot and cleanup
-            if let Some(stats) = shared_stats {
-                if let Some(mut out) = agg_output {
-                    let snap = stats.snapshot().await;
-                    let _ = out.write_snapshot(&snap).await;
-                }
-            }
-            if let Some(h) = agg_handle {
-                h.abort();
-            }
+            // Final snapshot and summary
+            session.finish().await?;
             Ok(())
         }
         Commands::Qry {
@@ -1052,7 +2106,13 @@ async fn main() -> Result<()> {
             endpoint,
             serve_prefix,
             reply_size,
+            reply_mode,
             proc_delay,
+            service_time,
+            chunks,
+            service_seed,
+            cpu_bound,
+            workers,
             qos,
             csv,
             enable_retry,
@@ -1061,10 +2121,10 @@ async fn main() -> Result<()> {
         } => {
             let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
             let mut conn = parse_connect_kv(&connect);
-            if conn.params.is_empty() {
-                if let Some(ep) = endpoint.first() {
-                    conn.params.insert("endpoint".into(), ep.clone());
-                }
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
             }
             // Wire retry options
             conn.retry_enabled = enable_retry;
@@ -1075,39 +2135,23 @@ async fn main() -> Result<()> {
             conn.params
                 .entry("qos".into())
                 .or_insert_with(|| qos.to_string());
-            // Externalize snapshotting
-            let shared_stats: Option<Arc<Stats>> = Some(Arc::new(Stats::new()));
-            let mut agg_output = if let Some(ref path) = csv {
-                Some(OutputWriter::new_csv(path.clone()).await?)
-            } else {
-                Some(OutputWriter::new_stdout())
-            };
-            // snapshot_interval_secs from CLI
-            let agg_stats_clone = shared_stats.clone();
-            let agg_handle: Option<tokio::task::JoinHandle<()>> =
-                if let Some(stats) = agg_stats_clone.clone() {
-                    let mut out = agg_output.take();
-                    Some(tokio::spawn(async move {
-                        let mut t = tokio::time::interval(std::time::Duration::from_secs(
-                            snapshot_interval_secs,
-                        ));
-                        loop {
-                            t.tick().await;
-                            let snap = stats.snapshot().await;
-                            if let Some(ref mut o) = out {
-                                let _ = o.write_snapshot(&snap).await;
-                            }
-                        }
-                    }))
-                } else {
-                    None
-                };
+            // Aggregate snapshots + final summary for all instances of this role
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let shared_stats = Some(session.stats());
             let config = QueryableConfig {
                 engine: engine.clone(),
                 connect: conn,
                 serve_prefix,
                 reply_size: reply_size as usize,
-                proc_delay_ms: proc_delay,
+                reply_mode,
+                service_time: service_time.unwrap_or(ServiceTime::Fixed {
+                    ms: proc_delay as f64,
+                }),
+                chunks,
+                service_seed,
+                cpu_bound,
+                workers,
+                queue_dir: Some(role_dir(ctx)),
                 output_file: None,
                 snapshot_interval_secs,
                 shared_stats: shared_stats.clone(),
@@ -1115,15 +2159,8 @@ async fn main() -> Result<()> {
                 test_stop_after_secs: None,
             };
             run_queryable(config).await?;
-            if let Some(stats) = shared_stats {
-                if let Some(mut out) = agg_output {
-                    let snap = stats.snapshot().await;
-                    let _ = out.write_snapshot(&snap).await;
-                }
-            }
-            if let Some(h) = agg_handle {
-                h.abort();
-            }
+            // Final snapshot and summary
+            session.finish().await?;
             Ok(())
         }
         Commands::RelPub {
@@ -1157,27 +2194,351 @@ async fn main() -> Result<()> {
                 seed: crash_seed,
             };
 
-            // Setup output
-            if let Some(ref path) = csv {
-                println!("Writing CSV output to: {}", path);
-            }
+            let engine = Engine::Mqtt; // Only MQTT supported
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
 
             let config = ReliablePublisherConfig {
-                engine: Engine::Mqtt, // Only MQTT supported
+                engine,
                 connect: conn,
                 key_expr: topic,
                 payload_size: payload as usize,
                 rate: rate.filter(|&r| r > 0).map(|r| r as f64),
                 duration_secs: Some(duration as u64),
-                output_file: csv,
+                output_file: None,
                 snapshot_interval_secs,
-                shared_stats: None,
-                disable_internal_snapshot: false,
+                shared_stats: Some(session.stats()),
+                disable_internal_snapshot: true,
                 crash_config,
                 ack_timeout_secs: ack_timeout,
             };
 
             run_reliable_publisher(config).await?;
+            session.finish().await?;
+            Ok(())
+        }
+        Commands::Replay {
+            engine,
+            connect,
+            endpoint,
+            trace,
+            topic_prefix,
+            topic_map,
+            speed,
+            loops,
+            duration,
+            qos,
+            csv,
+            enable_retry,
+            retry_count,
+            retry_delay,
+        } => {
+            let trace = load_trace(&trace)?;
+            let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
+            let mut conn = parse_connect_kv(&connect);
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
+            }
+            // Wire retry options
+            conn.retry_enabled = enable_retry;
+            conn.retry_count = retry_count;
+            conn.retry_delay_ms = retry_delay;
+            conn.retry_max_delay_ms = 30000;
+            // Inject QoS into connect params if not already provided
+            conn.params
+                .entry("qos".into())
+                .or_insert_with(|| qos.to_string());
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let config = ReplayConfig {
+                engine,
+                connect: conn,
+                trace,
+                topic_prefix,
+                topic_map,
+                speed,
+                loops,
+                duration_secs: duration,
+                output_file: None,
+                snapshot_interval_secs,
+                shared_stats: Some(session.stats()),
+                disable_internal_snapshot: true,
+            };
+            run_replay(config).await?;
+            session.finish().await?;
+            Ok(())
+        }
+        Commands::ConnStorm {
+            engine,
+            connect,
+            endpoint,
+            connections,
+            rate,
+            concurrency,
+            subscribe,
+            hold_secs,
+            connect_timeout_ms,
+            degrade_factor,
+            csv,
+        } => {
+            let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
+            let mut conn = parse_connect_kv(&connect);
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
+            }
+            let session = start_session(ctx, &engine, &conn, csv.as_deref()).await?;
+            let dir = role_dir(ctx);
+            let config = ConnStormConfig {
+                engine,
+                connect: conn,
+                connections,
+                rate: rate.filter(|r| *r > 0.0),
+                concurrency,
+                subscribe_prefix: subscribe,
+                hold_secs,
+                connect_timeout: std::time::Duration::from_millis(connect_timeout_ms),
+                degrade_factor,
+                output_dir: Some(dir.clone()),
+                output_file: None,
+                snapshot_interval_secs,
+                shared_stats: Some(session.stats()),
+                disable_internal_snapshot: true,
+            };
+            let summary = run_conn_storm(config).await?;
+            session.finish().await?;
+            println!(
+                "{} of {} connected in {:.2}s ({:.1}/s); connect p50 {:.2} ms, p99 {:.2} ms",
+                summary.connected,
+                summary.attempts,
+                summary.storm_secs,
+                summary.connect_rate,
+                summary.connect_p50_ms,
+                summary.connect_p99_ms
+            );
+            match summary.degraded_at {
+                Some(n) => println!(
+                    "Connect latency degraded past {}x the first block's median ({:.2} ms) at {} open connections",
+                    degrade_factor, summary.baseline_p50_ms, n
+                ),
+                None => println!("Connect latency did not degrade"),
+            }
+            for (reason, n) in &summary.failures {
+                println!("  {} x {}", n, reason);
+            }
+            println!(
+                "connects.csv and conn_storm.json written to {}",
+                dir.display()
+            );
+            Ok(())
+        }
+        Commands::Scenario {
+            file,
+            duration,
+            isolate,
+        } => {
+            let text = std::fs::read_to_string(&file)
+                .with_context(|| format!("reading scenario {}", file))?;
+            let mut cfg = ScenarioSpec::from_yaml(&text)?.resolve()?;
+            if let Some(secs) = duration {
+                cfg.duration_secs = secs.max(1);
+            }
+            cfg.isolate |= isolate;
+            let dir = role_dir(ctx);
+            std::fs::create_dir_all(&dir)?;
+            std::fs::write(dir.join("scenario.yaml"), &text)?;
+            cfg.output_dir = Some(dir.clone());
+            cfg.format = ctx.format;
+            cfg.snapshot_interval_secs = snapshot_interval_secs;
+            let result = run_scenario(&cfg).await?;
+            write_scenario_results(&result, &dir)?;
+            println!("{}", render_scenario_markdown(&result));
+            println!(
+                "scenario.csv, scenario.json and per-class snapshots written to {}",
+                dir.display()
+            );
+            Ok(())
+        }
+        Commands::Report { artifacts, out } => {
+            let artifacts = PathBuf::from(artifacts);
+            let out_dir = out
+                .map(PathBuf::from)
+                .unwrap_or_else(|| artifacts.join("report"));
+            let rows = run_report(&ReportConfig {
+                artifacts,
+                out_dir: out_dir.clone(),
+            })?;
+            println!(
+                "Report for {} runs written to {}",
+                rows.len(),
+                out_dir.display()
+            );
+            Ok(())
+        }
+        Commands::Compare {
+            baseline,
+            candidate,
+            metrics,
+            threshold_pct,
+            confidence,
+            test,
+            bootstrap_iters,
+            seed,
+            json,
+        } => {
+            let metrics = metrics
+                .iter()
+                .map(|m| {
+                    metric_by_name(m.trim())
+                        .ok_or_else(|| anyhow::anyhow!("unknown metric '{}'", m))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let cfg = CompareConfig {
+                metrics,
+                threshold_pct,
+                confidence,
+                test,
+                bootstrap_iters,
+                seed,
+            };
+            let base = load_result_set(Path::new(&baseline))?;
+            let cand = load_result_set(Path::new(&candidate))?;
+            let result = compare(&base, &cand, &cfg)?;
+            println!("{}", render_markdown(&result, &cfg));
+            if let Some(path) = json {
+                std::fs::write(&path, serde_json::to_vec_pretty(&result)?)?;
+            }
+            let regressions = result.regressions().count();
+            if regressions > 0 {
+                // A distinct exit code lets CI tell a regression from a failed comparison
+                eprintln!(
+                    "Error: {} metric(s) regressed by more than {}%",
+                    regressions, threshold_pct
+                );
+                std::process::exit(REGRESSION_EXIT_CODE);
+            }
+            Ok(())
+        }
+        Commands::FindMax {
+            engine,
+            connect,
+            endpoint,
+            topic_prefix,
+            driver,
+            publishers,
+            topics,
+            payload,
+            qos,
+            min_delivery,
+            max_p99_ms,
+            start_rate,
+            max_rate,
+            precision,
+            settle_secs,
+            window_secs,
+        } => {
+            let engine = parse_engine(&engine).unwrap_or(Engine::Zenoh);
+            let mut conn = parse_connect_kv(&connect);
+            if conn.params.is_empty()
+                && let Some(ep) = endpoint.first()
+            {
+                conn.params.insert("endpoint".into(), ep.clone());
+            }
+            conn.params
+                .entry("qos".into())
+                .or_insert_with(|| qos.to_string());
+            let cfg = FindMaxConfig {
+                engine,
+                connect: conn,
+                topic_prefix,
+                driver,
+                publishers,
+                topics,
+                payload_size: payload as usize,
+                settle_secs,
+                window_secs: window_secs.max(1),
+                slo: Slo {
+                    min_delivery,
+                    max_p99_ns: (max_p99_ms * 1e6) as u64,
+                },
+                start_rate,
+                max_rate,
+                precision,
+            };
+            let result = run_find_max(&cfg).await?;
+            let dir = role_dir(ctx);
+            mq_bench::find_max::write_results(&result, &dir)?;
+            println!("{}", mq_bench::find_max::render_markdown(&result));
+            println!("curve.csv and knee.json written to {}", dir.display());
+            Ok(())
+        }
+        Commands::Trials {
+            repeat,
+            cooldown_secs,
+            matrix,
+            shuffle,
+            seed,
+            companions,
+            settle_secs,
+            command,
+        } => {
+            let seed = seed.unwrap_or_else(|| mq_bench::rng::XorShift64::from_time().next_u64());
+            if shuffle {
+                println!("Trial order seed: {}", seed);
+            }
+            let cfg = TrialsConfig {
+                exe: std::env::current_exe()?,
+                forward: ctx.trial_args.clone(),
+                run_dir: ctx.run_dir.clone(),
+                base_run_id: ctx.run_id.clone(),
+                command,
+                companions: companions
+                    .iter()
+                    .map(|c| c.split_whitespace().map(str::to_string).collect())
+                    .collect(),
+                matrix: matrix
+                    .iter()
+                    .map(|m| parse_matrix_arg(m))
+                    .collect::<Result<_>>()?,
+                repeat,
+                cooldown: std::time::Duration::from_secs_f64(cooldown_secs.max(0.0)),
+                settle: std::time::Duration::from_secs_f64(settle_secs.max(0.0)),
+                shuffle,
+                seed,
+            };
+            let (trials, agg) = run_trials(&cfg).await?;
+            println!("{}", mq_bench::trials::render_markdown(&agg));
+            println!(
+                "{} trials ({} ok); trials.csv and aggregate.csv written to {}",
+                trials.len(),
+                trials.iter().filter(|t| t.ok).count(),
+                cfg.run_dir.display()
+            );
+            Ok(())
+        }
+        #[cfg(feature = "sqlite")]
+        Commands::Query {
+            db,
+            view,
+            engine,
+            payload,
+            sql,
+            csv,
+        } => {
+            if !db.exists() {
+                anyhow::bail!("results database {} does not exist", db.display());
+            }
+            let db = ResultsDb::open(&db)?;
+            let table = match sql {
+                Some(sql) => db.query(&sql)?,
+                None => db.query_view(view, &QueryFilter { engine, payload })?,
+            };
+            if csv {
+                print!("{}", table.to_csv()?);
+            } else {
+                print!("{}", table.to_markdown());
+            }
             Ok(())
         }
     }
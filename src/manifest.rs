@@ -0,0 +1,131 @@
+//! Run manifest: everything needed to reproduce a result directory later.
+//!
+//! Written as `manifest.json` into each role's artifact directory when the role
+//! starts, and rewritten with the end time when it finishes.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+use tokio::fs;
+
+/// `git describe` of the build, embedded by build.rs
+pub const GIT_DESCRIBE: &str = env!("MQ_BENCH_GIT_DESCRIBE");
+
+/// Host facts, best effort (fields are None where the platform does not expose them)
+#[derive(Debug, Clone, Serialize)]
+pub struct HostInfo {
+    pub hostname: Option<String>,
+    pub kernel: Option<String>,
+    pub cpu_model: Option<String>,
+    pub cpu_count: usize,
+}
+
+impl HostInfo {
+    pub fn collect() -> Self {
+        let hostname = read_trimmed("/proc/sys/kernel/hostname")
+            .or_else(|| std::env::var("HOSTNAME").ok().filter(|h| !h.is_empty()));
+        let cpu_model = std::fs::read_to_string("/proc/cpuinfo")
+            .ok()
+            .and_then(|info| cpu_model_from_cpuinfo(&info));
+        Self {
+            hostname,
+            kernel: read_trimmed("/proc/sys/kernel/osrelease"),
+            cpu_model,
+            cpu_count: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+        }
+    }
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn cpu_model_from_cpuinfo(info: &str) -> Option<String> {
+    info.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        match key.trim() {
+            // x86 uses "model name", some arm kernels only expose "Hardware"/"Processor"
+            "model name" | "Hardware" | "Processor" => Some(value.trim().to_string()),
+            _ => None,
+        }
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunManifest {
+    pub mq_bench_version: String,
+    pub git_describe: String,
+    pub run_id: String,
+    pub role: String,
+    pub instance: String,
+    pub engine: String,
+    /// Command line with connect secrets redacted
+    pub argv: Vec<String>,
+    /// Resolved role configuration (CLI arguments after defaults)
+    pub config: serde_json::Value,
+    pub host: HostInfo,
+    pub tokio_workers: Option<usize>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+impl RunManifest {
+    /// Capture build/host/runtime facts now; `started_at` is the current wall time.
+    pub fn new(
+        run_id: &str,
+        role: &str,
+        instance: &str,
+        engine: &str,
+        argv: Vec<String>,
+        config: serde_json::Value,
+    ) -> Self {
+        let tokio_workers = tokio::runtime::Handle::try_current()
+            .ok()
+            .map(|h| h.metrics().num_workers());
+        Self {
+            mq_bench_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_describe: GIT_DESCRIBE.to_string(),
+            run_id: run_id.to_string(),
+            role: role.to_string(),
+            instance: instance.to_string(),
+            engine: engine.to_string(),
+            argv,
+            config,
+            host: HostInfo::collect(),
+            tokio_workers,
+            started_at: Utc::now(),
+            ended_at: None,
+        }
+    }
+
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).await.ok();
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_model_prefers_first_model_name() {
+        let info = "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel name\t: Intel(R) Xeon(R) CPU\n\nprocessor\t: 1\nmodel name\t: Intel(R) Xeon(R) CPU\n";
+        assert_eq!(
+            cpu_model_from_cpuinfo(info).as_deref(),
+            Some("Intel(R) Xeon(R) CPU")
+        );
+        assert_eq!(cpu_model_from_cpuinfo("processor\t: 0\n"), None);
+    }
+}
@@ -1,3 +1,5 @@
 // Metrics collection and aggregation
+pub mod prometheus;
 pub mod sequence;
+pub mod sources;
 pub mod stats;
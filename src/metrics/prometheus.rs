@@ -0,0 +1,328 @@
+//! Prometheus text exposition of live `Stats`.
+//!
+//! A deliberately tiny HTTP/1.1 server: `GET /metrics` returns the current counters,
+//! gauges and latency histogram; anything else gets a 404. Reads never call
+//! `Stats::snapshot`, so scraping does not disturb the interval columns in CSV/JSONL.
+
+use crate::metrics::stats::Stats;
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+/// Histogram bucket upper bounds in nanoseconds (10us .. 10s)
+const LATENCY_BOUNDS_NS: &[u64] = &[
+    10_000,
+    25_000,
+    50_000,
+    100_000,
+    250_000,
+    500_000,
+    1_000_000,
+    2_500_000,
+    5_000_000,
+    10_000_000,
+    25_000_000,
+    50_000_000,
+    100_000_000,
+    250_000_000,
+    500_000_000,
+    1_000_000_000,
+    2_500_000_000,
+    5_000_000_000,
+    10_000_000_000,
+];
+
+/// Constant labels attached to every exported series
+#[derive(Debug, Clone, Default)]
+pub struct MetricLabels {
+    pub run_id: String,
+    pub role: String,
+    pub engine: String,
+    pub topic: String,
+}
+
+impl MetricLabels {
+    fn render(&self, extra: Option<(&str, &str)>) -> String {
+        let mut out = format!(
+            "run_id=\"{}\",role=\"{}\",engine=\"{}\",topic=\"{}\"",
+            escape(&self.run_id),
+            escape(&self.role),
+            escape(&self.engine),
+            escape(&self.topic)
+        );
+        if let Some((k, v)) = extra {
+            let _ = write!(out, ",{}=\"{}\"", k, escape(v));
+        }
+        out
+    }
+}
+
+fn escape(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render all metrics in Prometheus text format (version 0.0.4)
+pub async fn render(stats: &Stats, labels: &MetricLabels) -> String {
+    let c = stats.counters();
+    let l = labels.render(None);
+    let mut out = String::with_capacity(4096);
+
+    let counters: [(&str, &str, u64); 12] = [
+        (
+            "mq_bench_sent_total",
+            "Messages or queries sent",
+            c.sent_count,
+        ),
+        (
+            "mq_bench_received_total",
+            "Messages or replies received",
+            c.received_count,
+        ),
+        (
+            "mq_bench_errors_total",
+            "Errors (includes timeouts and late replies)",
+            c.error_count,
+        ),
+        (
+            "mq_bench_timeouts_total",
+            "Queries without a reply before the deadline",
+            c.timeout_count,
+        ),
+        (
+            "mq_bench_late_replies_total",
+            "Replies received after the deadline",
+            c.late_reply_count,
+        ),
+        (
+            "mq_bench_connection_attempts_total",
+            "Transport connection attempts",
+            c.connection_attempts,
+        ),
+        (
+            "mq_bench_connection_failures_total",
+            "Failed transport connections",
+            c.connection_failures,
+        ),
+        (
+            "mq_bench_crashes_injected_total",
+            "Injected crashes",
+            c.crashes_injected,
+        ),
+        (
+            "mq_bench_reconnects_total",
+            "Successful reconnects after a crash",
+            c.reconnects,
+        ),
+        (
+            "mq_bench_reconnect_failures_total",
+            "Failed reconnects after a crash",
+            c.reconnect_failures,
+        ),
+        (
+            "mq_bench_duplicates_total",
+            "Duplicate messages detected",
+            c.duplicate_count,
+        ),
+        ("mq_bench_gaps_total", "Sequence gaps detected", c.gap_count),
+    ];
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{}{{{}}} {}", name, l, value);
+    }
+
+    let gauges: [(&str, &str, f64); 3] = [
+        (
+            "mq_bench_connections",
+            "Open transport connections",
+            c.connections as f64,
+        ),
+        (
+            "mq_bench_active_connections",
+            "Connections that have sent or received",
+            c.active_connections as f64,
+        ),
+        (
+            "mq_bench_uptime_seconds",
+            "Seconds since the role started",
+            c.uptime.as_secs_f64(),
+        ),
+    ];
+    for (name, help, value) in gauges {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{}{{{}}} {}", name, l, value);
+    }
+
+    let lat = stats.latency_buckets(LATENCY_BOUNDS_NS).await;
+    let name = "mq_bench_latency_seconds";
+    let _ = writeln!(out, "# HELP {} End-to-end or round-trip latency", name);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (bound_ns, count) in &lat.buckets {
+        let le = format!("{}", *bound_ns as f64 / 1e9);
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}}} {}",
+            name,
+            labels.render(Some(("le", &le))),
+            count
+        );
+    }
+    let _ = writeln!(
+        out,
+        "{}_bucket{{{}}} {}",
+        name,
+        labels.render(Some(("le", "+Inf"))),
+        lat.count
+    );
+    let _ = writeln!(out, "{}_sum{{{}}} {}", name, l, lat.sum_ns / 1e9);
+    let _ = writeln!(out, "{}_count{{{}}} {}", name, l, lat.count);
+    out
+}
+
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(10);
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
+/// Bind `addr` and serve `/metrics` until the returned task is aborted.
+/// Binding happens before returning so a busy port fails the role at startup.
+pub async fn serve(
+    addr: SocketAddr,
+    stats: Arc<Stats>,
+    labels: MetricLabels,
+) -> Result<(SocketAddr, JoinHandle<()>)> {
+    let listener = TcpListener::bind(addr).await?;
+    let local = listener.local_addr()?;
+    info!(addr = %local, "Serving Prometheus metrics on /metrics");
+    let labels = Arc::new(labels);
+    let handle = tokio::spawn(async move {
+        // Accept errors such as EMFILE persist until descriptors free up, so back off
+        // instead of spinning on them
+        let mut backoff = ACCEPT_BACKOFF_MIN;
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(c) => {
+                    backoff = ACCEPT_BACKOFF_MIN;
+                    c
+                }
+                Err(e) => {
+                    debug!(error = %e, backoff_ms = backoff.as_millis() as u64, "Metrics accept error");
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+                    continue;
+                }
+            };
+            let stats = stats.clone();
+            let labels = labels.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_conn(stream, &stats, &labels).await {
+                    debug!(peer = %peer, error = %e, "Metrics connection error");
+                }
+            });
+        }
+    });
+    Ok((local, handle))
+}
+
+async fn handle_conn(mut stream: TcpStream, stats: &Stats, labels: &MetricLabels) -> Result<()> {
+    // Read the request head only; scrapers send no body with GET
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < 8192 {
+        let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut chunk)).await??;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let head = String::from_utf8_lossy(&buf);
+    let mut parts = head.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = path.split('?').next().unwrap_or("");
+
+    let (status, body) = if method == "GET" && (path == "/metrics" || path == "/") {
+        ("200 OK", render(stats, labels).await)
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels() -> MetricLabels {
+        MetricLabels {
+            run_id: "r1".into(),
+            role: "sub".into(),
+            engine: "mock".into(),
+            topic: "bench/\"x\"".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn renders_counters_gauges_and_cumulative_histogram() {
+        let stats = Stats::new();
+        stats.record_sent().await;
+        stats.record_received_batch(&[20_000, 2_000_000]).await;
+        stats.increment_active_connections();
+
+        let text = render(&stats, &labels()).await;
+        let l = r#"run_id="r1",role="sub",engine="mock",topic="bench/\"x\"""#;
+        assert!(text.contains(&format!("mq_bench_sent_total{{{}}} 1\n", l)));
+        assert!(text.contains(&format!("mq_bench_active_connections{{{}}} 1\n", l)));
+        assert!(text.contains(&format!(
+            "mq_bench_latency_seconds_bucket{{{},le=\"0.00001\"}} 0\n",
+            l
+        )));
+        assert!(text.contains(&format!(
+            "mq_bench_latency_seconds_bucket{{{},le=\"0.000025\"}} 1\n",
+            l
+        )));
+        assert!(text.contains(&format!(
+            "mq_bench_latency_seconds_bucket{{{},le=\"+Inf\"}} 2\n",
+            l
+        )));
+        assert!(text.contains(&format!("mq_bench_latency_seconds_count{{{}}} 2\n", l)));
+    }
+
+    #[tokio::test]
+    async fn serves_metrics_over_http() {
+        let stats = Arc::new(Stats::new());
+        stats.record_sent().await;
+        let (addr, handle) = serve("127.0.0.1:0".parse().unwrap(), stats, labels())
+            .await
+            .unwrap();
+
+        let get = |path: &'static str| async move {
+            let mut s = TcpStream::connect(addr).await.unwrap();
+            s.write_all(format!("GET {} HTTP/1.1\r\nHost: x\r\n\r\n", path).as_bytes())
+                .await
+                .unwrap();
+            let mut resp = String::new();
+            s.read_to_string(&mut resp).await.unwrap();
+            resp
+        };
+        let ok = get("/metrics").await;
+        assert!(ok.starts_with("HTTP/1.1 200 OK"));
+        assert!(ok.contains("# TYPE mq_bench_sent_total counter"));
+        assert!(get("/nope").await.starts_with("HTTP/1.1 404"));
+        handle.abort();
+    }
+}
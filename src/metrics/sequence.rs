@@ -62,12 +62,12 @@ impl SequenceTracker {
     /// lost before the first received message or after the last.
     #[inline]
     pub fn gap_count(&self) -> u64 {
-        if let Some(min) = self.min_seq {
-            if self.max_seq >= min {
-                let expected = self.max_seq - min + 1;
-                let actual = self.seen.len() as u64;
-                return expected.saturating_sub(actual);
-            }
+        if let Some(min) = self.min_seq
+            && self.max_seq >= min
+        {
+            let expected = self.max_seq - min + 1;
+            let actual = self.seen.len() as u64;
+            return expected.saturating_sub(actual);
         }
         0
     }
@@ -144,7 +144,7 @@ mod tests {
         tracker.record(1);
         tracker.record(5); // gap of 3 (missing 2,3,4)
         assert_eq!(tracker.gap_count(), 3);
-        
+
         tracker.record(3); // fill one gap
         assert_eq!(tracker.gap_count(), 2);
     }
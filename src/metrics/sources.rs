@@ -0,0 +1,197 @@
+//! Per-source accounting for fan-in: many publishers, one (wildcard) subscriber.
+//!
+//! Messages are attributed by the source tag mt-pub writes into each payload
+//! (`payload::tag_source`). Every source keeps its own sequence tracker and latency
+//! histogram, so delivery ratio, gaps and latency can be compared across sources and
+//! summarized with Jain's fairness index.
+
+use super::sequence::SequenceTracker;
+use anyhow::Result;
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+struct SourceEntry {
+    tracker: SequenceTracker,
+    hist: Histogram<u64>,
+}
+
+/// Sequence and latency state per source index
+#[derive(Default)]
+pub struct SourceTable {
+    sources: BTreeMap<u32, SourceEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceRow {
+    pub source: u32,
+    pub received: u64,
+    /// Highest sequence seen + 1 (sources number from 0); tail loss is not visible
+    pub expected: u64,
+    pub delivery_ratio: f64,
+    pub gaps: u64,
+    pub duplicates: u64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FanInSummary {
+    pub sources: usize,
+    pub received: u64,
+    /// Jain's index over per-source delivery ratios (1 = every source served alike)
+    pub jain_delivery: f64,
+    /// Jain's index over per-source received counts (1 only if sources send alike)
+    pub jain_received: f64,
+    pub min_delivery_ratio: f64,
+    pub worst_source: Option<u32>,
+    /// Highest per-source p99 latency
+    pub max_p99_ms: f64,
+}
+
+/// Jain's fairness index `(Σx)² / (n·Σx²)`: 1 when all values are equal, `1/n` when
+/// one value takes everything; 1 for no values or all zeros
+pub fn jain_index(values: &[f64]) -> f64 {
+    let sum: f64 = values.iter().sum();
+    let sum_sq: f64 = values.iter().map(|x| x * x).sum();
+    if values.is_empty() || sum_sq == 0.0 {
+        return 1.0;
+    }
+    sum * sum / (values.len() as f64 * sum_sq)
+}
+
+impl SourceTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one message; false for a duplicate (its latency is not recorded)
+    pub fn record(&mut self, source: u32, seq: u64, latency_ns: u64) -> bool {
+        let entry = self.sources.entry(source).or_insert_with(|| SourceEntry {
+            tracker: SequenceTracker::new(),
+            hist: Histogram::new_with_bounds(1, 60_000_000_000, 3).unwrap(),
+        });
+        if !entry.tracker.record(seq) {
+            return false;
+        }
+        let _ = entry.hist.record(latency_ns.max(1));
+        true
+    }
+
+    pub fn duplicate_count(&self) -> u64 {
+        self.sources
+            .values()
+            .map(|e| e.tracker.duplicate_count())
+            .sum()
+    }
+
+    pub fn gap_count(&self) -> u64 {
+        self.sources.values().map(|e| e.tracker.gap_count()).sum()
+    }
+
+    pub fn head_loss(&self) -> u64 {
+        self.sources.values().map(|e| e.tracker.head_loss()).sum()
+    }
+
+    pub fn rows(&self) -> Vec<SourceRow> {
+        let ms = |ns: u64| ns as f64 / 1e6;
+        self.sources
+            .iter()
+            .map(|(&source, e)| {
+                let received = e.tracker.unique_count();
+                let expected = e.tracker.max_seq() + 1;
+                SourceRow {
+                    source,
+                    received,
+                    expected,
+                    delivery_ratio: received as f64 / expected as f64,
+                    gaps: e.tracker.gap_count(),
+                    duplicates: e.tracker.duplicate_count(),
+                    p50_ms: ms(e.hist.value_at_quantile(0.50)),
+                    p99_ms: ms(e.hist.value_at_quantile(0.99)),
+                    max_ms: ms(e.hist.max()),
+                }
+            })
+            .collect()
+    }
+
+    pub fn summary(&self) -> FanInSummary {
+        let rows = self.rows();
+        let delivery: Vec<f64> = rows.iter().map(|r| r.delivery_ratio).collect();
+        let received: Vec<f64> = rows.iter().map(|r| r.received as f64).collect();
+        let worst = rows
+            .iter()
+            .min_by(|a, b| a.delivery_ratio.total_cmp(&b.delivery_ratio));
+        FanInSummary {
+            sources: rows.len(),
+            received: rows.iter().map(|r| r.received).sum(),
+            jain_delivery: jain_index(&delivery),
+            jain_received: jain_index(&received),
+            min_delivery_ratio: worst.map_or(0.0, |r| r.delivery_ratio),
+            worst_source: worst.map(|r| r.source),
+            max_p99_ms: rows.iter().map(|r| r.p99_ms).fold(0.0, f64::max),
+        }
+    }
+
+    /// Write `per_source.csv` and `fan_in.json` into `dir`
+    pub fn write(&self, dir: &Path) -> Result<FanInSummary> {
+        std::fs::create_dir_all(dir)?;
+        let mut w = csv::Writer::from_path(dir.join("per_source.csv"))?;
+        for row in self.rows() {
+            w.serialize(row)?;
+        }
+        w.flush()?;
+        let summary = self.summary();
+        std::fs::write(
+            dir.join("fan_in.json"),
+            serde_json::to_string_pretty(&summary)?,
+        )?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jain_index_bounds() {
+        assert_eq!(jain_index(&[5.0, 5.0, 5.0]), 1.0);
+        assert!((jain_index(&[1.0, 0.0, 0.0, 0.0]) - 0.25).abs() < 1e-12);
+        assert!((jain_index(&[1.0, 0.5]) - 0.9).abs() < 1e-12);
+        assert_eq!(jain_index(&[]), 1.0);
+    }
+
+    #[test]
+    fn sources_are_tracked_independently() {
+        let mut t = SourceTable::new();
+        // Source 0: all of 0..10; source 3: 0..10 with 5 missing and 2 repeated
+        for seq in 0..10 {
+            assert!(t.record(0, seq, 1_000_000));
+        }
+        for seq in (0..10).filter(|s| *s != 5) {
+            assert!(t.record(3, seq, 3_000_000));
+        }
+        assert!(!t.record(3, 2, 9_000_000));
+
+        let rows = t.rows();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            (rows[0].source, rows[0].received, rows[0].expected),
+            (0, 10, 10)
+        );
+        assert_eq!((rows[1].source, rows[1].received, rows[1].gaps), (3, 9, 1));
+        assert_eq!(rows[1].duplicates, 1);
+        assert!((rows[1].delivery_ratio - 0.9).abs() < 1e-12);
+        assert!((rows[1].max_ms - 3.0).abs() < 0.01);
+        assert_eq!(t.duplicate_count(), 1);
+        assert_eq!(t.gap_count(), 1);
+
+        let s = t.summary();
+        assert_eq!((s.sources, s.received), (2, 19));
+        assert_eq!(s.worst_source, Some(3));
+        assert!((s.jain_delivery - jain_index(&[1.0, 0.9])).abs() < 1e-12);
+    }
+}
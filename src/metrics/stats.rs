@@ -1,18 +1,145 @@
+use crate::rate::RateProfile;
+use crate::time_sync::now_unix_ns_estimate;
+use base64::Engine as _;
 use hdrhistogram::Histogram;
-use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Serialize, Serializer};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
+use tracing::warn;
+
+/// Decode a histogram written by `Stats::latency_histogram_base64`
+pub fn decode_histogram(encoded: &str) -> Option<Histogram<u64>> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()?;
+    hdrhistogram::serialization::Deserializer::new()
+        .deserialize(&mut bytes.as_slice())
+        .ok()
+}
+
+/// Merge encoded latency histograms (e.g. from several roles' `summary.json`).
+/// None if there are none or any fails to decode, so callers never mix merged and
+/// partial data.
+pub fn merge_histograms<'a>(encoded: impl IntoIterator<Item = &'a str>) -> Option<Histogram<u64>> {
+    let mut merged = Histogram::new_with_bounds(1, 60_000_000_000, 3).unwrap();
+    let mut any = false;
+    for e in encoded {
+        merged.add(decode_histogram(e)?).ok()?;
+        any = true;
+    }
+    any.then_some(merged)
+}
+
+/// Cumulative counter values read by `Stats::counters`
+#[derive(Debug, Clone, Default)]
+pub struct StatsCounters {
+    pub sent_count: u64,
+    pub received_count: u64,
+    pub error_count: u64,
+    pub timeout_count: u64,
+    pub late_reply_count: u64,
+    pub connections: u64,
+    pub active_connections: u64,
+    pub connection_attempts: u64,
+    pub connection_failures: u64,
+    pub crashes_injected: u64,
+    pub reconnects: u64,
+    pub reconnect_failures: u64,
+    pub duplicate_count: u64,
+    pub gap_count: u64,
+    pub warmup_excluded_count: u64,
+    pub cooldown_excluded_count: u64,
+    pub uptime: Duration,
+}
+
+/// Cumulative latency histogram read by `Stats::latency_buckets`
+#[derive(Debug, Clone, Default)]
+pub struct LatencyBuckets {
+    pub buckets: Vec<(u64, u64)>,
+    pub count: u64,
+    pub sum_ns: f64,
+}
+
+/// Warm-up/cool-down exclusion for reported statistics.
+///
+/// Samples are classified by their origin wall-clock time: the send time for sent
+/// messages and the publish timestamp (receive time minus latency) for received ones,
+/// so a publisher and its subscribers exclude the same messages without coordination.
+/// Warm-up covers `[anchor, anchor + warmup)`; cool-down covers the last `cooldown` of
+/// event time before `Stats::close_window`, which is held back until then.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MeasurementWindow {
+    pub warmup: Duration,
+    pub cooldown: Duration,
+    /// Warm-up start (unix ns); defaults to the first sample's event time
+    pub anchor_ns: Option<u64>,
+}
+
+impl MeasurementWindow {
+    pub fn is_enabled(&self) -> bool {
+        !self.warmup.is_zero() || !self.cooldown.is_zero()
+    }
+}
+
+/// Most samples held back for the cool-down. Beyond this the oldest are committed
+/// early, so very long cool-downs at high rates exclude slightly less than asked.
+const MAX_HELD_SAMPLES: usize = 1 << 20;
+
+/// A windowed event awaiting its warm-up/cool-down verdict
+#[derive(Debug, Clone, Copy)]
+enum Sample {
+    Sent,
+    Received(u64),
+    Error,
+    Timeout,
+    LateReply,
+}
+
+/// Held-back samples and the event-time span of committed ones
+#[derive(Debug, Default)]
+struct WindowState {
+    cfg: MeasurementWindow,
+    anchor_ns: Option<u64>,
+    newest_ns: u64,
+    closed: bool,
+    /// (event time, sample), bounded by `MAX_HELD_SAMPLES`
+    held: VecDeque<(u64, Sample)>,
+    /// Whether the bound has forced early commits (logged once)
+    overflowed: bool,
+    sent_span: Option<(u64, u64)>,
+    received_span: Option<(u64, u64)>,
+}
+
+fn widen(span: &mut Option<(u64, u64)>, t: u64) {
+    *span = Some(match *span {
+        Some((lo, hi)) => (lo.min(t), hi.max(t)),
+        None => (t, t),
+    });
+}
+
+fn span_duration(span: Option<(u64, u64)>) -> Option<Duration> {
+    span.map(|(lo, hi)| Duration::from_nanos(hi - lo))
+}
 
 /// Statistics collector for latency and throughput
 pub struct Stats {
     // Latency histogram (nanosecond precision)
     latency_hist: RwLock<Histogram<u64>>,
+    // Samples since the previous snapshot (reset by `snapshot`)
+    interval_hist: RwLock<Histogram<u64>>,
 
     // Counters
     pub sent_count: AtomicU64,
     pub received_count: AtomicU64,
     pub error_count: AtomicU64,
 
+    // Request/reply outcome counters (subsets of error_count)
+    pub timeout_count: AtomicU64,
+    pub late_reply_count: AtomicU64,
+
     // Connection counters
     pub connections: AtomicU64,
     pub active_connections: AtomicU64,
@@ -41,6 +168,21 @@ pub struct Stats {
     gap_count: AtomicU64,
     // Head loss: messages lost before first received (min_seq > 0)
     head_loss: AtomicU64,
+
+    // Warm-up/cool-down exclusion (off unless `set_window` enables it)
+    windowed: AtomicBool,
+    window: Mutex<WindowState>,
+    warmup_excluded: AtomicU64,
+    cooldown_excluded: AtomicU64,
+
+    // Rate profiles of the role's paced senders and when each started (`target_rate`)
+    target_rates: Mutex<Vec<(RateProfile, Instant)>>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Stats {
@@ -49,9 +191,12 @@ impl Stats {
         Self {
             // 1ns to 60s range, 3 significant digits
             latency_hist: RwLock::new(Histogram::new_with_bounds(1, 60_000_000_000, 3).unwrap()),
+            interval_hist: RwLock::new(Histogram::new_with_bounds(1, 60_000_000_000, 3).unwrap()),
             sent_count: AtomicU64::new(0),
             received_count: AtomicU64::new(0),
             error_count: AtomicU64::new(0),
+            timeout_count: AtomicU64::new(0),
+            late_reply_count: AtomicU64::new(0),
             connections: AtomicU64::new(0),
             active_connections: AtomicU64::new(0),
             connection_attempts: AtomicU64::new(0),
@@ -68,11 +213,150 @@ impl Stats {
             duplicate_count: AtomicU64::new(0),
             gap_count: AtomicU64::new(0),
             head_loss: AtomicU64::new(0),
+            windowed: AtomicBool::new(false),
+            window: Mutex::new(WindowState::default()),
+            warmup_excluded: AtomicU64::new(0),
+            cooldown_excluded: AtomicU64::new(0),
+            target_rates: Mutex::new(Vec::new()),
         }
     }
 
+    /// Count a paced sender's rate profile (started at `start`) in the snapshot's `target_rate`
+    pub fn add_target_rate(&self, profile: RateProfile, start: Instant) {
+        self.target_rates.lock().unwrap().push((profile, start));
+    }
+
+    /// Offered load right now: the sum of every registered sender's current target rate
+    pub fn target_rate(&self) -> f64 {
+        let now = Instant::now();
+        self.target_rates
+            .lock()
+            .unwrap()
+            .iter()
+            .fold(0.0, |sum, (p, start)| {
+                sum + p.rate_at(now.saturating_duration_since(*start))
+            })
+    }
+
+    /// Exclude warm-up/cool-down samples from the counts, throughput and histogram.
+    /// Call before recording starts; a disabled window leaves recording unchanged.
+    pub fn set_window(&self, cfg: MeasurementWindow) {
+        let mut w = self.window.lock().unwrap();
+        *w = WindowState {
+            cfg,
+            anchor_ns: cfg.anchor_ns,
+            ..Default::default()
+        };
+        self.windowed.store(cfg.is_enabled(), Ordering::Relaxed);
+    }
+
+    /// End the measurement window: samples still held back (the cool-down) and any
+    /// recorded afterwards are counted as cool-down exclusions.
+    pub async fn close_window(&self) {
+        if !self.windowed.load(Ordering::Relaxed) {
+            return;
+        }
+        let held = {
+            let mut w = self.window.lock().unwrap();
+            w.closed = true;
+            std::mem::take(&mut w.held)
+        };
+        self.cooldown_excluded
+            .fetch_add(held.len() as u64, Ordering::Relaxed);
+    }
+
+    /// Queue samples by event time and commit those that have left the cool-down horizon
+    async fn record_windowed(&self, samples: impl Iterator<Item = (u64, Sample)>) {
+        let mut committed = Vec::new();
+        {
+            let mut w = self.window.lock().unwrap();
+            for (t, sample) in samples {
+                if w.closed {
+                    self.cooldown_excluded.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                let anchor = *w.anchor_ns.get_or_insert(t);
+                if t < anchor.saturating_add(w.cfg.warmup.as_nanos() as u64) {
+                    self.warmup_excluded.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                w.newest_ns = w.newest_ns.max(t);
+                w.held.push_back((t, sample));
+            }
+            let horizon = w.newest_ns.saturating_sub(w.cfg.cooldown.as_nanos() as u64);
+            let overflow = w.held.len().saturating_sub(MAX_HELD_SAMPLES);
+            if overflow > 0 && !w.overflowed {
+                w.overflowed = true;
+                warn!(
+                    max = MAX_HELD_SAMPLES,
+                    "Cool-down buffer full; committing the oldest held samples early"
+                );
+            }
+            let mut i = 0;
+            while w
+                .held
+                .front()
+                .is_some_and(|(t, _)| i < overflow || *t <= horizon)
+            {
+                let (t, sample) = w.held.pop_front().unwrap();
+                match sample {
+                    Sample::Sent => widen(&mut w.sent_span, t),
+                    Sample::Received(_) => widen(&mut w.received_span, t),
+                    _ => {}
+                }
+                committed.push(sample);
+                i += 1;
+            }
+        }
+        let mut latencies = Vec::new();
+        for sample in committed {
+            match sample {
+                Sample::Sent => {
+                    self.sent_count.fetch_add(1, Ordering::Relaxed);
+                }
+                Sample::Received(lat) => latencies.push(lat),
+                Sample::Error => {
+                    self.error_count.fetch_add(1, Ordering::Relaxed);
+                }
+                Sample::Timeout => {
+                    self.timeout_count.fetch_add(1, Ordering::Relaxed);
+                    self.error_count.fetch_add(1, Ordering::Relaxed);
+                }
+                Sample::LateReply => {
+                    self.late_reply_count.fetch_add(1, Ordering::Relaxed);
+                    self.error_count.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        if !latencies.is_empty() {
+            self.received_count
+                .fetch_add(latencies.len() as u64, Ordering::Relaxed);
+            let mut hist = self.latency_hist.write().await;
+            let mut interval = self.interval_hist.write().await;
+            for lat in latencies {
+                let _ = hist.record(lat);
+                let _ = interval.record(lat);
+            }
+        }
+    }
+
+    /// Record an error-class sample now, through the window when one is set
+    async fn record_failure(&self, sample: Sample) -> bool {
+        if !self.windowed.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.record_windowed(std::iter::once((now_unix_ns_estimate(), sample)))
+            .await;
+        true
+    }
+
     /// Record a sent message
     pub async fn record_sent(&self) {
+        if self.windowed.load(Ordering::Relaxed) {
+            return self
+                .record_windowed(std::iter::once((now_unix_ns_estimate(), Sample::Sent)))
+                .await;
+        }
         self.sent_count.fetch_add(1, Ordering::Relaxed);
         let mut first = self.first_sent_time.write().await;
         if first.is_none() {
@@ -82,6 +366,12 @@ impl Stats {
 
     /// Record a received message with latency
     pub async fn record_received(&self, latency_ns: u64) {
+        if self.windowed.load(Ordering::Relaxed) {
+            let t = now_unix_ns_estimate().saturating_sub(latency_ns);
+            return self
+                .record_windowed(std::iter::once((t, Sample::Received(latency_ns))))
+                .await;
+        }
         self.received_count.fetch_add(1, Ordering::Relaxed);
         let mut first = self.first_received_time.write().await;
         if first.is_none() {
@@ -91,11 +381,33 @@ impl Stats {
         if let Ok(mut hist) = self.latency_hist.try_write() {
             let _ = hist.record(latency_ns);
         }
+        if let Ok(mut interval) = self.interval_hist.try_write() {
+            let _ = interval.record(latency_ns);
+        }
     }
 
     /// Record an error
     pub async fn record_error(&self) {
-        self.error_count.fetch_add(1, Ordering::Relaxed);
+        if !self.record_failure(Sample::Error).await {
+            self.error_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a query (or closed-loop message) that got no reply/ack before its deadline
+    /// (also counted as an error)
+    pub async fn record_timeout(&self) {
+        if !self.record_failure(Sample::Timeout).await {
+            self.timeout_count.fetch_add(1, Ordering::Relaxed);
+            self.error_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a reply that arrived after the query deadline (also counted as an error)
+    pub async fn record_late_reply(&self) {
+        if !self.record_failure(Sample::LateReply).await {
+            self.late_reply_count.fetch_add(1, Ordering::Relaxed);
+            self.error_count.fetch_add(1, Ordering::Relaxed);
+        }
     }
 
     /// Increment connection count (called when publisher/subscriber is created)
@@ -173,6 +485,16 @@ impl Stats {
         if latencies_ns.is_empty() {
             return;
         }
+        if self.windowed.load(Ordering::Relaxed) {
+            let now = now_unix_ns_estimate();
+            return self
+                .record_windowed(
+                    latencies_ns
+                        .iter()
+                        .map(|&lat| (now.saturating_sub(lat), Sample::Received(lat))),
+                )
+                .await;
+        }
         // Bump received count once
         self.received_count
             .fetch_add(latencies_ns.len() as u64, Ordering::Relaxed);
@@ -185,17 +507,99 @@ impl Stats {
         }
         // Record all latencies under a single histogram write lock
         let mut hist = self.latency_hist.write().await;
+        let mut interval = self.interval_hist.write().await;
         for &lat in latencies_ns {
             let _ = hist.record(lat);
+            let _ = interval.record(lat);
+        }
+    }
+
+    /// Read cumulative counters without touching interval state (safe for exporters)
+    pub fn counters(&self) -> StatsCounters {
+        StatsCounters {
+            sent_count: self.sent_count.load(Ordering::Relaxed),
+            received_count: self.received_count.load(Ordering::Relaxed),
+            error_count: self.error_count.load(Ordering::Relaxed),
+            timeout_count: self.timeout_count.load(Ordering::Relaxed),
+            late_reply_count: self.late_reply_count.load(Ordering::Relaxed),
+            connections: self.connections.load(Ordering::Relaxed),
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            connection_attempts: self.connection_attempts.load(Ordering::Relaxed),
+            connection_failures: self.connection_failures.load(Ordering::Relaxed),
+            crashes_injected: self.crashes_injected.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            reconnect_failures: self.reconnect_failures.load(Ordering::Relaxed),
+            duplicate_count: self.duplicate_count.load(Ordering::Relaxed),
+            gap_count: self.gap_count.load(Ordering::Relaxed),
+            warmup_excluded_count: self.warmup_excluded.load(Ordering::Relaxed),
+            cooldown_excluded_count: self.cooldown_excluded.load(Ordering::Relaxed),
+            uptime: self.start_time.elapsed(),
         }
     }
 
+    /// Cumulative latency distribution at the given upper bounds (ns), read-only.
+    /// Returns `(bound, count of samples <= bound)` pairs plus total count and sum.
+    pub async fn latency_buckets(&self, bounds_ns: &[u64]) -> LatencyBuckets {
+        let hist = self.latency_hist.read().await;
+        let count = hist.len();
+        let buckets = bounds_ns
+            .iter()
+            .map(|&b| {
+                (
+                    b,
+                    if count == 0 {
+                        0
+                    } else {
+                        hist.count_between(0, b)
+                    },
+                )
+            })
+            .collect();
+        LatencyBuckets {
+            buckets,
+            count,
+            sum_ns: hist.mean() * count as f64,
+        }
+    }
+
+    /// Cumulative latency histogram, V2-deflate serialized and base64 encoded so
+    /// per-role histograms can be merged later (None when empty)
+    pub async fn latency_histogram_base64(&self) -> Option<String> {
+        use hdrhistogram::serialization::{Serializer as _, V2DeflateSerializer};
+        let hist = self.latency_hist.read().await;
+        if hist.is_empty() {
+            return None;
+        }
+        let mut buf = Vec::new();
+        V2DeflateSerializer::new()
+            .serialize(&*hist, &mut buf)
+            .ok()?;
+        Some(base64::engine::general_purpose::STANDARD.encode(buf))
+    }
+
+    /// Latency quantiles without waiting for the histogram lock (None if busy or empty).
+    /// For synchronous exporter callbacks.
+    pub fn try_latency_quantiles(&self, quantiles: &[f64]) -> Option<Vec<u64>> {
+        let hist = self.latency_hist.try_read().ok()?;
+        if hist.is_empty() {
+            return None;
+        }
+        Some(
+            quantiles
+                .iter()
+                .map(|&q| hist.value_at_quantile(q))
+                .collect(),
+        )
+    }
+
     /// Get current snapshot of statistics
     pub async fn snapshot(&self) -> StatsSnapshot {
         let now = Instant::now();
         let sent = self.sent_count.load(Ordering::Relaxed);
         let received = self.received_count.load(Ordering::Relaxed);
         let errors = self.error_count.load(Ordering::Relaxed);
+        let timeouts = self.timeout_count.load(Ordering::Relaxed);
+        let late_replies = self.late_reply_count.load(Ordering::Relaxed);
         let conns = self.connections.load(Ordering::Relaxed);
         let active_conns = self.active_connections.load(Ordering::Relaxed);
         let conn_attempts = self.connection_attempts.load(Ordering::Relaxed);
@@ -218,6 +622,18 @@ impl Stats {
         let mean = hist.mean();
         let stddev = hist.stdev();
         let sample_count = hist.len();
+        drop(hist);
+        let (interval_p50, interval_p95, interval_p99, interval_samples) = {
+            let mut interval = self.interval_hist.write().await;
+            let v = (
+                interval.value_at_quantile(0.5),
+                interval.value_at_quantile(0.95),
+                interval.value_at_quantile(0.99),
+                interval.len(),
+            );
+            interval.reset();
+            v
+        };
 
         let total_elapsed = now.duration_since(self.start_time);
         let since_last = {
@@ -239,18 +655,24 @@ impl Stats {
             delta
         };
 
-        let since_first_sent = self
-            .first_sent_time
-            .read()
-            .await
-            .map(|t| now.checked_duration_since(t))
-            .flatten();
-        let since_first_received = self
-            .first_received_time
-            .read()
-            .await
-            .map(|t| now.checked_duration_since(t))
-            .flatten();
+        // With a window, throughput spans the committed samples' event times
+        let (since_first_sent, since_first_received) = if self.windowed.load(Ordering::Relaxed) {
+            let w = self.window.lock().unwrap();
+            (span_duration(w.sent_span), span_duration(w.received_span))
+        } else {
+            (
+                self.first_sent_time
+                    .read()
+                    .await
+                    .map(|t| now.checked_duration_since(t))
+                    .flatten(),
+                self.first_received_time
+                    .read()
+                    .await
+                    .map(|t| now.checked_duration_since(t))
+                    .flatten(),
+            )
+        };
 
         StatsSnapshot {
             timestamp: SystemTime::now()
@@ -260,6 +682,8 @@ impl Stats {
             sent_count: sent,
             received_count: received,
             error_count: errors,
+            timeout_count: timeouts,
+            late_reply_count: late_replies,
             total_duration: total_elapsed,
             interval_duration: since_last,
             interval_sent_count: interval_sent,
@@ -276,6 +700,10 @@ impl Stats {
             latency_ns_mean: mean,
             latency_ns_stddev: stddev,
             latency_sample_count: sample_count,
+            interval_latency_ns_p50: interval_p50,
+            interval_latency_ns_p95: interval_p95,
+            interval_latency_ns_p99: interval_p99,
+            interval_latency_sample_count: interval_samples,
             connections: conns,
             active_connections: active_conns,
             connection_attempts: conn_attempts,
@@ -286,6 +714,9 @@ impl Stats {
             duplicate_count: duplicates,
             gap_count: gaps,
             head_loss,
+            warmup_excluded_count: self.warmup_excluded.load(Ordering::Relaxed),
+            cooldown_excluded_count: self.cooldown_excluded.load(Ordering::Relaxed),
+            target_rate: self.target_rate(),
         }
     }
 
@@ -295,6 +726,8 @@ impl Stats {
         self.sent_count.store(0, Ordering::Relaxed);
         self.received_count.store(0, Ordering::Relaxed);
         self.error_count.store(0, Ordering::Relaxed);
+        self.timeout_count.store(0, Ordering::Relaxed);
+        self.late_reply_count.store(0, Ordering::Relaxed);
         self.connections.store(0, Ordering::Relaxed);
         self.active_connections.store(0, Ordering::Relaxed);
         self.connection_attempts.store(0, Ordering::Relaxed);
@@ -304,22 +737,33 @@ impl Stats {
         self.reconnect_failures.store(0, Ordering::Relaxed);
         self.duplicate_count.store(0, Ordering::Relaxed);
         self.gap_count.store(0, Ordering::Relaxed);
+        self.warmup_excluded.store(0, Ordering::Relaxed);
+        self.cooldown_excluded.store(0, Ordering::Relaxed);
+        let cfg = self.window.lock().unwrap().cfg;
+        self.set_window(cfg);
         self.latency_hist.write().await.reset();
+        self.interval_hist.write().await.reset();
         *self.last_snapshot.write().await = Instant::now();
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct StatsSnapshot {
     pub timestamp: u64,
     pub sent_count: u64,
     pub received_count: u64,
     pub error_count: u64,
+    pub timeout_count: u64,
+    pub late_reply_count: u64,
+    #[serde(rename = "total_duration_secs", serialize_with = "ser_secs")]
     pub total_duration: Duration,
+    #[serde(rename = "interval_duration_secs", serialize_with = "ser_secs")]
     pub interval_duration: Duration,
     pub interval_sent_count: u64,
     pub interval_received_count: u64,
+    #[serde(rename = "since_first_sent_secs", serialize_with = "ser_opt_secs")]
     pub since_first_sent: Option<Duration>,
+    #[serde(rename = "since_first_received_secs", serialize_with = "ser_opt_secs")]
     pub since_first_received: Option<Duration>,
     pub latency_ns_p25: u64,
     pub latency_ns_p50: u64,
@@ -331,6 +775,11 @@ pub struct StatsSnapshot {
     pub latency_ns_mean: f64,
     pub latency_ns_stddev: f64,
     pub latency_sample_count: u64,
+    /// Percentiles of the samples recorded since the previous snapshot
+    pub interval_latency_ns_p50: u64,
+    pub interval_latency_ns_p95: u64,
+    pub interval_latency_ns_p99: u64,
+    pub interval_latency_sample_count: u64,
     pub connections: u64,
     pub active_connections: u64,
     pub connection_attempts: u64,
@@ -341,6 +790,12 @@ pub struct StatsSnapshot {
     pub duplicate_count: u64,
     pub gap_count: u64,
     pub head_loss: u64,
+    /// Samples excluded by the warm-up window (not in counts, throughput or latency)
+    pub warmup_excluded_count: u64,
+    /// Samples excluded by the cool-down window
+    pub cooldown_excluded_count: u64,
+    /// Offered load (msg/s) the role's paced senders are targeting; 0 when unpaced
+    pub target_rate: f64,
 }
 
 impl StatsSnapshot {
@@ -389,7 +844,7 @@ impl StatsSnapshot {
     /// Convert to CSV row
     pub fn to_csv_row(&self) -> String {
         format!(
-            "{},{},{},{},{:.2},{:.2},{},{},{},{},{},{},{},{:.2},{:.2},{},{},{},{},{},{},{},{},{},{}",
+            "{},{},{},{},{:.2},{:.2},{},{},{},{},{},{},{},{:.2},{:.2},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.2}",
             self.timestamp,
             self.sent_count,
             self.received_count,
@@ -414,13 +869,31 @@ impl StatsSnapshot {
             self.reconnects,
             self.reconnect_failures,
             self.duplicate_count,
-            self.gap_count
+            self.gap_count,
+            self.timeout_count,
+            self.late_reply_count,
+            self.warmup_excluded_count,
+            self.cooldown_excluded_count,
+            self.target_rate
         )
     }
 
     /// CSV header
     pub fn csv_header() -> &'static str {
-        "timestamp,sent_count,received_count,error_count,total_throughput,interval_throughput,latency_ns_p25,latency_ns_p50,latency_ns_p75,latency_ns_p95,latency_ns_p99,latency_ns_min,latency_ns_max,latency_ns_mean,latency_ns_stddev,latency_sample_count,connections,active_connections,connection_attempts,connection_failures,crashes_injected,reconnects,reconnect_failures,duplicate_count,gap_count"
+        "timestamp,sent_count,received_count,error_count,total_throughput,interval_throughput,latency_ns_p25,latency_ns_p50,latency_ns_p75,latency_ns_p95,latency_ns_p99,latency_ns_min,latency_ns_max,latency_ns_mean,latency_ns_stddev,latency_sample_count,connections,active_connections,connection_attempts,connection_failures,crashes_injected,reconnects,reconnect_failures,duplicate_count,gap_count,timeout_count,late_reply_count,warmup_excluded_count,cooldown_excluded_count,target_rate"
+    }
+}
+
+/// Serialize a duration as fractional seconds
+fn ser_secs<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(d.as_secs_f64())
+}
+
+/// Serialize an optional duration as fractional seconds (null when absent)
+fn ser_opt_secs<S: Serializer>(d: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    match d {
+        Some(d) => s.serialize_some(&d.as_secs_f64()),
+        None => s.serialize_none(),
     }
 }
 
@@ -475,4 +948,113 @@ mod tests {
         assert_eq!(snap2.interval_received_count, 0);
         assert_eq!(snap2.interval_throughput(), 0.0);
     }
+
+    #[tokio::test]
+    async fn snapshot_serializes_durations_as_seconds() {
+        let stats = Stats::new();
+        stats.record_received(1_000).await;
+        let snap = stats.snapshot().await;
+        let v = serde_json::to_value(&snap).unwrap();
+        assert_eq!(v["received_count"], 1);
+        assert!(v["total_duration_secs"].is_f64());
+        assert!(v["since_first_received_secs"].is_f64());
+        assert!(v["since_first_sent_secs"].is_null());
+    }
+
+    #[tokio::test]
+    async fn interval_percentiles_cover_samples_since_last_snapshot() {
+        let stats = Stats::new();
+        stats.record_received_batch(&[1_000_000; 10]).await;
+        let first = stats.snapshot().await;
+        assert_eq!(first.interval_latency_sample_count, 10);
+
+        stats.record_received_batch(&[8_000_000; 10]).await;
+        let second = stats.snapshot().await;
+        assert_eq!(second.interval_latency_sample_count, 10);
+        assert!(second.interval_latency_ns_p50 > 7_000_000);
+        // Cumulative median still sits between both batches
+        assert!(second.latency_ns_p50 < 2_000_000);
+    }
+
+    #[tokio::test]
+    async fn window_excludes_warmup_and_held_back_cooldown() {
+        let stats = Stats::new();
+        stats.set_window(MeasurementWindow {
+            warmup: Duration::from_secs(2),
+            cooldown: Duration::from_secs(1),
+            anchor_ns: Some(now_unix_ns_estimate() - 10_000_000_000),
+        });
+        // Event times are receive time minus latency: 9s ago falls in the warm-up
+        stats.record_received(9_000_000_000).await;
+        stats
+            .record_received_batch(&[5_000_000_000, 4_000_000_000])
+            .await;
+        stats.record_received(500_000_000).await;
+
+        let snap = stats.snapshot().await;
+        assert_eq!(snap.warmup_excluded_count, 1);
+        assert_eq!(snap.received_count, 2, "newest second is still held back");
+        assert_eq!(snap.latency_sample_count, 2);
+        let span = snap.since_first_received.unwrap().as_secs_f64();
+        assert!((span - 1.0).abs() < 0.05, "span {}", span);
+
+        stats.close_window().await;
+        stats.record_received(1_000).await;
+        let snap = stats.snapshot().await;
+        assert_eq!(snap.received_count, 2);
+        assert_eq!(snap.cooldown_excluded_count, 2);
+    }
+
+    #[tokio::test]
+    async fn window_holds_errors_with_other_samples() {
+        let stats = Stats::new();
+        stats.set_window(MeasurementWindow {
+            warmup: Duration::from_secs(1),
+            cooldown: Duration::from_secs(1),
+            anchor_ns: Some(now_unix_ns_estimate()),
+        });
+        // Still inside the warm-up, so none of these count
+        stats.record_error().await;
+        stats.record_timeout().await;
+        stats.record_late_reply().await;
+
+        let snap = stats.snapshot().await;
+        assert_eq!(snap.warmup_excluded_count, 3);
+        assert_eq!(snap.error_count, 0);
+        assert_eq!(snap.timeout_count, 0);
+        assert_eq!(snap.late_reply_count, 0);
+    }
+
+    #[tokio::test]
+    async fn exporter_reads_do_not_consume_interval_deltas() {
+        let stats = Stats::new();
+        stats.record_received_batch(&[1_000, 2_000, 5_000]).await;
+
+        let counters = stats.counters();
+        assert_eq!(counters.received_count, 3);
+        let lat = stats.latency_buckets(&[1_500, 10_000]).await;
+        assert_eq!(lat.count, 3);
+        assert_eq!(lat.buckets, vec![(1_500, 1), (10_000, 3)]);
+
+        // The interval snapshot still sees all three receives
+        let snap = stats.snapshot().await;
+        assert_eq!(snap.interval_received_count, 3);
+    }
+
+    #[tokio::test]
+    async fn target_rate_sums_current_profile_rates() {
+        let stats = Stats::new();
+        assert!(stats.snapshot().await.to_csv_row().ends_with(",0.00"));
+
+        let steps = RateProfile::Steps {
+            rates: vec![100.0, 400.0],
+            step_secs: 1.0,
+        };
+        stats.add_target_rate(steps, Instant::now() - Duration::from_millis(1500));
+        stats.add_target_rate(RateProfile::Constant { rate: 50.0 }, Instant::now());
+        let snap = stats.snapshot().await;
+        assert_eq!(snap.target_rate, 450.0);
+        assert!(snap.to_csv_row().ends_with(",450.00"));
+        assert!(StatsSnapshot::csv_header().ends_with(",target_rate"));
+    }
 }
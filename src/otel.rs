@@ -0,0 +1,281 @@
+//! OpenTelemetry export (feature `otel`).
+//!
+//! - Metrics: `Stats` counters/gauges and latency quantiles as observable
+//!   instruments, pushed periodically over OTLP.
+//! - Traces: for every Nth message (pub, mt-pub, replay) a `mq.publish` producer
+//!   span is started and its context is written into the payload after the 24-byte
+//!   header (see `payload::TraceContext`); sub and mt-sub emit a child `mq.deliver`
+//!   consumer span covering publish timestamp -> receive, i.e. the broker leg.
+//! - `tracing` spans from mq-bench itself are bridged through `logging::init_with_otel`.
+
+use crate::metrics::prometheus::MetricLabels;
+use crate::metrics::stats::{Stats, StatsCounters};
+use crate::payload::{TraceContext, parse_header};
+use anyhow::{Result, anyhow};
+use opentelemetry::trace::{
+    Span, SpanContext, SpanId, SpanKind, TraceContextExt, TraceFlags, TraceId, TraceState, Tracer,
+};
+use opentelemetry::{Context, KeyValue, global};
+use opentelemetry_otlp::{Protocol, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+
+const SCOPE: &str = "mq-bench";
+
+/// Set once the OTLP providers are installed
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Trace every Nth message (0 = message tracing off)
+static SAMPLE_EVERY: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OtlpProtocol {
+    #[default]
+    Grpc,
+    Http,
+}
+
+pub fn parse_otlp_protocol(s: &str) -> Option<OtlpProtocol> {
+    match s.to_lowercase().as_str() {
+        "grpc" => Some(OtlpProtocol::Grpc),
+        "http" | "http/protobuf" => Some(OtlpProtocol::Http),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OtelConfig {
+    /// Collector base URL, e.g. http://127.0.0.1:4317 (gRPC) or http://127.0.0.1:4318 (HTTP)
+    pub endpoint: String,
+    pub protocol: OtlpProtocol,
+    /// Trace every Nth published message (0 disables message spans)
+    pub sample_every: u64,
+    pub metrics_interval: Duration,
+    /// Extra resource attributes (run id, role, ...)
+    pub resource: Vec<(String, String)>,
+}
+
+/// Keeps the providers alive; `shutdown` flushes pending spans and a final metrics export.
+pub struct OtelGuard {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl OtelGuard {
+    pub async fn shutdown(self) -> Result<()> {
+        ENABLED.store(false, Ordering::Relaxed);
+        SAMPLE_EVERY.store(0, Ordering::Relaxed);
+        // SDK shutdown blocks on the exporter threads; keep it off the async workers
+        tokio::task::spawn_blocking(move || {
+            let traces = self.tracer_provider.shutdown();
+            let metrics = self.meter_provider.shutdown();
+            traces
+                .and(metrics)
+                .map_err(|e| anyhow!("otel shutdown: {}", e))
+        })
+        .await?
+    }
+}
+
+/// Build OTLP exporters and install the global tracer/meter providers.
+/// Returns the guard and an SDK tracer for the `tracing` bridge.
+pub fn init(cfg: &OtelConfig) -> Result<(OtelGuard, SdkTracer)> {
+    let endpoint = cfg.endpoint.trim_end_matches('/');
+    let resource = Resource::builder()
+        .with_service_name(SCOPE)
+        .with_attributes(
+            cfg.resource
+                .iter()
+                .map(|(k, v)| KeyValue::new(k.clone(), v.clone())),
+        )
+        .build();
+
+    let (span_exporter, metric_exporter) = match cfg.protocol {
+        OtlpProtocol::Grpc => (
+            opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()?,
+            opentelemetry_otlp::MetricExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()?,
+        ),
+        // HTTP endpoints set in code are used verbatim, so add the signal paths
+        OtlpProtocol::Http => (
+            opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_protocol(Protocol::HttpBinary)
+                .with_endpoint(format!("{}/v1/traces", endpoint))
+                .build()?,
+            opentelemetry_otlp::MetricExporter::builder()
+                .with_http()
+                .with_protocol(Protocol::HttpBinary)
+                .with_endpoint(format!("{}/v1/metrics", endpoint))
+                .build()?,
+        ),
+    };
+
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_resource(resource.clone())
+        .with_batch_exporter(span_exporter)
+        .build();
+    let reader = PeriodicReader::builder(metric_exporter)
+        .with_interval(cfg.metrics_interval)
+        .build();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_resource(resource)
+        .with_reader(reader)
+        .build();
+
+    global::set_tracer_provider(tracer_provider.clone());
+    global::set_meter_provider(meter_provider.clone());
+    SAMPLE_EVERY.store(cfg.sample_every, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+
+    use opentelemetry::trace::TracerProvider as _;
+    let tracer = tracer_provider.tracer(SCOPE);
+    Ok((
+        OtelGuard {
+            tracer_provider,
+            meter_provider,
+        },
+        tracer,
+    ))
+}
+
+/// Whether OTLP export is active (set by `init`)
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Export `stats` through the global meter. Instruments are read at each collection,
+/// without touching the interval state used by the snapshot writers.
+pub fn register_stats(stats: Arc<Stats>, labels: &MetricLabels) {
+    let meter = global::meter(SCOPE);
+    let attrs: Arc<[KeyValue]> = Arc::from(vec![
+        KeyValue::new("run_id", labels.run_id.clone()),
+        KeyValue::new("role", labels.role.clone()),
+        KeyValue::new("engine", labels.engine.clone()),
+        KeyValue::new("topic", labels.topic.clone()),
+    ]);
+
+    type Read = fn(&StatsCounters) -> u64;
+    let counters: [(&'static str, Read); 12] = [
+        ("mq_bench.sent", |c| c.sent_count),
+        ("mq_bench.received", |c| c.received_count),
+        ("mq_bench.errors", |c| c.error_count),
+        ("mq_bench.timeouts", |c| c.timeout_count),
+        ("mq_bench.late_replies", |c| c.late_reply_count),
+        ("mq_bench.connection_attempts", |c| c.connection_attempts),
+        ("mq_bench.connection_failures", |c| c.connection_failures),
+        ("mq_bench.crashes_injected", |c| c.crashes_injected),
+        ("mq_bench.reconnects", |c| c.reconnects),
+        ("mq_bench.reconnect_failures", |c| c.reconnect_failures),
+        ("mq_bench.duplicates", |c| c.duplicate_count),
+        ("mq_bench.gaps", |c| c.gap_count),
+    ];
+    for (name, read) in counters {
+        let (stats, attrs) = (stats.clone(), attrs.clone());
+        meter
+            .u64_observable_counter(name)
+            .with_callback(move |obs| obs.observe(read(&stats.counters()), &attrs))
+            .build();
+    }
+    let gauges: [(&'static str, Read); 2] = [
+        ("mq_bench.connections", |c| c.connections),
+        ("mq_bench.active_connections", |c| c.active_connections),
+    ];
+    for (name, read) in gauges {
+        let (stats, attrs) = (stats.clone(), attrs.clone());
+        meter
+            .u64_observable_gauge(name)
+            .with_callback(move |obs| obs.observe(read(&stats.counters()), &attrs))
+            .build();
+    }
+
+    const QUANTILES: [(f64, &str); 4] = [(0.5, "0.5"), (0.95, "0.95"), (0.99, "0.99"), (1.0, "1")];
+    meter
+        .u64_observable_gauge("mq_bench.latency")
+        .with_unit("ns")
+        .with_description("Cumulative latency quantiles")
+        .with_callback(move |obs| {
+            let qs: Vec<f64> = QUANTILES.iter().map(|(q, _)| *q).collect();
+            if let Some(values) = stats.try_latency_quantiles(&qs) {
+                for ((_, label), v) in QUANTILES.iter().zip(values) {
+                    let mut kv = attrs.to_vec();
+                    kv.push(KeyValue::new("quantile", *label));
+                    obs.observe(v, &kv);
+                }
+            }
+        })
+        .build();
+}
+
+/// Start a producer span for a sampled message and write its context into `payload`.
+/// The span ends when the returned span is dropped (after the publish completes).
+pub fn start_publish(seq: u64, mut payload: Vec<u8>) -> (Vec<u8>, Option<global::BoxedSpan>) {
+    let span = start_publish_span(seq, &mut payload);
+    (payload, span)
+}
+
+fn start_publish_span(seq: u64, payload: &mut [u8]) -> Option<global::BoxedSpan> {
+    let every = SAMPLE_EVERY.load(Ordering::Relaxed);
+    if every == 0 || !seq.is_multiple_of(every) {
+        return None;
+    }
+    let tracer = global::tracer(SCOPE);
+    let span = tracer
+        .span_builder("mq.publish")
+        .with_kind(SpanKind::Producer)
+        .with_attributes([KeyValue::new("messaging.message.id", seq as i64)])
+        .start(&tracer);
+    let sc = span.span_context();
+    if !sc.is_valid() {
+        return None;
+    }
+    let tc = TraceContext {
+        trace_id: sc.trace_id().to_bytes(),
+        span_id: sc.span_id().to_bytes(),
+        flags: sc.trace_flags().to_u8(),
+    };
+    tc.encode_into(payload).then_some(span)
+}
+
+/// Emit the consumer span for a received message that carries trace context.
+/// Runs in the subscribe callback, so it returns immediately for untraced messages.
+pub fn record_delivery(payload: &[u8], recv_ns: u64) {
+    if SAMPLE_EVERY.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let (Some(tc), Ok(header)) = (TraceContext::decode(payload), parse_header(payload)) else {
+        return;
+    };
+    let parent = Context::new().with_remote_span_context(SpanContext::new(
+        TraceId::from_bytes(tc.trace_id),
+        SpanId::from_bytes(tc.span_id),
+        TraceFlags::new(tc.flags),
+        true,
+        TraceState::default(),
+    ));
+    let at = |ns: u64| UNIX_EPOCH + Duration::from_nanos(ns);
+    let tracer = global::tracer(SCOPE);
+    let mut span = tracer.build_with_context(
+        tracer
+            .span_builder("mq.deliver")
+            .with_kind(SpanKind::Consumer)
+            .with_start_time(at(header.timestamp_ns))
+            .with_attributes([
+                KeyValue::new("messaging.message.id", header.seq as i64),
+                KeyValue::new(
+                    "mq_bench.latency_ns",
+                    recv_ns.saturating_sub(header.timestamp_ns) as i64,
+                ),
+            ]),
+        &parent,
+    );
+    span.end_with_timestamp(at(recv_ns));
+}
@@ -1,22 +1,78 @@
 use crate::metrics::stats::StatsSnapshot;
 use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use tokio::fs::{self, File};
 use tokio::io::{AsyncWriteExt, BufWriter};
 
+/// Snapshot serialization format for file/stdout outputs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl OutputFormat {
+    /// File extension for snapshot files in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+pub fn parse_output_format(s: &str) -> Option<OutputFormat> {
+    match s.to_lowercase().as_str() {
+        "csv" => Some(OutputFormat::Csv),
+        "jsonl" | "ndjson" | "json" => Some(OutputFormat::Jsonl),
+        _ => None,
+    }
+}
+
+/// One JSON record per snapshot: every `StatsSnapshot` field plus the derived
+/// throughputs that the CSV rows carry.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotRecord {
+    #[serde(flatten)]
+    pub snapshot: StatsSnapshot,
+    pub total_throughput: f64,
+    pub interval_throughput: f64,
+}
+
+impl From<&StatsSnapshot> for SnapshotRecord {
+    fn from(snapshot: &StatsSnapshot) -> Self {
+        Self {
+            total_throughput: snapshot.total_throughput(),
+            interval_throughput: snapshot.interval_throughput(),
+            snapshot: snapshot.clone(),
+        }
+    }
+}
+
 pub enum OutputWriter {
     Csv(BufWriter<tokio::fs::File>),
+    Jsonl(BufWriter<tokio::fs::File>),
     Stdout,
+    StdoutJsonl,
+}
+
+async fn create_with_parents(path: &str) -> Result<File> {
+    // Ensure parent directory exists
+    if let Some(parent) = Path::new(path).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent).await.ok();
+    }
+    Ok(File::create(path).await?)
 }
 
 impl OutputWriter {
     pub async fn new_csv(path: String) -> Result<Self> {
-        // Ensure parent directory exists
-        if let Some(parent) = std::path::Path::new(&path).parent() {
-            if !parent.as_os_str().is_empty() {
-                fs::create_dir_all(parent).await.ok();
-            }
-        }
-        let file = File::create(&path).await?;
+        let file = create_with_parents(&path).await?;
         let mut writer = BufWriter::new(file);
 
         // Write CSV header
@@ -29,11 +85,33 @@ impl OutputWriter {
         Ok(Self::Csv(writer))
     }
 
+    pub async fn new_jsonl(path: String) -> Result<Self> {
+        let file = create_with_parents(&path).await?;
+        println!("Writing JSONL output to: {}", path);
+        Ok(Self::Jsonl(BufWriter::new(file)))
+    }
+
+    /// Open a file writer in the requested format
+    pub async fn new_file(path: String, format: OutputFormat) -> Result<Self> {
+        match format {
+            OutputFormat::Csv => Self::new_csv(path).await,
+            OutputFormat::Jsonl => Self::new_jsonl(path).await,
+        }
+    }
+
     pub fn new_stdout() -> Self {
         println!("Writing output to stdout");
         Self::Stdout
     }
 
+    /// Stdout writer in the requested format
+    pub fn new_stdout_with(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Csv => Self::new_stdout(),
+            OutputFormat::Jsonl => Self::StdoutJsonl,
+        }
+    }
+
     pub async fn write_snapshot(&mut self, snapshot: &StatsSnapshot) -> Result<()> {
         match self {
             Self::Csv(writer) => {
@@ -42,10 +120,102 @@ impl OutputWriter {
                 // Flush so external tail/readers see progress promptly
                 writer.flush().await?;
             }
+            Self::Jsonl(writer) => {
+                let line = serde_json::to_string(&SnapshotRecord::from(snapshot))?;
+                writer.write_all(line.as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
             Self::Stdout => {
                 println!("{}", snapshot.to_csv_row());
             }
+            Self::StdoutJsonl => {
+                println!(
+                    "{}",
+                    serde_json::to_string(&SnapshotRecord::from(snapshot))?
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Final per-role summary: what ran, against which engine, and how it ended.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub run_id: String,
+    pub role: String,
+    pub engine: String,
+    /// Engine connect parameters with secrets redacted
+    pub connect: BTreeMap<String, String>,
+    /// Resolved role configuration (CLI arguments after defaults)
+    pub config: serde_json::Value,
+    pub final_stats: SnapshotRecord,
+    /// Final cumulative latency histogram (`Stats::latency_histogram_base64`), so
+    /// reports can merge roles instead of averaging their percentiles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_histogram: Option<String>,
+}
+
+impl RunSummary {
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent).await.ok();
         }
+        let json = serde_json::to_vec_pretty(self)?;
+        fs::write(path, json).await?;
         Ok(())
     }
 }
+
+/// Summary path derived from a snapshot output path: `dir/pub.csv` -> `dir/pub.summary.json`
+pub fn summary_path_for(output_path: &str) -> PathBuf {
+    let p = Path::new(output_path);
+    let stem = p
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "run".to_string());
+    p.with_file_name(format!("{}.summary.json", stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::stats::Stats;
+
+    #[test]
+    fn summary_path_sits_next_to_output() {
+        assert_eq!(
+            summary_path_for("artifacts/run1/pub.csv"),
+            PathBuf::from("artifacts/run1/pub.summary.json")
+        );
+        assert_eq!(
+            summary_path_for("sub.jsonl"),
+            PathBuf::from("sub.summary.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn jsonl_writer_emits_one_record_per_snapshot() {
+        let dir = std::env::temp_dir().join(format!("mqb-jsonl-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("snap.jsonl");
+        let stats = Stats::new();
+        stats.record_sent().await;
+        let mut w = OutputWriter::new_jsonl(path.to_string_lossy().into_owned())
+            .await
+            .unwrap();
+        w.write_snapshot(&stats.snapshot().await).await.unwrap();
+        w.write_snapshot(&stats.snapshot().await).await.unwrap();
+        drop(w);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let v: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(v["sent_count"], 1);
+        assert!(v["total_throughput"].is_number());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -83,3 +83,201 @@ pub fn generate_payload(seq: u64, size: usize) -> Vec<u8> {
 pub fn parse_header(payload: &[u8]) -> Result<MessageHeader, String> {
     MessageHeader::decode(payload)
 }
+
+/// Marker written right after the header when a message carries trace context
+pub const TRACE_CONTEXT_MAGIC: [u8; 4] = *b"MQTC";
+
+/// Encoded trace context length: magic + trace id + span id + flags
+pub const TRACE_CONTEXT_LEN: usize = 4 + 16 + 8 + 1;
+
+/// W3C-style trace parent carried in the payload of sampled messages.
+/// Placed at bytes 24..53, overwriting the fill pattern, so message size is unchanged.
+/// A source-tagged payload needs `TRACE_AND_SOURCE_MIN_LEN` bytes to carry both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Write into a payload after its header; false if the payload is too small,
+    /// including too small to keep a source tag already at its tail
+    pub fn encode_into(&self, payload: &mut [u8]) -> bool {
+        if payload.len() < 24 + TRACE_CONTEXT_LEN {
+            return false;
+        }
+        if payload.len() < TRACE_AND_SOURCE_MIN_LEN && source_tag(payload).is_some() {
+            return false;
+        }
+        let buf = &mut payload[24..24 + TRACE_CONTEXT_LEN];
+        buf[0..4].copy_from_slice(&TRACE_CONTEXT_MAGIC);
+        buf[4..20].copy_from_slice(&self.trace_id);
+        buf[20..28].copy_from_slice(&self.span_id);
+        buf[28] = self.flags;
+        true
+    }
+
+    /// Read trace context from a received payload, if present
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        let buf = payload.get(24..24 + TRACE_CONTEXT_LEN)?;
+        if buf[0..4] != TRACE_CONTEXT_MAGIC {
+            return None;
+        }
+        let mut trace_id = [0u8; 16];
+        let mut span_id = [0u8; 8];
+        trace_id.copy_from_slice(&buf[4..20]);
+        span_id.copy_from_slice(&buf[20..28]);
+        Some(Self {
+            trace_id,
+            span_id,
+            flags: buf[28],
+        })
+    }
+}
+
+/// Marker for a source tag in the last bytes of a payload
+pub const SOURCE_TAG_MAGIC: [u8; 4] = *b"MQSR";
+
+/// Encoded source tag length: magic + source index
+pub const SOURCE_TAG_LEN: usize = 4 + 4;
+
+/// Smallest payload that carries both a trace context and a source tag (61 bytes)
+pub const TRACE_AND_SOURCE_MIN_LEN: usize = 24 + TRACE_CONTEXT_LEN + SOURCE_TAG_LEN;
+
+/// Tag a payload with the index of the source (publisher/key) that sent it, so one
+/// wildcard subscriber can account per source. Placed in the last 8 bytes; below
+/// `TRACE_AND_SOURCE_MIN_LEN` the tag wins and the trace context is left out.
+/// False if the payload is too small
+pub fn tag_source(payload: &mut [u8], source: u32) -> bool {
+    if payload.len() < 24 + SOURCE_TAG_LEN {
+        return false;
+    }
+    let at = payload.len() - SOURCE_TAG_LEN;
+    payload[at..at + 4].copy_from_slice(&SOURCE_TAG_MAGIC);
+    payload[at + 4..].copy_from_slice(&source.to_le_bytes());
+    true
+}
+
+/// Read the source tag from a received payload, if present
+pub fn source_tag(payload: &[u8]) -> Option<u32> {
+    if payload.len() < 24 + SOURCE_TAG_LEN {
+        return None;
+    }
+    let tag = &payload[payload.len() - SOURCE_TAG_LEN..];
+    if tag[0..4] != SOURCE_TAG_MAGIC {
+        return None;
+    }
+    Some(u32::from_le_bytes([tag[4], tag[5], tag[6], tag[7]]))
+}
+
+/// Marker for a chunk tag in the last bytes of a streamed reply
+pub const CHUNK_TAG_MAGIC: [u8; 4] = *b"MQCK";
+
+/// Encoded chunk tag length: magic + chunk index + chunk total
+pub const CHUNK_TAG_LEN: usize = 4 + 4 + 4;
+
+/// Tag a streamed reply chunk with its index and the number of chunks in the stream,
+/// so the requester can count lost chunks. Placed in the last 12 bytes; false if the
+/// payload is too small
+pub fn tag_chunk(payload: &mut [u8], index: u32, total: u32) -> bool {
+    if payload.len() < 24 + CHUNK_TAG_LEN {
+        return false;
+    }
+    let at = payload.len() - CHUNK_TAG_LEN;
+    payload[at..at + 4].copy_from_slice(&CHUNK_TAG_MAGIC);
+    payload[at + 4..at + 8].copy_from_slice(&index.to_le_bytes());
+    payload[at + 8..].copy_from_slice(&total.to_le_bytes());
+    true
+}
+
+/// Read the chunk tag `(index, total)` from a reply, if present
+pub fn chunk_tag(payload: &[u8]) -> Option<(u32, u32)> {
+    if payload.len() < 24 + CHUNK_TAG_LEN {
+        return None;
+    }
+    let tag = &payload[payload.len() - CHUNK_TAG_LEN..];
+    if tag[0..4] != CHUNK_TAG_MAGIC {
+        return None;
+    }
+    let index = u32::from_le_bytes([tag[4], tag[5], tag[6], tag[7]]);
+    let total = u32::from_le_bytes([tag[8], tag[9], tag[10], tag[11]]);
+    Some((index, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_context_round_trips_after_header() {
+        let tc = TraceContext {
+            trace_id: [7; 16],
+            span_id: [9; 8],
+            flags: 1,
+        };
+        let mut payload = generate_payload(42, 64);
+        assert_eq!(TraceContext::decode(&payload), None);
+        assert!(tc.encode_into(&mut payload));
+        assert_eq!(payload.len(), 64);
+        assert_eq!(parse_header(&payload).unwrap().seq, 42);
+        assert_eq!(TraceContext::decode(&payload), Some(tc));
+
+        // Too small to carry context: left untouched
+        let mut small = generate_payload(1, 32);
+        assert!(!tc.encode_into(&mut small));
+        assert_eq!(TraceContext::decode(&small), None);
+    }
+
+    #[test]
+    fn source_tag_round_trips_at_the_tail() {
+        let mut payload = generate_payload(5, 64);
+        assert_eq!(source_tag(&payload), None);
+        assert!(tag_source(&mut payload, 1234));
+        assert_eq!(payload.len(), 64);
+        assert_eq!(parse_header(&payload).unwrap().seq, 5);
+        assert_eq!(source_tag(&payload), Some(1234));
+
+        // Room for both the trace context and the tag
+        let tc = TraceContext {
+            trace_id: [1; 16],
+            span_id: [2; 8],
+            flags: 1,
+        };
+        let mut both = generate_payload(6, TRACE_AND_SOURCE_MIN_LEN);
+        assert!(tag_source(&mut both, 7));
+        assert!(tc.encode_into(&mut both));
+        assert_eq!(source_tag(&both), Some(7));
+        assert_eq!(TraceContext::decode(&both), Some(tc));
+
+        // Too small for both: the trace context stays out instead of clobbering the tag
+        let mut tight = generate_payload(6, 56);
+        assert!(tag_source(&mut tight, 7));
+        assert!(!tc.encode_into(&mut tight));
+        assert_eq!(source_tag(&tight), Some(7));
+        assert_eq!(TraceContext::decode(&tight), None);
+        // Untagged payloads of the same size still take the trace context
+        let mut untagged = generate_payload(6, 56);
+        assert!(tc.encode_into(&mut untagged));
+        assert_eq!(TraceContext::decode(&untagged), Some(tc));
+
+        let mut header_only = generate_payload(1, 24);
+        assert!(!tag_source(&mut header_only, 1));
+        assert_eq!(source_tag(&header_only), None);
+    }
+
+    #[test]
+    fn chunk_tag_round_trips_at_the_tail() {
+        let mut payload = generate_payload(3, 64);
+        assert_eq!(chunk_tag(&payload), None);
+        assert!(tag_chunk(&mut payload, 2, 5));
+        assert_eq!(parse_header(&payload).unwrap().seq, 3);
+        assert_eq!(chunk_tag(&payload), Some((2, 5)));
+        // Distinct from a source tag
+        assert_eq!(source_tag(&payload), None);
+
+        let mut small = generate_payload(1, 24 + CHUNK_TAG_LEN - 1);
+        assert!(!tag_chunk(&mut small, 0, 1));
+        assert_eq!(chunk_tag(&small), None);
+    }
+}
@@ -1,5 +1,326 @@
+use crate::metrics::stats::Stats;
+use crate::rng::XorShift64;
+use serde::Serialize;
+use std::f64::consts::TAU;
 use std::time::Duration;
-use tokio::time::{Interval, MissedTickBehavior, interval};
+use tokio::time::{Instant, Interval, MissedTickBehavior, interval, sleep_until};
+
+/// How send times are spread around the configured mean rate
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+#[serde(tag = "model", rename_all = "kebab-case")]
+pub enum ArrivalModel {
+    /// Evenly spaced sends (token bucket)
+    #[default]
+    Constant,
+    /// Exponential inter-arrival times
+    Poisson,
+    /// Markov-modulated on/off: exponentially distributed ON and OFF periods with the given
+    /// means; Poisson sends while ON at the rate that keeps the long-run mean
+    OnOff { on_ms: f64, off_ms: f64 },
+    /// `size` back-to-back sends every `size / rate` seconds
+    Burst { size: u32 },
+}
+
+/// Parse `constant`, `poisson`, `onoff:ON_MS:OFF_MS` or `burst:N`
+pub fn parse_arrival_model(s: &str) -> Option<ArrivalModel> {
+    let lower = s.to_lowercase();
+    let mut parts = lower.split(':');
+    let model = match parts.next()? {
+        "constant" | "const" => ArrivalModel::Constant,
+        "poisson" | "exp" => ArrivalModel::Poisson,
+        "onoff" | "on-off" => {
+            let on_ms: f64 = parts.next()?.parse().ok()?;
+            let off_ms: f64 = parts.next()?.parse().ok()?;
+            if !(on_ms > 0.0 && off_ms >= 0.0) {
+                return None;
+            }
+            ArrivalModel::OnOff { on_ms, off_ms }
+        }
+        "burst" => ArrivalModel::Burst {
+            size: parts.next()?.parse().ok().filter(|n| *n > 0)?,
+        },
+        _ => return None,
+    };
+    parts.next().is_none().then_some(model)
+}
+
+/// Mean send rate over time (msg/s per sender), measured from when sending starts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "profile", rename_all = "kebab-case")]
+pub enum RateProfile {
+    Constant {
+        rate: f64,
+    },
+    /// Linear from `from` to `to` over `secs`, then held at `to`
+    Ramp {
+        from: f64,
+        to: f64,
+        secs: f64,
+    },
+    /// Each rate for `step_secs`, the last one held
+    Steps {
+        rates: Vec<f64>,
+        step_secs: f64,
+    },
+    /// `mean + amp * sin(2 pi t / period)`, clamped at 0
+    Sine {
+        mean: f64,
+        amp: f64,
+        period_secs: f64,
+    },
+}
+
+impl RateProfile {
+    pub fn rate_at(&self, elapsed: Duration) -> f64 {
+        let t = elapsed.as_secs_f64();
+        let rate = match self {
+            RateProfile::Constant { rate } => *rate,
+            RateProfile::Ramp { from, to, secs } => {
+                let f = if *secs > 0.0 {
+                    (t / secs).min(1.0)
+                } else {
+                    1.0
+                };
+                from + (to - from) * f
+            }
+            RateProfile::Steps { rates, step_secs } => {
+                let i = if *step_secs > 0.0 {
+                    (t / step_secs) as usize
+                } else {
+                    usize::MAX
+                };
+                rates[i.min(rates.len() - 1)]
+            }
+            RateProfile::Sine {
+                mean,
+                amp,
+                period_secs,
+            } => mean + amp * (TAU * t / period_secs).sin(),
+        };
+        rate.max(0.0)
+    }
+
+    /// The same shape with every rate multiplied by `k`
+    pub fn scaled(&self, k: f64) -> Self {
+        match self {
+            RateProfile::Constant { rate } => RateProfile::Constant { rate: rate * k },
+            RateProfile::Ramp { from, to, secs } => RateProfile::Ramp {
+                from: from * k,
+                to: to * k,
+                secs: *secs,
+            },
+            RateProfile::Steps { rates, step_secs } => RateProfile::Steps {
+                rates: rates.iter().map(|r| r * k).collect(),
+                step_secs: *step_secs,
+            },
+            RateProfile::Sine {
+                mean,
+                amp,
+                period_secs,
+            } => RateProfile::Sine {
+                mean: mean * k,
+                amp: amp * k,
+                period_secs: *period_secs,
+            },
+        }
+    }
+}
+
+/// `300s`, `5m`, `500ms` or plain seconds
+fn parse_secs(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let (num, mult) = if let Some(n) = s.strip_suffix("ms") {
+        (n, 1e-3)
+    } else if let Some(n) = s.strip_suffix('s') {
+        (n, 1.0)
+    } else if let Some(n) = s.strip_suffix('m') {
+        (n, 60.0)
+    } else {
+        (s, 1.0)
+    };
+    let v: f64 = num.trim().parse().ok()?;
+    (v > 0.0).then_some(v * mult)
+}
+
+fn parse_rate(s: &str) -> Option<f64> {
+    s.trim().parse().ok().filter(|r: &f64| *r >= 0.0)
+}
+
+/// Parse `ramp:FROM..TO@DUR`, `steps:R1,R2,...@DUR` or `sine:mean=M,amp=A,period=DUR`
+pub fn parse_rate_profile(s: &str) -> Option<RateProfile> {
+    let (kind, spec) = s.trim().split_once(':')?;
+    match kind.to_lowercase().as_str() {
+        "ramp" => {
+            let (range, dur) = spec.split_once('@')?;
+            let (from, to) = range.split_once("..")?;
+            Some(RateProfile::Ramp {
+                from: parse_rate(from)?,
+                to: parse_rate(to)?,
+                secs: parse_secs(dur)?,
+            })
+        }
+        "steps" => {
+            let (rates, dur) = spec.split_once('@')?;
+            let rates = rates
+                .split(',')
+                .map(parse_rate)
+                .collect::<Option<Vec<_>>>()?;
+            Some(RateProfile::Steps {
+                rates,
+                step_secs: parse_secs(dur)?,
+            })
+        }
+        "sine" => {
+            let (mut mean, mut amp, mut period) = (None, None, None);
+            for kv in spec.split(',') {
+                let (k, v) = kv.split_once('=')?;
+                match k.trim() {
+                    "mean" => mean = parse_rate(v),
+                    "amp" => amp = parse_rate(v),
+                    "period" => period = parse_secs(v),
+                    _ => return None,
+                }
+            }
+            Some(RateProfile::Sine {
+                mean: mean?,
+                amp: amp?,
+                period_secs: period?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Arrival model, seed and optional rate profile for a sender
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct ArrivalConfig {
+    pub model: ArrivalModel,
+    /// Fixed seed for reproducible schedules (None: seeded from the clock)
+    pub seed: Option<u64>,
+    /// Time-varying mean rate per sender; replaces the fixed rate when set
+    pub profile: Option<RateProfile>,
+}
+
+impl ArrivalConfig {
+    /// The same model for concurrent sending stream `i`: seeded streams get distinct,
+    /// still reproducible seeds
+    pub fn for_stream(&self, i: u64) -> Self {
+        Self {
+            model: self.model,
+            seed: self.seed.map(|s| s ^ i.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+            profile: self.profile.clone(),
+        }
+    }
+
+    /// Whether sends are paced at all (a fixed rate or a profile)
+    pub fn is_paced(&self, rate: Option<f64>) -> bool {
+        rate.is_some() || self.profile.is_some()
+    }
+
+    /// Rate controller for a sender at `rate` (or the profile), with all rates multiplied
+    /// by `scale`; None when neither is set (unpaced)
+    pub fn controller(&self, rate: Option<f64>, scale: f64) -> Option<RateController> {
+        let profile = match (&self.profile, rate) {
+            (Some(p), _) => p.scaled(scale),
+            (None, Some(r)) => RateProfile::Constant { rate: r * scale },
+            (None, None) => return None,
+        };
+        Some(match (self.model, &profile) {
+            (ArrivalModel::Constant, RateProfile::Constant { rate }) => RateController::new(*rate),
+            (model, _) => {
+                let rng = match self.seed {
+                    Some(seed) => XorShift64::new(seed),
+                    None => XorShift64::from_time(),
+                };
+                let first = profile.rate_at(Duration::ZERO);
+                RateController::with_process(ArrivalProcess::new(model, first, rng), profile)
+            }
+        })
+    }
+}
+
+/// Generator of inter-arrival gaps for a model at a mean rate (no timers; used by
+/// `RateController` and directly testable)
+#[derive(Debug, Clone)]
+pub struct ArrivalProcess {
+    model: ArrivalModel,
+    rate: f64,
+    rng: XorShift64,
+    /// On/off: current state and the seconds left in it
+    on: bool,
+    state_left: f64,
+    /// Burst: sends left in the current burst after this one
+    burst_left: u32,
+}
+
+impl ArrivalProcess {
+    pub fn new(model: ArrivalModel, msgs_per_second: f64, rng: XorShift64) -> Self {
+        let mut p = Self {
+            model,
+            rate: msgs_per_second.max(f64::MIN_POSITIVE),
+            rng,
+            on: true,
+            state_left: 0.0,
+            burst_left: 0,
+        };
+        match model {
+            ArrivalModel::OnOff { on_ms, .. } => p.state_left = p.exp(on_ms / 1e3),
+            ArrivalModel::Burst { size } => p.burst_left = size.saturating_sub(1),
+            _ => {}
+        }
+        p
+    }
+
+    fn exp(&mut self, mean: f64) -> f64 {
+        -mean * (1.0 - self.rng.next_f64()).ln()
+    }
+
+    /// Seconds from the previous send to the next one
+    pub fn next_gap_secs(&mut self) -> f64 {
+        self.next_gap_secs_at(self.rate)
+    }
+
+    /// Like `next_gap_secs`, at the current mean rate of a profile
+    pub fn next_gap_secs_at(&mut self, rate: f64) -> f64 {
+        let rate = rate.max(f64::MIN_POSITIVE);
+        match self.model {
+            ArrivalModel::Constant => 1.0 / rate,
+            ArrivalModel::Poisson => self.exp(1.0 / rate),
+            ArrivalModel::OnOff { on_ms, off_ms } => {
+                let (on, off) = (on_ms / 1e3, off_ms / 1e3);
+                let peak = rate * (on + off) / on;
+                // Exponential gaps are memoryless, so a gap cut by the end of an ON
+                // period simply resumes in the next one
+                let mut gap = 0.0;
+                loop {
+                    if self.on {
+                        let g = self.exp(1.0 / peak);
+                        if g <= self.state_left {
+                            self.state_left -= g;
+                            return gap + g;
+                        }
+                        gap += self.state_left;
+                        self.on = false;
+                        self.state_left = self.exp(off);
+                    } else {
+                        gap += self.state_left;
+                        self.on = true;
+                        self.state_left = self.exp(on);
+                    }
+                }
+            }
+            ArrivalModel::Burst { size } => {
+                if self.burst_left > 0 {
+                    self.burst_left -= 1;
+                    0.0
+                } else {
+                    self.burst_left = size.saturating_sub(1);
+                    size as f64 / rate
+                }
+            }
+        }
+    }
+}
 
 /// Rate controller for open-loop message sending
 pub struct RateController {
@@ -11,14 +332,26 @@ pub struct RateController {
     frac_per_tick: u32, // fixed-point Q24.8-scale fractional tokens per tick
     frac_accum: u32,    // accumulator for fractional tokens
     max_tokens: u32,
+    /// Non-constant models or profiles: send times follow the process on an absolute schedule
+    process: Option<ArrivalProcess>,
+    next_at: Instant,
+    profile: RateProfile,
+    start: Instant,
 }
 
+/// While a profile is at zero, re-check it this often; longer gaps re-check the rate
+/// at this step too
+const IDLE_STEP: Duration = Duration::from_millis(10);
+
+/// Profile rates below this (msg/s) count as zero
+const MIN_RATE: f64 = 1e-6;
+
 impl RateController {
     /// Create new rate controller for target messages per second
     /// Implementation: token bucket with adaptive tick (<=1000 ticks/s) to amortize timer cost.
     pub fn new(msgs_per_second: f64) -> Self {
         // Adaptive tick: aim for <= 100 ticks per second; not less than 10ms
-        let ticks_per_sec = msgs_per_second.max(1.0).min(100.0);
+        let ticks_per_sec = msgs_per_second.clamp(1.0, 100.0);
         let tick = Duration::from_secs_f64(1.0 / ticks_per_sec);
         let mut ticker = interval(tick);
         ticker.set_missed_tick_behavior(MissedTickBehavior::Burst);
@@ -40,12 +373,62 @@ impl RateController {
             frac_per_tick,
             frac_accum: 0,
             max_tokens,
+            process: None,
+            next_at: Instant::now(),
+            profile: RateProfile::Constant {
+                rate: msgs_per_second.max(0.0),
+            },
+            start: Instant::now(),
         }
     }
 
+    /// Follow an arrival process whose mean rate tracks `profile`. The first send is
+    /// immediate; later sends keep to the cumulative schedule, so a late wake-up is
+    /// caught up and the mean rate holds.
+    pub fn with_process(process: ArrivalProcess, profile: RateProfile) -> Self {
+        let mut rc = Self::new(process.rate);
+        rc.process = Some(process);
+        rc.profile = profile;
+        rc.start = Instant::now();
+        rc.next_at = rc.start;
+        rc
+    }
+
+    /// Add this controller's target rate to the `target_rate` reported by `stats`
+    pub fn report_target_rate(&self, stats: &Stats) {
+        stats.add_target_rate(self.profile.clone(), self.start.into_std());
+    }
+
     /// Wait until it's time to send the next message
     #[inline(always)]
     pub async fn wait_for_next(&mut self) {
+        if let Some(process) = &mut self.process {
+            if self.next_at > Instant::now() {
+                sleep_until(self.next_at).await;
+            }
+            let mut rate = self.profile.rate_at(self.next_at - self.start);
+            while rate < MIN_RATE {
+                self.next_at += IDLE_STEP;
+                sleep_until(self.next_at).await;
+                rate = self.profile.rate_at(self.next_at - self.start);
+            }
+            // Draw the gap at the current rate, then spend it as expected messages against
+            // the profile, re-evaluating the rate at least every IDLE_STEP. A gap drawn
+            // near a zero crossing therefore shrinks as the rate recovers.
+            let mut work = process.next_gap_secs_at(rate) * rate;
+            loop {
+                let rate = self.profile.rate_at(self.next_at - self.start);
+                if rate >= MIN_RATE && work / rate <= IDLE_STEP.as_secs_f64() {
+                    self.next_at += Duration::from_secs_f64(work / rate);
+                    break;
+                }
+                self.next_at += IDLE_STEP;
+                if rate >= MIN_RATE {
+                    work -= rate * IDLE_STEP.as_secs_f64();
+                }
+            }
+            return;
+        }
         // Fast path: if we have tokens, consume and return immediately.
         if self.tokens > 0 {
             self.tokens -= 1;
@@ -58,7 +441,7 @@ impl RateController {
             // integer refill
             let mut new_tokens = self.tokens.saturating_add(self.tokens_per_tick);
             // handle fractional accumulation (Q24.8)
-            let acc = self.frac_accum as u32 + self.frac_per_tick as u32;
+            let acc = self.frac_accum + self.frac_per_tick;
             let carry = acc >> 8; // divide by FP scale (256)
             self.frac_accum = acc & 0xFF;
             new_tokens = new_tokens.saturating_add(carry);
@@ -86,6 +469,183 @@ mod tests {
     use super::*;
     use tokio::time::Instant as TokioInstant;
 
+    /// Mean and variance of `n` gaps
+    fn gap_moments(model: ArrivalModel, rate: f64, n: usize) -> (f64, f64) {
+        let mut p = ArrivalProcess::new(model, rate, XorShift64::new(7));
+        let gaps: Vec<f64> = (0..n).map(|_| p.next_gap_secs()).collect();
+        let mean = gaps.iter().sum::<f64>() / n as f64;
+        let var = gaps.iter().map(|g| (g - mean).powi(2)).sum::<f64>() / n as f64;
+        (mean, var)
+    }
+
+    /// Mean and variance of send counts per window of `window` seconds
+    fn count_moments(model: ArrivalModel, rate: f64, window: f64, windows: usize) -> (f64, f64) {
+        let mut p = ArrivalProcess::new(model, rate, XorShift64::new(11));
+        let mut counts = vec![0u64; windows];
+        let mut t = 0.0;
+        loop {
+            let w = (t / window) as usize;
+            if w >= windows {
+                break;
+            }
+            counts[w] += 1;
+            t += p.next_gap_secs();
+        }
+        let mean = counts.iter().sum::<u64>() as f64 / windows as f64;
+        let var = counts
+            .iter()
+            .map(|&c| (c as f64 - mean).powi(2))
+            .sum::<f64>()
+            / windows as f64;
+        (mean, var)
+    }
+
+    #[test]
+    fn arrival_models_parse() {
+        assert_eq!(parse_arrival_model("poisson"), Some(ArrivalModel::Poisson));
+        assert_eq!(
+            parse_arrival_model("onoff:500:1500"),
+            Some(ArrivalModel::OnOff {
+                on_ms: 500.0,
+                off_ms: 1500.0
+            })
+        );
+        assert_eq!(
+            parse_arrival_model("burst:10"),
+            Some(ArrivalModel::Burst { size: 10 })
+        );
+        for bad in ["burst:0", "onoff:0:10", "onoff:5", "poisson:1", "uniform"] {
+            assert_eq!(parse_arrival_model(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn poisson_gaps_are_exponential() {
+        // Exponential(rate): mean 1/rate, variance 1/rate^2
+        let (mean, var) = gap_moments(ArrivalModel::Poisson, 1000.0, 200_000);
+        assert!((mean * 1000.0 - 1.0).abs() < 0.02, "mean {}", mean);
+        assert!((var * 1e6 - 1.0).abs() < 0.05, "var {}", var);
+        // Counts per window are Poisson: variance equals mean
+        let (cm, cv) = count_moments(ArrivalModel::Poisson, 1000.0, 0.1, 2000);
+        assert!((cm - 100.0).abs() < 2.0, "count mean {}", cm);
+        assert!((cv / cm - 1.0).abs() < 0.15, "dispersion {}", cv / cm);
+    }
+
+    #[test]
+    fn on_off_keeps_mean_rate_and_is_overdispersed() {
+        let model = ArrivalModel::OnOff {
+            on_ms: 200.0,
+            off_ms: 800.0,
+        };
+        let (cm, cv) = count_moments(model, 500.0, 1.0, 3000);
+        assert!((cm / 500.0 - 1.0).abs() < 0.05, "count mean {}", cm);
+        // Modulation makes counts far burstier than Poisson
+        assert!(cv / cm > 10.0, "dispersion {}", cv / cm);
+    }
+
+    #[test]
+    fn bursts_send_back_to_back_then_wait() {
+        let model = ArrivalModel::Burst { size: 4 };
+        let mut p = ArrivalProcess::new(model, 100.0, XorShift64::new(1));
+        let gaps: Vec<f64> = (0..8).map(|_| p.next_gap_secs()).collect();
+        assert_eq!(gaps, [0.0, 0.0, 0.0, 0.04, 0.0, 0.0, 0.0, 0.04]);
+        // Mean gap 1/rate; variance of three zeros and one 4/rate per cycle
+        let (mean, var) = gap_moments(model, 100.0, 4000);
+        assert!((mean - 0.01).abs() < 1e-9);
+        assert!((var - 0.0003).abs() < 1e-9, "var {}", var);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn seeded_controller_follows_schedule() {
+        let cfg = ArrivalConfig {
+            model: ArrivalModel::Poisson,
+            seed: Some(5),
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let mut rc = cfg.controller(Some(100.0), 1.0).unwrap();
+        for _ in 0..1000 {
+            rc.wait_for_next().await;
+        }
+        // 999 gaps at 10ms mean on the paused clock
+        let secs = start.elapsed().as_secs_f64();
+        assert!((secs - 9.99).abs() < 1.0, "elapsed {}", secs);
+    }
+
+    #[test]
+    fn rate_profiles_parse_and_evaluate() {
+        let at = |p: &RateProfile, secs: f64| p.rate_at(Duration::from_secs_f64(secs));
+        let ramp = parse_rate_profile("ramp:100..10000@300s").unwrap();
+        assert_eq!(at(&ramp, 0.0), 100.0);
+        assert_eq!(at(&ramp, 150.0), 5050.0);
+        assert_eq!(at(&ramp, 900.0), 10000.0);
+
+        let steps = parse_rate_profile("steps:1000,2000,4000@1m").unwrap();
+        assert_eq!(at(&steps, 59.0), 1000.0);
+        assert_eq!(at(&steps, 61.0), 2000.0);
+        assert_eq!(at(&steps, 1e6), 4000.0);
+        assert_eq!(at(&steps.scaled(2.0), 0.0), 2000.0);
+
+        let sine = parse_rate_profile("sine:mean=5000,amp=3000,period=120s").unwrap();
+        assert!((at(&sine, 30.0) - 8000.0).abs() < 1e-6);
+        assert!((at(&sine, 90.0) - 2000.0).abs() < 1e-6);
+        let deep = parse_rate_profile("sine:mean=10,amp=20,period=4").unwrap();
+        assert_eq!(at(&deep, 3.0), 0.0);
+
+        for bad in [
+            "ramp:100@10s",
+            "steps:@5s",
+            "sine:mean=1,amp=1",
+            "saw:1..2@3s",
+        ] {
+            assert_eq!(parse_rate_profile(bad), None, "{}", bad);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn controller_follows_step_profile() {
+        let cfg = ArrivalConfig {
+            profile: parse_rate_profile("steps:100,400@1s"),
+            ..Default::default()
+        };
+        let mut rc = cfg.controller(None, 1.0).unwrap();
+        let start = Instant::now();
+        let mut sent_first_step = 0;
+        let mut sent = 0;
+        while start.elapsed() < Duration::from_secs(2) {
+            rc.wait_for_next().await;
+            sent += 1;
+            if start.elapsed() < Duration::from_secs(1) {
+                sent_first_step += 1;
+            }
+        }
+        assert!((95..=105).contains(&sent_first_step), "{}", sent_first_step);
+        assert!((490..=510).contains(&sent), "{}", sent);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn controller_follows_ramp_from_zero() {
+        let cfg = ArrivalConfig {
+            profile: parse_rate_profile("ramp:0..1000@1s"),
+            ..Default::default()
+        };
+        let mut rc = cfg.controller(None, 1.0).unwrap();
+        let start = Instant::now();
+        let mut times = Vec::new();
+        while start.elapsed() < Duration::from_secs(1) {
+            rc.wait_for_next().await;
+            times.push(start.elapsed().as_secs_f64());
+        }
+        // The first send waits out the zero rate, the second follows within tens of ms
+        // rather than a gap sized by the near-zero starting rate
+        assert!(times[0] <= 0.011, "{:?}", &times[..2]);
+        assert!(times[1] < 0.1, "{:?}", &times[..2]);
+        // ∫ rate dt = 500 messages over the ramp; n sends by t = sqrt(n / 500)
+        assert!((490..=510).contains(&times.len()), "{}", times.len());
+        let t100 = times[99];
+        assert!((t100 - (100.0f64 / 500.0).sqrt()).abs() < 0.02, "{}", t100);
+    }
+
     // This is a coarse-grained timing check to ensure the controller spaces events out.
     // It doesn't aim for perfect accuracy, just that the average interval is in the right ballpark.
     #[tokio::test]
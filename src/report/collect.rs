@@ -0,0 +1,455 @@
+//! Artifact discovery: turn run directories into `SummaryRow`s.
+//!
+//! Two layouts are understood:
+//! - per-role directories written by the roles themselves
+//!   (`<run_id>/<role>-<instance>/summary.json` + `manifest.json`)
+//! - legacy orchestrator directories holding `sub.csv`/`sub_agg.csv` and `pub.csv`,
+//!   with transport/payload/rate/fanout encoded in the run id
+//!   (`..._<transport>_s<subs>_p<payload>_r<rate>`)
+//!
+//! `docker_stats.csv` next to either is reduced to max CPU/memory.
+
+use super::SummaryRow;
+use crate::metrics::stats::merge_histograms;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+pub(crate) const RECEIVER_ROLES: &[&str] = &["sub", "mt-sub", "req", "mt-req"];
+/// Receivers whose `subscribers` setting counts toward a run's subscriptions
+pub(crate) const SUBSCRIBER_ROLES: &[&str] = &["sub", "mt-sub"];
+pub(crate) const SENDER_ROLES: &[&str] = &["pub", "mt-pub", "rel-pub", "replay", "req", "mt-req"];
+
+/// Scan `root` recursively and build one row per run directory.
+pub fn collect_rows(root: &Path) -> Result<Vec<SummaryRow>> {
+    let mut role_dirs: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    let mut legacy_dirs = Vec::new();
+    walk(root, &mut |dir| {
+        if dir.join("summary.json").is_file() && dir.join("manifest.json").is_file() {
+            let run_dir = dir.parent().unwrap_or(dir).to_path_buf();
+            role_dirs
+                .entry(run_dir)
+                .or_default()
+                .push(dir.to_path_buf());
+        } else if dir.join("sub.csv").is_file() || dir.join("sub_agg.csv").is_file() {
+            legacy_dirs.push(dir.to_path_buf());
+        }
+    })?;
+
+    let mut rows = Vec::new();
+    for (run_dir, roles) in role_dirs {
+        if let Some(row) = row_from_role_dirs(&run_dir, &roles)? {
+            rows.push(row);
+        }
+    }
+    for dir in legacy_dirs {
+        if let Some(row) = row_from_legacy_dir(&dir)? {
+            rows.push(row);
+        }
+    }
+    rows.sort_by(|a, b| {
+        (&a.transport, a.payload, a.rate, a.subs, &a.run_id).cmp(&(
+            &b.transport,
+            b.payload,
+            b.rate,
+            b.subs,
+            &b.run_id,
+        ))
+    });
+    Ok(rows)
+}
+
+fn walk(dir: &Path, visit: &mut dyn FnMut(&Path)) -> Result<()> {
+    visit(dir);
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_dir())
+        .collect();
+    entries.sort();
+    for e in entries {
+        walk(&e, visit)?;
+    }
+    Ok(())
+}
+
+/// One receiving or sending role instance, reduced to what the summary needs
+#[derive(Debug, Default)]
+struct RoleFinal {
+    role: String,
+    engine: String,
+    config: Value,
+    tps: f64,
+    sent: u64,
+    recv: u64,
+    errors: u64,
+    p50_ns: f64,
+    p95_ns: f64,
+    p99_ns: f64,
+    /// Encoded final latency histogram, absent in summaries from older builds
+    latency_histogram: Option<String>,
+}
+
+fn read_role_final(dir: &Path) -> Result<RoleFinal> {
+    let summary: Value = serde_json::from_slice(&fs::read(dir.join("summary.json"))?)?;
+    Ok(role_final(&summary))
+}
+
+fn role_final(summary: &Value) -> RoleFinal {
+    let s = &summary["final_stats"];
+    let num = |k: &str| s[k].as_f64().unwrap_or(0.0);
+    RoleFinal {
+        role: summary["role"].as_str().unwrap_or_default().to_string(),
+        engine: summary["engine"].as_str().unwrap_or_default().to_string(),
+        config: summary["config"].clone(),
+        tps: num("total_throughput"),
+        sent: s["sent_count"].as_u64().unwrap_or(0),
+        recv: s["received_count"].as_u64().unwrap_or(0),
+        errors: s["error_count"].as_u64().unwrap_or(0),
+        p50_ns: num("latency_ns_p50"),
+        p95_ns: num("latency_ns_p95"),
+        p99_ns: num("latency_ns_p99"),
+        latency_histogram: summary["latency_histogram"].as_str().map(str::to_string),
+    }
+}
+
+/// Rows from role summaries read outside a run tree (`summary.json` documents),
+/// one per `run_id`. Percentiles come from their latency histograms, as for run
+/// directories; `source` stands in for the artifacts directory.
+pub fn rows_from_summaries(source: &Path, summaries: &[Value]) -> Vec<SummaryRow> {
+    let mut runs: BTreeMap<String, Vec<RoleFinal>> = BTreeMap::new();
+    for summary in summaries {
+        let run_id = summary["run_id"].as_str().unwrap_or_default().to_string();
+        runs.entry(run_id).or_default().push(role_final(summary));
+    }
+    runs.into_iter()
+        .filter_map(|(run_id, finals)| row_from_finals(run_id, source, &finals))
+        .collect()
+}
+
+/// Subscriptions a receiving role held (mt-sub `-1` means one per key)
+pub(crate) fn subscriptions(config: &Value) -> u64 {
+    let n = config["subscribers"].as_i64().unwrap_or(1);
+    if n >= 0 {
+        return n as u64;
+    }
+    ["tenants", "regions", "services", "shards"]
+        .iter()
+        .map(|k| config[*k].as_u64().unwrap_or(1))
+        .product()
+}
+
+fn row_from_role_dirs(run_dir: &Path, dirs: &[PathBuf]) -> Result<Option<SummaryRow>> {
+    let finals: Vec<RoleFinal> = dirs
+        .iter()
+        .filter_map(|d| read_role_final(d).ok())
+        .collect();
+    let run_id = run_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let Some(mut row) = row_from_finals(run_id, run_dir, &finals) else {
+        return Ok(None);
+    };
+    let (max_cpu, max_mem_perc, max_mem_used) = docker_stats_max(run_dir, dirs);
+    row.max_cpu_perc = max_cpu;
+    row.max_mem_perc = max_mem_perc;
+    row.max_mem_used_bytes = max_mem_used;
+    Ok(Some(row))
+}
+
+fn row_from_finals(run_id: String, run_dir: &Path, finals: &[RoleFinal]) -> Option<SummaryRow> {
+    let receivers: Vec<&RoleFinal> = finals
+        .iter()
+        .filter(|f| RECEIVER_ROLES.contains(&f.role.as_str()))
+        .collect();
+    let senders: Vec<&RoleFinal> = finals
+        .iter()
+        .filter(|f| SENDER_ROLES.contains(&f.role.as_str()))
+        .collect();
+    let first = receivers.first().or(senders.first())?;
+    let sender_cfg = senders.first().map(|f| &f.config).unwrap_or(&first.config);
+
+    // Percentiles of several receivers come from their merged histograms. Summaries
+    // without one (older builds) fall back to a sample-weighted mean of the
+    // percentiles, which is only an approximation of the combined distribution.
+    let recv: u64 = receivers.iter().map(|f| f.recv).sum();
+    let with_samples: Vec<&&RoleFinal> = receivers.iter().filter(|f| f.recv > 0).collect();
+    let merged = merge_histograms(
+        with_samples
+            .iter()
+            .map(|f| f.latency_histogram.as_deref().unwrap_or_default()),
+    );
+    if merged.is_none() && with_samples.len() > 1 {
+        warn!(run = %run_dir.display(), "No latency histograms to merge; percentiles are approximate");
+    }
+    let percentile = |q: f64, pick: fn(&RoleFinal) -> f64| -> Option<f64> {
+        if recv == 0 {
+            return None;
+        }
+        if let Some(h) = &merged {
+            return Some(h.value_at_quantile(q) as f64 / 1e6);
+        }
+        let sum: f64 = receivers.iter().map(|f| pick(f) * f.recv as f64).sum();
+        Some(sum / recv as f64 / 1e6)
+    };
+    let subs: u64 = receivers
+        .iter()
+        .filter(|f| SUBSCRIBER_ROLES.contains(&f.role.as_str()))
+        .map(|f| subscriptions(&f.config))
+        .sum();
+
+    Some(SummaryRow {
+        transport: first.engine.clone(),
+        payload: sender_cfg["payload"].as_u64().unwrap_or(0),
+        rate: sender_cfg["rate"]
+            .as_i64()
+            .or_else(|| sender_cfg["qps"].as_i64())
+            .unwrap_or(0),
+        run_id,
+        sub_tps: receivers.iter().map(|f| f.tps).sum(),
+        p50_ms: percentile(0.50, |f| f.p50_ns),
+        p95_ms: percentile(0.95, |f| f.p95_ns),
+        p99_ms: percentile(0.99, |f| f.p99_ns),
+        pub_tps: (!senders.is_empty()).then(|| senders.iter().map(|f| f.tps).sum()),
+        sent: if senders.is_empty() {
+            receivers.iter().map(|f| f.sent).sum()
+        } else {
+            senders.iter().map(|f| f.sent).sum()
+        },
+        recv,
+        errors: finals.iter().map(|f| f.errors).sum(),
+        artifacts_dir: run_dir.to_string_lossy().into_owned(),
+        max_cpu_perc: None,
+        max_mem_perc: None,
+        max_mem_used_bytes: None,
+        subs: (subs > 0).then_some(subs),
+    })
+}
+
+/// Last data row of a snapshot CSV as header -> value
+fn last_csv_row(path: &Path) -> Option<BTreeMap<String, String>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .ok()?;
+    let headers = rdr.headers().ok()?.clone();
+    let last = rdr.records().filter_map(|r| r.ok()).last()?;
+    Some(
+        headers
+            .iter()
+            .zip(last.iter())
+            .map(|(h, v)| (h.to_string(), v.to_string()))
+            .collect(),
+    )
+}
+
+/// `..._<transport>_s<subs>_p<payload>_r<rate>` -> (transport, subs, payload, rate)
+fn parse_run_id(run_id: &str) -> (String, Option<u64>, u64, i64) {
+    let mut transport = Vec::new();
+    let (mut subs, mut payload, mut rate) = (None, 0, 0);
+    let tokens: Vec<&str> = run_id.split('_').collect();
+    // Skip everything up to a YYYYMMDD_HHMMSS timestamp if there is one
+    let start = tokens
+        .windows(2)
+        .position(|w| {
+            w[0].len() == 8
+                && w[1].len() == 6
+                && w.iter().all(|t| t.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .map(|i| i + 2)
+        .unwrap_or(0);
+    for tok in &tokens[start..] {
+        let num = |prefix: char| {
+            tok.strip_prefix(prefix)
+                .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|n| n.parse::<u64>().ok())
+        };
+        if let Some(n) = num('s') {
+            subs = Some(n);
+        } else if let Some(n) = num('p') {
+            payload = n;
+        } else if let Some(n) = num('r') {
+            rate = n as i64;
+        } else {
+            transport.push(*tok);
+        }
+    }
+    (transport.join("_"), subs, payload, rate)
+}
+
+fn row_from_legacy_dir(dir: &Path) -> Result<Option<SummaryRow>> {
+    let sub_csv = if dir.join("sub.csv").is_file() {
+        dir.join("sub.csv")
+    } else {
+        dir.join("sub_agg.csv")
+    };
+    let Some(sub) = last_csv_row(&sub_csv) else {
+        return Ok(None);
+    };
+    let publ = last_csv_row(&dir.join("pub.csv"));
+    let f = |row: &BTreeMap<String, String>, k: &str| -> Option<f64> {
+        row.get(k).and_then(|v| v.trim().parse::<f64>().ok())
+    };
+    let ms = |k: &str| f(&sub, k).filter(|v| *v > 0.0).map(|ns| ns / 1e6);
+
+    // Run id is the directory above the role directory (orchestrator layout)
+    let run_id = dir
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let (transport, subs, payload, rate) = parse_run_id(&run_id);
+    let (max_cpu, max_mem_perc, max_mem_used) = docker_stats_max(dir, &[]);
+
+    Ok(Some(SummaryRow {
+        transport,
+        payload,
+        rate,
+        run_id,
+        sub_tps: f(&sub, "total_throughput").unwrap_or(0.0),
+        p50_ms: ms("latency_ns_p50"),
+        p95_ms: ms("latency_ns_p95"),
+        p99_ms: ms("latency_ns_p99"),
+        pub_tps: publ.as_ref().and_then(|p| f(p, "total_throughput")),
+        sent: publ
+            .as_ref()
+            .and_then(|p| f(p, "sent_count"))
+            .or_else(|| f(&sub, "sent_count"))
+            .unwrap_or(0.0) as u64,
+        recv: f(&sub, "received_count").unwrap_or(0.0) as u64,
+        errors: f(&sub, "error_count").unwrap_or(0.0) as u64,
+        artifacts_dir: dir.to_string_lossy().into_owned(),
+        max_cpu_perc: max_cpu,
+        max_mem_perc,
+        max_mem_used_bytes: max_mem_used,
+        subs,
+    }))
+}
+
+/// "12.5MiB" -> bytes
+fn parse_bytes(v: &str) -> Option<f64> {
+    let v = v.trim();
+    let split = v
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(v.len());
+    let n: f64 = v[..split].parse().ok()?;
+    let mult = match &v[split..] {
+        "" | "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => 1.0,
+    };
+    Some(n * mult)
+}
+
+fn percent(v: &str) -> Option<f64> {
+    v.trim().trim_end_matches('%').parse().ok()
+}
+
+/// Max CPU %, memory % and memory bytes over every `docker_stats.csv` found in `dir`
+/// and `extra` dirs. Columns are looked up by name so both the local sampler format
+/// (with numeric `cpu_perc_num`/`mem_used_b` columns) and the remote collector work.
+fn docker_stats_max(dir: &Path, extra: &[PathBuf]) -> (Option<f64>, Option<f64>, Option<u64>) {
+    let (mut cpu, mut mem_perc, mut mem_used): (Option<f64>, Option<f64>, Option<f64>) =
+        (None, None, None);
+    let max = |acc: &mut Option<f64>, v: Option<f64>| {
+        if let Some(v) = v {
+            *acc = Some(acc.map_or(v, |a| a.max(v)));
+        }
+    };
+    for d in std::iter::once(dir).chain(extra.iter().map(|p| p.as_path())) {
+        let Ok(mut rdr) = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(d.join("docker_stats.csv"))
+        else {
+            continue;
+        };
+        let Ok(headers) = rdr.headers().cloned() else {
+            continue;
+        };
+        let col = |name: &str| headers.iter().position(|h| h == name);
+        let (cpu_num, cpu_str) = (col("cpu_perc_num"), col("cpu_perc"));
+        let (used_b, limit_b, perc_calc) =
+            (col("mem_used_b"), col("mem_limit_b"), col("mem_perc_calc"));
+        let (usage, perc) = (col("mem_usage"), col("mem_perc"));
+        for rec in rdr.records().filter_map(|r| r.ok()) {
+            let get = |i: Option<usize>| i.and_then(|i| rec.get(i));
+            max(
+                &mut cpu,
+                get(cpu_num)
+                    .and_then(|v| v.trim().parse().ok())
+                    .or_else(|| get(cpu_str).and_then(percent)),
+            );
+            // "12.5MiB / 1.2GiB" -> used / limit
+            let (used, limit) = match get(used_b).and_then(|v| v.trim().parse::<f64>().ok()) {
+                Some(u) => (
+                    Some(u),
+                    get(limit_b).and_then(|v| v.trim().parse::<f64>().ok()),
+                ),
+                None => {
+                    let mut parts = get(usage).unwrap_or("").split(" / ");
+                    (
+                        parts.next().and_then(parse_bytes),
+                        parts.next().and_then(parse_bytes),
+                    )
+                }
+            };
+            max(&mut mem_used, used);
+            let p = get(perc_calc)
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|p| *p > 0.0)
+                .or_else(|| match (used, limit) {
+                    (Some(u), Some(l)) if l > 0.0 => Some(u / l * 100.0),
+                    _ => get(perc).and_then(percent),
+                });
+            max(&mut mem_perc, p);
+        }
+    }
+    (cpu, mem_perc, mem_used.map(|b| b as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_id_tokens_give_transport_and_parameters() {
+        assert_eq!(
+            parse_run_id("bench_20250910_034853_zenoh_p1024_r5000"),
+            ("zenoh".to_string(), None, 1024, 5000)
+        );
+        assert_eq!(
+            parse_run_id("fx_20250910_034853_fanout_mqtt_s16_p4096_r1000"),
+            ("fanout_mqtt".to_string(), Some(16), 4096, 1000)
+        );
+    }
+
+    #[test]
+    fn docker_stats_formats_reduce_to_maxima() {
+        assert_eq!(parse_bytes("12.5MiB"), Some(12.5 * 1024.0 * 1024.0));
+        assert_eq!(parse_bytes("1.5kB"), Some(1500.0));
+        let dir = std::env::temp_dir().join(format!("mqb-dstats-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("docker_stats.csv"),
+            "timestamp,container,cpu_perc,mem_perc,mem_usage\n\
+             1,broker,12.5%,1.00%,10MiB / 1GiB\n\
+             2,broker,40.0%,2.00%,20MiB / 1GiB\n",
+        )
+        .unwrap();
+        let (cpu, perc, used) = docker_stats_max(&dir, &[]);
+        assert_eq!(cpu, Some(40.0));
+        assert_eq!(used, Some(20 * 1024 * 1024));
+        assert!((perc.unwrap() - 20.0 / 1024.0 * 100.0).abs() < 1e-9);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
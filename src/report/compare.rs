@@ -0,0 +1,593 @@
+//! `mq-bench compare`: baseline vs candidate result sets with significance testing.
+//!
+//! Runs are matched by configuration (transport, payload, rate, subs); several runs
+//! with the same configuration are treated as repetitions. For each metric the
+//! relative change of the means is reported with a bootstrap confidence interval
+//! and a two-sided Mann-Whitney U p-value (exact for small samples).
+//!
+//! A change counts as a regression when it is worse than the threshold and, with at
+//! least two repetitions per side, the selected test finds it significant. Single
+//! runs are judged on the threshold alone, and so are Mann-Whitney comparisons whose
+//! sample sizes cannot reach significance at all (2 vs 2 or 3 vs 3 runs at alpha
+//! 0.05): otherwise adding repetitions would make the gate weaker.
+
+use super::SummaryRow;
+use crate::rng::XorShift64;
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+/// Exact Mann-Whitney enumeration is used up to this many rank assignments
+const EXACT_MW_LIMIT: u64 = 200_000;
+
+/// Process exit code of `mq-bench compare` when a regression is found, distinct from
+/// the code 1 of load/I/O errors and clap's code 2 for usage errors
+pub const REGRESSION_EXIT_CODE: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignificanceTest {
+    MannWhitney,
+    Bootstrap,
+}
+
+pub fn parse_significance_test(s: &str) -> Option<SignificanceTest> {
+    match s.to_lowercase().as_str() {
+        "mann-whitney" | "mw" | "u" => Some(SignificanceTest::MannWhitney),
+        "bootstrap" => Some(SignificanceTest::Bootstrap),
+        _ => None,
+    }
+}
+
+/// A compared metric and whether larger values are better
+#[derive(Debug, Clone, Copy)]
+pub struct Metric {
+    pub name: &'static str,
+    pub higher_is_better: bool,
+    read: fn(&SummaryRow) -> Option<f64>,
+}
+
+pub const METRICS: &[Metric] = &[
+    Metric {
+        name: "sub_tps",
+        higher_is_better: true,
+        read: |r| Some(r.sub_tps),
+    },
+    Metric {
+        name: "pub_tps",
+        higher_is_better: true,
+        read: |r| r.pub_tps,
+    },
+    Metric {
+        name: "p50_ms",
+        higher_is_better: false,
+        read: |r| r.p50_ms,
+    },
+    Metric {
+        name: "p95_ms",
+        higher_is_better: false,
+        read: |r| r.p95_ms,
+    },
+    Metric {
+        name: "p99_ms",
+        higher_is_better: false,
+        read: |r| r.p99_ms,
+    },
+    Metric {
+        name: "max_cpu_perc",
+        higher_is_better: false,
+        read: |r| r.max_cpu_perc,
+    },
+    Metric {
+        name: "max_mem_perc",
+        higher_is_better: false,
+        read: |r| r.max_mem_perc,
+    },
+];
+
+pub const DEFAULT_METRICS: &[&str] = &["sub_tps", "p50_ms", "p95_ms", "p99_ms"];
+
+pub fn metric_by_name(name: &str) -> Option<Metric> {
+    METRICS.iter().copied().find(|m| m.name == name)
+}
+
+#[derive(Debug, Clone)]
+pub struct CompareConfig {
+    pub metrics: Vec<Metric>,
+    /// Regression threshold in percent (worse-direction change of the mean)
+    pub threshold_pct: f64,
+    /// Confidence level for the bootstrap interval, e.g. 0.95
+    pub confidence: f64,
+    pub test: SignificanceTest,
+    pub bootstrap_iters: usize,
+    pub seed: u64,
+}
+
+impl Default for CompareConfig {
+    fn default() -> Self {
+        Self {
+            metrics: DEFAULT_METRICS
+                .iter()
+                .filter_map(|n| metric_by_name(n))
+                .collect(),
+            threshold_pct: 5.0,
+            confidence: 0.95,
+            test: SignificanceTest::MannWhitney,
+            bootstrap_iters: 2000,
+            seed: 1,
+        }
+    }
+}
+
+/// Configuration key runs are matched on
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RunKey {
+    pub transport: String,
+    pub payload: u64,
+    pub rate: i64,
+    pub subs: Option<u64>,
+}
+
+impl RunKey {
+    fn of(r: &SummaryRow) -> Self {
+        Self {
+            transport: r.transport.clone(),
+            payload: r.payload,
+            rate: r.rate,
+            subs: r.subs,
+        }
+    }
+}
+
+impl std::fmt::Display for RunKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} p{} r{}", self.transport, self.payload, self.rate)?;
+        if let Some(s) = self.subs {
+            write!(f, " s{}", s)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricComparison {
+    pub key: RunKey,
+    pub metric: String,
+    pub baseline_n: usize,
+    pub candidate_n: usize,
+    pub baseline_mean: f64,
+    pub candidate_mean: f64,
+    /// (candidate - baseline) / baseline in percent
+    pub delta_pct: f64,
+    /// Bootstrap interval of `delta_pct` (None with fewer than two runs per side)
+    pub ci_low_pct: Option<f64>,
+    pub ci_high_pct: Option<f64>,
+    /// Two-sided Mann-Whitney U p-value (None with fewer than two runs per side)
+    pub p_value: Option<f64>,
+    /// Whether the verdict rests on the threshold alone (single runs, or too few
+    /// repetitions for the test to ever be significant)
+    pub threshold_only: bool,
+    pub regression: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompareResult {
+    pub comparisons: Vec<MetricComparison>,
+    pub only_baseline: Vec<RunKey>,
+    pub only_candidate: Vec<RunKey>,
+}
+
+impl CompareResult {
+    pub fn regressions(&self) -> impl Iterator<Item = &MetricComparison> {
+        self.comparisons.iter().filter(|c| c.regression)
+    }
+}
+
+fn group(rows: &[SummaryRow]) -> BTreeMap<RunKey, Vec<&SummaryRow>> {
+    let mut groups: BTreeMap<RunKey, Vec<&SummaryRow>> = BTreeMap::new();
+    for r in rows {
+        groups.entry(RunKey::of(r)).or_default().push(r);
+    }
+    groups
+}
+
+fn mean(v: &[f64]) -> f64 {
+    v.iter().sum::<f64>() / v.len() as f64
+}
+
+fn rel_delta_pct(base: f64, cand: f64) -> f64 {
+    if base == 0.0 {
+        if cand == 0.0 {
+            0.0
+        } else {
+            f64::INFINITY * cand.signum()
+        }
+    } else {
+        (cand - base) / base.abs() * 100.0
+    }
+}
+
+/// Percentile bootstrap interval of the relative change of means
+pub fn bootstrap_ci(
+    base: &[f64],
+    cand: &[f64],
+    iters: usize,
+    confidence: f64,
+    rng: &mut XorShift64,
+) -> (f64, f64) {
+    let resample_mean = |v: &[f64], rng: &mut XorShift64| {
+        (0..v.len()).map(|_| v[rng.below(v.len())]).sum::<f64>() / v.len() as f64
+    };
+    let mut deltas: Vec<f64> = (0..iters.max(1))
+        .map(|_| {
+            let b = resample_mean(base, rng);
+            let c = resample_mean(cand, rng);
+            rel_delta_pct(b, c)
+        })
+        .collect();
+    deltas.sort_by(|a, b| a.total_cmp(b));
+    let alpha = (1.0 - confidence).clamp(0.0, 1.0) / 2.0;
+    let at =
+        |q: f64| deltas[((q * (deltas.len() - 1) as f64).round() as usize).min(deltas.len() - 1)];
+    (at(alpha), at(1.0 - alpha))
+}
+
+/// Midranks (1-based) of the pooled samples, ties averaged
+fn midranks(pooled: &[f64]) -> Vec<f64> {
+    let mut idx: Vec<usize> = (0..pooled.len()).collect();
+    idx.sort_by(|&a, &b| pooled[a].total_cmp(&pooled[b]));
+    let mut ranks = vec![0.0; pooled.len()];
+    let mut i = 0;
+    while i < idx.len() {
+        let mut j = i;
+        while j + 1 < idx.len() && pooled[idx[j + 1]] == pooled[idx[i]] {
+            j += 1;
+        }
+        let r = (i + j) as f64 / 2.0 + 1.0;
+        for k in &idx[i..=j] {
+            ranks[*k] = r;
+        }
+        i = j + 1;
+    }
+    ranks
+}
+
+fn binomial(n: u64, k: u64) -> u64 {
+    let k = k.min(n - k);
+    (0..k).fold(1u64, |acc, i| acc.saturating_mul(n - i) / (i + 1))
+}
+
+/// Smallest two-sided exact Mann-Whitney p-value possible with `n1` vs `n2` samples
+/// (complete separation, no ties): 2 / C(n1 + n2, n1)
+pub fn mann_whitney_min_p(n1: usize, n2: usize) -> f64 {
+    if n1 == 0 || n2 == 0 {
+        return 1.0;
+    }
+    (2.0 / binomial((n1 + n2) as u64, n1 as u64) as f64).min(1.0)
+}
+
+/// Two-sided Mann-Whitney U test p-value.
+/// Small samples enumerate every rank assignment (exact, ties handled via midranks);
+/// larger ones use the tie-corrected normal approximation with continuity correction.
+pub fn mann_whitney_p(a: &[f64], b: &[f64]) -> f64 {
+    let (n1, n2) = (a.len(), b.len());
+    if n1 == 0 || n2 == 0 {
+        return 1.0;
+    }
+    let pooled: Vec<f64> = a.iter().chain(b).copied().collect();
+    let ranks = midranks(&pooled);
+    let n = (n1 + n2) as f64;
+    let expected = n1 as f64 * (n + 1.0) / 2.0;
+    let observed = (ranks[..n1].iter().sum::<f64>() - expected).abs();
+
+    if binomial((n1 + n2) as u64, n1 as u64) <= EXACT_MW_LIMIT {
+        let (mut extreme, mut total) = (0u64, 0u64);
+        let mut chosen = Vec::with_capacity(n1);
+        enumerate_rank_sums(&ranks, n1, 0, &mut chosen, &mut |sum| {
+            total += 1;
+            if (sum - expected).abs() >= observed - 1e-9 {
+                extreme += 1;
+            }
+        });
+        return extreme as f64 / total as f64;
+    }
+
+    let (n1f, n2f) = (n1 as f64, n2 as f64);
+    let mut tie_term = 0.0;
+    let mut sorted = pooled.clone();
+    sorted.sort_by(|x, y| x.total_cmp(y));
+    let mut i = 0;
+    while i < sorted.len() {
+        let j = sorted[i..].iter().take_while(|v| **v == sorted[i]).count();
+        tie_term += (j * j * j - j) as f64;
+        i += j;
+    }
+    let var = n1f * n2f / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if var <= 0.0 {
+        return 1.0;
+    }
+    let z = (observed - 0.5).max(0.0) / var.sqrt();
+    (2.0 * (1.0 - normal_cdf(z))).min(1.0)
+}
+
+fn enumerate_rank_sums(
+    ranks: &[f64],
+    k: usize,
+    start: usize,
+    chosen: &mut Vec<f64>,
+    visit: &mut dyn FnMut(f64),
+) {
+    if chosen.len() == k {
+        visit(chosen.iter().sum());
+        return;
+    }
+    let needed = k - chosen.len();
+    for i in start..=ranks.len() - needed {
+        chosen.push(ranks[i]);
+        enumerate_rank_sums(ranks, k, i + 1, chosen, visit);
+        chosen.pop();
+    }
+}
+
+/// Standard normal CDF (Abramowitz-Stegun 7.1.26 erf approximation)
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+/// Match runs by configuration and compare every selected metric.
+pub fn compare(
+    baseline: &[SummaryRow],
+    candidate: &[SummaryRow],
+    cfg: &CompareConfig,
+) -> Result<CompareResult> {
+    if !(0.0..1.0).contains(&cfg.confidence) {
+        bail!("confidence must be in [0, 1), got {}", cfg.confidence);
+    }
+    let (base, cand) = (group(baseline), group(candidate));
+    let mut rng = XorShift64::new(cfg.seed);
+    let mut result = CompareResult {
+        only_baseline: base
+            .keys()
+            .filter(|k| !cand.contains_key(k))
+            .cloned()
+            .collect(),
+        only_candidate: cand
+            .keys()
+            .filter(|k| !base.contains_key(k))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+    let alpha = 1.0 - cfg.confidence;
+
+    for (key, b_rows) in &base {
+        let Some(c_rows) = cand.get(key) else {
+            continue;
+        };
+        for metric in &cfg.metrics {
+            let b: Vec<f64> = b_rows.iter().filter_map(|r| (metric.read)(r)).collect();
+            let c: Vec<f64> = c_rows.iter().filter_map(|r| (metric.read)(r)).collect();
+            if b.is_empty() || c.is_empty() {
+                continue;
+            }
+            let delta_pct = rel_delta_pct(mean(&b), mean(&c));
+            let worse_pct = if metric.higher_is_better {
+                -delta_pct
+            } else {
+                delta_pct
+            };
+            let repeated = b.len() >= 2 && c.len() >= 2;
+            let (ci, p_value) = if repeated {
+                (
+                    Some(bootstrap_ci(
+                        &b,
+                        &c,
+                        cfg.bootstrap_iters,
+                        cfg.confidence,
+                        &mut rng,
+                    )),
+                    Some(mann_whitney_p(&b, &c)),
+                )
+            } else {
+                (None, None)
+            };
+            // A test that cannot reject at this alpha would veto every regression
+            let threshold_only = !repeated
+                || (cfg.test == SignificanceTest::MannWhitney
+                    && mann_whitney_min_p(b.len(), c.len()) > alpha);
+            let significant = match (cfg.test, ci, p_value) {
+                _ if threshold_only => true,
+                (SignificanceTest::MannWhitney, _, Some(p)) => p <= alpha,
+                // The whole interval must lie on the worse side of zero
+                (SignificanceTest::Bootstrap, Some((lo, hi)), _) => {
+                    if metric.higher_is_better {
+                        hi < 0.0
+                    } else {
+                        lo > 0.0
+                    }
+                }
+                _ => true,
+            };
+            result.comparisons.push(MetricComparison {
+                key: key.clone(),
+                metric: metric.name.to_string(),
+                baseline_n: b.len(),
+                candidate_n: c.len(),
+                baseline_mean: mean(&b),
+                candidate_mean: mean(&c),
+                delta_pct,
+                ci_low_pct: ci.map(|c| c.0),
+                ci_high_pct: ci.map(|c| c.1),
+                p_value,
+                threshold_only,
+                regression: worse_pct > cfg.threshold_pct && significant,
+            });
+        }
+    }
+    Ok(result)
+}
+
+/// Markdown table of all comparisons followed by unmatched configurations
+pub fn render_markdown(result: &CompareResult, cfg: &CompareConfig) -> String {
+    let opt = |v: Option<f64>, prec: usize| {
+        v.map(|v| format!("{:.*}", prec, v))
+            .unwrap_or_else(|| "-".into())
+    };
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "| config | metric | n (base/cand) | baseline | candidate | delta % | {:.0}% CI % | p | verdict |",
+        cfg.confidence * 100.0
+    );
+    let _ = writeln!(out, "|---|---|---:|---:|---:|---:|---:|---:|---|");
+    for c in &result.comparisons {
+        let ci = match (c.ci_low_pct, c.ci_high_pct) {
+            (Some(lo), Some(hi)) => format!("[{:+.2}, {:+.2}]", lo, hi),
+            _ => "-".into(),
+        };
+        let _ = writeln!(
+            out,
+            "| {} | {} | {}/{} | {:.3} | {:.3} | {:+.2} | {} | {} | {} |",
+            c.key,
+            c.metric,
+            c.baseline_n,
+            c.candidate_n,
+            c.baseline_mean,
+            c.candidate_mean,
+            c.delta_pct,
+            ci,
+            opt(c.p_value, 4),
+            match (c.regression, c.threshold_only && c.p_value.is_some()) {
+                (true, true) => "REGRESSION (threshold only)",
+                (true, false) => "REGRESSION",
+                (false, _) => "ok",
+            }
+        );
+    }
+    for (label, keys) in [
+        ("Only in baseline", &result.only_baseline),
+        ("Only in candidate", &result.only_candidate),
+    ] {
+        if !keys.is_empty() {
+            let names: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+            let _ = writeln!(out, "\n{}: {}", label, names.join(", "));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(transport: &str, tps: f64, p99: f64) -> SummaryRow {
+        SummaryRow {
+            transport: transport.into(),
+            payload: 1024,
+            rate: 1000,
+            sub_tps: tps,
+            p99_ms: Some(p99),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mann_whitney_exact_and_approximate() {
+        // Complete separation of 4 vs 4: 2 of 70 assignments are as extreme
+        let p = mann_whitney_p(&[1.0, 2.0, 3.0, 4.0], &[5.0, 6.0, 7.0, 8.0]);
+        assert!((p - 2.0 / 70.0).abs() < 1e-12);
+        assert_eq!(mann_whitney_p(&[1.0, 1.0], &[1.0, 1.0]), 1.0);
+        // Large identical distributions are not significant, shifted ones are
+        let a: Vec<f64> = (0..30).map(|i| i as f64).collect();
+        let b: Vec<f64> = (0..30).map(|i| i as f64 + 0.5).collect();
+        assert!(mann_whitney_p(&a, &b) > 0.5);
+        let c: Vec<f64> = (0..30).map(|i| i as f64 + 40.0).collect();
+        assert!(mann_whitney_p(&a, &c) < 1e-6);
+    }
+
+    #[test]
+    fn flags_significant_regressions_beyond_threshold() {
+        let base: Vec<SummaryRow> = [1000.0, 1010.0, 990.0, 1005.0]
+            .iter()
+            .map(|t| row("zenoh", *t, 2.0))
+            .chain([row("mqtt", 500.0, 5.0)])
+            .collect();
+        let cand: Vec<SummaryRow> = [900.0, 905.0, 895.0, 910.0]
+            .iter()
+            .map(|t| row("zenoh", *t, 2.02))
+            .chain([row("nats", 800.0, 1.0)])
+            .collect();
+        let cfg = CompareConfig::default();
+        let res = compare(&base, &cand, &cfg).unwrap();
+
+        let tps = res
+            .comparisons
+            .iter()
+            .find(|c| c.metric == "sub_tps")
+            .unwrap();
+        assert!((tps.delta_pct + 10.0).abs() < 0.5);
+        assert!(tps.regression);
+        assert!(tps.ci_high_pct.unwrap() < 0.0);
+        let p99 = res
+            .comparisons
+            .iter()
+            .find(|c| c.metric == "p99_ms")
+            .unwrap();
+        assert!(!p99.regression, "1% latency change is under the threshold");
+        assert_eq!(res.regressions().count(), 1);
+        assert_eq!(res.only_baseline[0].transport, "mqtt");
+        assert_eq!(res.only_candidate[0].transport, "nats");
+        assert!(render_markdown(&res, &cfg).contains("REGRESSION"));
+
+        // Same data judged by the bootstrap interval
+        let boot = compare(
+            &base,
+            &cand,
+            &CompareConfig {
+                test: SignificanceTest::Bootstrap,
+                ..cfg
+            },
+        )
+        .unwrap();
+        assert_eq!(boot.regressions().count(), 1);
+    }
+
+    #[test]
+    fn underpowered_repetitions_fall_back_to_the_threshold() {
+        assert!((mann_whitney_min_p(2, 2) - 1.0 / 3.0).abs() < 1e-12);
+        assert!((mann_whitney_min_p(3, 3) - 0.1).abs() < 1e-12);
+        assert!(mann_whitney_min_p(4, 4) < 0.05);
+
+        // 3 vs 3 runs, clearly 10% slower: p can never go below 0.1
+        let base: Vec<SummaryRow> = [1000.0, 1010.0, 990.0]
+            .iter()
+            .map(|t| row("zenoh", *t, 2.0))
+            .collect();
+        let cand: Vec<SummaryRow> = [900.0, 905.0, 895.0]
+            .iter()
+            .map(|t| row("zenoh", *t, 2.0))
+            .collect();
+        let cfg = CompareConfig::default();
+        let res = compare(&base, &cand, &cfg).unwrap();
+        let tps = res
+            .comparisons
+            .iter()
+            .find(|c| c.metric == "sub_tps")
+            .unwrap();
+        assert!(tps.p_value.unwrap() > 0.05);
+        assert!(tps.threshold_only && tps.regression);
+        assert!(render_markdown(&res, &cfg).contains("REGRESSION (threshold only)"));
+    }
+}
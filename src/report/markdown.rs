@@ -0,0 +1,58 @@
+//! `report.md`: per-payload result tables with links to the charts.
+
+use super::{ChartFile, SummaryRow};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+fn cell(v: Option<f64>, prec: usize) -> String {
+    v.map(|v| format!("{:.*}", prec, v))
+        .unwrap_or_else(|| "-".into())
+}
+
+pub fn render(rows: &[SummaryRow], charts: &[ChartFile]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# mq-bench report\n");
+    let transports: BTreeSet<&str> = rows.iter().map(|r| r.transport.as_str()).collect();
+    let _ = writeln!(
+        out,
+        "{} runs, transports: {}. Full data in [summary.csv](summary.csv).\n",
+        rows.len(),
+        transports.into_iter().collect::<Vec<_>>().join(", ")
+    );
+
+    let payloads: BTreeSet<u64> = rows.iter().map(|r| r.payload).collect();
+    for payload in payloads {
+        let _ = writeln!(out, "## Payload {} B\n", payload);
+        let _ = writeln!(
+            out,
+            "| transport | rate | subs | sub_tps | pub_tps | p50 ms | p95 ms | p99 ms | errors | max CPU % | max mem % | run |"
+        );
+        let _ = writeln!(
+            out,
+            "|---|---:|---:|---:|---:|---:|---:|---:|---:|---:|---:|---|"
+        );
+        for r in rows.iter().filter(|r| r.payload == payload) {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {:.2} | {} | {} | {} | {} | {} | {} | {} | {} |",
+                r.transport,
+                r.rate,
+                r.subs.map(|s| s.to_string()).unwrap_or_else(|| "-".into()),
+                r.sub_tps,
+                cell(r.pub_tps, 2),
+                cell(r.p50_ms, 3),
+                cell(r.p95_ms, 3),
+                cell(r.p99_ms, 3),
+                r.errors,
+                cell(r.max_cpu_perc, 2),
+                cell(r.max_mem_perc, 2),
+                r.run_id
+            );
+        }
+        out.push('\n');
+        for c in charts.iter().filter(|c| c.payload == payload) {
+            let _ = writeln!(out, "![{}]({})\n", c.title, c.path);
+        }
+    }
+    out
+}
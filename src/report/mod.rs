@@ -0,0 +1,513 @@
+//! `mq-bench report`: turn a tree of run artifacts into tables and charts.
+//!
+//! Output (in `ReportConfig::out_dir`):
+//! - `summary.csv`: one row per run, same columns as the orchestrator's
+//!   `results/summary.csv` plus `subs`
+//! - `charts/*.svg` and `charts/*.png`: throughput, p99, CPU and memory vs rate
+//!   per payload, and throughput vs fan-out where runs vary the subscriber count
+//! - `report.md`: per-payload tables linking the charts
+
+pub mod collect;
+pub mod compare;
+pub mod markdown;
+pub mod png;
+pub mod svg;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use svg::{Chart, Series};
+use tracing::info;
+
+#[derive(Debug, Clone)]
+pub struct ReportConfig {
+    /// Root to scan (an artifacts directory or a single run directory)
+    pub artifacts: PathBuf,
+    pub out_dir: PathBuf,
+}
+
+/// One benchmark run, aggregated over its role instances
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SummaryRow {
+    pub transport: String,
+    pub payload: u64,
+    pub rate: i64,
+    pub run_id: String,
+    pub sub_tps: f64,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub pub_tps: Option<f64>,
+    pub sent: u64,
+    pub recv: u64,
+    pub errors: u64,
+    pub artifacts_dir: String,
+    pub max_cpu_perc: Option<f64>,
+    pub max_mem_perc: Option<f64>,
+    pub max_mem_used_bytes: Option<u64>,
+    pub subs: Option<u64>,
+}
+
+pub const SUMMARY_HEADER: &[&str] = &[
+    "transport",
+    "payload",
+    "rate",
+    "run_id",
+    "sub_tps",
+    "p50_ms",
+    "p95_ms",
+    "p99_ms",
+    "pub_tps",
+    "sent",
+    "recv",
+    "errors",
+    "artifacts_dir",
+    "max_cpu_perc",
+    "max_mem_perc",
+    "max_mem_used_bytes",
+    "subs",
+];
+
+fn fmt_opt(v: Option<f64>, prec: usize) -> String {
+    v.map(|v| format!("{:.*}", prec, v)).unwrap_or_default()
+}
+
+impl SummaryRow {
+    /// Values in `SUMMARY_HEADER` order, formatted like the orchestrator scripts
+    pub fn csv_record(&self) -> Vec<String> {
+        vec![
+            self.transport.clone(),
+            self.payload.to_string(),
+            self.rate.to_string(),
+            self.run_id.clone(),
+            format!("{:.2}", self.sub_tps),
+            fmt_opt(self.p50_ms, 3),
+            fmt_opt(self.p95_ms, 3),
+            fmt_opt(self.p99_ms, 3),
+            fmt_opt(self.pub_tps, 2),
+            self.sent.to_string(),
+            self.recv.to_string(),
+            self.errors.to_string(),
+            self.artifacts_dir.clone(),
+            fmt_opt(self.max_cpu_perc, 2),
+            fmt_opt(self.max_mem_perc, 2),
+            self.max_mem_used_bytes
+                .map(|b| b.to_string())
+                .unwrap_or_default(),
+            self.subs.map(|s| s.to_string()).unwrap_or_default(),
+        ]
+    }
+}
+
+pub fn write_summary_csv(rows: &[SummaryRow], path: &Path) -> Result<()> {
+    let mut w = csv::Writer::from_path(path)?;
+    w.write_record(SUMMARY_HEADER)?;
+    for row in rows {
+        w.write_record(row.csv_record())?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+/// Read a `summary.csv` (this report's or the orchestrator's) by column name.
+/// Empty or missing columns become `None`/0.
+pub fn read_summary_csv(path: &Path) -> Result<Vec<SummaryRow>> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let mut rows = Vec::new();
+    for rec in rdr.records() {
+        let rec = rec?;
+        let get = |k: &str| {
+            headers
+                .iter()
+                .position(|h| h == k)
+                .and_then(|i| rec.get(i))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let num = |k: &str| get(k).and_then(|v| v.parse::<f64>().ok());
+        rows.push(SummaryRow {
+            transport: get("transport").unwrap_or_default().to_string(),
+            payload: num("payload").unwrap_or(0.0) as u64,
+            rate: num("rate").unwrap_or(0.0) as i64,
+            run_id: get("run_id").unwrap_or_default().to_string(),
+            sub_tps: num("sub_tps").unwrap_or(0.0),
+            p50_ms: num("p50_ms"),
+            p95_ms: num("p95_ms"),
+            p99_ms: num("p99_ms"),
+            pub_tps: num("pub_tps"),
+            sent: num("sent").unwrap_or(0.0) as u64,
+            recv: num("recv").unwrap_or(0.0) as u64,
+            errors: num("errors").unwrap_or(0.0) as u64,
+            artifacts_dir: get("artifacts_dir").unwrap_or_default().to_string(),
+            max_cpu_perc: num("max_cpu_perc"),
+            max_mem_perc: num("max_mem_perc"),
+            max_mem_used_bytes: num("max_mem_used_bytes").map(|b| b as u64),
+            subs: num("subs").map(|s| s as u64),
+        });
+    }
+    Ok(rows)
+}
+
+/// Load a result set: an artifacts directory (scanned like `report`), a
+/// `summary.csv`, a JSON array / JSON lines of summary rows, or role
+/// `summary.json` documents (percentiles from their latency histograms).
+pub fn load_result_set(path: &Path) -> Result<Vec<SummaryRow>> {
+    if path.is_dir() {
+        return collect::collect_rows(path);
+    }
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => read_summary_csv(path),
+        Some("json") | Some("jsonl") => {
+            let text = fs::read_to_string(path)?;
+            let values: Vec<serde_json::Value> = match serde_json::from_str(&text) {
+                Ok(serde_json::Value::Array(items)) => items,
+                Ok(single) => vec![single],
+                Err(_) => text
+                    .lines()
+                    .filter(|l| !l.trim().is_empty())
+                    .map(serde_json::from_str)
+                    .collect::<Result<_, _>>()?,
+            };
+            if !values.is_empty() && values.iter().all(|v| v.get("final_stats").is_some()) {
+                return Ok(collect::rows_from_summaries(path, &values));
+            }
+            values
+                .into_iter()
+                .map(|v| Ok(serde_json::from_value(v)?))
+                .collect()
+        }
+        Some("hlog") | Some("hgrm") => bail!(
+            "{}: HdrHistogram logs carry no run configuration to match on; pass the run \
+             directories or their summary.json files, which embed each role's latency histogram",
+            path.display()
+        ),
+        _ => bail!(
+            "unsupported result set {} (expected a directory, .csv, .json or .jsonl)",
+            path.display()
+        ),
+    }
+}
+
+/// A chart file written for the report, relative to the output directory
+#[derive(Debug, Clone)]
+pub struct ChartFile {
+    pub payload: u64,
+    pub title: String,
+    pub path: String,
+}
+
+/// Per-payload "metric vs rate" charts, one series per transport
+fn rate_charts(rows: &[SummaryRow]) -> Vec<(u64, String, Chart)> {
+    type Metric = fn(&SummaryRow) -> Option<f64>;
+    let metrics: [(&str, &str, &str, Metric); 4] = [
+        ("throughput_vs_rate", "Throughput", "msg/s", |r| {
+            Some(r.sub_tps)
+        }),
+        ("p99_vs_rate", "p99 latency", "ms", |r| r.p99_ms),
+        ("max_cpu_vs_rate", "Max CPU", "%", |r| r.max_cpu_perc),
+        ("max_mem_vs_rate", "Max memory", "%", |r| r.max_mem_perc),
+    ];
+    let payloads: BTreeSet<u64> = rows.iter().map(|r| r.payload).collect();
+    let mut charts = Vec::new();
+    for payload in payloads {
+        let at_payload: Vec<&SummaryRow> = rows
+            .iter()
+            .filter(|r| r.payload == payload && r.rate > 0)
+            .collect();
+        for (stem, label, unit, metric) in metrics {
+            let series = series_by_transport(&at_payload, |r| r.rate as f64, metric);
+            if series.is_empty() {
+                continue;
+            }
+            charts.push((
+                payload,
+                format!("{}_payload{}", stem, payload),
+                Chart {
+                    title: format!("{} vs rate (payload {} B)", label, payload),
+                    x_label: "offered rate (msg/s)".into(),
+                    y_label: format!("{} ({})", label, unit),
+                    series,
+                },
+            ));
+        }
+    }
+    charts
+}
+
+/// Throughput vs subscriber count for each (payload, rate) that has more than one fan-out
+fn fanout_charts(rows: &[SummaryRow]) -> Vec<(u64, String, Chart)> {
+    let groups: BTreeSet<(u64, i64)> = rows.iter().map(|r| (r.payload, r.rate)).collect();
+    let mut charts = Vec::new();
+    for (payload, rate) in groups {
+        let group: Vec<&SummaryRow> = rows
+            .iter()
+            .filter(|r| r.payload == payload && r.rate == rate && r.subs.is_some())
+            .collect();
+        let fanouts: BTreeSet<u64> = group.iter().filter_map(|r| r.subs).collect();
+        if fanouts.len() < 2 {
+            continue;
+        }
+        let series =
+            series_by_transport(&group, |r| r.subs.unwrap_or(0) as f64, |r| Some(r.sub_tps));
+        charts.push((
+            payload,
+            format!("throughput-vs-fanout_payload{}_rate{}", payload, rate),
+            Chart {
+                title: format!(
+                    "Throughput vs fan-out (payload {} B, rate {})",
+                    payload, rate
+                ),
+                x_label: "subscribers".into(),
+                y_label: "Throughput (msg/s)".into(),
+                series,
+            },
+        ));
+    }
+    charts
+}
+
+fn series_by_transport(
+    rows: &[&SummaryRow],
+    x: fn(&SummaryRow) -> f64,
+    y: fn(&SummaryRow) -> Option<f64>,
+) -> Vec<Series> {
+    let transports: BTreeSet<&str> = rows.iter().map(|r| r.transport.as_str()).collect();
+    transports
+        .into_iter()
+        .filter_map(|t| {
+            let mut points: Vec<(f64, f64)> = rows
+                .iter()
+                .filter(|r| r.transport == t)
+                .filter_map(|r| y(r).map(|v| (x(r), v)))
+                .collect();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            (!points.is_empty()).then(|| Series {
+                name: t.to_string(),
+                points,
+            })
+        })
+        .collect()
+}
+
+/// Scan, aggregate and write `summary.csv`, `charts/*.{svg,png}` and `report.md`.
+pub fn run_report(cfg: &ReportConfig) -> Result<Vec<SummaryRow>> {
+    if !cfg.artifacts.is_dir() {
+        bail!("artifacts directory not found: {}", cfg.artifacts.display());
+    }
+    let rows = collect::collect_rows(&cfg.artifacts)?;
+    if rows.is_empty() {
+        bail!("no run artifacts found under {}", cfg.artifacts.display());
+    }
+    let charts_dir = cfg.out_dir.join("charts");
+    fs::create_dir_all(&charts_dir)?;
+    write_summary_csv(&rows, &cfg.out_dir.join("summary.csv"))?;
+
+    let mut charts = Vec::new();
+    let png_opts = png::options();
+    for (payload, stem, chart) in rate_charts(&rows).into_iter().chain(fanout_charts(&rows)) {
+        let rel = format!("charts/{}.svg", stem);
+        let svg = chart.render();
+        fs::write(
+            charts_dir.join(format!("{}.png", stem)),
+            png::render(&svg, &png_opts)?,
+        )?;
+        fs::write(cfg.out_dir.join(&rel), svg)?;
+        charts.push(ChartFile {
+            payload,
+            title: chart.title,
+            path: rel,
+        });
+    }
+    fs::write(
+        cfg.out_dir.join("report.md"),
+        markdown::render(&rows, &charts),
+    )?;
+    info!(
+        runs = rows.len(),
+        charts = charts.len(),
+        out = %cfg.out_dir.display(),
+        "Report written"
+    );
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_role(
+        run_dir: &Path,
+        role: &str,
+        engine: &str,
+        config: serde_json::Value,
+        stats: serde_json::Value,
+    ) {
+        let dir = run_dir.join(format!("{}-1", role));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("manifest.json"), "{}").unwrap();
+        let summary = json!({
+            "run_id": run_dir.file_name().unwrap().to_string_lossy(),
+            "role": role,
+            "engine": engine,
+            "config": config,
+            "final_stats": stats,
+        });
+        fs::write(dir.join("summary.json"), summary.to_string()).unwrap();
+    }
+
+    #[test]
+    fn report_aggregates_role_dirs_and_writes_outputs() {
+        let root = std::env::temp_dir().join(format!("mqb-report-{}", uuid::Uuid::new_v4()));
+        for (run, engine, rate, tps) in [
+            ("r1", "zenoh", 1000, 990.0),
+            ("r2", "zenoh", 5000, 4900.0),
+            ("r3", "mqtt", 1000, 980.0),
+        ] {
+            let run_dir = root.join(run);
+            write_role(
+                &run_dir,
+                "pub",
+                engine,
+                json!({"role": "pub", "payload": 1024, "rate": rate}),
+                json!({"sent_count": 100, "total_throughput": tps}),
+            );
+            write_role(
+                &run_dir,
+                "sub",
+                engine,
+                json!({"role": "sub", "subscribers": 2}),
+                json!({"received_count": 90, "error_count": 1, "total_throughput": tps,
+                       "latency_ns_p50": 1_000_000, "latency_ns_p95": 2_000_000, "latency_ns_p99": 3_500_000}),
+            );
+        }
+        let out = root.join("report");
+        let rows = run_report(&ReportConfig {
+            artifacts: root.clone(),
+            out_dir: out.clone(),
+        })
+        .unwrap();
+
+        assert_eq!(rows.len(), 3);
+        let r1 = rows.iter().find(|r| r.run_id == "r1").unwrap();
+        assert_eq!(
+            (r1.transport.as_str(), r1.payload, r1.rate),
+            ("zenoh", 1024, 1000)
+        );
+        assert_eq!(
+            (r1.sent, r1.recv, r1.errors, r1.subs),
+            (100, 90, 1, Some(2))
+        );
+        assert_eq!(r1.p99_ms, Some(3.5));
+
+        let csv = fs::read_to_string(out.join("summary.csv")).unwrap();
+        assert!(csv.starts_with(&SUMMARY_HEADER.join(",")));
+        assert!(
+            out.join("charts/throughput_vs_rate_payload1024.svg")
+                .is_file()
+        );
+        assert!(out.join("charts/p99_vs_rate_payload1024.svg").is_file());
+        let png = fs::read(out.join("charts/p99_vs_rate_payload1024.png")).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        let md = fs::read_to_string(out.join("report.md")).unwrap();
+        assert!(md.contains("## Payload 1024 B"));
+        assert!(md.contains("charts/throughput_vs_rate_payload1024.svg"));
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn receiver_percentiles_come_from_merged_histograms() {
+        let root = std::env::temp_dir().join(format!("mqb-report-{}", uuid::Uuid::new_v4()));
+        let run_dir = root.join("r1");
+        // One fast and one slow subscriber: averaging their p50s would give ~50 ms
+        for (instance, latency_ns) in [(1, 1_000_000u64), (2, 100_000_000)] {
+            let stats = crate::metrics::stats::Stats::new();
+            for _ in 0..100 {
+                stats.record_received(latency_ns).await;
+            }
+            let dir = run_dir.join(format!("sub-{}", instance));
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("manifest.json"), "{}").unwrap();
+            let summary = json!({
+                "role": "sub",
+                "engine": "zenoh",
+                "config": {"role": "sub", "subscribers": 1},
+                "final_stats": {"received_count": 100, "latency_ns_p50": latency_ns,
+                                "latency_ns_p99": latency_ns},
+                "latency_histogram": stats.latency_histogram_base64().await,
+            });
+            fs::write(dir.join("summary.json"), summary.to_string()).unwrap();
+        }
+        let rows = collect::collect_rows(&root).unwrap();
+        assert_eq!(rows.len(), 1);
+        let (p50, p99) = (rows[0].p50_ms.unwrap(), rows[0].p99_ms.unwrap());
+        assert!((p50 - 1.0).abs() < 0.01, "p50 {}", p50);
+        assert!((p99 - 100.0).abs() < 0.1, "p99 {}", p99);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn result_sets_read_role_summaries_with_histograms() {
+        let root = std::env::temp_dir().join(format!("mqb-report-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        // Reported percentiles are stale; the histogram is what counts
+        let stats = crate::metrics::stats::Stats::new();
+        for i in 1..=100u64 {
+            stats.record_received(i * 1_000_000).await;
+        }
+        let lines = [
+            json!({"run_id": "r1", "role": "pub", "engine": "zenoh",
+                   "config": {"payload": 512, "rate": 100}, "final_stats": {"sent_count": 100}}),
+            json!({"run_id": "r1", "role": "sub", "engine": "zenoh", "config": {"subscribers": 1},
+                   "final_stats": {"received_count": 100, "latency_ns_p99": 1},
+                   "latency_histogram": stats.latency_histogram_base64().await}),
+        ];
+        let path = root.join("summaries.jsonl");
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        fs::write(&path, text.join("\n")).unwrap();
+
+        let rows = load_result_set(&path).unwrap();
+        assert_eq!(rows.len(), 1);
+        let r = &rows[0];
+        assert_eq!((r.run_id.as_str(), r.payload, r.rate), ("r1", 512, 100));
+        assert_eq!((r.sent, r.recv, r.subs), (100, 100, Some(1)));
+        let p99 = r.p99_ms.unwrap();
+        assert!((p99 - 99.0).abs() < 0.1, "p99 {}", p99);
+
+        // A single summary.json document is a result set too
+        let single = root.join("summary.json");
+        fs::write(&single, lines[1].to_string()).unwrap();
+        assert_eq!(load_result_set(&single).unwrap().len(), 1);
+
+        let hlog = root.join("latency.hlog");
+        fs::write(&hlog, "").unwrap();
+        let err = load_result_set(&hlog).unwrap_err().to_string();
+        assert!(err.contains("summary.json"), "{}", err);
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn legacy_orchestrator_dirs_are_read_by_header() {
+        let root = std::env::temp_dir().join(format!("mqb-report-{}", uuid::Uuid::new_v4()));
+        let art = root.join("bench_20250910_034853_fanout_zenoh_s4_p256_r100/artifacts");
+        fs::create_dir_all(&art).unwrap();
+        fs::write(
+            art.join("sub_agg.csv"),
+            "timestamp,sent_count,received_count,error_count,total_throughput,interval_throughput,latency_ns_p50,latency_ns_p95,latency_ns_p99\n\
+             1,0,100,0,100.0,100.0,500000,900000,1000000\n\
+             2,0,400,0,398.5,300.0,500000,900000,2000000\n",
+        )
+        .unwrap();
+        let rows = collect::collect_rows(&root).unwrap();
+        assert_eq!(rows.len(), 1);
+        let r = &rows[0];
+        assert_eq!(r.transport, "fanout_zenoh");
+        assert_eq!((r.subs, r.payload, r.rate), (Some(4), 256, 100));
+        assert_eq!((r.recv, r.sub_tps, r.p99_ms), (400, 398.5, Some(2.0)));
+        let _ = fs::remove_dir_all(root);
+    }
+}
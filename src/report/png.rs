@@ -0,0 +1,33 @@
+//! PNG rasterisation of the report's SVG charts (resvg; labels use system fonts).
+
+use anyhow::{Result, anyhow};
+use resvg::{tiny_skia, usvg};
+
+/// Pixels per SVG unit, so the PNGs stay sharp in slides and docs
+const SCALE: f32 = 2.0;
+
+/// Parse options with the system fonts loaded; build once per report
+pub fn options() -> usvg::Options<'static> {
+    let mut opts = usvg::Options::default();
+    opts.fontdb_mut().load_system_fonts();
+    opts
+}
+
+/// Render an SVG document to PNG bytes on a white background
+pub fn render(svg: &str, opts: &usvg::Options) -> Result<Vec<u8>> {
+    let tree = usvg::Tree::from_str(svg, opts)?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(SCALE)
+        .ok_or_else(|| anyhow!("empty chart"))?;
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| anyhow!("chart too large to rasterise"))?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(SCALE, SCALE),
+        &mut pixmap.as_mut(),
+    );
+    Ok(pixmap.encode_png()?)
+}
@@ -0,0 +1,207 @@
+//! Minimal SVG line charts for the report (no plotting dependency).
+
+use std::fmt::Write as _;
+
+const WIDTH: f64 = 720.0;
+const HEIGHT: f64 = 440.0;
+const MARGIN_LEFT: f64 = 80.0;
+const MARGIN_RIGHT: f64 = 150.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 60.0;
+const TICKS: usize = 5;
+const COLORS: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub name: String,
+    /// (x, y) sorted by x
+    pub points: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chart {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub series: Vec<Series>,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Compact tick label: 1500 -> "1.5k", 0.25 -> "0.25"
+fn tick_label(v: f64) -> String {
+    let a = v.abs();
+    let (v, suffix) = if a >= 1e9 {
+        (v / 1e9, "G")
+    } else if a >= 1e6 {
+        (v / 1e6, "M")
+    } else if a >= 1e3 {
+        (v / 1e3, "k")
+    } else {
+        (v, "")
+    };
+    let s = format!("{:.2}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", s, suffix)
+}
+
+/// Axis range padded so a single point or flat line still gets a visible span
+fn range(values: impl Iterator<Item = f64>, from_zero: bool) -> (f64, f64) {
+    let (mut lo, mut hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+        (lo.min(v), hi.max(v))
+    });
+    if !lo.is_finite() {
+        return (0.0, 1.0);
+    }
+    if from_zero {
+        lo = lo.min(0.0);
+    }
+    if (hi - lo).abs() < f64::EPSILON {
+        hi = lo + lo.abs().max(1.0);
+    }
+    (lo, hi)
+}
+
+impl Chart {
+    pub fn render(&self) -> String {
+        let points = || self.series.iter().flat_map(|s| s.points.iter());
+        let (x0, x1) = range(points().map(|p| p.0), false);
+        let (y0, y1) = range(points().map(|p| p.1), true);
+        let plot_w = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+        let plot_h = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+        let sx = |x: f64| MARGIN_LEFT + (x - x0) / (x1 - x0) * plot_w;
+        let sy = |y: f64| MARGIN_TOP + plot_h - (y - y0) / (y1 - y0) * plot_h;
+
+        let mut out = String::with_capacity(4096);
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+            w = WIDTH,
+            h = HEIGHT
+        );
+        let _ = writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#);
+        let _ = writeln!(
+            out,
+            r#"<text x="{}" y="24" text-anchor="middle" font-size="15">{}</text>"#,
+            MARGIN_LEFT + plot_w / 2.0,
+            escape(&self.title)
+        );
+
+        // Grid and tick labels
+        for i in 0..=TICKS {
+            let f = i as f64 / TICKS as f64;
+            let (xv, yv) = (x0 + f * (x1 - x0), y0 + f * (y1 - y0));
+            let (x, y) = (sx(xv), sy(yv));
+            let _ = writeln!(
+                out,
+                r##"<line x1="{l}" y1="{y:.1}" x2="{r}" y2="{y:.1}" stroke="#e0e0e0"/><text x="{tx}" y="{ty:.1}" text-anchor="end">{label}</text>"##,
+                l = MARGIN_LEFT,
+                r = MARGIN_LEFT + plot_w,
+                y = y,
+                tx = MARGIN_LEFT - 6.0,
+                ty = y + 4.0,
+                label = tick_label(yv)
+            );
+            let _ = writeln!(
+                out,
+                r##"<line x1="{x:.1}" y1="{t}" x2="{x:.1}" y2="{b}" stroke="#e0e0e0"/><text x="{x:.1}" y="{ty}" text-anchor="middle">{label}</text>"##,
+                x = x,
+                t = MARGIN_TOP,
+                b = MARGIN_TOP + plot_h,
+                ty = MARGIN_TOP + plot_h + 18.0,
+                label = tick_label(xv)
+            );
+        }
+        let _ = writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="black"/>"#,
+            MARGIN_LEFT, MARGIN_TOP, plot_w, plot_h
+        );
+        let _ = writeln!(
+            out,
+            r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            MARGIN_LEFT + plot_w / 2.0,
+            HEIGHT - 16.0,
+            escape(&self.x_label)
+        );
+        let _ = writeln!(
+            out,
+            r#"<text transform="translate(18,{}) rotate(-90)" text-anchor="middle">{}</text>"#,
+            MARGIN_TOP + plot_h / 2.0,
+            escape(&self.y_label)
+        );
+
+        // Series with markers, legend on the right
+        for (i, s) in self.series.iter().enumerate() {
+            let color = COLORS[i % COLORS.len()];
+            let path: Vec<String> = s
+                .points
+                .iter()
+                .map(|&(x, y)| format!("{:.1},{:.1}", sx(x), sy(y)))
+                .collect();
+            let _ = writeln!(
+                out,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+                path.join(" "),
+                color
+            );
+            for &(x, y) in &s.points {
+                let _ = writeln!(
+                    out,
+                    r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="{}"/>"#,
+                    sx(x),
+                    sy(y),
+                    color
+                );
+            }
+            let ly = MARGIN_TOP + 10.0 + i as f64 * 18.0;
+            let lx = MARGIN_LEFT + plot_w + 14.0;
+            let _ = writeln!(
+                out,
+                r#"<line x1="{}" y1="{ly}" x2="{}" y2="{ly}" stroke="{}" stroke-width="2"/><text x="{}" y="{}">{}</text>"#,
+                lx,
+                lx + 18.0,
+                color,
+                lx + 24.0,
+                ly + 4.0,
+                escape(&s.name),
+                ly = ly
+            );
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_series_and_escapes_text() {
+        let chart = Chart {
+            title: "a < b".into(),
+            x_label: "x".into(),
+            y_label: "y".into(),
+            series: vec![Series {
+                name: "zenoh".into(),
+                points: vec![(100.0, 10.0), (1000.0, 1500.0)],
+            }],
+        };
+        let svg = chart.render();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("a &lt; b"));
+        assert_eq!(svg.matches("<polyline").count(), 1);
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert_eq!(tick_label(1500.0), "1.5k");
+        assert_eq!(tick_label(0.25), "0.25");
+    }
+}
@@ -0,0 +1,547 @@
+//! SQLite results store (feature `sqlite`).
+//!
+//! `--results-db` records every role invocation into one database next to (or
+//! instead of) the artifact directories: `runs` holds the manifest and config
+//! columns, `snapshots` one row per periodic snapshot and `summaries` the final
+//! stats. Snapshot and summary columns are the CSV columns, so they grow the same
+//! append-only way; missing columns are added when an older database is opened.
+//! `run_results` aggregates the roles of a run like `report` does, and backs the
+//! `mq-bench query` views.
+
+use crate::manifest::RunManifest;
+use crate::metrics::stats::StatsSnapshot;
+use crate::output::{RunSummary, SnapshotRecord};
+use crate::report::collect::{self, subscriptions};
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, params, params_from_iter};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+
+const TABLES: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    instance TEXT NOT NULL,
+    engine TEXT NOT NULL,
+    topic TEXT,
+    payload INTEGER,
+    rate INTEGER,
+    subs INTEGER,
+    mq_bench_version TEXT,
+    git_describe TEXT,
+    hostname TEXT,
+    kernel TEXT,
+    cpu_model TEXT,
+    cpu_count INTEGER,
+    tokio_workers INTEGER,
+    argv TEXT,
+    config TEXT,
+    connect TEXT,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    UNIQUE (run_id, role, instance)
+);
+CREATE TABLE IF NOT EXISTS snapshots (
+    run INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    PRIMARY KEY (run, seq)
+);
+CREATE TABLE IF NOT EXISTS summaries (
+    run INTEGER PRIMARY KEY REFERENCES runs(id) ON DELETE CASCADE,
+    final_stats TEXT NOT NULL
+);
+";
+
+/// Recreated on every open so an older database picks up view changes.
+/// Role lists come from the report collector so both classify runs alike.
+fn views_sql() -> String {
+    let roles = |list: &[&str]| {
+        list.iter()
+            .map(|r| format!("'{}'", r))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let senders = roles(collect::SENDER_ROLES);
+    let receivers = roles(collect::RECEIVER_ROLES);
+    let subscribers = roles(collect::SUBSCRIBER_ROLES);
+    let weighted = |col: &str, name: &str| {
+        format!(
+            "SUM(CASE WHEN r.role IN ({receivers}) THEN s.{col} * s.received_count END)
+        / NULLIF(SUM(CASE WHEN r.role IN ({receivers}) THEN s.received_count END), 0) / 1e6 AS {name}"
+        )
+    };
+    format!(
+        "
+DROP VIEW IF EXISTS run_results;
+CREATE VIEW run_results AS
+SELECT r.run_id AS run_id,
+    MAX(r.engine) AS engine,
+    MAX(CASE WHEN r.role IN ({senders}) THEN r.payload END) AS payload,
+    MAX(CASE WHEN r.role IN ({senders}) THEN r.rate END) AS rate,
+    SUM(CASE WHEN r.role IN ({subscribers}) THEN r.subs END) AS subs,
+    SUM(CASE WHEN r.role IN ({receivers}) THEN s.total_throughput END) AS sub_tps,
+    SUM(CASE WHEN r.role IN ({senders}) THEN s.total_throughput END) AS pub_tps,
+    {p50},
+    {p95},
+    {p99},
+    SUM(s.error_count) AS errors,
+    MIN(r.started_at) AS started_at
+FROM runs r JOIN summaries s ON s.run = r.id
+GROUP BY r.run_id;
+",
+        p50 = weighted("latency_ns_p50", "p50_ms"),
+        p95 = weighted("latency_ns_p95", "p95_ms"),
+        p99 = weighted("latency_ns_p99", "p99_ms"),
+    )
+}
+
+/// Canned views for `mq-bench query`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueryView {
+    /// One row per run (`run_results`)
+    Runs,
+    /// Engine with the highest receive throughput for each payload size
+    BestEngine,
+    /// p99 latency and throughput per engine, payload and offered rate
+    P99VsRate,
+}
+
+pub fn parse_query_view(s: &str) -> Option<QueryView> {
+    match s.to_lowercase().as_str() {
+        "runs" => Some(QueryView::Runs),
+        "best-engine" => Some(QueryView::BestEngine),
+        "p99-vs-rate" => Some(QueryView::P99VsRate),
+        _ => None,
+    }
+}
+
+impl QueryView {
+    fn sql(self) -> &'static str {
+        match self {
+            QueryView::Runs => {
+                "SELECT run_id, engine, payload, rate, subs, ROUND(sub_tps, 2) AS sub_tps, \
+                 ROUND(pub_tps, 2) AS pub_tps, ROUND(p50_ms, 3) AS p50_ms, ROUND(p99_ms, 3) AS p99_ms, errors \
+                 FROM run_results ORDER BY started_at"
+            }
+            QueryView::BestEngine => {
+                "SELECT payload, engine, runs, ROUND(max_sub_tps, 2) AS max_sub_tps, ROUND(min_p99_ms, 3) AS min_p99_ms FROM ( \
+                 SELECT payload, engine, COUNT(*) AS runs, MAX(sub_tps) AS max_sub_tps, MIN(p99_ms) AS min_p99_ms, \
+                 ROW_NUMBER() OVER (PARTITION BY payload ORDER BY MAX(sub_tps) DESC) AS rank \
+                 FROM run_results WHERE sub_tps IS NOT NULL GROUP BY payload, engine) \
+                 WHERE rank = 1 ORDER BY payload"
+            }
+            QueryView::P99VsRate => {
+                "SELECT engine, payload, rate, COUNT(*) AS runs, ROUND(AVG(p99_ms), 3) AS p99_ms, \
+                 ROUND(AVG(sub_tps), 2) AS sub_tps \
+                 FROM run_results WHERE p99_ms IS NOT NULL GROUP BY engine, payload, rate \
+                 ORDER BY engine, payload, rate"
+            }
+        }
+    }
+}
+
+/// Optional filters applied to a canned view
+#[derive(Debug, Clone, Default)]
+pub struct QueryFilter {
+    pub engine: Option<String>,
+    pub payload: Option<u64>,
+}
+
+/// Result of a query, every cell rendered as text (NULL as empty)
+#[derive(Debug, Clone, Default)]
+pub struct QueryTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl QueryTable {
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "| {} |", self.columns.join(" | "));
+        let _ = writeln!(out, "|{}", "---|".repeat(self.columns.len()));
+        for row in &self.rows {
+            let _ = writeln!(out, "| {} |", row.join(" | "));
+        }
+        out
+    }
+
+    pub fn to_csv(&self) -> Result<String> {
+        let mut w = csv::Writer::from_writer(Vec::new());
+        w.write_record(&self.columns)?;
+        for row in &self.rows {
+            w.write_record(row)?;
+        }
+        Ok(String::from_utf8(w.into_inner()?)?)
+    }
+}
+
+/// Snapshot/summary metric columns: the CSV columns, in CSV order
+fn metric_columns() -> impl Iterator<Item = &'static str> {
+    StatsSnapshot::csv_header().split(',')
+}
+
+fn sql_value(v: &serde_json::Value) -> SqlValue {
+    match v {
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => n.as_f64().map(SqlValue::Real).unwrap_or(SqlValue::Null),
+        },
+        serde_json::Value::String(s) => SqlValue::Text(s.clone()),
+        serde_json::Value::Bool(b) => SqlValue::Integer(*b as i64),
+        _ => SqlValue::Null,
+    }
+}
+
+fn text(v: ValueRef<'_>) -> String {
+    match v {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) | ValueRef::Blob(t) => String::from_utf8_lossy(t).into_owned(),
+    }
+}
+
+pub struct ResultsDb {
+    conn: Connection,
+}
+
+impl ResultsDb {
+    /// Open (or create) the database and bring its schema up to date.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        // Roles of one run usually write the same file from separate processes
+        conn.busy_timeout(Duration::from_secs(10))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let db = Self { conn };
+        db.conn.execute_batch(TABLES)?;
+        for table in ["snapshots", "summaries"] {
+            db.add_missing_columns(table)?;
+        }
+        db.conn.execute_batch(&views_sql())?;
+        Ok(db)
+    }
+
+    fn add_missing_columns(&self, table: &str) -> Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?;
+        let existing: Vec<String> = stmt
+            .query_map([], |r| r.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        for col in metric_columns().filter(|c| !existing.iter().any(|e| e == c)) {
+            self.conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN \"{}\" NUMERIC", table, col),
+                [],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Register a role invocation; a rerun of the same run/role/instance replaces its rows.
+    pub fn begin_run(
+        &self,
+        manifest: &RunManifest,
+        topic: &str,
+        connect: &BTreeMap<String, String>,
+    ) -> Result<i64> {
+        self.conn.execute(
+            "DELETE FROM runs WHERE run_id = ?1 AND role = ?2 AND instance = ?3",
+            params![manifest.run_id, manifest.role, manifest.instance],
+        )?;
+        let config = &manifest.config;
+        let is_sender = collect::SENDER_ROLES.contains(&manifest.role.as_str());
+        let payload = config["payload"].as_u64().filter(|_| is_sender);
+        let rate = config["rate"]
+            .as_i64()
+            .or_else(|| config["qps"].as_i64())
+            .filter(|_| is_sender);
+        let subs = collect::SUBSCRIBER_ROLES
+            .contains(&manifest.role.as_str())
+            .then(|| subscriptions(config) as i64);
+        self.conn.execute(
+            "INSERT INTO runs (run_id, role, instance, engine, topic, payload, rate, subs, \
+             mq_bench_version, git_describe, hostname, kernel, cpu_model, cpu_count, tokio_workers, \
+             argv, config, connect, started_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+            params![
+                manifest.run_id,
+                manifest.role,
+                manifest.instance,
+                manifest.engine,
+                topic,
+                payload.map(|p| p as i64),
+                rate,
+                subs,
+                manifest.mq_bench_version,
+                manifest.git_describe,
+                manifest.host.hostname,
+                manifest.host.kernel,
+                manifest.host.cpu_model,
+                manifest.host.cpu_count as i64,
+                manifest.tokio_workers.map(|w| w as i64),
+                serde_json::to_string(&manifest.argv)?,
+                serde_json::to_string(config)?,
+                serde_json::to_string(connect)?,
+                manifest.started_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn insert_metrics(
+        &self,
+        table: &str,
+        keys: &[(&str, SqlValue)],
+        record: &serde_json::Value,
+    ) -> Result<()> {
+        let names: Vec<String> = keys
+            .iter()
+            .map(|(k, _)| k.to_string())
+            .chain(metric_columns().map(|c| format!("\"{}\"", c)))
+            .collect();
+        let values: Vec<SqlValue> = keys
+            .iter()
+            .map(|(_, v)| v.clone())
+            .chain(metric_columns().map(|c| sql_value(&record[c])))
+            .collect();
+        let placeholders = vec!["?"; names.len()].join(", ");
+        self.conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+                table,
+                names.join(", "),
+                placeholders
+            ),
+            params_from_iter(values),
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_snapshot(&self, run: i64, seq: u64, snap: &StatsSnapshot) -> Result<()> {
+        let record = serde_json::to_value(SnapshotRecord::from(snap))?;
+        self.insert_metrics(
+            "snapshots",
+            &[
+                ("run", SqlValue::Integer(run)),
+                ("seq", SqlValue::Integer(seq as i64)),
+            ],
+            &record,
+        )
+    }
+
+    /// Store the final stats and close the run.
+    pub fn finish_run(
+        &self,
+        run: i64,
+        summary: &RunSummary,
+        ended_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let record = serde_json::to_value(&summary.final_stats)?;
+        self.insert_metrics(
+            "summaries",
+            &[
+                ("run", SqlValue::Integer(run)),
+                ("final_stats", SqlValue::Text(record.to_string())),
+            ],
+            &record,
+        )?;
+        self.conn.execute(
+            "UPDATE runs SET ended_at = ?1 WHERE id = ?2",
+            params![ended_at.to_rfc3339(), run],
+        )?;
+        Ok(())
+    }
+
+    /// Run arbitrary SQL and return its rows as text.
+    pub fn query(&self, sql: &str) -> Result<QueryTable> {
+        self.query_with(sql, &[])
+    }
+
+    /// Run a canned view, optionally narrowed to one engine and/or payload size.
+    pub fn query_view(&self, view: QueryView, filter: &QueryFilter) -> Result<QueryTable> {
+        let sql = format!(
+            "SELECT * FROM ({}) WHERE (?1 IS NULL OR engine = ?1) AND (?2 IS NULL OR payload = ?2)",
+            view.sql()
+        );
+        self.query_with(
+            &sql,
+            &[
+                filter
+                    .engine
+                    .clone()
+                    .map(SqlValue::Text)
+                    .unwrap_or(SqlValue::Null),
+                filter
+                    .payload
+                    .map(|p| SqlValue::Integer(p as i64))
+                    .unwrap_or(SqlValue::Null),
+            ],
+        )
+    }
+
+    fn query_with(&self, sql: &str, args: &[SqlValue]) -> Result<QueryTable> {
+        let mut stmt = self.conn.prepare(sql)?;
+        if stmt.column_count() == 0 {
+            bail!("query returns no columns");
+        }
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let n = columns.len();
+        let rows = stmt
+            .query_map(params_from_iter(args), |row| {
+                (0..n)
+                    .map(|i| row.get_ref(i).map(text))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(QueryTable { columns, rows })
+    }
+}
+
+/// One role invocation's rows: registered on start, a row per snapshot, summary on finish
+pub struct RunRecorder {
+    db: ResultsDb,
+    run: i64,
+    seq: u64,
+}
+
+impl RunRecorder {
+    pub fn begin(
+        path: &Path,
+        manifest: &RunManifest,
+        topic: &str,
+        connect: &BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let db = ResultsDb::open(path)?;
+        let run = db.begin_run(manifest, topic, connect)?;
+        Ok(Self { db, run, seq: 0 })
+    }
+
+    pub fn snapshot(&mut self, snap: &StatsSnapshot) -> Result<()> {
+        self.seq += 1;
+        self.db.insert_snapshot(self.run, self.seq, snap)
+    }
+
+    pub fn finish(&self, summary: &RunSummary, ended_at: DateTime<Utc>) -> Result<()> {
+        self.db.finish_run(self.run, summary, ended_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::stats::Stats;
+
+    async fn record(
+        db: &ResultsDb,
+        run_id: &str,
+        role: &str,
+        engine: &str,
+        config: serde_json::Value,
+        latency_ns: u64,
+        count: usize,
+    ) {
+        let manifest = RunManifest::new(run_id, role, "1", engine, vec![], config.clone());
+        let run = db
+            .begin_run(&manifest, "bench/topic", &BTreeMap::new())
+            .unwrap();
+        let stats = Stats::new();
+        for _ in 0..count {
+            stats.record_sent().await;
+            stats.record_received(latency_ns).await;
+        }
+        let snap = stats.snapshot().await;
+        db.insert_snapshot(run, 1, &snap).unwrap();
+        let summary = RunSummary {
+            run_id: run_id.into(),
+            role: role.into(),
+            engine: engine.into(),
+            connect: BTreeMap::new(),
+            config,
+            final_stats: SnapshotRecord::from(&snap),
+            latency_histogram: None,
+        };
+        db.finish_run(run, &summary, Utc::now()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn records_runs_and_answers_views() {
+        let db = ResultsDb::open_in_memory().unwrap();
+        let pub_cfg = |rate: i64| serde_json::json!({"role": "pub", "payload": 1024, "rate": rate});
+        let sub_cfg = serde_json::json!({"role": "sub", "subscribers": 2});
+        for (run, engine, rate, lat) in [
+            ("r1", "zenoh", 100, 1_000_000),
+            ("r2", "zenoh", 1000, 3_000_000),
+            ("r3", "mqtt", 100, 5_000_000),
+        ] {
+            record(&db, run, "pub", engine, pub_cfg(rate), lat, 10).await;
+            record(&db, run, "sub", engine, sub_cfg.clone(), lat, 10).await;
+        }
+        // Rerunning a role replaces its rows instead of duplicating them
+        record(&db, "r3", "sub", "mqtt", sub_cfg.clone(), 5_000_000, 10).await;
+        // mt-req both sends and receives, like req
+        let mt_req_cfg = serde_json::json!({"role": "mt-req", "payload": 64, "qps": 50});
+        record(&db, "r4", "mt-req", "nats", mt_req_cfg, 2_000_000, 10).await;
+
+        let runs = db
+            .query_view(QueryView::Runs, &QueryFilter::default())
+            .unwrap();
+        assert_eq!(runs.rows.len(), 4);
+        let col = |t: &QueryTable, name: &str| t.columns.iter().position(|c| c == name).unwrap();
+        assert_eq!(runs.rows[0][col(&runs, "payload")], "1024");
+        assert_eq!(runs.rows[0][col(&runs, "subs")], "2");
+        let mt_req = &runs.rows[3];
+        assert_eq!(mt_req[col(&runs, "payload")], "64");
+        assert_eq!(mt_req[col(&runs, "rate")], "50");
+        assert_eq!(mt_req[col(&runs, "subs")], "");
+        let p: f64 = mt_req[col(&runs, "p99_ms")].parse().unwrap();
+        assert!((p - 2.0).abs() < 0.1, "mt-req p99 {}", p);
+
+        let p99 = db
+            .query_view(
+                QueryView::P99VsRate,
+                &QueryFilter {
+                    engine: Some("zenoh".into()),
+                    payload: None,
+                },
+            )
+            .unwrap();
+        let rates: Vec<&str> = p99
+            .rows
+            .iter()
+            .map(|r| r[col(&p99, "rate")].as_str())
+            .collect();
+        assert_eq!(rates, ["100", "1000"]);
+        let p: f64 = p99.rows[1][col(&p99, "p99_ms")].parse().unwrap();
+        assert!((p - 3.0).abs() < 0.1, "p99 {}", p);
+
+        let best = db
+            .query_view(QueryView::BestEngine, &QueryFilter::default())
+            .unwrap();
+        assert_eq!(best.rows.len(), 2);
+
+        let counts = db
+            .query("SELECT (SELECT COUNT(*) FROM snapshots), (SELECT COUNT(*) FROM summaries)")
+            .unwrap();
+        assert_eq!(counts.rows[0], ["7", "7"]);
+        assert!(counts.to_markdown().starts_with("| "));
+    }
+}
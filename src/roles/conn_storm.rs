@@ -0,0 +1,466 @@
+//! Connection storm.
+//!
+//! Opens many independent transports at a target rate (or as fast as the concurrency
+//! limit allows), optionally subscribes on each, holds them, then closes them all.
+//! An attempt is the transport connect plus `connect_probe`, which opens and holds a
+//! broker connection of its own for adapters that otherwise connect per operation,
+//! so each attempt is a real connection to the broker. Every attempt is timed and
+//! classified, so the result shows how fast a broker accepts
+//! connections and at what open-connection count connect latency degrades.
+//! Attempts do not retry: a refused or timed-out connect is a data point.
+
+use crate::metrics::stats::Stats;
+use crate::output::OutputWriter;
+use crate::rate::ArrivalConfig;
+use crate::transport::{
+    ConnectOptions, Engine, ProbeConnection, Subscription, Transport, TransportBuilder,
+};
+use anyhow::{Result, bail};
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::signal;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{Instant, interval, sleep, timeout};
+use tracing::{debug, info, warn};
+
+/// Error messages longer than this are cut before grouping failure reasons
+const MAX_REASON_LEN: usize = 120;
+
+pub struct ConnStormConfig {
+    pub engine: Engine,
+    pub connect: ConnectOptions,
+    /// Transports to open
+    pub connections: u64,
+    /// Connect attempts started per second (None: as fast as `concurrency` allows)
+    pub rate: Option<f64>,
+    /// Connect attempts in flight at once
+    pub concurrency: usize,
+    /// Subscribe to `{prefix}/{i}` on connection `i` once it is up
+    pub subscribe_prefix: Option<String>,
+    /// How long to keep all connections open after the last attempt
+    pub hold_secs: u64,
+    /// Give up on a connect (or subscribe) after this long
+    pub connect_timeout: Duration,
+    /// Latency counts as degraded once a block's median exceeds this multiple of the first block's
+    pub degrade_factor: f64,
+    /// Write `connects.csv` and `conn_storm.json` here
+    pub output_dir: Option<PathBuf>,
+    pub output_file: Option<String>,
+    pub snapshot_interval_secs: u64,
+    // Aggregation support
+    pub shared_stats: Option<Arc<Stats>>, // when set, use this shared collector
+    pub disable_internal_snapshot: bool,  // when true, do not launch internal snapshot logger
+}
+
+/// One connect attempt, in start order
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnAttempt {
+    pub index: u64,
+    /// Attempt start, from the start of the storm
+    pub start_ms: f64,
+    /// Connections open when the attempt started
+    pub open_before: u64,
+    pub connect_ms: Option<f64>,
+    pub subscribe_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnStormSummary {
+    pub attempts: u64,
+    pub connected: u64,
+    pub failed: u64,
+    /// Failure reason -> attempts
+    pub failures: BTreeMap<String, u64>,
+    /// Time from the first attempt start to the last attempt end
+    pub storm_secs: f64,
+    /// Successful connects per second over `storm_secs`
+    pub connect_rate: f64,
+    pub connect_p50_ms: f64,
+    pub connect_p90_ms: f64,
+    pub connect_p99_ms: f64,
+    pub connect_max_ms: f64,
+    /// Median connect latency of the first block of successful attempts
+    pub baseline_p50_ms: f64,
+    /// Open-connection count when latency first degraded (None: it never did)
+    pub degraded_at: Option<u64>,
+}
+
+/// An open transport, the probe connection it holds and its subscription
+type Held = (
+    Box<dyn Transport>,
+    Option<Box<dyn ProbeConnection>>,
+    Option<Box<dyn Subscription>>,
+);
+
+/// Group errors by message (trimmed), so identical failures are counted together
+fn failure_reason(e: &dyn std::fmt::Display) -> String {
+    let mut s = e.to_string();
+    if s.len() > MAX_REASON_LEN {
+        let mut cut = MAX_REASON_LEN;
+        while !s.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        s.truncate(cut);
+    }
+    s
+}
+
+/// Split successful attempts (in start order) into blocks of `block` and return the first
+/// block's median latency plus the `open_before` of the first block whose median exceeds
+/// `factor` times it
+pub fn degradation_point(samples: &[(u64, f64)], block: usize, factor: f64) -> (f64, Option<u64>) {
+    let block = block.max(1);
+    let median = |chunk: &[(u64, f64)]| {
+        let mut v: Vec<f64> = chunk.iter().map(|s| s.1).collect();
+        v.sort_by(f64::total_cmp);
+        v[v.len() / 2]
+    };
+    let mut chunks = samples.chunks(block).filter(|c| c.len() == block);
+    let Some(first) = chunks.next() else {
+        return (0.0, None);
+    };
+    let baseline = median(first);
+    let degraded = chunks
+        .find(|c| median(c) > baseline * factor)
+        .map(|c| c[0].0);
+    (baseline, degraded)
+}
+
+fn summarize(attempts: &[ConnAttempt], storm: Duration, degrade_factor: f64) -> ConnStormSummary {
+    let mut hist = Histogram::<u64>::new_with_bounds(1, 600_000_000_000, 3).unwrap();
+    let mut failures = BTreeMap::new();
+    let mut ok = Vec::new();
+    for a in attempts {
+        match (&a.error, a.connect_ms) {
+            (Some(reason), _) => *failures.entry(reason.clone()).or_insert(0) += 1,
+            (None, Some(ms)) => {
+                let _ = hist.record(((ms * 1e6) as u64).max(1));
+                ok.push((a.open_before, ms));
+            }
+            (None, None) => {}
+        }
+    }
+    let ms = |ns: u64| ns as f64 / 1e6;
+    let (baseline, degraded_at) = degradation_point(&ok, (ok.len() / 20).max(10), degrade_factor);
+    let connected = ok.len() as u64;
+    ConnStormSummary {
+        attempts: attempts.len() as u64,
+        connected,
+        failed: attempts.len() as u64 - connected,
+        failures,
+        storm_secs: storm.as_secs_f64(),
+        connect_rate: connected as f64 / storm.as_secs_f64().max(1e-9),
+        connect_p50_ms: ms(hist.value_at_quantile(0.50)),
+        connect_p90_ms: ms(hist.value_at_quantile(0.90)),
+        connect_p99_ms: ms(hist.value_at_quantile(0.99)),
+        connect_max_ms: ms(hist.max()),
+        baseline_p50_ms: baseline,
+        degraded_at,
+    }
+}
+
+fn write_results(
+    dir: &std::path::Path,
+    attempts: &[ConnAttempt],
+    summary: &ConnStormSummary,
+) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut w = csv::Writer::from_path(dir.join("connects.csv"))?;
+    for a in attempts {
+        w.serialize(a)?;
+    }
+    w.flush()?;
+    std::fs::write(
+        dir.join("conn_storm.json"),
+        serde_json::to_string_pretty(summary)?,
+    )?;
+    Ok(())
+}
+
+/// The parts of the config each attempt task needs
+struct ConnStormParams {
+    engine: Engine,
+    connect: ConnectOptions,
+    subscribe_prefix: Option<String>,
+    connect_timeout: Duration,
+}
+
+async fn open_one(
+    config: &ConnStormParams,
+    index: u64,
+    stats: &Stats,
+) -> (Option<f64>, Option<f64>, Result<Held, String>) {
+    stats.record_connection_attempt();
+    let t0 = Instant::now();
+    // Several adapters connect lazily; the probe opens a real broker connection and
+    // keeps it for the hold, so every attempt times network work
+    let connected = async {
+        let transport =
+            TransportBuilder::connect(config.engine.clone(), config.connect.clone()).await?;
+        match transport.connect_probe().await {
+            Ok(probe) => Ok((transport, probe)),
+            Err(e) => {
+                let _ = transport.shutdown().await;
+                Err(e)
+            }
+        }
+    };
+    let (transport, probe) = match timeout(config.connect_timeout, connected).await {
+        Ok(Ok(t)) => t,
+        Ok(Err(e)) => return (None, None, Err(failure_reason(&e))),
+        Err(_) => return (None, None, Err("connect timeout".into())),
+    };
+    let connect_ms = t0.elapsed().as_secs_f64() * 1e3;
+    let Some(prefix) = &config.subscribe_prefix else {
+        return (Some(connect_ms), None, Ok((transport, probe, None)));
+    };
+    let key = format!("{}/{}", prefix, index);
+    let t1 = Instant::now();
+    let sub = match timeout(
+        config.connect_timeout,
+        transport.subscribe(&key, Box::new(|_| {})),
+    )
+    .await
+    {
+        Ok(Ok(s)) => Ok(s),
+        Ok(Err(e)) => Err(failure_reason(&e)),
+        Err(_) => Err("subscribe timeout".into()),
+    };
+    let sub = match sub {
+        Ok(s) => s,
+        Err(reason) => {
+            if let Some(probe) = probe {
+                let _ = probe.shutdown().await;
+            }
+            let _ = transport.shutdown().await;
+            return (Some(connect_ms), None, Err(reason));
+        }
+    };
+    let subscribe_ms = t1.elapsed().as_secs_f64() * 1e3;
+    (
+        Some(connect_ms),
+        Some(subscribe_ms),
+        Ok((transport, probe, Some(sub))),
+    )
+}
+
+pub async fn run_conn_storm(config: ConnStormConfig) -> Result<ConnStormSummary> {
+    if config.concurrency == 0 {
+        bail!("concurrency must be at least 1");
+    }
+    info!(
+        engine = ?config.engine,
+        connections = config.connections,
+        rate = ?config.rate,
+        concurrency = config.concurrency,
+        subscribe = ?config.subscribe_prefix,
+        hold_secs = config.hold_secs,
+        "Starting connection storm"
+    );
+
+    let stats = if let Some(s) = &config.shared_stats {
+        s.clone()
+    } else {
+        Arc::new(Stats::new())
+    };
+
+    let mut output = if let Some(ref path) = config.output_file {
+        Some(OutputWriter::new_csv(path.clone()).await?)
+    } else if config.shared_stats.is_none() {
+        Some(OutputWriter::new_stdout())
+    } else {
+        None
+    };
+
+    let snapshot_handle = if !config.disable_internal_snapshot {
+        let stats_clone = Arc::clone(&stats);
+        let interval_secs = config.snapshot_interval_secs;
+        Some(tokio::spawn(async move {
+            let mut interval_timer = interval(Duration::from_secs(interval_secs));
+            loop {
+                interval_timer.tick().await;
+                let snap = stats_clone.snapshot().await;
+                debug!(
+                    connections = snap.connections,
+                    attempts = snap.connection_attempts,
+                    failures = snap.connection_failures,
+                    "Connection storm stats"
+                );
+            }
+        }))
+    } else {
+        None
+    };
+
+    let params = Arc::new(ConnStormParams {
+        engine: config.engine.clone(),
+        connect: config.connect.clone(),
+        subscribe_prefix: config.subscribe_prefix.clone(),
+        connect_timeout: config.connect_timeout,
+    });
+    let permits = Arc::new(Semaphore::new(config.concurrency));
+    let open = Arc::new(AtomicU64::new(0));
+    let mut rc = ArrivalConfig::default().controller(config.rate, 1.0);
+    let mut tasks = JoinSet::new();
+    let start = Instant::now();
+    let ctrl_c = signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut interrupted = false;
+
+    for index in 0..config.connections {
+        let permit = tokio::select! {
+            p = permits.clone().acquire_owned() => p?,
+            _ = &mut ctrl_c => {
+                interrupted = true;
+                break;
+            }
+        };
+        if let Some(rc) = &mut rc {
+            rc.wait_for_next().await;
+        }
+        let params = params.clone();
+        let stats = stats.clone();
+        let open = open.clone();
+        tasks.spawn(async move {
+            let start_ms = start.elapsed().as_secs_f64() * 1e3;
+            let open_before = open.load(Ordering::Relaxed);
+            let (connect_ms, subscribe_ms, res) = open_one(&params, index, &stats).await;
+            drop(permit);
+            let error = match &res {
+                Ok(_) => {
+                    open.fetch_add(1, Ordering::Relaxed);
+                    stats.increment_connections();
+                    None
+                }
+                Err(reason) => {
+                    stats.record_connection_failure();
+                    Some(reason.clone())
+                }
+            };
+            let attempt = ConnAttempt {
+                index,
+                start_ms,
+                open_before,
+                connect_ms,
+                subscribe_ms,
+                error,
+            };
+            (attempt, res.ok(), Instant::now())
+        });
+    }
+
+    let mut attempts = Vec::with_capacity(config.connections as usize);
+    let mut held: Vec<Held> = Vec::new();
+    let mut last_end = start;
+    while let Some(joined) = tasks.join_next().await {
+        let (attempt, h, ended) = joined?;
+        last_end = last_end.max(ended);
+        attempts.push(attempt);
+        held.extend(h);
+    }
+    attempts.sort_by_key(|a| a.index);
+    let summary = summarize(&attempts, last_end - start, config.degrade_factor);
+    info!(
+        connected = summary.connected,
+        failed = summary.failed,
+        rate = format!("{:.1}/s", summary.connect_rate),
+        p50_ms = format!("{:.2}", summary.connect_p50_ms),
+        p99_ms = format!("{:.2}", summary.connect_p99_ms),
+        degraded_at = ?summary.degraded_at,
+        "Connection storm done, holding connections"
+    );
+    for (reason, n) in &summary.failures {
+        warn!(count = n, reason = %reason, "Connect failures");
+    }
+
+    if !interrupted && config.hold_secs > 0 {
+        tokio::select! {
+            _ = sleep(Duration::from_secs(config.hold_secs)) => {}
+            _ = &mut ctrl_c => info!("Ctrl+C received, closing connections"),
+        }
+    }
+    for (transport, probe, sub) in held {
+        if let Some(sub) = sub {
+            let _ = sub.shutdown().await;
+        }
+        if let Some(probe) = probe {
+            let _ = probe.shutdown().await;
+        }
+        let _ = transport.shutdown().await;
+        stats.decrement_connections();
+    }
+
+    if let Some(dir) = &config.output_dir {
+        write_results(dir, &attempts, &summary)?;
+    }
+    let final_stats = stats.snapshot().await;
+    if let Some(ref mut out) = output {
+        out.write_snapshot(&final_stats).await?;
+    }
+    if let Some(h) = snapshot_handle {
+        h.abort();
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degradation_is_found_at_first_slow_block() {
+        // Two fast blocks, then latency doubles from the 20th open connection
+        let samples: Vec<(u64, f64)> = (0..40)
+            .map(|i| {
+                (
+                    i,
+                    if i < 20 {
+                        1.0 + (i % 3) as f64 * 0.1
+                    } else {
+                        3.0
+                    },
+                )
+            })
+            .collect();
+        let (baseline, at) = degradation_point(&samples, 10, 2.0);
+        assert!((baseline - 1.1).abs() < 1e-9);
+        assert_eq!(at, Some(20));
+
+        let flat: Vec<(u64, f64)> = (0..40).map(|i| (i, 1.0)).collect();
+        assert_eq!(degradation_point(&flat, 10, 2.0), (1.0, None));
+        assert_eq!(degradation_point(&flat[..5], 10, 2.0), (0.0, None));
+    }
+
+    #[test]
+    fn summary_groups_failures_and_ignores_them_for_latency() {
+        let attempt = |index, connect_ms: Option<f64>, error: Option<&str>| ConnAttempt {
+            index,
+            start_ms: 0.0,
+            open_before: index,
+            connect_ms,
+            subscribe_ms: None,
+            error: error.map(str::to_string),
+        };
+        let attempts = vec![
+            attempt(0, Some(2.0), None),
+            attempt(1, None, Some("connect timeout")),
+            attempt(2, Some(4.0), None),
+            attempt(3, None, Some("connect: refused")),
+            attempt(4, None, Some("connect timeout")),
+        ];
+        let s = summarize(&attempts, Duration::from_secs(2), 2.0);
+        assert_eq!((s.attempts, s.connected, s.failed), (5, 2, 3));
+        assert_eq!(s.failures["connect timeout"], 2);
+        assert_eq!(s.failures["connect: refused"], 1);
+        assert!((s.connect_rate - 1.0).abs() < 1e-9);
+        assert!((s.connect_max_ms - 4.0).abs() < 0.01);
+        assert_eq!(s.degraded_at, None);
+    }
+}
@@ -1,8 +1,10 @@
 // Empty module placeholder
+pub mod conn_storm;
 pub mod multi_query;
 pub mod multi_topic;
 pub mod publisher;
 pub mod queryable;
 pub mod reliable_publisher;
+pub mod replay;
 pub mod requester;
 pub mod subscriber;
@@ -1,44 +1,526 @@
-#![allow(dead_code)]
-use anyhow::Result;
+use anyhow::{Result, bail};
+use bytes::Bytes;
+use futures::future::join_all;
+use std::collections::HashSet;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use crate::metrics::stats::Stats;
+use crate::payload::generate_payload;
+use crate::rate::ArrivalConfig;
+use crate::roles::queryable::{ChunkCount, Dispatch, ReplyMode, Server, ServiceTime, check_chunks};
+use crate::roles::requester::{Outcome, run_query};
+use crate::transport::{ConnectOptions, Engine, QueryRegistration, Transport, TransportBuilder};
 
 /// Multi-prefix query driver (single process)
 /// Serves many prefixes in one process or drives many requester keys without many OS processes.
+/// Templates take `{t}` (tenant), `{r}` (region), `{n}` (namespace) and `{id}` placeholders.
 pub struct MultiQueryConfig {
-    pub req_endpoint: String,
-    pub qry_endpoint: String,
+    pub engine: Engine,
+    /// Connect options for the requesters
+    pub req_connect: ConnectOptions,
+    /// Connect options for the in-process queryables
+    pub qry_connect: ConnectOptions,
     pub serve_prefix_template: String, // e.g. bench/qry/t{t}/r{r}/ns{n}
     pub key_template: String,          // e.g. bench/qry/t{t}/r{r}/ns{n}/item/{id}
     pub tenants: u32,
     pub regions: u32,
     pub namespaces: u32,
     pub ids: u32,
-    pub requesters: u32,
+    /// Logical requesters, one key each (<= T*R*N*ID); negative => one per key, 0 => none
+    pub requesters: i64,
     pub qps_per_req: Option<u32>,
+    /// How queries are spread around the rate (per requester)
+    pub arrival: ArrivalConfig,
+    /// In-flight queries per requester
     pub concurrency: u32,
     pub timeout_ms: u64,
-    pub duration_secs: u64,
+    /// Request body size: 0 sends an empty body, otherwise at least 24 bytes
+    pub request_size: usize,
+    /// Register queryables on every rendered serve prefix in this process
+    pub serve: bool,
     pub reply_size: usize,
-    pub proc_delay_ms: u64,
+    pub reply_mode: ReplyMode,
+    pub service_time: ServiceTime,
+    /// Stream each reply as this many chunks (as for qry)
+    pub chunks: Option<ChunkCount>,
+    /// Seed for service-time and chunk-count sampling (default: from the clock)
+    pub service_seed: Option<u64>,
+    /// Burn CPU for the service time instead of sleeping
+    pub cpu_bound: bool,
+    /// Queries served at once by one worker pool for all prefixes; 0 spawns a task per query
+    pub workers: usize,
+    pub duration_secs: u64,
     pub snapshot_interval_secs: u64,
-    pub output_file: Option<String>,
+    pub share_transport: bool, // when true, one transport for all requesters (and one for all queryables)
+    pub ramp_up_secs: f64,     // total time over which requesters start (0 = all at once)
+    // Aggregation support
+    pub shared_stats: Option<Arc<Stats>>, // when set, aggregate externally
+    pub disable_internal_snapshot: bool,
+}
+
+/// Fill a key template for one (tenant, region, namespace, id)
+fn render(template: &str, t: u32, r: u32, n: u32, id: u32) -> String {
+    template
+        .replace("{t}", &t.to_string())
+        .replace("{r}", &r.to_string())
+        .replace("{n}", &n.to_string())
+        .replace("{id}", &id.to_string())
+}
+
+/// Key index to (tenant, region, namespace, id), tenant varying fastest
+fn key_dims(i: u64, dims: (u32, u32, u32, u32)) -> (u32, u32, u32, u32) {
+    let (t, r, n) = (dims.0 as u64, dims.1 as u64, dims.2 as u64);
+    (
+        (i % t) as u32,
+        ((i / t) % r) as u32,
+        ((i / (t * r)) % n) as u32,
+        (i / (t * r * n)) as u32,
+    )
+}
+
+impl MultiQueryConfig {
+    fn dims(&self) -> (u32, u32, u32, u32) {
+        (self.tenants, self.regions, self.namespaces, self.ids)
+    }
+
+    fn total_keys(&self) -> u64 {
+        (self.tenants as u64)
+            .saturating_mul(self.regions as u64)
+            .saturating_mul(self.namespaces as u64)
+            .saturating_mul(self.ids as u64)
+    }
+
+    /// Request key of key index `i`
+    pub fn key(&self, i: u64) -> String {
+        let (t, r, n, id) = key_dims(i, self.dims());
+        render(&self.key_template, t, r, n, id)
+    }
+
+    /// Distinct queryable registrations, in key order. A serve template equal to the key
+    /// template serves each key exactly; otherwise each prefix serves `<prefix>/**`
+    pub fn serve_exprs(&self) -> Vec<String> {
+        let exact = self.serve_prefix_template == self.key_template;
+        let mut seen = HashSet::new();
+        let mut exprs = Vec::new();
+        for i in 0..self.total_keys() {
+            let (t, r, n, id) = key_dims(i, self.dims());
+            let prefix = render(&self.serve_prefix_template, t, r, n, id);
+            if seen.insert(prefix.clone()) {
+                exprs.push(if exact {
+                    prefix
+                } else {
+                    format!("{}/**", prefix)
+                });
+            }
+        }
+        exprs
+    }
+}
+
+/// In-process queryables: registrations, the transports that hold them and the
+/// dispatch (as for qry) that serves them all
+struct Served {
+    guards: Vec<Box<dyn QueryRegistration>>,
+    transports: Vec<Box<dyn Transport>>,
+    dispatch: Dispatch,
+}
+
+async fn register_queryables(config: &MultiQueryConfig, stats: Arc<Stats>) -> Result<Served> {
+    let exprs = config.serve_exprs();
+    let server = Server::new(
+        stats.clone(),
+        config.reply_size,
+        config.reply_mode,
+        config.service_time,
+        config.chunks,
+        config.cpu_bound,
+        config.service_seed,
+    );
+    let mut served = Served {
+        guards: Vec::with_capacity(exprs.len()),
+        transports: Vec::new(),
+        dispatch: Dispatch::new(server, config.workers),
+    };
+    for (i, expr) in exprs.iter().enumerate() {
+        if i == 0 || !config.share_transport {
+            stats.record_connection_attempt();
+            match TransportBuilder::connect_with_retry(
+                config.engine.clone(),
+                config.qry_connect.clone(),
+            )
+            .await
+            {
+                Ok(t) => {
+                    stats.increment_connections();
+                    served.transports.push(t);
+                }
+                Err(e) => {
+                    stats.record_connection_failure();
+                    bail!("queryable transport connect error: {}", e);
+                }
+            }
+        }
+        let transport = served.transports.last().unwrap();
+        let guard = transport
+            .register_queryable(expr, served.dispatch.handler())
+            .await
+            .map_err(|e| {
+                anyhow::Error::msg(format!("register_queryable error ({}): {}", expr, e))
+            })?;
+        served.guards.push(guard);
+    }
+    Ok(served)
+}
+
+/// One logical requester: paced queries on one key, at most `concurrency` in flight
+async fn drive_requester(
+    transport: Arc<Box<dyn Transport>>,
+    key: String,
+    config: RequesterParams,
+    stats: Arc<Stats>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut rate = config.arrival.controller(config.qps, 1.0);
+    if let Some(rc) = &rate {
+        rc.report_target_rate(&stats);
+    }
+    let slots = Arc::new(Semaphore::new(config.concurrency.max(1) as usize));
+    let mut tasks = JoinSet::new();
+    let mut seq = 0u64;
+    while !stop.load(Ordering::Relaxed) {
+        if let Some(rc) = &mut rate {
+            rc.wait_for_next().await;
+        }
+        let Ok(permit) = slots.clone().acquire_owned().await else {
+            break;
+        };
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        stats.record_sent().await;
+        let body = if config.request_size > 0 {
+            Bytes::from(generate_payload(seq, config.request_size))
+        } else {
+            Bytes::new()
+        };
+        seq += 1;
+        let (transport, key, stats) = (transport.clone(), key.clone(), stats.clone());
+        let timeout = config.timeout;
+        tasks.spawn(async move {
+            // Same deadline and late-reply classification as req
+            match run_query(
+                transport.as_ref().as_ref(),
+                &key,
+                body,
+                false,
+                timeout,
+                permit,
+            )
+            .await
+            {
+                Outcome::Reply(ns) => stats.record_received(ns).await,
+                Outcome::Stream(r) => stats.record_received(r.last_ns).await,
+                Outcome::Error => stats.record_error().await,
+                Outcome::Timeout(_) => stats.record_timeout().await,
+                Outcome::Late(_) => stats.record_late_reply().await,
+            }
+        });
+        // Reap finished queries so the set does not grow over long runs
+        while tasks.try_join_next().is_some() {}
+    }
+    while tasks.join_next().await.is_some() {}
+}
+
+/// Per-requester settings copied into each driver task
+#[derive(Clone)]
+struct RequesterParams {
+    qps: Option<f64>,
+    arrival: ArrivalConfig,
+    concurrency: u32,
+    timeout: Duration,
+    request_size: usize,
 }
 
-/// Stub runner for future implementation
 pub async fn run_multi_query(config: MultiQueryConfig) -> Result<()> {
-    println!(
-        "[multi_query] req_ep={} qry_ep={} dims=T{}xR{}xN{}xID{} reqs={} qps={:?} conc={} timeout={}ms dur={}s",
-        config.req_endpoint,
-        config.qry_endpoint,
-        config.tenants,
-        config.regions,
-        config.namespaces,
-        config.ids,
-        config.requesters,
-        config.qps_per_req,
-        config.concurrency,
-        config.timeout_ms,
-        config.duration_secs
+    let total_keys = config.total_keys();
+    let reqs: u64 = if config.requesters < 0 {
+        total_keys
+    } else {
+        (config.requesters as u64).min(total_keys)
+    };
+    if total_keys == 0 {
+        bail!("tenants, regions, namespaces and ids must all be at least 1");
+    }
+    if (1..24).contains(&config.request_size) {
+        bail!(
+            "request size {} is below the 24-byte payload header (use 0 for an empty body)",
+            config.request_size
+        );
+    }
+    if config.serve && config.reply_size < 24 {
+        bail!(
+            "reply size {} is below the 24-byte payload header",
+            config.reply_size
+        );
+    }
+    if config.serve {
+        check_chunks(config.chunks, config.reply_mode, config.reply_size)?;
+    }
+
+    info!(
+        engine = ?config.engine,
+        key_template = %config.key_template,
+        serve_prefix_template = %config.serve_prefix_template,
+        tenants = config.tenants,
+        regions = config.regions,
+        namespaces = config.namespaces,
+        ids = config.ids,
+        reqs = reqs,
+        serve = config.serve,
+        qps = ?config.qps_per_req,
+        concurrency = config.concurrency,
+        timeout_ms = config.timeout_ms,
+        share_transport = config.share_transport,
+        duration_secs = config.duration_secs,
+        "[multi_query] starting"
+    );
+
+    // Stats
+    let stats: Arc<Stats> = config
+        .shared_stats
+        .clone()
+        .unwrap_or_else(|| Arc::new(Stats::new()));
+    // With requesters in the same process, replies served here are counted apart so
+    // the aggregated stats describe the requesters only
+    let qry_stats = if reqs > 0 {
+        Arc::new(Stats::new())
+    } else {
+        stats.clone()
+    };
+
+    // Optional internal snapshot
+    let snapshot_handle = if !config.disable_internal_snapshot {
+        let stats_clone = stats.clone();
+        let every = config.snapshot_interval_secs;
+        Some(tokio::spawn(async move {
+            let mut t = tokio::time::interval(Duration::from_secs(every));
+            loop {
+                t.tick().await;
+                let s = stats_clone.snapshot().await;
+                debug!(
+                    sent = s.sent_count,
+                    received = s.received_count,
+                    errors = s.error_count,
+                    interval_tps = %format!("{:.2}", s.interval_throughput()),
+                    "[multi_query] snapshot"
+                );
+            }
+        }))
+    } else {
+        None
+    };
+
+    // Queryables first, so the first queries find them
+    let served = if config.serve {
+        let served = register_queryables(&config, qry_stats.clone()).await?;
+        info!(
+            registrations = served.guards.len(),
+            transports = served.transports.len(),
+            "[multi_query] queryables registered"
+        );
+        Some(served)
+    } else {
+        None
+    };
+
+    let params = RequesterParams {
+        qps: config.qps_per_req.map(|q| q as f64),
+        arrival: config.arrival.clone(),
+        concurrency: config.concurrency,
+        timeout: Duration::from_millis(config.timeout_ms),
+        request_size: config.request_size,
+    };
+    // Spread requester starts over the ramp-up time
+    let ramp_delay = if config.ramp_up_secs > 0.0 && reqs > 1 {
+        Duration::from_secs_f64(config.ramp_up_secs / (reqs - 1) as f64)
+    } else {
+        Duration::ZERO
+    };
+    let start = Instant::now();
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut handles = Vec::with_capacity(reqs as usize);
+    let mut shared: Option<Arc<Box<dyn Transport>>> = None;
+    let mut transports: Vec<Arc<Box<dyn Transport>>> = Vec::new();
+    for i in 0..reqs {
+        if i > 0 && !ramp_delay.is_zero() {
+            tokio::time::sleep(ramp_delay).await;
+        }
+        if start.elapsed().as_secs() >= config.duration_secs {
+            break;
+        }
+        let transport = match &shared {
+            Some(t) => t.clone(),
+            None => {
+                stats.record_connection_attempt();
+                let t: Arc<Box<dyn Transport>> = match TransportBuilder::connect_with_retry(
+                    config.engine.clone(),
+                    config.req_connect.clone(),
+                )
+                .await
+                {
+                    Ok(t) => Arc::new(t),
+                    Err(e) => {
+                        warn!(error = %e, "Transport connect error");
+                        stats.record_connection_failure();
+                        continue; // Skip this requester but continue with others
+                    }
+                };
+                stats.increment_connections();
+                if config.share_transport {
+                    shared = Some(t.clone());
+                }
+                transports.push(t.clone());
+                t
+            }
+        };
+        let params = RequesterParams {
+            arrival: params.arrival.for_stream(i),
+            ..params.clone()
+        };
+        handles.push(tokio::spawn(drive_requester(
+            transport,
+            config.key(i),
+            params,
+            stats.clone(),
+            stop.clone(),
+        )));
+    }
+
+    // Wait and stop
+    let remaining = Duration::from_secs(config.duration_secs).saturating_sub(start.elapsed());
+    tokio::select! {
+        _ = tokio::time::sleep(remaining) => {},
+        _ = tokio::signal::ctrl_c() => {},
+    }
+    stop.store(true, Ordering::Relaxed);
+    let _ = join_all(handles).await;
+
+    for t in transports {
+        stats.decrement_connections();
+        let _ = t.shutdown().await;
+    }
+    if let Some(served) = served {
+        for g in served.guards {
+            let _ = g.shutdown().await;
+        }
+        served.dispatch.shutdown();
+        if let Some(q) = served.dispatch.queue_summary() {
+            info!(
+                workers = q.workers,
+                dequeued = q.dequeued,
+                max_depth = q.max_depth,
+                delay_p50_ms = format!("{:.3}", q.delay_p50_ms),
+                delay_p99_ms = format!("{:.3}", q.delay_p99_ms),
+                "[multi_query] queue"
+            );
+        }
+        for t in served.transports {
+            qry_stats.decrement_connections();
+            let _ = t.shutdown().await;
+        }
+        if reqs > 0 {
+            let c = qry_stats.counters();
+            info!(
+                served = c.sent_count,
+                timestamped_requests = c.received_count,
+                errors = c.error_count,
+                "[multi_query] in-process queryables"
+            );
+        }
+    }
+    if let Some(h) = snapshot_handle {
+        h.abort();
+    }
+    let final_stats = stats.snapshot().await;
+    info!(
+        sent = final_stats.sent_count,
+        received = final_stats.received_count,
+        errors = final_stats.error_count,
+        timeouts = final_stats.timeout_count,
+        late_replies = final_stats.late_reply_count,
+        p50_ms = format!("{:.3}", final_stats.latency_ns_p50 as f64 / 1e6),
+        p99_ms = format!("{:.3}", final_stats.latency_ns_p99 as f64 / 1e6),
+        total_tps = format!("{:.2}", final_stats.total_throughput()),
+        "[multi_query] done"
     );
-    // TODO: implement single-process multi-requester + optional in-proc queryable registration
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(serve_prefix_template: &str, key_template: &str) -> MultiQueryConfig {
+        MultiQueryConfig {
+            engine: Engine::Mock,
+            req_connect: ConnectOptions::default(),
+            qry_connect: ConnectOptions::default(),
+            serve_prefix_template: serve_prefix_template.into(),
+            key_template: key_template.into(),
+            tenants: 2,
+            regions: 3,
+            namespaces: 2,
+            ids: 4,
+            requesters: -1,
+            qps_per_req: None,
+            arrival: ArrivalConfig::default(),
+            concurrency: 1,
+            timeout_ms: 1000,
+            request_size: 0,
+            serve: true,
+            reply_size: 64,
+            reply_mode: ReplyMode::Fixed,
+            service_time: ServiceTime::default(),
+            chunks: None,
+            service_seed: None,
+            cpu_bound: false,
+            workers: 0,
+            duration_secs: 1,
+            snapshot_interval_secs: 1,
+            share_transport: true,
+            ramp_up_secs: 0.0,
+            shared_stats: None,
+            disable_internal_snapshot: true,
+        }
+    }
+
+    #[test]
+    fn keys_and_prefixes_follow_the_templates() {
+        let c = config("q/t{t}/r{r}/ns{n}", "q/t{t}/r{r}/ns{n}/item/{id}");
+        assert_eq!(c.total_keys(), 48);
+        assert_eq!(c.key(0), "q/t0/r0/ns0/item/0");
+        assert_eq!(c.key(1), "q/t1/r0/ns0/item/0");
+        assert_eq!(c.key(2), "q/t0/r1/ns0/item/0");
+        assert_eq!(c.key(47), "q/t1/r2/ns1/item/3");
+        let keys: HashSet<String> = (0..48).map(|i| c.key(i)).collect();
+        assert_eq!(keys.len(), 48);
+
+        // One wildcard registration per (tenant, region, namespace)
+        let exprs = c.serve_exprs();
+        assert_eq!(exprs.len(), 12);
+        assert_eq!(exprs[0], "q/t0/r0/ns0/**");
+
+        // Serving the key template itself registers every key exactly
+        let exact = config("q/{t}/{r}/{n}/{id}", "q/{t}/{r}/{n}/{id}");
+        let exprs = exact.serve_exprs();
+        assert_eq!(exprs.len(), 48);
+        assert_eq!(exprs[5], exact.key(5));
+    }
+}
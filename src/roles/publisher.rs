@@ -1,23 +1,110 @@
 use crate::crash::{CrashConfig, CrashInjector};
 use crate::metrics::stats::Stats;
 use crate::output::OutputWriter;
-use crate::payload::generate_payload;
-use crate::rate::RateController;
-use crate::transport::{ConnectOptions, Engine, Transport, TransportBuilder, TransportError};
+use crate::payload::{generate_payload, parse_header};
+use crate::rate::ArrivalConfig;
+use crate::transport::{
+    ConnectOptions, Engine, Transport, TransportBuilder, TransportError, TransportMessage,
+};
 use anyhow::Result;
 use bytes::Bytes;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::signal;
+use tokio::sync::Semaphore;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
+/// Closed-loop sending: at most `window` messages published and not yet seen back
+#[derive(Debug, Clone)]
+pub struct ClosedLoopConfig {
+    pub window: usize,
+    /// Key whose messages acknowledge ours (None: the publish key, seen by our own
+    /// subscription; set it when an echo republishes elsewhere)
+    pub ack_key: Option<String>,
+    /// Unacknowledged messages older than this free their slot and count as timeouts
+    pub ack_timeout: Duration,
+}
+
+/// In-flight messages of a closed-loop publisher, keyed by sequence. The header
+/// timestamp tells our messages from other publishers' on a shared key.
+struct InflightWindow {
+    slots: Arc<Semaphore>,
+    pending: Arc<Mutex<HashMap<u64, (u64, Instant)>>>,
+    ack_timeout: Duration,
+}
+
+impl InflightWindow {
+    fn new(cfg: &ClosedLoopConfig) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(cfg.window.max(1))),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            ack_timeout: cfg.ack_timeout,
+        }
+    }
+
+    /// Subscription handler that frees a slot for each of our messages it sees
+    fn ack_handler(&self) -> Box<dyn Fn(TransportMessage) + Send + Sync + 'static> {
+        let slots = self.slots.clone();
+        let pending = self.pending.clone();
+        Box::new(move |msg: TransportMessage| {
+            let Ok(h) = parse_header(&msg.payload.as_cow()) else {
+                return;
+            };
+            let mut pending = pending.lock().unwrap();
+            if pending
+                .get(&h.seq)
+                .is_some_and(|(ts, _)| *ts == h.timestamp_ns)
+            {
+                pending.remove(&h.seq);
+                slots.add_permits(1);
+            }
+        })
+    }
+
+    /// Wait for a free slot. Every `ack_timeout` without one, expired messages are
+    /// dropped from the window; returns how many were.
+    async fn acquire(&self) -> u64 {
+        let mut expired = 0;
+        loop {
+            if let Ok(permit) = tokio::time::timeout(self.ack_timeout, self.slots.acquire()).await {
+                permit.expect("window semaphore closed").forget();
+                return expired;
+            }
+            let mut pending = self.pending.lock().unwrap();
+            let before = pending.len();
+            pending.retain(|_, (_, at)| at.elapsed() < self.ack_timeout);
+            let n = before - pending.len();
+            self.slots.add_permits(n);
+            expired += n as u64;
+        }
+    }
+
+    /// Track a message about to be published (before the send, as the ack may beat it)
+    fn track(&self, seq: u64, timestamp_ns: u64) {
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(seq, (timestamp_ns, Instant::now()));
+    }
+
+    /// Forget a message whose publish failed and free its slot
+    fn untrack(&self, seq: u64) {
+        if self.pending.lock().unwrap().remove(&seq).is_some() {
+            self.slots.add_permits(1);
+        }
+    }
+}
+
 pub struct PublisherConfig {
     pub engine: Engine,
     pub connect: ConnectOptions,
     pub key_expr: String,
     pub payload_size: usize,
     pub rate: Option<f64>,
+    /// How sends are spread around `rate`
+    pub arrival: ArrivalConfig,
     pub duration_secs: Option<u64>,
     pub output_file: Option<String>,
     pub snapshot_interval_secs: u64,
@@ -26,6 +113,8 @@ pub struct PublisherConfig {
     pub disable_internal_snapshot: bool,  // when true, do not launch internal snapshot logger
     // Crash injection
     pub crash_config: CrashConfig,
+    /// Bounded in-flight window (None: open loop)
+    pub closed_loop: Option<ClosedLoopConfig>,
 }
 
 pub async fn run_publisher(config: PublisherConfig) -> Result<()> {
@@ -34,9 +123,11 @@ pub async fn run_publisher(config: PublisherConfig) -> Result<()> {
         key = %config.key_expr,
         payload_size = config.payload_size,
         rate = ?config.rate,
+        arrival = ?config.arrival.model,
         duration_secs = ?config.duration_secs,
         endpoint = ?config.connect.params.get("endpoint"),
         crash_enabled = config.crash_config.is_enabled(),
+        window = ?config.closed_loop.as_ref().map(|c| c.window),
         "Starting publisher"
     );
 
@@ -96,18 +187,22 @@ pub async fn run_publisher(config: PublisherConfig) -> Result<()> {
 
     // Publishing state (persists across reconnects)
     let mut sequence = 0u64;
-    let start_time = std::time::Instant::now();
-    let mut rate_controller = config.rate.map(|r| RateController::new(r));
+    let start_time = Instant::now();
+    let window = config.closed_loop.as_ref().map(InflightWindow::new);
+    let mut rate_controller = config.arrival.controller(config.rate, 1.0);
+    if let Some(rc) = &rate_controller {
+        rc.report_target_rate(&stats);
+    }
     let mut stopped = false;
 
     // Outer loop: handles reconnection after crashes
     'reconnect: while !stopped {
         // Check duration limit before connecting
-        if let Some(duration) = config.duration_secs {
-            if start_time.elapsed().as_secs() >= duration {
-                info!("Duration limit reached, stopping publisher");
-                break;
-            }
+        if let Some(duration) = config.duration_secs
+            && start_time.elapsed().as_secs() >= duration
+        {
+            info!("Duration limit reached, stopping publisher");
+            break;
         }
 
         // Connect with retry
@@ -140,15 +235,31 @@ pub async fn run_publisher(config: PublisherConfig) -> Result<()> {
             }
         };
 
+        // Closed loop: watch for our own messages coming back
+        let _ack_sub = match (&window, &config.closed_loop) {
+            (Some(w), Some(cl)) => {
+                let ack_key = cl.ack_key.as_deref().unwrap_or(&config.key_expr);
+                match transport.subscribe(ack_key, w.ack_handler()).await {
+                    Ok(sub) => Some(sub),
+                    Err(e) => {
+                        error!(error = %e, key = %ack_key, "Ack subscription error");
+                        stats.record_connection_failure();
+                        break;
+                    }
+                }
+            }
+            _ => None,
+        };
+
         // Inner publishing loop
         let crash_triggered = loop {
             // Check duration limit
-            if let Some(duration) = config.duration_secs {
-                if start_time.elapsed().as_secs() >= duration {
-                    info!("Duration limit reached, stopping publisher");
-                    stopped = true;
-                    break false;
-                }
+            if let Some(duration) = config.duration_secs
+                && start_time.elapsed().as_secs() >= duration
+            {
+                info!("Duration limit reached, stopping publisher");
+                stopped = true;
+                break false;
             }
 
             // Check for crash injection (only if crashes remaining)
@@ -213,8 +324,31 @@ pub async fn run_publisher(config: PublisherConfig) -> Result<()> {
                 }
             }
 
+            // Closed loop: wait for a free slot in the window
+            if let Some(w) = &window {
+                let expired = tokio::select! {
+                    n = w.acquire() => n,
+                    _ = signal::ctrl_c() => {
+                        info!("Ctrl+C received, stopping publisher");
+                        stopped = true;
+                        break false;
+                    }
+                };
+                for _ in 0..expired {
+                    stats.record_timeout().await;
+                }
+            }
+
             // Generate and send payload
             let payload = generate_payload(sequence, config.payload_size);
+            if let Some(w) = &window
+                && let Ok(h) = parse_header(&payload)
+            {
+                w.track(sequence, h.timestamp_ns);
+            }
+            // Sampled messages carry a trace context; the span ends after the publish
+            #[cfg(feature = "otel")]
+            let (payload, _span) = crate::otel::start_publish(sequence, payload);
             let bytes = Bytes::from(payload);
 
             match publisher.publish(bytes).await {
@@ -225,6 +359,9 @@ pub async fn run_publisher(config: PublisherConfig) -> Result<()> {
                 Err(e) => {
                     warn!(error = %e, "Send error");
                     stats.record_error().await;
+                    if let Some(w) = &window {
+                        w.untrack(sequence);
+                    }
                     // Check if error is recoverable
                     if matches!(e, TransportError::Disconnected) {
                         break true; // Trigger reconnect
@@ -251,7 +388,10 @@ pub async fn run_publisher(config: PublisherConfig) -> Result<()> {
         if crash_triggered && config.connect.retry_enabled {
             // Sample repair time and wait before reconnecting
             let repair_time = crash_injector.sample_repair_time();
-            info!(repair_secs = repair_time.as_secs_f64(), "Simulating repair delay");
+            info!(
+                repair_secs = repair_time.as_secs_f64(),
+                "Simulating repair delay"
+            );
             tokio::time::sleep(repair_time).await;
 
             // Schedule next crash (deterministic timeline includes the repair downtime)
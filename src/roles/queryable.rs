@@ -1,21 +1,220 @@
 use crate::metrics::stats::Stats;
 use crate::output::OutputWriter;
-use crate::payload::generate_payload;
-use crate::transport::{ConnectOptions, Engine, IncomingQuery, TransportBuilder};
-use anyhow::Result;
+use crate::payload::{CHUNK_TAG_LEN, MessageHeader, generate_payload, parse_header, tag_chunk};
+use crate::rng::XorShift64;
+use crate::transport::{ConnectOptions, Engine, IncomingQuery, QueryResponder, TransportBuilder};
+use anyhow::{Result, bail};
 use bytes::Bytes;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::signal;
 use tokio::time::{interval, sleep};
 use tracing::{debug, info, warn};
 
+/// How a queryable builds its reply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyMode {
+    /// `reply_size` bytes, the same for every query
+    #[default]
+    Fixed,
+    /// The request body, unchanged
+    Echo,
+    /// `reply_size` bytes carrying the request's header (sequence and send time)
+    Transform,
+}
+
+pub fn parse_reply_mode(s: &str) -> Option<ReplyMode> {
+    match s.to_lowercase().as_str() {
+        "fixed" => Some(ReplyMode::Fixed),
+        "echo" => Some(ReplyMode::Echo),
+        "transform" => Some(ReplyMode::Transform),
+        _ => None,
+    }
+}
+
+/// Build the reply to `request`; `template` is the fixed reply of `reply_size` bytes
+fn build_reply(mode: ReplyMode, request: Bytes, template: &Bytes) -> Bytes {
+    match mode {
+        ReplyMode::Fixed => template.clone(),
+        ReplyMode::Echo => request,
+        ReplyMode::Transform => match parse_header(&request) {
+            Ok(h) if template.len() >= 24 => {
+                let mut reply = template.to_vec();
+                let header = MessageHeader {
+                    payload_size: reply.len(),
+                    ..h
+                };
+                reply[..24].copy_from_slice(&header.encode());
+                Bytes::from(reply)
+            }
+            _ => template.clone(),
+        },
+    }
+}
+
+/// Service time per query, sampled on arrival
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "model", rename_all = "lowercase")]
+pub enum ServiceTime {
+    Fixed {
+        ms: f64,
+    },
+    /// Exponential with the given mean
+    Exp {
+        mean_ms: f64,
+    },
+    /// `median * e^(sigma * N(0,1))`: long right tail for larger sigma
+    LogNormal {
+        median_ms: f64,
+        sigma: f64,
+    },
+    /// `slow_ms` with probability `p_slow`, otherwise `fast_ms`
+    Bimodal {
+        fast_ms: f64,
+        slow_ms: f64,
+        p_slow: f64,
+    },
+}
+
+impl Default for ServiceTime {
+    fn default() -> Self {
+        Self::Fixed { ms: 0.0 }
+    }
+}
+
+/// Longest service time a single query can draw; tails beyond it are clamped
+pub const MAX_SERVICE_MS: f64 = 3_600_000.0;
+/// Largest accepted lognormal sigma (a 1-in-1000 draw is ~10^10 x the median)
+pub const MAX_LOGNORMAL_SIGMA: f64 = 8.0;
+
+/// Parse `fixed:MS`, `exp:MEAN_MS`, `lognormal:MEDIAN_MS:SIGMA` or
+/// `bimodal:FAST_MS:SLOW_MS:P_SLOW`
+pub fn parse_service_time(s: &str) -> Option<ServiceTime> {
+    let lower = s.to_lowercase();
+    let mut parts = lower.split(':');
+    let kind = parts.next()?;
+    let args: Vec<f64> = parts.map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    if args.iter().any(|a| !a.is_finite() || *a < 0.0) {
+        return None;
+    }
+    match (kind, args.as_slice()) {
+        ("fixed", &[ms]) => Some(ServiceTime::Fixed { ms }),
+        ("exp", &[mean_ms]) => Some(ServiceTime::Exp { mean_ms }),
+        ("lognormal", &[median_ms, sigma]) if sigma <= MAX_LOGNORMAL_SIGMA => {
+            Some(ServiceTime::LogNormal { median_ms, sigma })
+        }
+        ("bimodal", &[fast_ms, slow_ms, p_slow]) if p_slow <= 1.0 => Some(ServiceTime::Bimodal {
+            fast_ms,
+            slow_ms,
+            p_slow,
+        }),
+        _ => None,
+    }
+}
+
+impl ServiceTime {
+    pub fn sample(&self, rng: &mut XorShift64) -> Duration {
+        let ms = match *self {
+            Self::Fixed { ms } => ms,
+            Self::Exp { mean_ms } => -mean_ms * (1.0 - rng.next_f64()).ln(),
+            Self::LogNormal { median_ms, sigma } => {
+                // Box-Muller
+                let (u1, u2) = (1.0 - rng.next_f64(), rng.next_f64());
+                let z = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+                median_ms * (sigma * z).exp()
+            }
+            Self::Bimodal {
+                fast_ms,
+                slow_ms,
+                p_slow,
+            } => {
+                if rng.next_f64() < p_slow {
+                    slow_ms
+                } else {
+                    fast_ms
+                }
+            }
+        };
+        Duration::from_secs_f64(ms.clamp(0.0, MAX_SERVICE_MS) / 1e3)
+    }
+
+    pub fn mean_ms(&self) -> f64 {
+        match *self {
+            Self::Fixed { ms } => ms,
+            Self::Exp { mean_ms } => mean_ms,
+            Self::LogNormal { median_ms, sigma } => median_ms * (sigma * sigma / 2.0).exp(),
+            Self::Bimodal {
+                fast_ms,
+                slow_ms,
+                p_slow,
+            } => fast_ms * (1.0 - p_slow) + slow_ms * p_slow,
+        }
+    }
+}
+
+/// Chunks per streamed reply, drawn uniformly from `min..=max` for each query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChunkCount {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Parse `K` (fixed) or `MIN..MAX` (uniform, inclusive); at least one chunk
+pub fn parse_chunk_count(s: &str) -> Option<ChunkCount> {
+    let (min, max) = match s.split_once("..") {
+        Some((a, b)) => (a.trim().parse().ok()?, b.trim().parse().ok()?),
+        None => {
+            let k = s.trim().parse().ok()?;
+            (k, k)
+        }
+    };
+    (min >= 1 && min <= max).then_some(ChunkCount { min, max })
+}
+
+impl ChunkCount {
+    pub fn sample(&self, rng: &mut XorShift64) -> u32 {
+        let span = (self.max - self.min) as f64 + 1.0;
+        (self.min + (rng.next_f64() * span) as u32).min(self.max)
+    }
+}
+
+/// Spin on the current thread for `d`: service that competes for cores
+fn burn(d: Duration) {
+    let end = Instant::now() + d;
+    let mut x = 0u64;
+    while Instant::now() < end {
+        for _ in 0..256 {
+            x = x
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+        }
+        std::hint::black_box(x);
+    }
+}
+
 pub struct QueryableConfig {
     pub engine: Engine,
     pub connect: ConnectOptions,
     pub serve_prefix: Vec<String>,
     pub reply_size: usize,
-    pub proc_delay_ms: u64,
+    pub reply_mode: ReplyMode,
+    pub service_time: ServiceTime,
+    /// Stream each reply as this many chunks, then end the query; each chunk takes its
+    /// own service time. None sends a single reply
+    pub chunks: Option<ChunkCount>,
+    /// Seed for service-time and chunk-count sampling (default: from the clock)
+    pub service_seed: Option<u64>,
+    /// Burn CPU for the service time (on the blocking pool) instead of sleeping
+    pub cpu_bound: bool,
+    /// Queries served at once by a fixed worker pool; 0 spawns a task per query
+    pub workers: usize,
+    /// With workers: write `queue.csv` (per snapshot interval) and `queue.json` here
+    pub queue_dir: Option<PathBuf>,
     pub output_file: Option<String>,
     pub snapshot_interval_secs: u64,
     // Aggregation/external snapshot support
@@ -25,16 +224,345 @@ pub struct QueryableConfig {
     pub test_stop_after_secs: Option<u64>,
 }
 
+/// A query waiting for service
+pub(crate) struct Job {
+    request: Bytes,
+    responder: QueryResponder,
+    /// Request-leg latency, when the request carried a payload header
+    request_latency_ns: Option<u64>,
+    /// Service time of the (first) reply
+    service: Duration,
+    /// Chunks to stream, when streaming
+    chunks: Option<u32>,
+    arrived: Instant,
+}
+
+/// Serves jobs: service time, then the reply (or each chunk in turn)
+pub(crate) struct Server {
+    stats: Arc<Stats>,
+    template: Bytes,
+    reply_mode: ReplyMode,
+    service_time: ServiceTime,
+    chunks: Option<ChunkCount>,
+    cpu_bound: bool,
+    rng: std::sync::Mutex<XorShift64>,
+}
+
+/// Reject chunking that cannot carry the chunk tag
+pub(crate) fn check_chunks(
+    chunks: Option<ChunkCount>,
+    reply_mode: ReplyMode,
+    reply_size: usize,
+) -> Result<()> {
+    if chunks.is_some() {
+        // Chunks carry their index and total at the tail; an echoed (possibly empty)
+        // request has no room for it
+        if reply_mode == ReplyMode::Echo {
+            bail!("echo replies cannot be streamed in chunks");
+        }
+        if reply_size < 24 + CHUNK_TAG_LEN {
+            bail!(
+                "streamed replies need at least {} bytes (payload header and chunk tag), got {}",
+                24 + CHUNK_TAG_LEN,
+                reply_size
+            );
+        }
+    }
+    Ok(())
+}
+
+impl Server {
+    /// The reply template is built once to avoid per-reply allocations
+    pub(crate) fn new(
+        stats: Arc<Stats>,
+        reply_size: usize,
+        reply_mode: ReplyMode,
+        service_time: ServiceTime,
+        chunks: Option<ChunkCount>,
+        cpu_bound: bool,
+        service_seed: Option<u64>,
+    ) -> Self {
+        Self {
+            stats,
+            template: Bytes::from(generate_payload(0, reply_size)),
+            reply_mode,
+            service_time,
+            chunks,
+            cpu_bound,
+            rng: std::sync::Mutex::new(match service_seed {
+                Some(seed) => XorShift64::new(seed),
+                None => XorShift64::from_time(),
+            }),
+        }
+    }
+
+    /// Sample a query's service time and chunk count on arrival
+    fn job(&self, request: Bytes, responder: QueryResponder) -> Job {
+        // Requests with a header carry their send time: request-leg latency
+        let request_latency_ns = parse_header(&request).ok().map(|h| {
+            let now_ns = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos() as u64;
+            now_ns.saturating_sub(h.timestamp_ns)
+        });
+        let mut rng = self.rng.lock().unwrap();
+        Job {
+            request,
+            responder,
+            request_latency_ns,
+            service: self.service_time.sample(&mut rng),
+            chunks: self.chunks.map(|c| c.sample(&mut rng)),
+            arrived: Instant::now(),
+        }
+    }
+
+    async fn serve(&self, job: Job) {
+        if let Some(ns) = job.request_latency_ns {
+            self.stats.record_received(ns).await;
+        }
+        let Some(total) = job.chunks else {
+            self.work(job.service).await;
+            let payload = build_reply(self.reply_mode, job.request, &self.template);
+            self.reply(&job.responder, payload).await;
+            return;
+        };
+        for index in 0..total {
+            let service = if index == 0 {
+                job.service
+            } else {
+                self.service_time.sample(&mut self.rng.lock().unwrap())
+            };
+            self.work(service).await;
+            let mut chunk =
+                build_reply(self.reply_mode, job.request.clone(), &self.template).to_vec();
+            tag_chunk(&mut chunk, index, total);
+            if !self.reply(&job.responder, Bytes::from(chunk)).await {
+                return;
+            }
+        }
+        if let Err(e) = job.responder.end().await {
+            warn!(error = %e, "Queryable end-of-stream error");
+            self.stats.record_error().await;
+        }
+    }
+
+    async fn work(&self, service: Duration) {
+        if service.is_zero() {
+            return;
+        }
+        if self.cpu_bound {
+            let _ = tokio::task::spawn_blocking(move || burn(service)).await;
+        } else {
+            sleep(service).await;
+        }
+    }
+
+    /// Send one reply or chunk; false on error
+    async fn reply(&self, responder: &QueryResponder, payload: Bytes) -> bool {
+        if let Err(e) = responder.send(payload).await {
+            warn!(error = %e, "Queryable reply error");
+            self.stats.record_error().await;
+            false
+        } else {
+            self.stats.record_sent().await;
+            true
+        }
+    }
+}
+
+/// Hands queries to a `Server`: a task per query, or a worker pool behind an unbounded
+/// queue, so queueing shows up as delay and depth rather than as dropped queries
+pub(crate) struct Dispatch {
+    server: Arc<Server>,
+    pool: Option<flume::Sender<Job>>,
+    queue: Arc<QueueStats>,
+    workers: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl Dispatch {
+    pub(crate) fn new(server: Server, workers: usize) -> Self {
+        let server = Arc::new(server);
+        let queue = Arc::new(QueueStats::new());
+        let mut handles = Vec::new();
+        let pool = (workers > 0).then(|| {
+            let (tx, rx) = flume::unbounded::<Job>();
+            for _ in 0..workers {
+                let (rx, server, queue) = (rx.clone(), server.clone(), queue.clone());
+                handles.push(tokio::spawn(async move {
+                    while let Ok(job) = rx.recv_async().await {
+                        queue.dequeue(job.arrived.elapsed());
+                        server.serve(job).await;
+                    }
+                }));
+            }
+            tx
+        });
+        Self {
+            server,
+            pool,
+            queue,
+            workers: handles,
+        }
+    }
+
+    /// Handler for `Transport::register_queryable`
+    pub(crate) fn handler(&self) -> Box<dyn Fn(IncomingQuery) + Send + Sync + 'static> {
+        let (server, queue, pool) = (self.server.clone(), self.queue.clone(), self.pool.clone());
+        Box::new(
+            move |IncomingQuery {
+                      payload, responder, ..
+                  }| {
+                let job = server.job(payload.into_bytes(), responder);
+                match &pool {
+                    Some(tx) => {
+                        queue.enqueue();
+                        let _ = tx.send(job);
+                    }
+                    // No pool: spawn to avoid blocking the transport callback
+                    None => {
+                        let server = server.clone();
+                        tokio::spawn(async move { server.serve(job).await });
+                    }
+                }
+            },
+        )
+    }
+
+    /// Queue summary, when serving from a worker pool
+    pub(crate) fn queue_summary(&self) -> Option<QueueSummary> {
+        self.pool
+            .as_ref()
+            .map(|_| self.queue.summary(self.workers.len()))
+    }
+
+    /// Stop the workers; queued queries go unanswered
+    pub(crate) fn shutdown(&self) {
+        for h in &self.workers {
+            h.abort();
+        }
+    }
+}
+
+/// Worker-pool queue: depth and the time queries wait for a free worker
+struct QueueStats {
+    depth: AtomicU64,
+    /// Peak depth since the last row
+    interval_peak: AtomicU64,
+    delays: std::sync::Mutex<QueueDelays>,
+}
+
+struct QueueDelays {
+    interval: Histogram<u64>,
+    total: Histogram<u64>,
+    max_depth: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueRow {
+    pub t_secs: f64,
+    pub depth: u64,
+    pub peak_depth: u64,
+    pub dequeued: u64,
+    pub delay_p50_ms: f64,
+    pub delay_p99_ms: f64,
+    pub delay_max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueSummary {
+    pub workers: usize,
+    pub dequeued: u64,
+    pub max_depth: u64,
+    pub delay_mean_ms: f64,
+    pub delay_p50_ms: f64,
+    pub delay_p99_ms: f64,
+    pub delay_max_ms: f64,
+}
+
+/// Longest queue wait tracked: a thousand queued queries at `MAX_SERVICE_MS` each.
+/// Longer waits are recorded at this bound rather than dropped.
+const MAX_QUEUE_DELAY_NS: u64 = MAX_SERVICE_MS as u64 * 1_000_000 * 1000;
+
+impl QueueStats {
+    fn new() -> Self {
+        let hist = || Histogram::new_with_bounds(1, MAX_QUEUE_DELAY_NS, 3).unwrap();
+        Self {
+            depth: AtomicU64::new(0),
+            interval_peak: AtomicU64::new(0),
+            delays: std::sync::Mutex::new(QueueDelays {
+                interval: hist(),
+                total: hist(),
+                max_depth: 0,
+            }),
+        }
+    }
+
+    fn enqueue(&self) {
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+        self.interval_peak.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    fn dequeue(&self, waited: Duration) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+        let ns = (waited.as_nanos() as u64).max(1);
+        let mut d = self.delays.lock().unwrap();
+        d.interval.saturating_record(ns);
+        d.total.saturating_record(ns);
+    }
+
+    /// Row for the interval since the previous call; resets the interval state
+    fn row(&self, t_secs: f64) -> QueueRow {
+        let ms = |ns: u64| ns as f64 / 1e6;
+        let depth = self.depth.load(Ordering::Relaxed);
+        let peak = self.interval_peak.swap(depth, Ordering::Relaxed).max(depth);
+        let mut d = self.delays.lock().unwrap();
+        d.max_depth = d.max_depth.max(peak);
+        let row = QueueRow {
+            t_secs,
+            depth,
+            peak_depth: peak,
+            dequeued: d.interval.len(),
+            delay_p50_ms: ms(d.interval.value_at_quantile(0.50)),
+            delay_p99_ms: ms(d.interval.value_at_quantile(0.99)),
+            delay_max_ms: ms(d.interval.max()),
+        };
+        d.interval.reset();
+        row
+    }
+
+    fn summary(&self, workers: usize) -> QueueSummary {
+        let ms = |ns: u64| ns as f64 / 1e6;
+        let peak = self.interval_peak.load(Ordering::Relaxed);
+        let d = self.delays.lock().unwrap();
+        QueueSummary {
+            workers,
+            dequeued: d.total.len(),
+            max_depth: d.max_depth.max(peak),
+            delay_mean_ms: d.total.mean() / 1e6,
+            delay_p50_ms: ms(d.total.value_at_quantile(0.50)),
+            delay_p99_ms: ms(d.total.value_at_quantile(0.99)),
+            delay_max_ms: ms(d.total.max()),
+        }
+    }
+}
+
 pub async fn run_queryable(config: QueryableConfig) -> Result<()> {
     info!(
         engine = ?config.engine,
         prefixes = ?config.serve_prefix,
         reply_size = config.reply_size,
-        proc_delay_ms = config.proc_delay_ms,
+        reply_mode = ?config.reply_mode,
+        service_time = ?config.service_time,
+        chunks = ?config.chunks,
+        cpu_bound = config.cpu_bound,
+        workers = config.workers,
         endpoint = ?config.connect.params.get("endpoint"),
         "Starting queryable"
     );
 
+    check_chunks(config.chunks, config.reply_mode, config.reply_size)?;
+
     // Stats early so we can track connection failures
     let stats = if let Some(s) = &config.shared_stats {
         s.clone()
@@ -44,19 +572,17 @@ pub async fn run_queryable(config: QueryableConfig) -> Result<()> {
 
     // Transport session with optional retry
     stats.record_connection_attempt();
-    let transport = match TransportBuilder::connect_with_retry(
-        config.engine.clone(),
-        config.connect.clone(),
-    )
-    .await
-    {
-        Ok(t) => t,
-        Err(e) => {
-            warn!(error = %e, "Transport connect error");
-            stats.record_connection_failure();
-            return Ok(());
-        }
-    };
+    let transport =
+        match TransportBuilder::connect_with_retry(config.engine.clone(), config.connect.clone())
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => {
+                warn!(error = %e, "Transport connect error");
+                stats.record_connection_failure();
+                return Ok(());
+            }
+        };
 
     let mut output = if let Some(ref path) = config.output_file {
         Some(OutputWriter::new_csv(path.clone()).await?)
@@ -86,37 +612,47 @@ pub async fn run_queryable(config: QueryableConfig) -> Result<()> {
         None
     };
 
-    // Prepare a reusable payload buffer to avoid per-reply allocations
-    let reply_size = config.reply_size;
-    let payload_template = generate_payload(0, reply_size);
+    let dispatch = Dispatch::new(
+        Server::new(
+            stats.clone(),
+            config.reply_size,
+            config.reply_mode,
+            config.service_time,
+            config.chunks,
+            config.cpu_bound,
+            config.service_seed,
+        ),
+        config.workers,
+    );
+    let queue_writer = match (&dispatch.pool, &config.queue_dir) {
+        (Some(_), Some(dir)) => {
+            std::fs::create_dir_all(dir)?;
+            let mut w = csv::Writer::from_path(dir.join("queue.csv"))?;
+            let queue = dispatch.queue.clone();
+            let every = Duration::from_secs(config.snapshot_interval_secs.max(1));
+            Some(tokio::spawn(async move {
+                let start = Instant::now();
+                let mut ticker = interval(every);
+                ticker.tick().await;
+                loop {
+                    ticker.tick().await;
+                    let row = queue.row(start.elapsed().as_secs_f64());
+                    if let Err(e) = w.serialize(row) {
+                        warn!(error = %e, "queue.csv write failed");
+                        break;
+                    }
+                    let _ = w.flush();
+                }
+            }))
+        }
+        _ => None,
+    };
 
     // Register queryables with handler-based API, keep guards alive
     let mut _guards = Vec::new();
     for prefix in &config.serve_prefix {
-        let stats_worker = stats.clone();
-        let proc_delay = config.proc_delay_ms;
-        let payload_template = payload_template.clone();
         let guard = transport
-            .register_queryable(
-                prefix,
-                Box::new(move |IncomingQuery { responder, .. }| {
-                    // Minimal handler: spawn to avoid blocking zenoh callback
-                    let stats_worker = stats_worker.clone();
-                    let payload_template = payload_template.clone();
-                    tokio::spawn(async move {
-                        if proc_delay > 0 {
-                            sleep(Duration::from_millis(proc_delay)).await;
-                        }
-                        let payload = Bytes::from(payload_template.clone());
-                        if let Err(e) = responder.send(payload).await {
-                            warn!(error = %e, "Queryable reply error");
-                            stats_worker.record_error().await;
-                        } else {
-                            stats_worker.record_sent().await;
-                        }
-                    });
-                }),
-            )
+            .register_queryable(prefix, dispatch.handler())
             .await
             .map_err(|e| anyhow::Error::msg(format!("register_queryable error: {}", e)))?;
         _guards.push(guard);
@@ -139,23 +675,187 @@ pub async fn run_queryable(config: QueryableConfig) -> Result<()> {
     let final_stats = stats.snapshot().await;
     info!(
         served = final_stats.sent_count,
+        timestamped_requests = final_stats.received_count,
+        request_p50_ns = final_stats.latency_ns_p50,
         errors = final_stats.error_count,
         "Final Queryable Statistics"
     );
     if let Some(ref mut out) = output {
         out.write_snapshot(&final_stats).await?;
     }
+    if let Some(summary) = dispatch.queue_summary() {
+        info!(
+            workers = summary.workers,
+            dequeued = summary.dequeued,
+            max_depth = summary.max_depth,
+            delay_p50_ms = format!("{:.3}", summary.delay_p50_ms),
+            delay_p99_ms = format!("{:.3}", summary.delay_p99_ms),
+            "Queryable queue"
+        );
+        if let Some(dir) = &config.queue_dir {
+            std::fs::write(
+                dir.join("queue.json"),
+                serde_json::to_string_pretty(&summary)?,
+            )?;
+        }
+    }
 
     if let Some(h) = snapshot_handle {
         h.abort();
     }
-    // Shutdown queryable registrations then transport
+    if let Some(h) = queue_writer {
+        h.abort();
+    }
+    // Shutdown queryable registrations then transport; queued queries go unanswered
     for g in _guards {
         let _ = g.shutdown().await;
     }
+    dispatch.shutdown();
     transport
         .shutdown()
         .await
         .map_err(|e| anyhow::Error::msg(format!("transport shutdown error: {}", e)))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replies_follow_the_mode() {
+        let template = Bytes::from(generate_payload(0, 64));
+        let request = Bytes::from(generate_payload(7, 256));
+        let sent_ns = parse_header(&request).unwrap().timestamp_ns;
+
+        assert_eq!(
+            build_reply(ReplyMode::Fixed, request.clone(), &template),
+            template
+        );
+        assert_eq!(
+            build_reply(ReplyMode::Echo, request.clone(), &template),
+            request
+        );
+        let reply = build_reply(ReplyMode::Transform, request, &template);
+        assert_eq!(reply.len(), 64);
+        let h = parse_header(&reply).unwrap();
+        assert_eq!((h.seq, h.timestamp_ns, h.payload_size), (7, sent_ns, 64));
+        // Requests without a header get the fixed reply
+        assert_eq!(
+            build_reply(ReplyMode::Transform, Bytes::new(), &template),
+            template
+        );
+        assert_eq!(parse_reply_mode("Echo"), Some(ReplyMode::Echo));
+        assert_eq!(parse_reply_mode("mirror"), None);
+    }
+
+    #[test]
+    fn service_times_parse_and_sample_around_their_mean() {
+        assert_eq!(
+            parse_service_time("fixed:2.5"),
+            Some(ServiceTime::Fixed { ms: 2.5 })
+        );
+        assert_eq!(
+            parse_service_time("bimodal:1:20:0.1"),
+            Some(ServiceTime::Bimodal {
+                fast_ms: 1.0,
+                slow_ms: 20.0,
+                p_slow: 0.1
+            })
+        );
+        for bad in [
+            "exp",
+            "exp:-1",
+            "bimodal:1:2:1.5",
+            "lognormal:1",
+            "lognormal:1:9",
+            "gamma:2",
+        ] {
+            assert_eq!(parse_service_time(bad), None, "{}", bad);
+        }
+
+        let mut rng = XorShift64::new(7);
+        for spec in ["exp:4", "lognormal:2:0.5", "bimodal:1:20:0.1"] {
+            let model = parse_service_time(spec).unwrap();
+            let n = 50_000;
+            let total: f64 = (0..n)
+                .map(|_| model.sample(&mut rng).as_secs_f64() * 1e3)
+                .sum();
+            let mean = total / n as f64;
+            assert!(
+                (mean / model.mean_ms() - 1.0).abs() < 0.05,
+                "{}: {} vs {}",
+                spec,
+                mean,
+                model.mean_ms()
+            );
+        }
+    }
+
+    #[test]
+    fn extreme_service_times_clamp_instead_of_panicking() {
+        let mut rng = XorShift64::new(7);
+        let max = Duration::from_secs_f64(MAX_SERVICE_MS / 1e3);
+        for spec in ["exp:1e300", "lognormal:1e300:8", "fixed:1e308"] {
+            let model = parse_service_time(spec).unwrap();
+            for _ in 0..1000 {
+                assert!(model.sample(&mut rng) <= max, "{}", spec);
+            }
+        }
+    }
+
+    #[test]
+    fn queue_rows_track_depth_and_delay_per_interval() {
+        let q = QueueStats::new();
+        for _ in 0..3 {
+            q.enqueue();
+        }
+        q.dequeue(Duration::from_millis(2));
+        q.dequeue(Duration::from_millis(10));
+        let row = q.row(1.0);
+        assert_eq!((row.depth, row.peak_depth, row.dequeued), (1, 3, 2));
+        assert!((row.delay_max_ms - 10.0).abs() < 0.1, "{:?}", row);
+
+        // The next interval starts from the current depth
+        q.dequeue(Duration::from_millis(40));
+        let row = q.row(2.0);
+        assert_eq!((row.depth, row.peak_depth, row.dequeued), (0, 1, 1));
+        let summary = q.summary(1);
+        assert_eq!((summary.dequeued, summary.max_depth), (3, 3));
+        assert!((summary.delay_max_ms - 40.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn queue_delays_past_a_minute_are_kept() {
+        let q = QueueStats::new();
+        q.enqueue();
+        q.dequeue(Duration::from_secs(600));
+        let row = q.row(1.0);
+        assert_eq!(row.dequeued, 1);
+        assert!(
+            (row.delay_max_ms / 600_000.0 - 1.0).abs() < 0.01,
+            "{:?}",
+            row
+        );
+        assert!((q.summary(1).delay_p99_ms / 600_000.0 - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn chunk_counts_parse_and_stay_in_range() {
+        assert_eq!(parse_chunk_count("4"), Some(ChunkCount { min: 4, max: 4 }));
+        assert_eq!(
+            parse_chunk_count("2..8"),
+            Some(ChunkCount { min: 2, max: 8 })
+        );
+        for bad in ["0", "5..2", "0..3", "x", "1..", "-1"] {
+            assert_eq!(parse_chunk_count(bad), None, "{}", bad);
+        }
+
+        let mut rng = XorShift64::new(11);
+        let range = parse_chunk_count("2..8").unwrap();
+        let samples: Vec<u32> = (0..10_000).map(|_| range.sample(&mut rng)).collect();
+        assert!(samples.iter().all(|k| (2..=8).contains(k)));
+        assert!(samples.contains(&2) && samples.contains(&8));
+        assert_eq!(parse_chunk_count("3").unwrap().sample(&mut rng), 3);
+    }
+}
@@ -20,13 +20,12 @@ use crate::transport::{ConnectOptions, Engine};
 use anyhow::Result;
 use bytes::Bytes;
 use rumqttc::{AsyncClient, Event, Incoming, MqttOptions, QoS};
-use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
 use tokio::sync::mpsc;
 use tokio::time::interval;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 
 pub struct ReliablePublisherConfig {
     pub engine: Engine,
@@ -156,7 +155,7 @@ pub async fn run_reliable_publisher(config: ReliablePublisherConfig) -> Result<(
     let mut confirmed_sequence = 0u64; // Last sequence that was ACKed
     let mut pending_sequence = 0u64; // Next sequence to send
     let start_time = std::time::Instant::now();
-    let mut rate_controller = config.rate.map(|r| RateController::new(r));
+    let mut rate_controller = config.rate.map(RateController::new);
     let mut stopped = false;
     let ack_timeout = Duration::from_secs(config.ack_timeout_secs);
 
@@ -170,11 +169,11 @@ pub async fn run_reliable_publisher(config: ReliablePublisherConfig) -> Result<(
     // Outer loop: handles reconnection after crashes
     'reconnect: while !stopped {
         // Check duration limit before connecting
-        if let Some(duration) = config.duration_secs {
-            if start_time.elapsed().as_secs() >= duration {
-                info!("Duration limit reached, stopping reliable publisher");
-                break;
-            }
+        if let Some(duration) = config.duration_secs
+            && start_time.elapsed().as_secs() >= duration
+        {
+            info!("Duration limit reached, stopping reliable publisher");
+            break;
         }
 
         // On reconnect after crash, resume from last confirmed sequence
@@ -193,17 +192,12 @@ pub async fn run_reliable_publisher(config: ReliablePublisherConfig) -> Result<(
         options.set_keep_alive(Duration::from_secs(30));
         options.set_clean_session(clean_session);
         options.set_max_packet_size(16 * 1024 * 1024, 16 * 1024 * 1024);
-        
+
         let (client, mut eventloop) = AsyncClient::new(options, 1024);
 
         // Channel for ACK notifications
         let (ack_tx, mut ack_rx) = mpsc::channel::<AckEvent>(64);
 
-        // Track inflight messages: packet_id -> sequence_number
-        let inflight: Arc<tokio::sync::Mutex<HashMap<u16, u64>>> =
-            Arc::new(tokio::sync::Mutex::new(HashMap::new()));
-        let inflight_clone = Arc::clone(&inflight);
-
         // Spawn eventloop poller that sends ACK notifications
         let ack_tx_clone = ack_tx.clone();
         let poller = tokio::spawn(async move {
@@ -235,24 +229,23 @@ pub async fn run_reliable_publisher(config: ReliablePublisherConfig) -> Result<(
         // Inner publishing loop
         let crash_triggered = 'publish: loop {
             // Check duration limit
-            if let Some(duration) = config.duration_secs {
-                if start_time.elapsed().as_secs() >= duration {
-                    info!("Duration limit reached, stopping reliable publisher");
-                    stopped = true;
-                    break 'publish false;
-                }
+            if let Some(duration) = config.duration_secs
+                && start_time.elapsed().as_secs() >= duration
+            {
+                info!("Duration limit reached, stopping reliable publisher");
+                stopped = true;
+                break 'publish false;
             }
 
             // Check for crash injection
             if crash_injector.is_enabled()
                 && crash_injector.has_crashes_remaining()
                 && crash_injector.should_crash()
+                && crash_injector.consume_crash()
             {
-                if crash_injector.consume_crash() {
-                    info!("Crash injection triggered");
-                    stats.record_crash_injected();
-                    break 'publish true;
-                }
+                info!("Crash injection triggered");
+                stats.record_crash_injected();
+                break 'publish true;
             }
 
             // Determine if we should wait for crash timer
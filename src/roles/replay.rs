@@ -0,0 +1,448 @@
+//! Trace replay publisher.
+//!
+//! Reads a recorded message log (`timestamp,topic,size` as CSV, or JSONL objects with
+//! the same fields) and publishes it with the original inter-arrival times and sizes.
+//! Recorded topics are rewritten by prefix rules and placed under a benchmark prefix;
+//! payloads carry the usual header, numbered by one sequence across all topics, so a
+//! wildcard subscriber measures latency, gaps and duplicates as usual.
+
+use crate::metrics::stats::Stats;
+use crate::output::OutputWriter;
+use crate::payload::generate_payload;
+use crate::transport::{ConnectOptions, Engine, Publisher, TransportBuilder, TransportError};
+use anyhow::{Context, Result, bail};
+use bytes::Bytes;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal;
+use tokio::time::{Instant, interval, sleep_until};
+use tracing::{debug, info, warn};
+
+/// Smallest payload that still fits the message header
+const MIN_PAYLOAD: usize = 24;
+
+/// One recorded message, timed from the first record of the trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceRecord {
+    pub offset: Duration,
+    pub topic: String,
+    pub size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawTimestamp {
+    /// Seconds (UNIX or any other origin; only differences matter)
+    Secs(f64),
+    /// RFC 3339
+    Text(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct RawRecord {
+    timestamp: RawTimestamp,
+    topic: String,
+    size: usize,
+}
+
+impl RawTimestamp {
+    fn secs(&self) -> Result<f64> {
+        let secs = match self {
+            RawTimestamp::Secs(s) => *s,
+            RawTimestamp::Text(t) => match t.trim().parse::<f64>() {
+                Ok(s) => s,
+                Err(_) => {
+                    let ts = chrono::DateTime::parse_from_rfc3339(t.trim())
+                        .with_context(|| format!("bad timestamp '{}'", t))?;
+                    ts.timestamp() as f64 + ts.timestamp_subsec_nanos() as f64 / 1e9
+                }
+            },
+        };
+        if !secs.is_finite() {
+            bail!("timestamp {} is not a finite number of seconds", secs);
+        }
+        Ok(secs)
+    }
+}
+
+/// A record whose timestamp has been checked, with the trace line it came from
+#[derive(Debug)]
+struct TimedRecord {
+    line: usize,
+    secs: f64,
+    topic: String,
+    size: usize,
+}
+
+impl RawRecord {
+    fn timed(self, line: usize) -> Result<TimedRecord> {
+        Ok(TimedRecord {
+            line,
+            secs: self
+                .timestamp
+                .secs()
+                .with_context(|| format!("line {}", line))?,
+            topic: self.topic,
+            size: self.size,
+        })
+    }
+}
+
+/// Load a trace: `.jsonl`/`.ndjson` files as JSON lines, anything else as CSV with a header.
+/// Records are sorted by time (stable, so equal timestamps keep file order).
+pub fn load_trace(path: &Path) -> Result<Vec<TraceRecord>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("reading trace {}", path.display()))?;
+    let jsonl = matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("jsonl" | "ndjson")
+    );
+    let raw = if jsonl {
+        parse_jsonl(&text)
+    } else {
+        parse_csv(&text)
+    }
+    .with_context(|| format!("parsing trace {}", path.display()))?;
+    to_records(raw)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<TimedRecord>> {
+    let mut rd = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(text.as_bytes());
+    let headers = rd.headers()?.clone();
+    rd.records()
+        .map(|r| {
+            let rec = r?;
+            let line = rec.position().map_or(0, |p| p.line() as usize);
+            let raw: RawRecord = rec
+                .deserialize(Some(&headers))
+                .with_context(|| format!("line {}", line))?;
+            raw.timed(line)
+        })
+        .collect()
+}
+
+fn parse_jsonl(text: &str) -> Result<Vec<TimedRecord>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            let raw: RawRecord =
+                serde_json::from_str(l).with_context(|| format!("line {}", i + 1))?;
+            raw.timed(i + 1)
+        })
+        .collect()
+}
+
+fn to_records(mut timed: Vec<TimedRecord>) -> Result<Vec<TraceRecord>> {
+    if timed.is_empty() {
+        bail!("trace has no records");
+    }
+    timed.sort_by(|a, b| a.secs.total_cmp(&b.secs));
+    let t0 = timed[0].secs;
+    timed
+        .into_iter()
+        .map(|r| {
+            let offset = Duration::try_from_secs_f64(r.secs - t0).with_context(|| {
+                format!(
+                    "line {}: timestamp is {} s after the first record",
+                    r.line,
+                    r.secs - t0
+                )
+            })?;
+            Ok(TraceRecord {
+                offset,
+                topic: r.topic,
+                size: r.size,
+            })
+        })
+        .collect()
+}
+
+/// Parse a `FROM=TO` topic remapping rule
+pub fn parse_topic_map(s: &str) -> Option<(String, String)> {
+    let (from, to) = s.split_once('=')?;
+    (!from.is_empty()).then(|| (from.to_string(), to.to_string()))
+}
+
+/// Benchmark topic for a recorded one: the first rule whose FROM prefixes the topic
+/// swaps it for TO, and the result is placed under `prefix` (if non-empty)
+pub fn map_topic(topic: &str, rules: &[(String, String)], prefix: &str) -> String {
+    let mapped = rules
+        .iter()
+        .find_map(|(from, to)| {
+            topic
+                .strip_prefix(from.as_str())
+                .map(|rest| format!("{}{}", to, rest))
+        })
+        .unwrap_or_else(|| topic.to_string());
+    let mapped = mapped.trim_matches('/');
+    let prefix = prefix.trim_end_matches('/');
+    match (prefix.is_empty(), mapped.is_empty()) {
+        (true, _) => mapped.to_string(),
+        (false, true) => prefix.to_string(),
+        (false, false) => format!("{}/{}", prefix, mapped),
+    }
+}
+
+pub struct ReplayConfig {
+    pub engine: Engine,
+    pub connect: ConnectOptions,
+    pub trace: Vec<TraceRecord>,
+    /// Benchmark prefix the (remapped) recorded topics are placed under
+    pub topic_prefix: String,
+    /// `(FROM, TO)` topic prefix rewrites, first match wins
+    pub topic_map: Vec<(String, String)>,
+    /// Replay speed: 2.0 halves every recorded gap
+    pub speed: f64,
+    /// Passes over the trace (0: repeat until the duration ends)
+    pub loops: u32,
+    pub duration_secs: Option<u64>,
+    pub output_file: Option<String>,
+    pub snapshot_interval_secs: u64,
+    // Aggregation support
+    pub shared_stats: Option<Arc<Stats>>, // when set, use this shared collector
+    pub disable_internal_snapshot: bool,  // when true, do not launch internal snapshot logger
+}
+
+/// Trace time between the starts of consecutive passes: the span plus one mean gap,
+/// so the last and first records are not sent back to back
+fn loop_period(trace: &[TraceRecord]) -> Duration {
+    let span = trace.last().map(|r| r.offset).unwrap_or_default();
+    match trace.len() {
+        0 | 1 => Duration::from_secs(1),
+        n => span + span / (n as u32 - 1),
+    }
+}
+
+pub async fn run_replay(config: ReplayConfig) -> Result<()> {
+    if config.trace.is_empty() {
+        bail!("trace has no records");
+    }
+    if config.speed.is_nan() || config.speed <= 0.0 {
+        bail!("replay speed must be positive");
+    }
+    let topics: Vec<String> = config
+        .trace
+        .iter()
+        .map(|r| map_topic(&r.topic, &config.topic_map, &config.topic_prefix))
+        .collect();
+    info!(
+        engine = ?config.engine,
+        records = config.trace.len(),
+        span_secs = config.trace.last().unwrap().offset.as_secs_f64(),
+        speed = config.speed,
+        loops = config.loops,
+        duration_secs = ?config.duration_secs,
+        endpoint = ?config.connect.params.get("endpoint"),
+        "Starting trace replay"
+    );
+
+    let stats = if let Some(s) = &config.shared_stats {
+        s.clone()
+    } else {
+        Arc::new(Stats::new())
+    };
+
+    let mut output = if let Some(ref path) = config.output_file {
+        Some(OutputWriter::new_csv(path.clone()).await?)
+    } else if config.shared_stats.is_none() {
+        Some(OutputWriter::new_stdout())
+    } else {
+        None
+    };
+
+    let snapshot_handle = if !config.disable_internal_snapshot {
+        let stats_clone = Arc::clone(&stats);
+        let interval_secs = config.snapshot_interval_secs;
+        Some(tokio::spawn(async move {
+            let mut interval_timer = interval(Duration::from_secs(interval_secs));
+            loop {
+                interval_timer.tick().await;
+                let snap = stats_clone.snapshot().await;
+                debug!(
+                    sent = snap.sent_count,
+                    errors = snap.error_count,
+                    "Replay stats"
+                );
+            }
+        }))
+    } else {
+        None
+    };
+
+    stats.record_connection_attempt();
+    let transport =
+        match TransportBuilder::connect_with_retry(config.engine.clone(), config.connect.clone())
+            .await
+        {
+            Ok(t) => t,
+            Err(e) => {
+                warn!(error = %e, "Transport connect error");
+                stats.record_connection_failure();
+                return Ok(());
+            }
+        };
+
+    // Declare every topic up front so the first message on a topic is not delayed
+    let mut publishers: HashMap<&str, Box<dyn Publisher>> = HashMap::new();
+    for topic in &topics {
+        if publishers.contains_key(topic.as_str()) {
+            continue;
+        }
+        let p = transport
+            .create_publisher(topic)
+            .await
+            .map_err(|e| anyhow::anyhow!("create_publisher error ({}): {}", topic, e))?;
+        stats.increment_connections();
+        publishers.insert(topic, p);
+    }
+    info!(topics = publishers.len(), "Declared replay topics");
+
+    let period = loop_period(&config.trace);
+    let limit = config.duration_secs.map(Duration::from_secs);
+    let start = Instant::now();
+    let ctrl_c = signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut sequence = 0u64;
+
+    'replay: for pass in 0u32.. {
+        if config.loops > 0 && pass >= config.loops {
+            break;
+        }
+        for (record, topic) in config.trace.iter().zip(&topics) {
+            let at = (period * pass + record.offset).div_f64(config.speed);
+            if limit.is_some_and(|l| at >= l) {
+                info!("Duration limit reached, stopping replay");
+                break 'replay;
+            }
+            // A late wake-up sends immediately and catches up on the schedule
+            tokio::select! {
+                _ = sleep_until(start + at) => {}
+                _ = &mut ctrl_c => {
+                    info!("Ctrl+C received, stopping replay");
+                    break 'replay;
+                }
+            }
+            let payload = generate_payload(sequence, record.size.max(MIN_PAYLOAD));
+            #[cfg(feature = "otel")]
+            let (payload, _span) = crate::otel::start_publish(sequence, payload);
+            match publishers[topic.as_str()]
+                .publish(Bytes::from(payload))
+                .await
+            {
+                Ok(_) => {
+                    stats.record_sent().await;
+                    sequence += 1;
+                }
+                Err(e) => {
+                    warn!(error = %e, topic = %topic, "Send error");
+                    stats.record_error().await;
+                    if matches!(e, TransportError::Disconnected) {
+                        break 'replay;
+                    }
+                }
+            }
+        }
+    }
+
+    drop(publishers);
+    let _ = transport.shutdown().await;
+
+    let final_stats = stats.snapshot().await;
+    info!(
+        sent = final_stats.sent_count,
+        errors = final_stats.error_count,
+        duration = format!("{:.2}s", final_stats.total_duration.as_secs_f64()),
+        "Final Replay Statistics"
+    );
+    if let Some(ref mut out) = output {
+        out.write_snapshot(&final_stats).await?;
+    }
+    if let Some(h) = snapshot_handle {
+        h.abort();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_and_jsonl_traces_load_sorted_and_relative() {
+        let csv = "timestamp,topic,size\n\
+                   1700000000.5, gw/1/temp, 64\n\
+                   1700000000.0, gw/2/temp, 10\n\
+                   1700000001.0, gw/1/alarm, 2048\n";
+        let recs = to_records(parse_csv(csv).unwrap()).unwrap();
+        assert_eq!(recs[0].topic, "gw/2/temp");
+        assert_eq!(recs[1].offset, Duration::from_millis(500));
+        assert_eq!(recs[2].size, 2048);
+
+        let jsonl = r#"{"timestamp":"2025-01-01T00:00:00Z","topic":"a","size":100}
+
+{"timestamp":"2025-01-01T00:00:00.250Z","topic":"b","size":200}"#;
+        let recs = to_records(parse_jsonl(jsonl).unwrap()).unwrap();
+        assert_eq!(recs.len(), 2);
+        assert_eq!(recs[1].offset, Duration::from_millis(250));
+
+        assert!(to_records(parse_csv("timestamp,topic,size\n").unwrap()).is_err());
+        assert!(parse_csv("timestamp,topic,size\nx,a,1\n").is_err());
+    }
+
+    #[test]
+    fn unusable_timestamps_fail_with_their_line() {
+        let err = |r: Result<Vec<TraceRecord>>| format!("{:#}", r.unwrap_err());
+        let csv = "timestamp,topic,size\n0,a,1\ninf,b,1\n";
+        let e = err(parse_csv(csv).and_then(to_records));
+        assert!(e.contains("line 3") && e.contains("finite"), "{}", e);
+
+        let jsonl = "{\"timestamp\":0,\"topic\":\"a\",\"size\":1}\n\
+                     {\"timestamp\":\"NaN\",\"topic\":\"b\",\"size\":1}";
+        let e = err(parse_jsonl(jsonl).and_then(to_records));
+        assert!(e.contains("line 2") && e.contains("finite"), "{}", e);
+
+        // Finite, but too far apart for a Duration
+        let csv = "timestamp,topic,size\n0,a,1\n1e300,b,1\n";
+        let e = err(parse_csv(csv).and_then(to_records));
+        assert!(e.contains("line 3"), "{}", e);
+    }
+
+    #[test]
+    fn topics_are_remapped_under_prefix() {
+        let rules = vec![
+            parse_topic_map("gw/=site/").unwrap(),
+            parse_topic_map("/legacy=").unwrap(),
+        ];
+        assert_eq!(
+            map_topic("gw/7/temp", &rules, "bench/replay"),
+            "bench/replay/site/7/temp"
+        );
+        assert_eq!(
+            map_topic("/legacy/x", &rules, "bench/replay/"),
+            "bench/replay/x"
+        );
+        assert_eq!(map_topic("other", &rules, ""), "other");
+        assert_eq!(parse_topic_map("=x"), None);
+        assert_eq!(parse_topic_map("novalue"), None);
+    }
+
+    #[test]
+    fn loop_period_adds_one_mean_gap() {
+        let rec = |ms| TraceRecord {
+            offset: Duration::from_millis(ms),
+            topic: "t".into(),
+            size: 1,
+        };
+        assert_eq!(
+            loop_period(&[rec(0), rec(100), rec(400)]),
+            Duration::from_millis(600)
+        );
+        assert_eq!(loop_period(&[rec(0)]), Duration::from_secs(1));
+    }
+}
@@ -1,16 +1,20 @@
 use crate::metrics::stats::Stats;
 use crate::output::OutputWriter;
-use crate::rate::RateController;
-use crate::transport::{ConnectOptions, Engine, TransportBuilder};
-use anyhow::Result;
+use crate::payload::{chunk_tag, generate_payload};
+use crate::rate::ArrivalConfig;
+use crate::transport::{ConnectOptions, Engine, Transport, TransportBuilder, TransportError};
+use anyhow::{Result, bail};
 use bytes::Bytes;
-use futures::Future;
-use std::pin::Pin;
+use flume;
+use futures::StreamExt;
+use hdrhistogram::Histogram;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-// use tokio::signal;
-use flume;
-use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
@@ -18,8 +22,18 @@ pub struct RequesterConfig {
     pub engine: Engine,
     pub connect: ConnectOptions,
     pub key_expr: String,
+    /// Request body size: 0 sends an empty body, otherwise at least 24 bytes starting
+    /// with the payload header (sequence and send time)
+    pub request_size: usize,
     pub qps: Option<u32>,
+    /// How queries are spread around `qps`
+    pub arrival: ArrivalConfig,
     pub concurrency: u32,
+    /// Stream each query's replies to the end (`Transport::request_stream`); the whole
+    /// stream must finish within `timeout_ms` and its latency is the last chunk's
+    pub stream: bool,
+    /// With stream: write `stream.json` (first/last-chunk latency, chunk loss) here
+    pub stream_dir: Option<PathBuf>,
     pub timeout_ms: u64,
     pub duration_secs: u64,
     pub output_file: Option<String>,
@@ -29,11 +43,228 @@ pub struct RequesterConfig {
     pub disable_internal_snapshot: bool,  // when true, do not launch internal snapshot logger
 }
 
+/// Terminal outcome of a dispatched query. Every query yields exactly one.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Outcome {
+    /// Reply received before the deadline, with its round-trip latency (ns)
+    Reply(u64),
+    /// Reply stream ended before the deadline
+    Stream(StreamReply),
+    /// Transport reported an error before the deadline
+    Error,
+    /// No reply before the deadline nor within the late-reply grace window, with what
+    /// a streamed query received before giving up
+    Timeout(Option<StreamReply>),
+    /// Reply arrived after the deadline but within the grace window (for a stream, its
+    /// end), with what a streamed query received by then
+    Late(Option<StreamReply>),
+}
+
+/// One completed reply stream; latencies from dispatch (ns)
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StreamReply {
+    pub(crate) first_ns: u64,
+    pub(crate) last_ns: u64,
+    /// Distinct chunks received
+    pub(crate) chunks: u32,
+    /// Chunks the queryable sent, from the chunk tags
+    pub(crate) expected: u32,
+}
+
+/// Replies seen so far on one stream. The caller keeps it, so a stream that misses
+/// its deadline still reports the chunks and first-chunk latency it got.
+#[derive(Debug, Default)]
+struct StreamProgress {
+    first_ns: Option<u64>,
+    seen: BTreeSet<u32>,
+    untagged: u32,
+    expected: u32,
+}
+
+impl StreamProgress {
+    /// Counts so far, ending at `last_ns`; None if no reply arrived
+    fn reply(&self, last_ns: u64) -> Option<StreamReply> {
+        let first_ns = self.first_ns?;
+        let chunks = self.seen.len() as u32 + self.untagged;
+        Some(StreamReply {
+            first_ns,
+            last_ns,
+            chunks,
+            expected: self.expected.max(chunks),
+        })
+    }
+}
+
+/// Read a reply stream to its end, recording each reply in `progress`
+async fn read_stream(
+    transport: &dyn Transport,
+    key_expr: &str,
+    body: Bytes,
+    t0: Instant,
+    progress: &std::sync::Mutex<StreamProgress>,
+) -> Result<StreamReply, TransportError> {
+    let mut replies = transport.request_stream(key_expr, body).await?;
+    while let Some(reply) = replies.next().await {
+        let reply = reply?;
+        let mut p = progress.lock().unwrap();
+        p.first_ns.get_or_insert(t0.elapsed().as_nanos() as u64);
+        // Untagged replies (single-reply adapters, echo) count as one chunk each
+        match chunk_tag(&reply.as_cow()) {
+            Some((index, total)) => {
+                p.seen.insert(index);
+                p.expected = p.expected.max(total);
+            }
+            None => p.untagged += 1,
+        }
+    }
+    progress
+        .lock()
+        .unwrap()
+        .reply(t0.elapsed().as_nanos() as u64)
+        .ok_or_else(|| TransportError::Request("reply stream ended empty".into()))
+}
+
+/// Send one query (or read one reply stream) and classify it. `permit` is released at
+/// the deadline; a timed-out query then keeps waiting for a late reply for one more
+/// timeout period so it is classified exactly once. A query that fails within that
+/// grace period counts as an error, not a timeout.
+pub(crate) async fn run_query(
+    transport: &dyn Transport,
+    key_expr: &str,
+    body: Bytes,
+    stream: bool,
+    timeout: Duration,
+    permit: OwnedSemaphorePermit,
+) -> Outcome {
+    let t0 = Instant::now();
+    let progress = std::sync::Mutex::new(StreamProgress::default());
+    let fut = async {
+        if stream {
+            read_stream(transport, key_expr, body, t0, &progress)
+                .await
+                .map(Outcome::Stream)
+        } else {
+            let _payload = transport.request(key_expr, body).await?;
+            Ok(Outcome::Reply(t0.elapsed().as_nanos() as u64))
+        }
+    };
+    tokio::pin!(fut);
+    match tokio::time::timeout(timeout, &mut fut).await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(e)) => {
+            warn!(key = %key_expr, error = %e, "Requester query error");
+            Outcome::Error
+        }
+        Err(_) => {
+            // Free the slot now; keep listening for a late reply
+            drop(permit);
+            let late = tokio::time::timeout(timeout, &mut fut).await;
+            let partial = progress
+                .lock()
+                .unwrap()
+                .reply(t0.elapsed().as_nanos() as u64);
+            match late {
+                Ok(Ok(_payload)) => Outcome::Late(partial),
+                Ok(Err(e)) => {
+                    warn!(key = %key_expr, error = %e, "Requester query error after deadline");
+                    Outcome::Error
+                }
+                Err(_) => Outcome::Timeout(partial),
+            }
+        }
+    }
+}
+
+/// Chunk accounting and first/last-chunk latency over streams that got at least one
+/// reply. Streams that missed the deadline count toward chunks and first-chunk latency
+/// only.
+struct StreamStats {
+    first: Histogram<u64>,
+    last: Histogram<u64>,
+    streams: u64,
+    chunks: u64,
+    expected: u64,
+    incomplete: u64,
+    missed_deadline: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamSummary {
+    pub streams: u64,
+    /// Streams missing at least one chunk or their end
+    pub incomplete_streams: u64,
+    /// Streams that had not ended by the deadline (late or timed out)
+    pub missed_deadline_streams: u64,
+    pub chunks_received: u64,
+    pub chunks_expected: u64,
+    pub chunk_loss_ratio: f64,
+    pub first_chunk_p50_ms: f64,
+    pub first_chunk_p99_ms: f64,
+    pub last_chunk_p50_ms: f64,
+    pub last_chunk_p99_ms: f64,
+    pub last_chunk_max_ms: f64,
+}
+
+impl StreamStats {
+    fn new() -> Self {
+        let hist = || Histogram::new_with_bounds(1, 60_000_000_000, 3).unwrap();
+        Self {
+            first: hist(),
+            last: hist(),
+            streams: 0,
+            chunks: 0,
+            expected: 0,
+            incomplete: 0,
+            missed_deadline: 0,
+        }
+    }
+
+    /// Record a stream; `ended` is false when it missed the deadline
+    fn record(&mut self, r: StreamReply, ended: bool) {
+        self.streams += 1;
+        let _ = self.first.record(r.first_ns.max(1));
+        if ended {
+            let _ = self.last.record(r.last_ns.max(1));
+        } else {
+            self.missed_deadline += 1;
+        }
+        self.chunks += r.chunks as u64;
+        self.expected += r.expected as u64;
+        if r.chunks < r.expected || !ended {
+            self.incomplete += 1;
+        }
+    }
+
+    fn summary(&self) -> StreamSummary {
+        let ms = |ns: u64| ns as f64 / 1e6;
+        StreamSummary {
+            streams: self.streams,
+            incomplete_streams: self.incomplete,
+            missed_deadline_streams: self.missed_deadline,
+            chunks_received: self.chunks,
+            chunks_expected: self.expected,
+            chunk_loss_ratio: if self.expected > 0 {
+                1.0 - self.chunks as f64 / self.expected as f64
+            } else {
+                0.0
+            },
+            first_chunk_p50_ms: ms(self.first.value_at_quantile(0.50)),
+            first_chunk_p99_ms: ms(self.first.value_at_quantile(0.99)),
+            last_chunk_p50_ms: ms(self.last.value_at_quantile(0.50)),
+            last_chunk_p99_ms: ms(self.last.value_at_quantile(0.99)),
+            last_chunk_max_ms: ms(self.last.max()),
+        }
+    }
+}
+
 pub async fn run_requester(config: RequesterConfig) -> Result<()> {
     info!(
         engine = ?config.engine,
         key_expr = %config.key_expr,
+        request_size = config.request_size,
+        stream = config.stream,
         qps = ?config.qps,
+        arrival = ?config.arrival.model,
         concurrency = config.concurrency,
         timeout_ms = config.timeout_ms,
         duration_secs = config.duration_secs,
@@ -41,6 +272,13 @@ pub async fn run_requester(config: RequesterConfig) -> Result<()> {
         "Starting requester"
     );
 
+    if (1..24).contains(&config.request_size) {
+        bail!(
+            "request size {} is below the 24-byte payload header (use 0 for an empty body)",
+            config.request_size
+        );
+    }
+
     // Stats early so we can track connection failures
     let stats = if let Some(s) = &config.shared_stats {
         s.clone()
@@ -50,19 +288,17 @@ pub async fn run_requester(config: RequesterConfig) -> Result<()> {
 
     // Transport session with optional retry
     stats.record_connection_attempt();
-    let transport: Arc<Box<dyn crate::transport::Transport>> = match TransportBuilder::connect_with_retry(
-        config.engine.clone(),
-        config.connect.clone(),
-    )
-    .await
-    {
-        Ok(t) => Arc::from(t),
-        Err(e) => {
-            error!(error = %e, "Transport connect error");
-            stats.record_connection_failure();
-            return Ok(());
-        }
-    };
+    let transport: Arc<Box<dyn Transport>> =
+        match TransportBuilder::connect_with_retry(config.engine.clone(), config.connect.clone())
+            .await
+        {
+            Ok(t) => Arc::from(t),
+            Err(e) => {
+                error!(error = %e, "Transport connect error");
+                stats.record_connection_failure();
+                return Ok(());
+            }
+        };
 
     let mut output = if let Some(ref path) = config.output_file {
         Some(OutputWriter::new_csv(path.clone()).await?)
@@ -72,26 +308,50 @@ pub async fn run_requester(config: RequesterConfig) -> Result<()> {
         None
     };
 
-    // Stats worker channel: batch updates to avoid contention from many tasks
-    #[derive(Clone, Copy)]
-    enum Ev {
-        Sent,
-        Recv(u64),
-        Err,
-    }
-    let (tx, rx) = flume::bounded::<Ev>(10_000);
-    {
-        let stats_worker = stats.clone();
-        tokio::spawn(async move {
-            while let Ok(ev) = rx.recv_async().await {
-                match ev {
-                    Ev::Sent => stats_worker.record_sent().await,
-                    Ev::Recv(ns) => stats_worker.record_received(ns).await,
-                    Ev::Err => stats_worker.record_error().await,
+    // Outcome channel + batching worker, mirroring the subscriber's lossless path:
+    // unbounded so no outcome is ever dropped, drained in batches to amortize locking.
+    let (tx, rx) = flume::unbounded::<Outcome>();
+    let stats_worker = stats.clone();
+    let worker_handle = tokio::spawn(async move {
+        let mut buf = Vec::with_capacity(1024);
+        let mut latencies = Vec::with_capacity(1024);
+        let mut streams = StreamStats::new();
+        while let Ok(first) = rx.recv_async().await {
+            buf.clear();
+            buf.push(first);
+            while let Ok(v) = rx.try_recv() {
+                buf.push(v);
+                if buf.len() >= 1024 {
+                    break;
                 }
             }
-        });
-    }
+            latencies.clear();
+            for outcome in buf.drain(..) {
+                match outcome {
+                    Outcome::Reply(ns) => latencies.push(ns),
+                    Outcome::Stream(r) => {
+                        latencies.push(r.last_ns);
+                        streams.record(r, true);
+                    }
+                    Outcome::Error => stats_worker.record_error().await,
+                    Outcome::Timeout(partial) => {
+                        stats_worker.record_timeout().await;
+                        if let Some(r) = partial {
+                            streams.record(r, false);
+                        }
+                    }
+                    Outcome::Late(partial) => {
+                        stats_worker.record_late_reply().await;
+                        if let Some(r) = partial {
+                            streams.record(r, false);
+                        }
+                    }
+                }
+            }
+            stats_worker.record_received_batch(&latencies).await;
+        }
+        streams
+    });
 
     // Snapshot task (optional)
     let snapshot_handle = if !config.disable_internal_snapshot {
@@ -106,6 +366,8 @@ pub async fn run_requester(config: RequesterConfig) -> Result<()> {
                     sent = snap.sent_count,
                     received = snap.received_count,
                     errors = snap.error_count,
+                    timeouts = snap.timeout_count,
+                    late = snap.late_reply_count,
                     "Requester stats"
                 );
             }
@@ -114,81 +376,104 @@ pub async fn run_requester(config: RequesterConfig) -> Result<()> {
         None
     };
 
-    // Query loop
+    // Query loop: a semaphore bounds in-flight queries; see `run_query` for how each
+    // is classified.
     let start = Instant::now();
-    let mut rate = config.qps.map(|q| RateController::new(q as f64));
-    let mut inflight: FuturesUnordered<
-        Pin<Box<dyn Future<Output = Result<Option<Duration>, ()>> + Send>>,
-    > = FuturesUnordered::new();
+    let mut rate = config.arrival.controller(config.qps.map(|q| q as f64), 1.0);
+    if let Some(rc) = &rate {
+        rc.report_target_rate(&stats);
+    }
+    let slots = Arc::new(Semaphore::new(config.concurrency.max(1) as usize));
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut tasks = JoinSet::new();
     let mut total_sent = 0u64;
-    let mut total_recv = 0u64;
 
     loop {
-        // Check duration
         if start.elapsed().as_secs() >= config.duration_secs {
             info!("Duration limit reached, stopping requester");
             break;
         }
-
-        // Maintain concurrency
-        while inflight.len() < config.concurrency as usize {
-            if let Some(rc) = &mut rate {
-                rc.wait_for_next().await;
-            }
-            let key_expr = config.key_expr.clone();
-            let tx_ev = tx.clone();
-            let timeout_ms = config.timeout_ms;
-            let transport = Arc::clone(&transport);
-            let fut: Pin<Box<dyn Future<Output = Result<Option<Duration>, ()>> + Send>> =
-                Box::pin(async move {
-                    let t0 = Instant::now();
-                    // First-reply request with timeout around the transport call
-                    let fut = transport.request(&key_expr, Bytes::new());
-                    match tokio::time::timeout(Duration::from_millis(timeout_ms), fut).await {
-                        Ok(Ok(_payload)) => {
-                            let now = t0.elapsed();
-                            let _ = tx_ev.try_send(Ev::Sent);
-                            let _ = tx_ev.try_send(Ev::Recv(now.as_nanos() as u64));
-                            Ok(Some(now))
-                        }
-                        Ok(Err(e)) => {
-                            warn!(error = %e, "Requester query error");
-                            let _ = tx_ev.try_send(Ev::Err);
-                            Err(())
-                        }
-                        Err(_to) => {
-                            warn!("Requester timeout");
-                            let _ = tx_ev.try_send(Ev::Err);
-                            Err(())
-                        }
-                    }
-                });
-            inflight.push(fut);
-            total_sent += 1;
+        if let Some(rc) = &mut rate {
+            rc.wait_for_next().await;
         }
-
-        // Poll for finished queries
-        if let Some(_res) = inflight.next().await {
-            total_recv += 1;
+        let permit = match slots.clone().acquire_owned().await {
+            Ok(p) => p,
+            Err(_) => break,
+        };
+        // Waiting for a free slot may have crossed the deadline
+        if start.elapsed().as_secs() >= config.duration_secs {
+            info!("Duration limit reached, stopping requester");
+            break;
         }
-    }
 
-    // Drain remaining inflight
-    while let Some(_res) = inflight.next().await {
-        total_recv += 1;
+        // Count at dispatch so failed queries are still accounted as sent
+        stats.record_sent().await;
+        let body = if config.request_size > 0 {
+            Bytes::from(generate_payload(total_sent, config.request_size))
+        } else {
+            Bytes::new()
+        };
+        total_sent += 1;
+
+        let key_expr = config.key_expr.clone();
+        let transport = Arc::clone(&transport);
+        let tx_outcome = tx.clone();
+        let stream = config.stream;
+        tasks.spawn(async move {
+            let outcome = run_query(
+                transport.as_ref().as_ref(),
+                &key_expr,
+                body,
+                stream,
+                timeout,
+                permit,
+            )
+            .await;
+            let _ = tx_outcome.send(outcome);
+        });
+
+        // Reap finished tasks so the set does not grow over long runs
+        while tasks.try_join_next().is_some() {}
     }
 
+    // Drain remaining in-flight (and late-watching) queries, then the outcome worker
+    while tasks.join_next().await.is_some() {}
+    drop(tx);
+    let streams = worker_handle.await?;
+
     // Final stats
     let final_stats = stats.snapshot().await;
     info!(
         queries_sent = total_sent,
-        queries_completed = total_recv,
+        replies = final_stats.received_count,
         errors = final_stats.error_count,
+        timeouts = final_stats.timeout_count,
+        late_replies = final_stats.late_reply_count,
         "Final Requester Statistics"
     );
     if let Some(ref mut out) = output {
         out.write_snapshot(&final_stats).await?;
     }
+    if config.stream {
+        let summary = streams.summary();
+        info!(
+            streams = summary.streams,
+            incomplete = summary.incomplete_streams,
+            chunks = summary.chunks_received,
+            chunk_loss = format!("{:.4}", summary.chunk_loss_ratio),
+            first_p50_ms = format!("{:.3}", summary.first_chunk_p50_ms),
+            last_p50_ms = format!("{:.3}", summary.last_chunk_p50_ms),
+            last_p99_ms = format!("{:.3}", summary.last_chunk_p99_ms),
+            "Requester streams"
+        );
+        if let Some(dir) = &config.stream_dir {
+            std::fs::create_dir_all(dir)?;
+            std::fs::write(
+                dir.join("stream.json"),
+                serde_json::to_string_pretty(&summary)?,
+            )?;
+        }
+    }
 
     if let Some(h) = snapshot_handle {
         h.abort();
@@ -200,3 +485,31 @@ pub async fn run_requester(config: RequesterConfig) -> Result<()> {
         .map_err(|e| anyhow::Error::msg(format!("transport shutdown error: {}", e)))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_stats_count_lost_chunks() {
+        let mut s = StreamStats::new();
+        let ms = 1_000_000;
+        let reply = |first: u64, last: u64, chunks| StreamReply {
+            first_ns: first * ms,
+            last_ns: last * ms,
+            chunks,
+            expected: 4,
+        };
+        s.record(reply(1, 4, 4), true);
+        s.record(reply(2, 8, 2), true);
+        // Missed its deadline: chunks and first chunk count, the last chunk does not
+        s.record(reply(1, 50, 1), false);
+        let summary = s.summary();
+        assert_eq!((summary.streams, summary.incomplete_streams), (3, 2));
+        assert_eq!(summary.missed_deadline_streams, 1);
+        assert_eq!((summary.chunks_received, summary.chunks_expected), (7, 12));
+        assert!((summary.chunk_loss_ratio - 5.0 / 12.0).abs() < 1e-12);
+        assert!((summary.first_chunk_p50_ms - 1.0).abs() < 0.01);
+        assert!((summary.last_chunk_max_ms - 8.0).abs() < 0.01);
+    }
+}
@@ -1,12 +1,14 @@
 use crate::crash::{CrashConfig, CrashInjector};
 use crate::metrics::sequence::SequenceTracker;
+use crate::metrics::sources::SourceTable;
 use crate::metrics::stats::Stats;
 use crate::output::OutputWriter;
-use crate::payload::parse_header;
+use crate::payload::{parse_header, source_tag};
 use crate::time_sync::now_unix_ns_estimate;
 use crate::transport::{ConnectOptions, Engine, Transport, TransportBuilder, TransportMessage};
 use anyhow::Result;
 use flume;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
@@ -27,6 +29,19 @@ pub struct SubscriberConfig {
     pub test_stop_after_secs: Option<u64>,
     // Crash injection
     pub crash_config: CrashConfig,
+    /// Fan-in: account per source tag (mt-pub key) and write `per_source.csv` and
+    /// `fan_in.json` here at the end
+    pub per_source_dir: Option<PathBuf>,
+}
+
+/// Push duplicate/gap/head-loss totals (single stream plus per-source) into the stats
+fn publish_sequence_stats(stats: &Stats, tracker: &SequenceTracker, sources: Option<&SourceTable>) {
+    let (dups, gaps, head) = sources.map_or((0, 0, 0), |s| {
+        (s.duplicate_count(), s.gap_count(), s.head_loss())
+    });
+    stats.set_duplicates(tracker.duplicate_count() + dups);
+    stats.set_gaps(tracker.gap_count() + gaps);
+    stats.set_head_loss(tracker.head_loss() + head);
 }
 
 pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
@@ -57,11 +72,17 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
     // Shared sequence tracker - wrapped in Arc<Mutex<>> so snapshot task can access stats
     // This persists across reconnections to track all sequences throughout the test
     let seq_tracker = Arc::new(Mutex::new(SequenceTracker::new()));
+    // Fan-in: tagged messages are tracked per source instead (sources reuse sequence numbers)
+    let sources = config
+        .per_source_dir
+        .as_ref()
+        .map(|_| Arc::new(Mutex::new(SourceTable::new())));
 
     // Start snapshot task (only if not disabled)
     let snapshot_handle = if !config.disable_internal_snapshot {
         let stats_clone = Arc::clone(&stats);
         let seq_tracker_snap = Arc::clone(&seq_tracker);
+        let sources_snap = sources.clone();
         let interval_secs = config.snapshot_interval_secs;
         let mut out = output.take();
         Some(tokio::spawn(async move {
@@ -71,9 +92,12 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
                 // Update stats with current sequence tracker state before snapshot
                 {
                     let tracker = seq_tracker_snap.lock().await;
-                    stats_clone.set_duplicates(tracker.duplicate_count());
-                    stats_clone.set_gaps(tracker.gap_count());
-                    stats_clone.set_head_loss(tracker.head_loss());
+                    match &sources_snap {
+                        Some(s) => {
+                            publish_sequence_stats(&stats_clone, &tracker, Some(&*s.lock().await))
+                        }
+                        None => publish_sequence_stats(&stats_clone, &tracker, None),
+                    }
                 }
                 let snapshot = stats_clone.snapshot().await;
                 if let Some(ref mut o) = out {
@@ -97,11 +121,12 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
         None
     };
 
-    // Channel + worker to avoid per-message work in callback; send (recv_time, header_bytes)
-    // This channel persists across reconnections
-    let (tx, rx) = flume::unbounded::<(u64, [u8; 24])>();
+    // Channel + worker to avoid per-message work in callback; send (recv_time, header_bytes,
+    // source tag). This channel persists across reconnections
+    let (tx, rx) = flume::unbounded::<(u64, [u8; 24], Option<u32>)>();
     let stats_worker = stats.clone();
     let seq_tracker_worker = Arc::clone(&seq_tracker);
+    let sources_worker = sources.clone();
     let worker_handle = tokio::spawn(async move {
         let mut buf = Vec::with_capacity(1024);
         loop {
@@ -126,12 +151,21 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
             let mut latencies = Vec::with_capacity(buf.len());
             {
                 let mut tracker = seq_tracker_worker.lock().await;
-                for (recv_ns, hdr) in buf.drain(..) {
+                let mut per_source = match &sources_worker {
+                    Some(s) => Some(s.lock().await),
+                    None => None,
+                };
+                for (recv_ns, hdr, source) in buf.drain(..) {
                     if let Ok(h) = parse_header(&hdr) {
+                        let latency = recv_ns.saturating_sub(h.timestamp_ns);
                         // Track sequence (handles duplicates)
-                        if tracker.record(h.seq) {
+                        let new = match (source, per_source.as_mut()) {
+                            (Some(src), Some(table)) => table.record(src, h.seq, latency),
+                            _ => tracker.record(h.seq),
+                        };
+                        if new {
                             // Only record latency for new messages
-                            latencies.push(recv_ns.saturating_sub(h.timestamp_ns));
+                            latencies.push(latency);
                         }
                     }
                 }
@@ -153,11 +187,11 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
     // Outer loop: handles reconnection after crashes
     'reconnect: while !stopped {
         // Check test timeout before connecting
-        if let Some(s) = config.test_stop_after_secs {
-            if start_time.elapsed().as_secs() >= s {
-                info!("Test timeout reached, stopping subscriber");
-                break;
-            }
+        if let Some(s) = config.test_stop_after_secs
+            && start_time.elapsed().as_secs() >= s
+        {
+            info!("Test timeout reached, stopping subscriber");
+            break;
         }
 
         // Initialize Transport with optional retry
@@ -182,6 +216,7 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
 
         // Subscribe via Transport with a handler
         let handler_tx = tx.clone();
+        let tagged = sources.is_some();
         let subscription = match transport
             .subscribe(
                 &config.key_expr,
@@ -192,7 +227,10 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
                     if bytes.len() >= 24 {
                         hdr.copy_from_slice(&bytes[..24]);
                         let recv = now_unix_ns_estimate();
-                        let _ = handler_tx.try_send((recv, hdr));
+                        let source = if tagged { source_tag(&bytes) } else { None };
+                        let _ = handler_tx.try_send((recv, hdr, source));
+                        #[cfg(feature = "otel")]
+                        crate::otel::record_delivery(&bytes, recv);
                     }
                 }),
             )
@@ -247,7 +285,9 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
                 Duration::MAX
             };
 
-            let wait_time = time_to_crash.min(remaining_test_time).min(Duration::from_secs(1));
+            let wait_time = time_to_crash
+                .min(remaining_test_time)
+                .min(Duration::from_secs(1));
 
             tokio::select! {
                 _ = tokio::time::sleep(wait_time) => {
@@ -281,7 +321,10 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
         if crash_triggered && config.connect.retry_enabled {
             // Sample repair time and wait before reconnecting
             let repair_time = crash_injector.sample_repair_time();
-            info!(repair_secs = repair_time.as_secs_f64(), "Simulating repair delay");
+            info!(
+                repair_secs = repair_time.as_secs_f64(),
+                "Simulating repair delay"
+            );
             tokio::time::sleep(repair_time).await;
 
             // Schedule next crash (deterministic timeline includes the repair downtime)
@@ -304,9 +347,21 @@ pub async fn run_subscriber(config: SubscriberConfig) -> Result<()> {
     // Update stats with final duplicate/gap counts from sequence tracker
     {
         let tracker = seq_tracker.lock().await;
-        stats.set_duplicates(tracker.duplicate_count());
-        stats.set_gaps(tracker.gap_count());
-        stats.set_head_loss(tracker.head_loss());
+        match &sources {
+            Some(s) => publish_sequence_stats(&stats, &tracker, Some(&*s.lock().await)),
+            None => publish_sequence_stats(&stats, &tracker, None),
+        }
+    }
+    if let (Some(dir), Some(sources)) = (&config.per_source_dir, &sources) {
+        let summary = sources.lock().await.write(dir)?;
+        info!(
+            sources = summary.sources,
+            jain_delivery = format!("{:.4}", summary.jain_delivery),
+            min_delivery = format!("{:.4}", summary.min_delivery_ratio),
+            worst_source = ?summary.worst_source,
+            max_p99_ms = format!("{:.2}", summary.max_p99_ms),
+            "Fan-in per-source summary"
+        );
     }
 
     // Final statistics
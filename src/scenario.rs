@@ -0,0 +1,761 @@
+//! `mq-bench scenario`: mixed pub/sub and request/reply workloads in one process.
+//!
+//! A YAML scenario lists workloads. Each is one role (`pub`, `sub`, `req` or `qry`)
+//! with its own engine, key and load, tagged with a workload class such as
+//! `telemetry` or `commands`. All workloads run concurrently: receivers (`sub`, `qry`)
+//! come up first, senders (`pub`, `req`) run for the scenario duration, and receivers
+//! stop after a short drain. Every (class, role) pair has its own stats collector and
+//! snapshot series, so one class's latency can be read under the load of the others.
+//!
+//! With `isolate: true` every class first runs alone for the same duration. The
+//! summary then pairs each (class, role) with its isolated baseline and reports the
+//! mixed/isolated latency ratio: the interference the other classes cause.
+//!
+//! ```yaml
+//! duration_secs: 30
+//! isolate: true
+//! engine: zenoh
+//! connect: { endpoint: tcp/127.0.0.1:7447 }
+//! workloads:
+//!   - { class: telemetry, role: sub, key: edge/telemetry, count: 8 }
+//!   - { class: telemetry, role: pub, key: edge/telemetry, count: 8, rate: 2000, payload: 512 }
+//!   - { class: commands, role: qry, key: edge/cmd, payload: 4096, reply_mode: transform }
+//!   - { class: commands, role: req, key: edge/cmd, payload: 64, rate: 50, concurrency: 4 }
+//! ```
+
+use crate::crash::CrashConfig;
+use crate::metrics::stats::{Stats, StatsSnapshot};
+use crate::output::{OutputFormat, OutputWriter};
+use crate::rate::ArrivalConfig;
+use crate::roles::publisher::{PublisherConfig, run_publisher};
+use crate::roles::queryable::{
+    ChunkCount, QueryableConfig, ReplyMode, ServiceTime, parse_chunk_count, parse_service_time,
+    run_queryable,
+};
+use crate::roles::requester::{RequesterConfig, run_requester};
+use crate::roles::subscriber::{SubscriberConfig, run_subscriber};
+use crate::transport::config::parse_engine;
+use crate::transport::{ConnectOptions, Engine};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::{interval, sleep};
+use tracing::info;
+
+/// Time for receivers to subscribe / register before senders start
+const RECEIVER_READY_SECS: u64 = 1;
+/// Time after senders stop for in-flight messages and replies to arrive
+const DRAIN_SECS: u64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkloadRole {
+    Pub,
+    Sub,
+    Req,
+    Qry,
+}
+
+impl WorkloadRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pub => "pub",
+            Self::Sub => "sub",
+            Self::Req => "req",
+            Self::Qry => "qry",
+        }
+    }
+}
+
+/// Scenario file as written; `resolve` turns it into a `ScenarioConfig`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioSpec {
+    #[serde(default = "default_duration_secs")]
+    pub duration_secs: u64,
+    /// Run every class alone first to measure interference
+    #[serde(default)]
+    pub isolate: bool,
+    /// Engine for workloads that do not name their own
+    #[serde(default = "default_engine")]
+    pub engine: String,
+    /// Connect options for workloads that do not give their own
+    #[serde(default)]
+    pub connect: BTreeMap<String, String>,
+    pub workloads: Vec<WorkloadSpec>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkloadSpec {
+    pub class: String,
+    pub role: WorkloadRole,
+    /// Key (pub/sub) or subject (req/qry); `count` > 1 uses `<key>/0` … `<key>/<count-1>`
+    pub key: String,
+    pub engine: Option<String>,
+    pub connect: Option<BTreeMap<String, String>>,
+    #[serde(default = "default_count")]
+    pub count: u32,
+    /// pub: msg/s per instance; req: queries/s per instance (unset: as fast as possible)
+    pub rate: Option<f64>,
+    /// pub: message size; req: request size (0: empty body); qry: reply size
+    #[serde(default = "default_payload")]
+    pub payload: usize,
+    /// qry: `fixed` (payload-sized), `echo` (the request) or `transform` (payload-sized
+    /// with the request's header)
+    #[serde(default)]
+    pub reply_mode: ReplyMode,
+    /// req: in-flight queries per instance
+    #[serde(default = "default_concurrency")]
+    pub concurrency: u32,
+    /// req: reply deadline
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// req: read each reply stream to its end (latency is the last chunk's)
+    #[serde(default)]
+    pub stream: bool,
+    /// qry: stream replies in chunks, as `qry --chunks` (`K` or `MIN..MAX`)
+    pub chunks: Option<String>,
+    /// qry: fixed processing delay per query (when `service_time` is not given)
+    #[serde(default)]
+    pub proc_delay_ms: u64,
+    /// qry: service-time model, as `qry --service-time`
+    pub service_time: Option<String>,
+    /// qry: worker pool size (0: a task per query)
+    #[serde(default)]
+    pub workers: usize,
+    /// qry: burn CPU for the service time instead of sleeping
+    #[serde(default)]
+    pub cpu_bound: bool,
+}
+
+fn default_duration_secs() -> u64 {
+    30
+}
+fn default_engine() -> String {
+    "zenoh".into()
+}
+fn default_count() -> u32 {
+    1
+}
+fn default_payload() -> usize {
+    1024
+}
+fn default_concurrency() -> u32 {
+    16
+}
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub class: String,
+    pub role: WorkloadRole,
+    pub engine: Engine,
+    pub connect: ConnectOptions,
+    pub key: String,
+    pub count: u32,
+    pub rate: Option<f64>,
+    pub payload_size: usize,
+    pub reply_mode: ReplyMode,
+    pub concurrency: u32,
+    pub timeout_ms: u64,
+    pub stream: bool,
+    pub service_time: ServiceTime,
+    pub chunks: Option<ChunkCount>,
+    pub workers: usize,
+    pub cpu_bound: bool,
+}
+
+impl Workload {
+    /// Key of instance `i`: the key itself for a single instance
+    fn instance_key(&self, i: u32) -> String {
+        if self.count > 1 {
+            format!("{}/{}", self.key, i)
+        } else {
+            self.key.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScenarioConfig {
+    pub duration_secs: u64,
+    pub isolate: bool,
+    pub workloads: Vec<Workload>,
+    /// Per-phase snapshot series go to `<dir>/mixed/` and `<dir>/isolated/`
+    pub output_dir: Option<PathBuf>,
+    pub format: OutputFormat,
+    pub snapshot_interval_secs: u64,
+}
+
+impl ScenarioSpec {
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        serde_yaml::from_str(text).context("invalid scenario file")
+    }
+
+    /// Validate workloads and resolve engines and connect options
+    pub fn resolve(self) -> Result<ScenarioConfig> {
+        if self.workloads.is_empty() {
+            bail!("scenario has no workloads");
+        }
+        if self.duration_secs == 0 {
+            bail!("scenario duration_secs must be at least 1");
+        }
+        let mut workloads = Vec::with_capacity(self.workloads.len());
+        for (i, w) in self.workloads.into_iter().enumerate() {
+            if w.class.is_empty() || w.key.is_empty() {
+                bail!("workload {} needs a class and a key", i);
+            }
+            if w.count == 0 {
+                bail!("workload {} ({}): count must be at least 1", i, w.class);
+            }
+            // The payload header is 24 bytes; requests may also go out empty
+            if w.payload < 24 && !(w.role == WorkloadRole::Req && w.payload == 0) {
+                bail!(
+                    "workload {} ({}): payload must be at least 24 bytes (req: or 0)",
+                    i,
+                    w.class
+                );
+            }
+            if w.rate.is_some_and(|r| r <= 0.0) {
+                bail!("workload {} ({}): rate must be positive", i, w.class);
+            }
+            // The requester paces whole queries per second
+            if w.role == WorkloadRole::Req
+                && w.rate
+                    .is_some_and(|r| r.fract() != 0.0 || r > u32::MAX as f64)
+            {
+                bail!(
+                    "workload {} ({}): req rate must be a whole number of queries/s",
+                    i,
+                    w.class
+                );
+            }
+            let engine_name = w.engine.as_deref().unwrap_or(&self.engine);
+            let engine = parse_engine(engine_name)
+                .with_context(|| format!("workload {}: unknown engine '{}'", i, engine_name))?;
+            let service_time = match &w.service_time {
+                Some(spec) => parse_service_time(spec)
+                    .with_context(|| format!("workload {}: invalid service_time '{}'", i, spec))?,
+                None => ServiceTime::Fixed {
+                    ms: w.proc_delay_ms as f64,
+                },
+            };
+            let chunks = match &w.chunks {
+                Some(spec) => Some(
+                    parse_chunk_count(spec)
+                        .with_context(|| format!("workload {}: invalid chunks '{}'", i, spec))?,
+                ),
+                None => None,
+            };
+            let connect = ConnectOptions {
+                params: w.connect.unwrap_or_else(|| self.connect.clone()),
+                ..Default::default()
+            };
+            workloads.push(Workload {
+                class: w.class,
+                role: w.role,
+                engine,
+                connect,
+                key: w.key,
+                count: w.count,
+                rate: w.rate,
+                payload_size: w.payload,
+                reply_mode: w.reply_mode,
+                concurrency: w.concurrency,
+                timeout_ms: w.timeout_ms,
+                stream: w.stream,
+                service_time,
+                chunks,
+                workers: w.workers,
+                cpu_bound: w.cpu_bound,
+            });
+        }
+        Ok(ScenarioConfig {
+            duration_secs: self.duration_secs,
+            isolate: self.isolate,
+            workloads,
+            output_dir: None,
+            format: OutputFormat::Csv,
+            snapshot_interval_secs: 1,
+        })
+    }
+}
+
+/// Final stats of one (class, role) in one phase
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupResult {
+    pub class: String,
+    pub role: WorkloadRole,
+    pub instances: u32,
+    pub sent: u64,
+    pub received: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    /// Received (or, for senders without replies, sent) per second of the duration
+    pub throughput: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl GroupResult {
+    fn from_snapshot(
+        class: &str,
+        role: WorkloadRole,
+        instances: u32,
+        s: &StatsSnapshot,
+        secs: u64,
+    ) -> Self {
+        let ms = |ns: u64| ns as f64 / 1e6;
+        let count = if s.received_count > 0 {
+            s.received_count
+        } else {
+            s.sent_count
+        };
+        Self {
+            class: class.to_string(),
+            role,
+            instances,
+            sent: s.sent_count,
+            received: s.received_count,
+            errors: s.error_count,
+            timeouts: s.timeout_count,
+            throughput: count as f64 / secs.max(1) as f64,
+            p50_ms: ms(s.latency_ns_p50),
+            p95_ms: ms(s.latency_ns_p95),
+            p99_ms: ms(s.latency_ns_p99),
+            max_ms: ms(s.latency_ns_max),
+        }
+    }
+
+    fn has_latency(&self) -> bool {
+        // qry receives only timestamped requests: its latency is the request leg
+        self.role != WorkloadRole::Pub && self.received > 0
+    }
+}
+
+/// Mixed-phase stats of one (class, role), with its isolated baseline when measured
+#[derive(Debug, Clone, Serialize)]
+pub struct ScenarioRow {
+    pub class: String,
+    pub role: WorkloadRole,
+    pub sent: u64,
+    pub received: u64,
+    pub errors: u64,
+    pub timeouts: u64,
+    pub throughput: f64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub isolated_throughput: Option<f64>,
+    pub isolated_p50_ms: Option<f64>,
+    pub isolated_p99_ms: Option<f64>,
+    /// Mixed p50 / isolated p50 (latency-bearing roles only)
+    pub p50_ratio: Option<f64>,
+    /// Mixed p99 / isolated p99: > 1 means the other classes slow this one down
+    pub p99_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScenarioResult {
+    pub duration_secs: u64,
+    pub mixed: Vec<GroupResult>,
+    /// Each class run alone (empty without `isolate`)
+    pub isolated: Vec<GroupResult>,
+}
+
+impl ScenarioResult {
+    /// Join the mixed phase with the isolated baselines
+    pub fn rows(&self) -> Vec<ScenarioRow> {
+        let ratio = |mixed: f64, alone: f64| (alone > 0.0).then(|| mixed / alone);
+        self.mixed
+            .iter()
+            .map(|m| {
+                let alone = self
+                    .isolated
+                    .iter()
+                    .find(|i| i.class == m.class && i.role == m.role);
+                let latency = alone.filter(|a| a.has_latency() && m.has_latency());
+                ScenarioRow {
+                    class: m.class.clone(),
+                    role: m.role,
+                    sent: m.sent,
+                    received: m.received,
+                    errors: m.errors,
+                    timeouts: m.timeouts,
+                    throughput: m.throughput,
+                    p50_ms: m.p50_ms,
+                    p99_ms: m.p99_ms,
+                    isolated_throughput: alone.map(|a| a.throughput),
+                    isolated_p50_ms: latency.map(|a| a.p50_ms),
+                    isolated_p99_ms: latency.map(|a| a.p99_ms),
+                    p50_ratio: latency.and_then(|a| ratio(m.p50_ms, a.p50_ms)),
+                    p99_ratio: latency.and_then(|a| ratio(m.p99_ms, a.p99_ms)),
+                }
+            })
+            .collect()
+    }
+}
+
+pub async fn run_scenario(config: &ScenarioConfig) -> Result<ScenarioResult> {
+    let classes: BTreeSet<&str> = config.workloads.iter().map(|w| w.class.as_str()).collect();
+    info!(
+        workloads = config.workloads.len(),
+        classes = ?classes,
+        duration_secs = config.duration_secs,
+        isolate = config.isolate,
+        "Starting scenario"
+    );
+    let mut isolated = Vec::new();
+    if config.isolate {
+        for class in &classes {
+            info!(class, "[scenario] isolated phase");
+            let workloads: Vec<&Workload> = config
+                .workloads
+                .iter()
+                .filter(|w| w.class == *class)
+                .collect();
+            isolated.extend(run_phase(config, &workloads, "isolated").await?);
+        }
+    }
+    info!("[scenario] mixed phase");
+    let all: Vec<&Workload> = config.workloads.iter().collect();
+    let mixed = run_phase(config, &all, "mixed").await?;
+    Ok(ScenarioResult {
+        duration_secs: config.duration_secs,
+        mixed,
+        isolated,
+    })
+}
+
+struct Group {
+    instances: u32,
+    stats: Arc<Stats>,
+    output: Option<OutputWriter>,
+}
+
+/// Run `workloads` concurrently once; one result per (class, role)
+async fn run_phase(
+    config: &ScenarioConfig,
+    workloads: &[&Workload],
+    phase: &str,
+) -> Result<Vec<GroupResult>> {
+    let mut groups: BTreeMap<(String, WorkloadRole), Group> = BTreeMap::new();
+    for w in workloads {
+        let key = (w.class.clone(), w.role);
+        if let Some(g) = groups.get_mut(&key) {
+            g.instances += w.count;
+            continue;
+        }
+        let output = match &config.output_dir {
+            Some(dir) => {
+                let path = dir.join(phase).join(format!(
+                    "{}-{}.{}",
+                    w.class,
+                    w.role.as_str(),
+                    config.format.extension()
+                ));
+                Some(OutputWriter::new_file(path.display().to_string(), config.format).await?)
+            }
+            None => None,
+        };
+        groups.insert(
+            key,
+            Group {
+                instances: w.count,
+                stats: Arc::new(Stats::new()),
+                output,
+            },
+        );
+    }
+
+    let duration = config.duration_secs;
+    let receiver_secs = RECEIVER_READY_SECS + duration + DRAIN_SECS;
+    let mut tasks = JoinSet::new();
+    for w in workloads {
+        let stats = groups[&(w.class.clone(), w.role)].stats.clone();
+        for i in 0..w.count {
+            let key = w.instance_key(i);
+            let (engine, connect) = (w.engine.clone(), w.connect.clone());
+            match w.role {
+                WorkloadRole::Sub => {
+                    tasks.spawn(run_subscriber(SubscriberConfig {
+                        engine,
+                        connect,
+                        key_expr: key,
+                        output_file: None,
+                        snapshot_interval_secs: config.snapshot_interval_secs,
+                        shared_stats: Some(stats.clone()),
+                        disable_internal_snapshot: true,
+                        test_stop_after_secs: Some(receiver_secs),
+                        crash_config: CrashConfig::default(),
+                        per_source_dir: None,
+                    }));
+                }
+                WorkloadRole::Qry => {
+                    tasks.spawn(run_queryable(QueryableConfig {
+                        engine,
+                        connect,
+                        serve_prefix: vec![key],
+                        reply_size: w.payload_size,
+                        reply_mode: w.reply_mode,
+                        service_time: w.service_time,
+                        chunks: w.chunks,
+                        service_seed: None,
+                        cpu_bound: w.cpu_bound,
+                        workers: w.workers,
+                        queue_dir: None,
+                        output_file: None,
+                        snapshot_interval_secs: config.snapshot_interval_secs,
+                        shared_stats: Some(stats.clone()),
+                        disable_internal_snapshot: true,
+                        test_stop_after_secs: Some(receiver_secs),
+                    }));
+                }
+                WorkloadRole::Pub => {
+                    let cfg = PublisherConfig {
+                        engine,
+                        connect,
+                        key_expr: key,
+                        payload_size: w.payload_size,
+                        rate: w.rate,
+                        arrival: ArrivalConfig::default(),
+                        duration_secs: Some(duration),
+                        output_file: None,
+                        snapshot_interval_secs: config.snapshot_interval_secs,
+                        shared_stats: Some(stats.clone()),
+                        disable_internal_snapshot: true,
+                        crash_config: CrashConfig::default(),
+                        closed_loop: None,
+                    };
+                    tasks.spawn(async move {
+                        sleep(Duration::from_secs(RECEIVER_READY_SECS)).await;
+                        run_publisher(cfg).await
+                    });
+                }
+                WorkloadRole::Req => {
+                    let cfg = RequesterConfig {
+                        engine,
+                        connect,
+                        key_expr: key,
+                        request_size: w.payload_size,
+                        stream: w.stream,
+                        stream_dir: None,
+                        qps: w.rate.map(|r| r as u32),
+                        arrival: ArrivalConfig::default(),
+                        concurrency: w.concurrency,
+                        timeout_ms: w.timeout_ms,
+                        duration_secs: duration,
+                        output_file: None,
+                        snapshot_interval_secs: config.snapshot_interval_secs,
+                        shared_stats: Some(stats.clone()),
+                        disable_internal_snapshot: true,
+                    };
+                    tasks.spawn(async move {
+                        sleep(Duration::from_secs(RECEIVER_READY_SECS)).await;
+                        run_requester(cfg).await
+                    });
+                }
+            }
+        }
+    }
+
+    // Snapshot every group on the shared interval until all workloads return
+    let mut ticker = interval(Duration::from_secs(config.snapshot_interval_secs.max(1)));
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            res = tasks.join_next() => match res {
+                Some(res) => res.context("workload task")??,
+                None => break,
+            },
+            _ = ticker.tick() => {
+                for g in groups.values_mut() {
+                    if let Some(out) = &mut g.output {
+                        out.write_snapshot(&g.stats.snapshot().await).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut results = Vec::with_capacity(groups.len());
+    for ((class, role), mut g) in groups {
+        let snap = g.stats.snapshot().await;
+        if let Some(out) = &mut g.output {
+            out.write_snapshot(&snap).await?;
+        }
+        let result = GroupResult::from_snapshot(&class, role, g.instances, &snap, duration);
+        info!(
+            phase,
+            class = %result.class,
+            role = role.as_str(),
+            sent = result.sent,
+            received = result.received,
+            p99_ms = format!("{:.3}", result.p99_ms),
+            "[scenario] group"
+        );
+        results.push(result);
+    }
+    Ok(results)
+}
+
+fn opt(v: Option<f64>, precision: usize) -> String {
+    v.map_or_else(|| "-".into(), |v| format!("{:.*}", precision, v))
+}
+
+/// One markdown row per (class, role) with its isolated baseline and interference ratio
+pub fn render_markdown(result: &ScenarioResult) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "| class | role | sent | received | errors | timeouts | throughput | p50 ms | p99 ms | isolated p99 ms | p99 ratio |\n|---|---|---|---|---|---|---|---|---|---|---|"
+    );
+    for r in result.rows() {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} | {:.1} | {:.3} | {:.3} | {} | {} |",
+            r.class,
+            r.role.as_str(),
+            r.sent,
+            r.received,
+            r.errors,
+            r.timeouts,
+            r.throughput,
+            r.p50_ms,
+            r.p99_ms,
+            opt(r.isolated_p99_ms, 3),
+            opt(r.p99_ratio, 2)
+        );
+    }
+    out
+}
+
+/// Write `scenario.csv` (one row per class and role) and `scenario.json` into `dir`
+pub fn write_results(result: &ScenarioResult, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut w = csv::Writer::from_path(dir.join("scenario.csv"))?;
+    for row in result.rows() {
+        w.serialize(row)?;
+    }
+    w.flush()?;
+    std::fs::write(
+        dir.join("scenario.json"),
+        serde_json::to_vec_pretty(&serde_json::json!({
+            "duration_secs": result.duration_secs,
+            "rows": result.rows(),
+            "mixed": result.mixed,
+            "isolated": result.isolated,
+        }))?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_resolves_defaults_and_overrides() {
+        let spec = ScenarioSpec::from_yaml(
+            r#"
+duration_secs: 5
+engine: zenoh
+connect: { endpoint: "tcp/127.0.0.1:7447" }
+workloads:
+  - { class: telemetry, role: sub, key: edge/t, count: 2 }
+  - { class: telemetry, role: pub, key: edge/t, count: 2, rate: 100 }
+  - { class: commands, role: req, key: edge/cmd, engine: nats, connect: { url: "nats://n:4222" } }
+"#,
+        )
+        .unwrap();
+        let cfg = spec.resolve().unwrap();
+        assert_eq!(cfg.duration_secs, 5);
+        assert!(!cfg.isolate);
+        let [sub, publ, req] = &cfg.workloads[..] else {
+            panic!("{:?}", cfg.workloads)
+        };
+        assert_eq!(sub.role, WorkloadRole::Sub);
+        assert_eq!(
+            (sub.instance_key(0), sub.instance_key(1)),
+            ("edge/t/0".to_string(), "edge/t/1".to_string())
+        );
+        assert_eq!(sub.connect.params["endpoint"], "tcp/127.0.0.1:7447");
+        assert_eq!((publ.rate, publ.payload_size), (Some(100.0), 1024));
+        assert!(matches!(req.engine, Engine::Nats));
+        assert_eq!(req.instance_key(0), "edge/cmd");
+        assert!(!req.connect.params.contains_key("endpoint"));
+        assert_eq!((req.concurrency, req.timeout_ms), (16, 1000));
+    }
+
+    #[test]
+    fn spec_rejects_bad_workloads() {
+        let resolve = |yaml: &str| ScenarioSpec::from_yaml(yaml).and_then(|s| s.resolve());
+        assert!(resolve("workloads: []").is_err());
+        assert!(
+            resolve("workloads: [{ class: a, role: pub, key: k, engine: carrier-pigeon }]")
+                .is_err()
+        );
+        assert!(resolve("workloads: [{ class: a, role: pub, key: k, count: 0 }]").is_err());
+        assert!(resolve("workloads: [{ class: a, role: pub, key: k, rate: 0 }]").is_err());
+        assert!(resolve("workloads: [{ class: a, role: push, key: k }]").is_err());
+        assert!(resolve("workloads: [{ class: a, role: pub, key: k, qos: 1 }]").is_err());
+        assert!(resolve("workloads: [{ class: a, role: qry, key: k, payload: 0 }]").is_err());
+        assert!(
+            resolve("workloads: [{ class: a, role: qry, key: k, service_time: 'gamma:3' }]")
+                .is_err()
+        );
+        assert!(resolve("workloads: [{ class: a, role: req, key: k, payload: 0 }]").is_ok());
+        assert!(resolve("workloads: [{ class: a, role: req, key: k, rate: 0.5 }]").is_err());
+        assert!(resolve("workloads: [{ class: a, role: req, key: k, rate: 20.5 }]").is_err());
+        assert!(resolve("workloads: [{ class: a, role: req, key: k, rate: 20.0 }]").is_ok());
+        assert!(resolve("workloads: [{ class: a, role: pub, key: k, rate: 0.5 }]").is_ok());
+    }
+
+    #[test]
+    fn rows_pair_mixed_with_isolated_latency() {
+        let group = |class: &str, role, p99_ms: f64| GroupResult {
+            class: class.into(),
+            role,
+            instances: 1,
+            sent: 100,
+            received: if role == WorkloadRole::Pub { 0 } else { 100 },
+            errors: 0,
+            timeouts: 0,
+            throughput: 10.0,
+            p50_ms: p99_ms / 2.0,
+            p95_ms: p99_ms,
+            p99_ms,
+            max_ms: p99_ms,
+        };
+        let result = ScenarioResult {
+            duration_secs: 10,
+            mixed: vec![
+                group("commands", WorkloadRole::Req, 6.0),
+                group("telemetry", WorkloadRole::Pub, 0.0),
+            ],
+            isolated: vec![
+                group("commands", WorkloadRole::Req, 2.0),
+                group("telemetry", WorkloadRole::Pub, 0.0),
+            ],
+        };
+        let rows = result.rows();
+        assert_eq!(rows[0].isolated_p99_ms, Some(2.0));
+        assert_eq!(rows[0].p99_ratio, Some(3.0));
+        assert_eq!(rows[0].p50_ratio, Some(3.0));
+        // Senders without replies carry no latency, only throughput
+        assert_eq!(rows[1].isolated_throughput, Some(10.0));
+        assert_eq!(rows[1].p99_ratio, None);
+        assert!(render_markdown(&result).contains(
+            "| commands | req | 100 | 100 | 0 | 0 | 10.0 | 3.000 | 6.000 | 2.000 | 3.00 |"
+        ));
+    }
+}
@@ -0,0 +1,310 @@
+//! Per-role output plumbing shared by the CLI roles.
+//!
+//! A `RoleSession` owns the aggregate `Stats` for one role invocation, drives the
+//! periodic snapshot writers, and on `finish` writes the final snapshot and summary.
+//! With a run directory it also keeps `manifest.json` there; the `--csv` file (or
+//! stdout) receives the same snapshots as a mirror. With `--results-db` the same
+//! snapshots and summary are also recorded in the SQLite results store.
+
+use crate::manifest::RunManifest;
+use crate::metrics::prometheus::{self, MetricLabels};
+use crate::metrics::stats::{MeasurementWindow, Stats, StatsSnapshot};
+use crate::output::{OutputFormat, OutputWriter, RunSummary, SnapshotRecord, summary_path_for};
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// What a role session records and where it writes it.
+#[derive(Debug, Clone, Default)]
+pub struct SessionOptions {
+    pub run_id: String,
+    pub role: String,
+    /// Distinguishes concurrent processes running the same role within a run
+    pub instance: String,
+    pub engine: String,
+    /// Connect params (already redacted)
+    pub connect: BTreeMap<String, String>,
+    /// Resolved role configuration
+    pub config: serde_json::Value,
+    /// Command line (already redacted), recorded in the manifest
+    pub argv: Vec<String>,
+    /// Per-role artifact directory (`out_dir/run_id/<role>-<instance>`)
+    pub run_dir: Option<PathBuf>,
+    /// Snapshot mirror file (stdout if None)
+    pub output_path: Option<String>,
+    pub format: OutputFormat,
+    pub snapshot_interval_secs: u64,
+    /// Serve Prometheus metrics on this address while the role runs
+    pub metrics_listen: Option<SocketAddr>,
+    /// Topic/key label for exported metrics
+    pub topic: String,
+    /// Warm-up/cool-down exclusion applied to the role's stats
+    pub window: MeasurementWindow,
+    /// Show the live dashboard instead of stdout snapshots
+    #[cfg(feature = "tui")]
+    pub tui: bool,
+    /// Also record the run in this SQLite results store
+    #[cfg(feature = "sqlite")]
+    pub results_db: Option<PathBuf>,
+}
+
+pub struct RoleSession {
+    opts: SessionOptions,
+    stats: Arc<Stats>,
+    manifest: RunManifest,
+    metrics_server: Option<JoinHandle<()>>,
+    stop: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<StatsSnapshot>,
+    #[cfg(feature = "tui")]
+    dashboard: Option<crate::tui::Dashboard>,
+    #[cfg(feature = "sqlite")]
+    recorder: Option<Arc<std::sync::Mutex<crate::results_db::RunRecorder>>>,
+}
+
+impl RoleSession {
+    /// Create the run directory and manifest, open the outputs and start the periodic writer.
+    pub async fn start(opts: SessionOptions) -> Result<Self> {
+        let stats = Arc::new(Stats::new());
+        stats.set_window(opts.window);
+        let labels = MetricLabels {
+            run_id: opts.run_id.clone(),
+            role: opts.role.clone(),
+            engine: opts.engine.clone(),
+            topic: opts.topic.clone(),
+        };
+        #[cfg(feature = "otel")]
+        if crate::otel::is_enabled() {
+            crate::otel::register_stats(stats.clone(), &labels);
+        }
+        let metrics_server = match opts.metrics_listen {
+            Some(addr) => Some(prometheus::serve(addr, stats.clone(), labels).await?.1),
+            None => None,
+        };
+        let mut outputs = Vec::with_capacity(2);
+        let manifest = RunManifest::new(
+            &opts.run_id,
+            &opts.role,
+            &opts.instance,
+            &opts.engine,
+            opts.argv.clone(),
+            opts.config.clone(),
+        );
+        if let Some(dir) = &opts.run_dir {
+            tokio::fs::create_dir_all(dir).await?;
+            manifest.write(&dir.join("manifest.json")).await?;
+            let path = dir.join(format!("snapshots.{}", opts.format.extension()));
+            outputs.push(
+                OutputWriter::new_file(path.to_string_lossy().into_owned(), opts.format).await?,
+            );
+        }
+        #[cfg(feature = "tui")]
+        let to_stdout = !opts.tui;
+        #[cfg(not(feature = "tui"))]
+        let to_stdout = true;
+        match &opts.output_path {
+            Some(path) => outputs.push(OutputWriter::new_file(path.clone(), opts.format).await?),
+            // The dashboard owns the terminal
+            None if to_stdout => outputs.push(OutputWriter::new_stdout_with(opts.format)),
+            None => {}
+        }
+        #[cfg(feature = "tui")]
+        let dashboard = match opts.tui {
+            true => Some(crate::tui::Dashboard::start(crate::tui::DashboardInfo {
+                run_id: opts.run_id.clone(),
+                role: opts.role.clone(),
+                engine: opts.engine.clone(),
+                topic: opts.topic.clone(),
+                dump_dir: opts.run_dir.clone().unwrap_or_else(|| PathBuf::from(".")),
+            })?),
+            false => None,
+        };
+        #[cfg(feature = "tui")]
+        let feed = dashboard.as_ref().map(|d| d.feed());
+        #[cfg(feature = "sqlite")]
+        let recorder = match &opts.results_db {
+            Some(path) => Some(Arc::new(std::sync::Mutex::new(
+                crate::results_db::RunRecorder::begin(path, &manifest, &opts.topic, &opts.connect)?,
+            ))),
+            None => None,
+        };
+        #[cfg(feature = "sqlite")]
+        let recorder_loop = recorder.clone();
+        let (stop, mut stop_rx) = oneshot::channel::<()>();
+        let every = Duration::from_secs(opts.snapshot_interval_secs.max(1));
+        let stats_loop = stats.clone();
+        let handle = tokio::spawn(async move {
+            let mut t = tokio::time::interval(every);
+            loop {
+                tokio::select! {
+                    _ = t.tick() => {
+                        let snap = stats_loop.snapshot().await;
+                        for out in outputs.iter_mut() {
+                            let _ = out.write_snapshot(&snap).await;
+                        }
+                        #[cfg(feature = "sqlite")]
+                        if let Some(rec) = &recorder_loop
+                            && let Err(e) = rec.lock().unwrap().snapshot(&snap)
+                        {
+                            warn!(error = %e, "Results DB snapshot error");
+                        }
+                        #[cfg(feature = "tui")]
+                        if let Some(feed) = &feed {
+                            let _ = feed.send(snap);
+                        }
+                    }
+                    _ = &mut stop_rx => break,
+                }
+            }
+            // Samples still held for the cool-down are excluded from the final snapshot,
+            // which is written by the same writers as the periodic rows
+            stats_loop.close_window().await;
+            let snap = stats_loop.snapshot().await;
+            for out in outputs.iter_mut() {
+                if let Err(e) = out.write_snapshot(&snap).await {
+                    warn!(error = %e, "Final snapshot write error");
+                }
+            }
+            #[cfg(feature = "sqlite")]
+            if let Some(rec) = &recorder_loop
+                && let Err(e) = rec.lock().unwrap().snapshot(&snap)
+            {
+                warn!(error = %e, "Results DB snapshot error");
+            }
+            snap
+        });
+        Ok(Self {
+            opts,
+            stats,
+            manifest,
+            metrics_server,
+            stop: Some(stop),
+            handle,
+            #[cfg(feature = "tui")]
+            dashboard,
+            #[cfg(feature = "sqlite")]
+            recorder,
+        })
+    }
+
+    /// Aggregate stats collector to hand to role runners
+    pub fn stats(&self) -> Arc<Stats> {
+        self.stats.clone()
+    }
+
+    /// Stop the periodic writer, write the final snapshot, summary and manifest end time.
+    pub async fn finish(mut self) -> Result<StatsSnapshot> {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        let final_stats = (&mut self.handle).await?;
+        #[cfg(feature = "tui")]
+        if let Some(dashboard) = self.dashboard.take() {
+            tokio::task::spawn_blocking(move || dashboard.stop()).await?;
+        }
+        if let Some(server) = self.metrics_server.take() {
+            server.abort();
+        }
+        let summary = self.summary(&final_stats).await;
+        self.manifest.ended_at = Some(Utc::now());
+        if let Some(dir) = &self.opts.run_dir {
+            summary.write(&dir.join("summary.json")).await?;
+            self.manifest.write(&dir.join("manifest.json")).await?;
+        }
+        #[cfg(feature = "sqlite")]
+        if let Some(rec) = self.recorder.take() {
+            let ended_at = self.manifest.ended_at.unwrap_or_else(Utc::now);
+            rec.lock().unwrap().finish(&summary, ended_at)?;
+        }
+        if let Some(path) = &self.opts.output_path {
+            summary.write(&summary_path_for(path)).await?;
+        }
+        Ok(final_stats)
+    }
+
+    async fn summary(&self, final_stats: &StatsSnapshot) -> RunSummary {
+        RunSummary {
+            run_id: self.opts.run_id.clone(),
+            role: self.opts.role.clone(),
+            engine: self.opts.engine.clone(),
+            connect: self.opts.connect.clone(),
+            config: self.opts.config.clone(),
+            final_stats: SnapshotRecord::from(final_stats),
+            latency_histogram: self.stats.latency_histogram_base64().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn finish_writes_final_snapshot_and_summary() {
+        let dir = std::env::temp_dir().join(format!("mqb-session-{}", uuid::Uuid::new_v4()));
+        let csv = dir.join("pub.csv");
+        let session = RoleSession::start(SessionOptions {
+            run_id: "r1".into(),
+            role: "pub".into(),
+            engine: "mock".into(),
+            output_path: Some(csv.to_string_lossy().into_owned()),
+            snapshot_interval_secs: 60,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        session.stats().record_sent().await;
+        let final_stats = session.finish().await.unwrap();
+        assert_eq!(final_stats.sent_count, 1);
+
+        // Last CSV row is the final snapshot (sent_count column)
+        let rows = std::fs::read_to_string(&csv).unwrap();
+        assert_eq!(rows.lines().last().unwrap().split(',').nth(1), Some("1"));
+
+        let summary: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join("pub.summary.json")).unwrap()).unwrap();
+        assert_eq!(summary["role"], "pub");
+        assert_eq!(summary["final_stats"]["sent_count"], 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn run_dir_holds_snapshots_summary_and_manifest() {
+        let dir = std::env::temp_dir().join(format!("mqb-rundir-{}", uuid::Uuid::new_v4()));
+        let run_dir = dir.join("r1").join("sub-7");
+        let session = RoleSession::start(SessionOptions {
+            run_id: "r1".into(),
+            role: "sub".into(),
+            instance: "7".into(),
+            engine: "mock".into(),
+            run_dir: Some(run_dir.clone()),
+            output_path: Some(dir.join("mirror.jsonl").to_string_lossy().into_owned()),
+            format: OutputFormat::Jsonl,
+            snapshot_interval_secs: 60,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        session.stats().record_received(1_000).await;
+        session.finish().await.unwrap();
+
+        let snapshots = std::fs::read_to_string(run_dir.join("snapshots.jsonl")).unwrap();
+        let mirror = std::fs::read_to_string(dir.join("mirror.jsonl")).unwrap();
+        assert_eq!(snapshots.lines().count(), mirror.lines().count());
+        assert!(run_dir.join("summary.json").exists());
+        assert!(dir.join("mirror.summary.json").exists());
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(run_dir.join("manifest.json")).unwrap()).unwrap();
+        assert_eq!(manifest["instance"], "7");
+        assert!(manifest["git_describe"].is_string());
+        assert!(manifest["ended_at"].is_string());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,7 +1,7 @@
 //! AMQP (RabbitMQ) adapter using lapin. QoS: at-most-once.
 use crate::transport::{
-    ConnectOptions, IncomingQuery, Payload, Publisher, QueryRegistration, Subscription, Transport,
-    TransportError, TransportMessage,
+    ConnectOptions, IncomingQuery, Payload, ProbeConnection, Publisher, QueryRegistration,
+    Subscription, Transport, TransportError, TransportMessage,
 };
 use bytes::Bytes;
 use futures::StreamExt;
@@ -55,6 +55,15 @@ pub async fn connect(opts: ConnectOptions) -> Result<Box<dyn Transport>, Transpo
     Ok(Box::new(AmqpTransport { url }))
 }
 
+impl AmqpTransport {
+    /// Open a new connection; the AMQP handshake (Connection.Start..Open-Ok) is the round-trip
+    async fn open_connection(&self) -> Result<Connection, TransportError> {
+        Connection::connect(&self.url, ConnectionProperties::default())
+            .await
+            .map_err(|e| TransportError::Connect(e.to_string()))
+    }
+}
+
 #[async_trait::async_trait]
 impl Transport for AmqpTransport {
     async fn subscribe(
@@ -162,8 +171,14 @@ impl Transport for AmqpTransport {
         Ok(())
     }
     async fn health_check(&self) -> Result<(), TransportError> {
+        let conn = self.open_connection().await?;
+        let _ = conn.close(200, "health check").await;
         Ok(())
     }
+    async fn connect_probe(&self) -> Result<Option<Box<dyn ProbeConnection>>, TransportError> {
+        let conn = self.open_connection().await?;
+        Ok(Some(Box::new(AmqpProbe { conn })))
+    }
     async fn force_disconnect(&self) -> Result<(), TransportError> {
         // AMQP: Transport stores URL; actual connections are created per operation.
         // This is a no-op since we can't force-close connections we don't hold.
@@ -172,6 +187,18 @@ impl Transport for AmqpTransport {
     }
 }
 
+struct AmqpProbe {
+    conn: Connection,
+}
+
+#[async_trait::async_trait]
+impl ProbeConnection for AmqpProbe {
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        let _ = self.conn.close(200, "shutdown").await;
+        Ok(())
+    }
+}
+
 struct AmqpSubscription {
     handle: JoinHandle<()>,
     _conn: Connection,
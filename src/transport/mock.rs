@@ -2,18 +2,32 @@
 
 use super::{
     ConnectOptions, IncomingQuery, Payload, Publisher, QueryRegistration, QueryResponder,
-    QueryResponderInner, Subscription, Transport, TransportError, TransportMessage,
+    QueryResponderInner, ReplyStream, Subscription, Transport, TransportError, TransportMessage,
 };
 use bytes::Bytes;
+use futures::StreamExt;
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex, OnceLock},
 };
 
+type SubHandler = Arc<dyn Fn(TransportMessage) + Send + Sync + 'static>;
+type QryHandler = Arc<dyn Fn(IncomingQuery) + Send + Sync + 'static>;
+
 #[derive(Default)]
 struct Bus {
-    subs: HashMap<String, Vec<Arc<dyn Fn(TransportMessage) + Send + Sync + 'static>>>,
-    qrys: HashMap<String, Arc<dyn Fn(IncomingQuery) + Send + Sync + 'static>>,
+    subs: HashMap<String, Vec<SubHandler>>,
+    /// `prefix/**` subscriptions, keyed by prefix
+    wildcards: HashMap<String, Vec<SubHandler>>,
+    qrys: HashMap<String, QryHandler>,
+    /// `prefix/**` queryables, keyed by prefix
+    qry_wildcards: HashMap<String, QryHandler>,
+}
+
+/// Whether `key` lies strictly below `prefix`
+fn under_prefix(key: &str, prefix: &str) -> bool {
+    key.strip_prefix(prefix)
+        .is_some_and(|rest| rest.starts_with('/'))
 }
 
 #[derive(Clone, Default)]
@@ -25,7 +39,7 @@ pub struct MockTransport {
 
 static BUS: OnceLock<SharedBus> = OnceLock::new();
 fn shared_bus() -> SharedBus {
-    BUS.get_or_init(|| SharedBus::default()).clone()
+    BUS.get_or_init(SharedBus::default).clone()
 }
 
 pub async fn connect(_opts: ConnectOptions) -> Result<Box<dyn Transport>, TransportError> {
@@ -40,10 +54,11 @@ impl Transport for MockTransport {
         handler: Box<dyn Fn(TransportMessage) + Send + Sync + 'static>,
     ) -> Result<Box<dyn Subscription>, TransportError> {
         let mut bus = self.bus.0.lock().unwrap();
-        bus.subs
-            .entry(expr.to_string())
-            .or_default()
-            .push(Arc::from(handler));
+        let table = match expr.strip_suffix("/**") {
+            Some(prefix) => bus.wildcards.entry(prefix.to_string()),
+            None => bus.subs.entry(expr.to_string()),
+        };
+        table.or_default().push(Arc::from(handler));
         Ok(Box::new(MockSub {
             bus: self.bus.clone(),
             expr: expr.to_string(),
@@ -57,34 +72,27 @@ impl Transport for MockTransport {
         }))
     }
 
-    async fn request(&self, subject: &str, _payload: Bytes) -> Result<Payload, TransportError> {
-        let q = {
-            let bus = self.bus.0.lock().unwrap();
-            bus.qrys.get(subject).cloned()
-        };
-        if let Some(cb) = q {
-            let (tx, rx) = flume::bounded(1);
-            let responder = MockResponder {
-                tx: tx.clone(),
-                _subject: subject.to_string(),
-            };
-            let incoming = IncomingQuery {
-                subject: subject.to_string(),
-                payload: Payload::from_bytes(Bytes::new()),
-                correlation: None,
-                responder: QueryResponder {
-                    inner: Arc::new(responder),
-                },
-            };
-            cb(incoming);
-            let bytes = rx
-                .recv_async()
-                .await
-                .map_err(|e| TransportError::Request(e.to_string()))?;
-            Ok(Payload::from_bytes(bytes))
-        } else {
-            Err(TransportError::Request("no queryable".into()))
-        }
+    async fn request(&self, subject: &str, payload: Bytes) -> Result<Payload, TransportError> {
+        let (tx, rx) = flume::bounded(1);
+        self.dispatch(subject, payload, tx)?;
+        let bytes = rx
+            .recv_async()
+            .await
+            .map_err(|e| TransportError::Request(e.to_string()))?;
+        Ok(Payload::from_bytes(bytes))
+    }
+
+    async fn request_stream(
+        &self,
+        subject: &str,
+        payload: Bytes,
+    ) -> Result<ReplyStream, TransportError> {
+        // Ends once the responder calls `end` or is dropped
+        let (tx, rx) = flume::unbounded();
+        self.dispatch(subject, payload, tx)?;
+        Ok(Box::pin(
+            rx.into_stream().map(|b| Ok(Payload::from_bytes(b))),
+        ))
     }
 
     async fn register_queryable(
@@ -93,7 +101,12 @@ impl Transport for MockTransport {
         handler: Box<dyn Fn(IncomingQuery) + Send + Sync + 'static>,
     ) -> Result<Box<dyn QueryRegistration>, TransportError> {
         let mut bus = self.bus.0.lock().unwrap();
-        bus.qrys.insert(subject.to_string(), Arc::from(handler));
+        match subject.strip_suffix("/**") {
+            Some(prefix) => bus
+                .qry_wildcards
+                .insert(prefix.to_string(), Arc::from(handler)),
+            None => bus.qrys.insert(subject.to_string(), Arc::from(handler)),
+        };
         Ok(Box::new(MockQry {
             bus: self.bus.clone(),
             subject: subject.to_string(),
@@ -110,7 +123,43 @@ impl Transport for MockTransport {
         // Mock transport: clear all subscriptions and queryables to simulate disconnect
         let mut bus = self.bus.0.lock().unwrap();
         bus.subs.clear();
+        bus.wildcards.clear();
         bus.qrys.clear();
+        bus.qry_wildcards.clear();
+        Ok(())
+    }
+}
+
+impl MockTransport {
+    /// Hand a query to the registered queryable; replies go to `tx`
+    fn dispatch(
+        &self,
+        subject: &str,
+        payload: Bytes,
+        tx: flume::Sender<Bytes>,
+    ) -> Result<(), TransportError> {
+        let cb = {
+            let bus = self.bus.0.lock().unwrap();
+            bus.qrys.get(subject).cloned().or_else(|| {
+                bus.qry_wildcards
+                    .iter()
+                    .find(|(prefix, _)| under_prefix(subject, prefix))
+                    .map(|(_, h)| h.clone())
+            })
+        }
+        .ok_or_else(|| TransportError::Request("no queryable".into()))?;
+        let responder = MockResponder {
+            tx: Mutex::new(Some(tx)),
+            _subject: subject.to_string(),
+        };
+        cb(IncomingQuery {
+            subject: subject.to_string(),
+            payload: Payload::from_bytes(payload),
+            correlation: None,
+            responder: QueryResponder {
+                inner: Arc::new(responder),
+            },
+        });
         Ok(())
     }
 }
@@ -123,7 +172,11 @@ struct MockSub {
 impl Subscription for MockSub {
     async fn shutdown(&self) -> Result<(), TransportError> {
         let mut bus = self.bus.0.lock().unwrap();
-        if let Some(vec) = bus.subs.get_mut(&self.expr) {
+        let table = match self.expr.strip_suffix("/**") {
+            Some(prefix) => bus.wildcards.get_mut(prefix),
+            None => bus.subs.get_mut(&self.expr),
+        };
+        if let Some(vec) = table {
             vec.clear();
         }
         Ok(())
@@ -137,16 +190,20 @@ struct MockPub {
 #[async_trait::async_trait]
 impl Publisher for MockPub {
     async fn publish(&self, payload: Bytes) -> Result<(), TransportError> {
-        let handlers = {
+        let handlers: Vec<SubHandler> = {
             let bus = self.bus.0.lock().unwrap();
-            bus.subs.get(&self.topic).cloned()
+            let exact = bus.subs.get(&self.topic).into_iter().flatten();
+            let wild = bus
+                .wildcards
+                .iter()
+                .filter(|(prefix, _)| under_prefix(&self.topic, prefix))
+                .flat_map(|(_, hs)| hs);
+            exact.chain(wild).cloned().collect()
         };
-        if let Some(hs) = handlers {
-            for h in hs {
-                h(TransportMessage {
-                    payload: Payload::from_bytes(payload.clone()),
-                });
-            }
+        for h in handlers {
+            h(TransportMessage {
+                payload: Payload::from_bytes(payload.clone()),
+            });
         }
         Ok(())
     }
@@ -160,22 +217,30 @@ struct MockQry {
 impl QueryRegistration for MockQry {
     async fn shutdown(&self) -> Result<(), TransportError> {
         let mut bus = self.bus.0.lock().unwrap();
-        bus.qrys.remove(&self.subject);
+        match self.subject.strip_suffix("/**") {
+            Some(prefix) => bus.qry_wildcards.remove(prefix),
+            None => bus.qrys.remove(&self.subject),
+        };
         Ok(())
     }
 }
 
 struct MockResponder {
-    tx: flume::Sender<Bytes>,
+    /// Taken by `end`, which closes the requester's reply stream
+    tx: Mutex<Option<flume::Sender<Bytes>>>,
     _subject: String,
 }
 #[async_trait::async_trait]
 impl QueryResponderInner for MockResponder {
     async fn send(&self, payload: Bytes) -> Result<(), TransportError> {
-        let _ = self.tx.send_async(payload).await;
+        let tx = self.tx.lock().unwrap().clone();
+        if let Some(tx) = tx {
+            let _ = tx.send_async(payload).await;
+        }
         Ok(())
     }
     async fn end(&self) -> Result<(), TransportError> {
+        self.tx.lock().unwrap().take();
         Ok(())
     }
 }
@@ -228,4 +293,37 @@ mod tests {
 
         let _payload = t.request("q1", bytes::Bytes::new()).await.expect("request");
     }
+
+    #[tokio::test]
+    async fn request_stream_yields_every_reply_until_end() {
+        let t = super::connect(ConnectOptions::default())
+            .await
+            .expect("connect");
+        let _q = t
+            .register_queryable(
+                "q-stream",
+                Box::new(move |inq| {
+                    let responder = inq.responder;
+                    tokio::spawn(async move {
+                        for i in 0..3u8 {
+                            let _ = responder.send(Bytes::from(vec![i])).await;
+                        }
+                        let _ = responder.end().await;
+                        // Sends after `end` are not delivered
+                        let _ = responder.send(Bytes::from_static(b"late")).await;
+                    });
+                }),
+            )
+            .await
+            .expect("queryable");
+
+        let replies: Vec<Vec<u8>> = t
+            .request_stream("q-stream", Bytes::new())
+            .await
+            .expect("request_stream")
+            .map(|r| r.expect("reply").as_cow().to_vec())
+            .collect()
+            .await;
+        assert_eq!(replies, vec![vec![0], vec![1], vec![2]]);
+    }
 }
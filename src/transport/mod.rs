@@ -82,6 +82,13 @@ pub struct TransportMessage {
 
 pub type QueryStream = Pin<Box<dyn Stream<Item = Result<IncomingQuery, TransportError>> + Send>>;
 
+/// End-of-stream frame for adapters that emulate reply streams on a reply subject or
+/// topic, distinct from any reply (including an empty one)
+pub const STREAM_END_FRAME: &[u8] = b"\0MQB-STREAM-END\0";
+
+/// Replies to one streamed query, in arrival order; ends after the responder's `end`
+pub type ReplyStream = Pin<Box<dyn Stream<Item = Result<Payload, TransportError>> + Send>>;
+
 #[derive(Debug)]
 pub struct IncomingQuery {
     pub subject: String,
@@ -162,6 +169,16 @@ pub trait Transport: Send + Sync {
     // Pre-declare publisher for high-throughput publish on the same topic.
     async fn create_publisher(&self, topic: &str) -> Result<Box<dyn Publisher>, TransportError>;
     async fn request(&self, subject: &str, payload: Bytes) -> Result<Payload, TransportError>;
+    /// Query accepting any number of replies, finished by the responder's `end`.
+    /// Adapters without multi-reply support yield only the reply of `request`.
+    async fn request_stream(
+        &self,
+        subject: &str,
+        payload: Bytes,
+    ) -> Result<ReplyStream, TransportError> {
+        let reply = self.request(subject, payload).await?;
+        Ok(Box::pin(futures::stream::once(async move { Ok(reply) })))
+    }
     // Handler-based queryable registration. Returns a guard handle to keep it alive.
     async fn register_queryable(
         &self,
@@ -169,7 +186,16 @@ pub trait Transport: Send + Sync {
         handler: Box<dyn Fn(IncomingQuery) + Send + Sync + 'static>,
     ) -> Result<Box<dyn QueryRegistration>, TransportError>;
     async fn shutdown(&self) -> Result<(), TransportError>;
+    /// Round-trip to the broker. Adapters that connect per operation use a short-lived
+    /// connection and keep nothing open afterwards.
     async fn health_check(&self) -> Result<(), TransportError>;
+    /// Open a broker connection, round-trip on it and keep it open until the returned
+    /// handle is shut down. Adapters that already hold a session only health-check it
+    /// and return None.
+    async fn connect_probe(&self) -> Result<Option<Box<dyn ProbeConnection>>, TransportError> {
+        self.health_check().await?;
+        Ok(None)
+    }
     /// Force-close the underlying connection to simulate a crash.
     /// After calling this, all operations should return TransportError::Disconnected.
     async fn force_disconnect(&self) -> Result<(), TransportError>;
@@ -186,6 +212,14 @@ pub trait Subscription: Send + Sync {
     }
 }
 
+/// A connection held open by `Transport::connect_probe`; dropping it closes it too.
+#[async_trait::async_trait]
+pub trait ProbeConnection: Send + Sync {
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        Ok(())
+    }
+}
+
 #[async_trait::async_trait]
 pub trait Publisher: Send + Sync {
     async fn publish(&self, payload: Bytes) -> Result<(), TransportError>;
@@ -216,7 +250,7 @@ impl TransportBuilder {
             Engine::Zenoh => {
                 #[cfg(feature = "transport-zenoh")]
                 {
-                    return crate::transport::zenoh::connect(opts).await;
+                    crate::transport::zenoh::connect(opts).await
                 }
                 #[cfg(not(feature = "transport-zenoh"))]
                 {
@@ -226,7 +260,7 @@ impl TransportBuilder {
             Engine::Redis => {
                 #[cfg(feature = "transport-redis")]
                 {
-                    return crate::transport::redis::connect(opts).await;
+                    crate::transport::redis::connect(opts).await
                 }
                 #[cfg(not(feature = "transport-redis"))]
                 {
@@ -236,7 +270,7 @@ impl TransportBuilder {
             Engine::Mqtt => {
                 #[cfg(feature = "transport-mqtt")]
                 {
-                    return crate::transport::mqtt::connect(opts).await;
+                    crate::transport::mqtt::connect(opts).await
                 }
                 #[cfg(not(feature = "transport-mqtt"))]
                 {
@@ -246,7 +280,7 @@ impl TransportBuilder {
             Engine::Nats => {
                 #[cfg(feature = "transport-nats")]
                 {
-                    return crate::transport::nats::connect(opts).await;
+                    crate::transport::nats::connect(opts).await
                 }
                 #[cfg(not(feature = "transport-nats"))]
                 {
@@ -256,7 +290,7 @@ impl TransportBuilder {
             Engine::Amqp => {
                 #[cfg(feature = "transport-amqp-0-9")]
                 {
-                    return crate::transport::amqp::connect(opts).await;
+                    crate::transport::amqp::connect(opts).await
                 }
                 #[cfg(not(feature = "transport-amqp-0-9"))]
                 {
@@ -264,9 +298,7 @@ impl TransportBuilder {
                 }
             }
             #[cfg(any(test, feature = "transport-mock"))]
-            Engine::Mock => {
-                return crate::transport::mock::connect(opts).await;
-            }
+            Engine::Mock => crate::transport::mock::connect(opts).await,
             _ => Err(TransportError::Connect("engine not yet implemented".into())),
         }
     }
@@ -284,7 +316,10 @@ impl TransportBuilder {
 
         let max_attempts = opts.retry_count.max(1);
         let initial_delay = std::time::Duration::from_millis(opts.retry_delay_ms.max(100));
-        let max_delay = std::time::Duration::from_millis(opts.retry_max_delay_ms.max(initial_delay.as_millis() as u64));
+        let max_delay = std::time::Duration::from_millis(
+            opts.retry_max_delay_ms
+                .max(initial_delay.as_millis() as u64),
+        );
         let mut current_delay = initial_delay;
         let mut last_error = None;
 
@@ -1,10 +1,13 @@
 //! MQTT adapter (feature `transport-mqtt`), using rumqttc (async) with QoS 0.
+//! Queries carry their reply topic in an envelope; for streamed queries
+//! `STREAM_END_FRAME` marks the end.
 use crate::transport::{
-    ConnectOptions, IncomingQuery, Payload, Publisher, QueryRegistration, QueryResponder,
-    QueryResponderInner, Subscription, Transport, TransportError, TransportMessage,
+    ConnectOptions, IncomingQuery, Payload, ProbeConnection, Publisher, QueryRegistration,
+    QueryResponder, QueryResponderInner, ReplyStream, STREAM_END_FRAME, Subscription, Transport,
+    TransportError, TransportMessage,
 };
 use bytes::Bytes;
-use rumqttc::{AsyncClient, Event, Incoming, MqttOptions, QoS};
+use rumqttc::{AsyncClient, Event, EventLoop, Incoming, MqttOptions, Outgoing, QoS};
 use std::time::Duration;
 use tokio::task::JoinHandle;
 
@@ -123,14 +126,11 @@ impl Transport for MqttTransport {
         // Use a stable, per-topic client_id so broker-side session state (clean_session=false)
         // can be recovered across reconnects, while still avoiding collisions across many topics.
         let topic = map_expr(expr);
-        let base = self
-            .client_id
-            .as_deref()
-            .unwrap_or_else(|| {
-                // If no base client_id is provided, fall back to a process-unique value.
-                // Multi-topic roles are expected to provide a base id (derived from run-id).
-                "mqb"
-            });
+        let base = self.client_id.as_deref().unwrap_or_else(|| {
+            // If no base client_id is provided, fall back to a process-unique value.
+            // Multi-topic roles are expected to provide a base id (derived from run-id).
+            "mqb"
+        });
         let base = sanitize_client_id_base(base);
         let topic_hash = fnv1a64_bytes(topic.as_bytes());
         // When no explicit client_id is provided (base == "mqb"), append a UUID so that
@@ -138,7 +138,12 @@ impl Transport for MqttTransport {
         // all N subscribers would share the same CID and the broker would keep kicking each
         // previous connection, leaving only one subscriber active at a time.
         let cid = if self.client_id.is_none() {
-            format!("sub-{}-{:016x}-{}", base, topic_hash, uuid::Uuid::new_v4().simple())
+            format!(
+                "sub-{}-{:016x}-{}",
+                base,
+                topic_hash,
+                uuid::Uuid::new_v4().simple()
+            )
         } else {
             format!("sub-{}-{:016x}", base, topic_hash)
         };
@@ -147,10 +152,10 @@ impl Transport for MqttTransport {
         options.set_keep_alive(self.keep_alive);
         options.set_max_packet_size(self.max_in, self.max_out);
         options.set_clean_session(self.clean_session);
-        if let Some(user) = &self.username {
-            if let Some(pass) = &self.password {
-                options.set_credentials(user, pass);
-            }
+        if let Some(user) = &self.username
+            && let Some(pass) = &self.password
+        {
+            options.set_credentials(user, pass);
         }
         let (client, mut eventloop) = AsyncClient::new(options, 65536);
         client
@@ -183,14 +188,11 @@ impl Transport for MqttTransport {
         // Dedicated client + background poller for publisher
         // Use a stable, per-topic client_id so broker-side inflight state can be recovered
         // across reconnects, while still avoiding collisions across many topics.
-        let base = self
-            .client_id
-            .as_deref()
-            .unwrap_or_else(|| {
-                // If no base client_id is provided, fall back to a process-unique value.
-                // Multi-topic roles are expected to provide a base id (derived from run-id).
-                "mqb"
-            });
+        let base = self.client_id.as_deref().unwrap_or_else(|| {
+            // If no base client_id is provided, fall back to a process-unique value.
+            // Multi-topic roles are expected to provide a base id (derived from run-id).
+            "mqb"
+        });
         let base = sanitize_client_id_base(base);
         let topic_hash = fnv1a64_bytes(topic.as_bytes());
         let cid = format!("pub-{}-{:016x}", base, topic_hash);
@@ -198,10 +200,10 @@ impl Transport for MqttTransport {
         options.set_keep_alive(self.keep_alive);
         options.set_max_packet_size(self.max_in, self.max_out);
         options.set_clean_session(self.clean_session);
-        if let Some(user) = &self.username {
-            if let Some(pass) = &self.password {
-                options.set_credentials(user, pass);
-            }
+        if let Some(user) = &self.username
+            && let Some(pass) = &self.password
+        {
+            options.set_credentials(user, pass);
         }
         let (client, mut eventloop) = AsyncClient::new(options, 65536);
         let poller = tokio::spawn(async move {
@@ -217,58 +219,13 @@ impl Transport for MqttTransport {
         }))
     }
 
-    async fn request(&self, _subject: &str, _payload: Bytes) -> Result<Payload, TransportError> {
-        // Per-request client with unique reply topic
-        let corr = uuid::Uuid::new_v4().to_string();
-        let reply_topic = format!("mqb/replies/{}", corr);
-
-        let mut sub_opts = MqttOptions::new(
-            format!("req-sub-{}", uuid::Uuid::new_v4()),
-            self.host.clone(),
-            self.port,
-        );
-        sub_opts.set_keep_alive(self.keep_alive);
-        sub_opts.set_max_packet_size(self.max_in, self.max_out);
-        if let Some(user) = &self.username {
-            if let Some(pass) = &self.password {
-                sub_opts.set_credentials(user, pass);
-            }
-        }
-        let (sub_client, mut sub_el) = AsyncClient::new(sub_opts, 65536);
-        sub_client
-            .subscribe(&reply_topic, self.qos)
-            .await
-            .map_err(|e| TransportError::Request(e.to_string()))?;
-
-        // Publisher client
-        let mut pub_opts = MqttOptions::new(
-            format!("req-pub-{}", uuid::Uuid::new_v4()),
-            self.host.clone(),
-            self.port,
-        );
-        pub_opts.set_keep_alive(self.keep_alive);
-        pub_opts.set_max_packet_size(self.max_in, self.max_out);
-        if let Some(user) = &self.username {
-            if let Some(pass) = &self.password {
-                pub_opts.set_credentials(user, pass);
-            }
-        }
-        let (pub_client, mut pub_el) = AsyncClient::new(pub_opts, 65536);
-        // Drive publisher eventloop in background
-        let _pub_poller = tokio::spawn(async move { while pub_el.poll().await.is_ok() {} });
-
-        // Build envelope: [u16 reply_len][reply_topic][payload]
-        let env = encode_req_env(&reply_topic, _payload.as_ref());
-        pub_client
-            .publish(_subject, self.qos, false, env)
-            .await
-            .map_err(|e| TransportError::Request(e.to_string()))?;
-
+    async fn request(&self, subject: &str, payload: Bytes) -> Result<Payload, TransportError> {
+        let mut q = self.send_query(subject, payload).await?;
         // Wait for first reply
         let res = loop {
-            match sub_el.poll().await {
+            match q.sub_el.poll().await {
                 Ok(Event::Incoming(Incoming::Publish(p))) => {
-                    if p.topic == reply_topic {
+                    if p.topic == q.reply_topic {
                         break Ok(Payload::from_bytes(Bytes::from(p.payload.to_vec())));
                     }
                 }
@@ -277,11 +234,41 @@ impl Transport for MqttTransport {
             }
         };
         // cleanup: drop clients; poller will end
-        drop(sub_client);
-        drop(pub_client);
+        drop(q);
         res
     }
 
+    async fn request_stream(
+        &self,
+        subject: &str,
+        payload: Bytes,
+    ) -> Result<ReplyStream, TransportError> {
+        let mut q = self.send_query(subject, payload).await?;
+        let (tx, rx) = flume::unbounded();
+        // Forward replies until the end frame, an error, or the stream is dropped
+        tokio::spawn(async move {
+            loop {
+                match q.sub_el.poll().await {
+                    Ok(Event::Incoming(Incoming::Publish(p))) if p.topic == q.reply_topic => {
+                        if p.payload.as_ref() == STREAM_END_FRAME
+                            || tx
+                                .send(Ok(Payload::from_bytes(Bytes::from(p.payload.to_vec()))))
+                                .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        let _ = tx.send(Err(TransportError::Request(e.to_string())));
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(rx.into_stream()))
+    }
+
     async fn register_queryable(
         &self,
         _subject: &str,
@@ -297,14 +284,15 @@ impl Transport for MqttTransport {
         );
         options.set_keep_alive(self.keep_alive);
         options.set_max_packet_size(self.max_in, self.max_out);
-        if let Some(user) = &self.username {
-            if let Some(pass) = &self.password {
-                options.set_credentials(user, pass);
-            }
+        if let Some(user) = &self.username
+            && let Some(pass) = &self.password
+        {
+            options.set_credentials(user, pass);
         }
         let (client, mut eventloop) = AsyncClient::new(options, 65536);
+        // `prefix/**` serves every topic below the prefix
         client
-            .subscribe(&subject, self.qos)
+            .subscribe(map_expr(&subject), self.qos)
             .await
             .map_err(|e| TransportError::Subscribe(e.to_string()))?;
         let handle: JoinHandle<()> = tokio::spawn(async move {
@@ -339,8 +327,21 @@ impl Transport for MqttTransport {
         Ok(())
     }
     async fn health_check(&self) -> Result<(), TransportError> {
+        let (client, mut eventloop) = self.connect_client().await?;
+        let _ = client.try_disconnect();
+        // Poll until the DISCONNECT is written (or the connection drops)
+        while let Ok(ev) = eventloop.poll().await {
+            if matches!(ev, Event::Outgoing(Outgoing::Disconnect)) {
+                break;
+            }
+        }
         Ok(())
     }
+    async fn connect_probe(&self) -> Result<Option<Box<dyn ProbeConnection>>, TransportError> {
+        let (client, mut eventloop) = self.connect_client().await?;
+        let poller = tokio::spawn(async move { while eventloop.poll().await.is_ok() {} });
+        Ok(Some(Box::new(MqttProbe { client, poller })))
+    }
     async fn force_disconnect(&self) -> Result<(), TransportError> {
         // MQTT: Transport stores connection params; actual clients are created per operation.
         // This is a no-op since we can't force-close connections we don't hold.
@@ -381,6 +382,26 @@ impl Publisher for MqttPublisher {
     }
 }
 
+struct MqttProbe {
+    client: AsyncClient,
+    poller: JoinHandle<()>,
+}
+
+#[async_trait::async_trait]
+impl ProbeConnection for MqttProbe {
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        let _ = self.client.disconnect().await;
+        self.poller.abort();
+        Ok(())
+    }
+}
+
+impl Drop for MqttProbe {
+    fn drop(&mut self) {
+        self.poller.abort();
+    }
+}
+
 struct MqttSubscription {
     handle: JoinHandle<()>,
 }
@@ -399,6 +420,77 @@ impl Subscription for MqttSubscription {
     }
 }
 
+/// A published query and the clients waiting for its replies
+struct PendingQuery {
+    reply_topic: String,
+    sub_el: EventLoop,
+    _sub_client: AsyncClient,
+    _pub_client: AsyncClient,
+}
+
+impl MqttTransport {
+    fn client_options(&self, id: String) -> MqttOptions {
+        let mut opts = MqttOptions::new(id, self.host.clone(), self.port);
+        opts.set_keep_alive(self.keep_alive);
+        opts.set_max_packet_size(self.max_in, self.max_out);
+        if let Some(user) = &self.username
+            && let Some(pass) = &self.password
+        {
+            opts.set_credentials(user, pass);
+        }
+        opts
+    }
+
+    /// Start a fresh client and poll its event loop until the CONNACK
+    async fn connect_client(&self) -> Result<(AsyncClient, EventLoop), TransportError> {
+        let opts = self.client_options(format!("probe-{}", uuid::Uuid::new_v4().simple()));
+        let (client, mut eventloop) = AsyncClient::new(opts, 16);
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Incoming::ConnAck(_))) => return Ok((client, eventloop)),
+                Ok(_) => {}
+                Err(e) => return Err(TransportError::Connect(e.to_string())),
+            }
+        }
+    }
+
+    /// Subscribe a per-request client to a unique reply topic and publish the query
+    async fn send_query(
+        &self,
+        subject: &str,
+        payload: Bytes,
+    ) -> Result<PendingQuery, TransportError> {
+        let corr = uuid::Uuid::new_v4().to_string();
+        let reply_topic = format!("mqb/replies/{}", corr);
+
+        let sub_opts = self.client_options(format!("req-sub-{}", uuid::Uuid::new_v4()));
+        let (sub_client, sub_el) = AsyncClient::new(sub_opts, 65536);
+        sub_client
+            .subscribe(&reply_topic, self.qos)
+            .await
+            .map_err(|e| TransportError::Request(e.to_string()))?;
+
+        // Publisher client
+        let pub_opts = self.client_options(format!("req-pub-{}", uuid::Uuid::new_v4()));
+        let (pub_client, mut pub_el) = AsyncClient::new(pub_opts, 65536);
+        // Drive publisher eventloop in background
+        let _pub_poller = tokio::spawn(async move { while pub_el.poll().await.is_ok() {} });
+
+        // Build envelope: [u16 reply_len][reply_topic][payload]
+        let env = encode_req_env(&reply_topic, payload.as_ref());
+        pub_client
+            .publish(subject, self.qos, false, env)
+            .await
+            .map_err(|e| TransportError::Request(e.to_string()))?;
+        Ok(PendingQuery {
+            reply_topic,
+            sub_el,
+            _sub_client: sub_client,
+            _pub_client: pub_client,
+        })
+    }
+}
+
 struct MqttQueryRegistration {
     handle: JoinHandle<()>,
 }
@@ -426,7 +518,7 @@ impl QueryResponderInner for MqttResponder {
         Ok(())
     }
     async fn end(&self) -> Result<(), TransportError> {
-        Ok(())
+        self.send(Bytes::from_static(STREAM_END_FRAME)).await
     }
 }
 
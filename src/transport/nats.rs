@@ -1,7 +1,9 @@
 //! NATS adapter (feature `transport-nats`) using async-nats. QoS is at-most-once.
+//! Streamed queries reply on a private inbox; `STREAM_END_FRAME` marks the end.
 use crate::transport::{
-    ConnectOptions, IncomingQuery, Payload, Publisher, QueryRegistration, QueryResponder,
-    QueryResponderInner, Subscription, Transport, TransportError, TransportMessage,
+    ConnectOptions, IncomingQuery, Payload, ProbeConnection, Publisher, QueryRegistration,
+    QueryResponder, QueryResponderInner, ReplyStream, STREAM_END_FRAME, Subscription, Transport,
+    TransportError, TransportMessage,
 };
 use bytes::Bytes;
 use futures::StreamExt;
@@ -34,6 +36,20 @@ pub async fn connect(opts: ConnectOptions) -> Result<Box<dyn Transport>, Transpo
     Ok(Box::new(NatsTransport { url }))
 }
 
+impl NatsTransport {
+    /// Connect a new client and wait for the server's PONG to a flush
+    async fn ping_client(&self) -> Result<async_nats::Client, TransportError> {
+        let client = async_nats::connect(&self.url)
+            .await
+            .map_err(|e| TransportError::Connect(e.to_string()))?;
+        client
+            .flush()
+            .await
+            .map_err(|e| TransportError::Other(e.to_string()))?;
+        Ok(client)
+    }
+}
+
 #[async_trait::async_trait]
 impl Transport for NatsTransport {
     async fn subscribe(
@@ -87,6 +103,33 @@ impl Transport for NatsTransport {
         Ok(Payload::from_bytes(Bytes::from(resp.payload.to_vec())))
     }
 
+    async fn request_stream(
+        &self,
+        subject: &str,
+        payload: Bytes,
+    ) -> Result<ReplyStream, TransportError> {
+        let client = async_nats::connect(&self.url)
+            .await
+            .map_err(|e| TransportError::Connect(e.to_string()))?;
+        let inbox = client.new_inbox();
+        let sub = client
+            .subscribe(inbox.clone())
+            .await
+            .map_err(|e| TransportError::Subscribe(e.to_string()))?;
+        client
+            .publish_with_reply(map_topic(subject), inbox, payload.to_vec().into())
+            .await
+            .map_err(|e| TransportError::Request(e.to_string()))?;
+        let stream = sub
+            .take_while(|m| futures::future::ready(m.payload.as_ref() != STREAM_END_FRAME))
+            .map(move |m| {
+                // The client lives as long as the stream
+                let _ = &client;
+                Ok(Payload::from_bytes(Bytes::from(m.payload.to_vec())))
+            });
+        Ok(Box::pin(stream))
+    }
+
     async fn register_queryable(
         &self,
         subject: &str,
@@ -95,10 +138,11 @@ impl Transport for NatsTransport {
         let client = async_nats::connect(&self.url)
             .await
             .map_err(|e| TransportError::Connect(e.to_string()))?;
-        let subject = map_topic(subject);
+        // `prefix/**` serves every subject below the prefix
+        let subject = map_expr(subject);
         let handler = std::sync::Arc::new(handler);
         let mut sub = client
-            .subscribe(subject.clone())
+            .subscribe(subject)
             .await
             .map_err(|e| TransportError::Subscribe(e.to_string()))?;
         let handle = tokio::spawn(async move {
@@ -109,7 +153,7 @@ impl Transport for NatsTransport {
                     reply: reply_to,
                 };
                 let incoming = IncomingQuery {
-                    subject: subject.clone(),
+                    subject: msg.subject.to_string(),
                     payload: Payload::from_bytes(Bytes::from(msg.payload.to_vec())),
                     correlation: None,
                     responder: QueryResponder {
@@ -126,7 +170,11 @@ impl Transport for NatsTransport {
         Ok(())
     }
     async fn health_check(&self) -> Result<(), TransportError> {
-        Ok(())
+        self.ping_client().await.map(drop)
+    }
+    async fn connect_probe(&self) -> Result<Option<Box<dyn ProbeConnection>>, TransportError> {
+        let client = self.ping_client().await?;
+        Ok(Some(Box::new(NatsProbe { _client: client })))
     }
     async fn force_disconnect(&self) -> Result<(), TransportError> {
         // NATS: Transport only stores URL; actual connections are per-operation.
@@ -166,6 +214,13 @@ impl Subscription for NatsSubscription {
     }
 }
 
+struct NatsProbe {
+    // the connection closes once the last client handle is dropped
+    _client: async_nats::Client,
+}
+
+impl ProbeConnection for NatsProbe {}
+
 struct NatsQueryRegistration {
     handle: JoinHandle<()>,
 }
@@ -195,7 +250,7 @@ impl QueryResponderInner for NatsResponder {
         Ok(())
     }
     async fn end(&self) -> Result<(), TransportError> {
-        Ok(())
+        self.send(Bytes::from_static(STREAM_END_FRAME)).await
     }
 }
 
@@ -2,8 +2,8 @@
 //! Pub/Sub via channels (PUBLISH/SUBSCRIBE). Req/Rep via LIST (RPUSH/BLPOP) as a baseline.
 
 use crate::transport::{
-    ConnectOptions, IncomingQuery, Payload, Publisher, QueryRegistration, QueryResponder,
-    QueryResponderInner, Subscription, Transport, TransportError, TransportMessage,
+    ConnectOptions, IncomingQuery, Payload, ProbeConnection, Publisher, QueryRegistration,
+    QueryResponder, QueryResponderInner, Subscription, Transport, TransportError, TransportMessage,
 };
 use bytes::Bytes;
 use futures::StreamExt;
@@ -37,6 +37,22 @@ pub async fn connect(opts: ConnectOptions) -> Result<Box<dyn Transport>, Transpo
     Ok(Box::new(RedisTransport { client, pub_mode }))
 }
 
+impl RedisTransport {
+    /// Open a new connection and PING on it
+    async fn ping_connection(&self) -> Result<redis::aio::MultiplexedConnection, TransportError> {
+        let mut conn = self
+            .client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(|e| TransportError::Connect(e.to_string()))?;
+        let _: String = redis::cmd("PING")
+            .query_async(&mut conn)
+            .await
+            .map_err(|e| TransportError::Other(e.to_string()))?;
+        Ok(conn)
+    }
+}
+
 #[async_trait::async_trait]
 impl Transport for RedisTransport {
     async fn subscribe(
@@ -110,13 +126,13 @@ impl Transport for RedisTransport {
         }
     }
 
-    async fn request(&self, _subject: &str, _payload: Bytes) -> Result<Payload, TransportError> {
+    async fn request(&self, subject: &str, payload: Bytes) -> Result<Payload, TransportError> {
         // Keys
-        let req_key = format!("mq:req:{}", _subject);
-        let reply_key = new_reply_key(_subject);
+        let req_key = format!("mq:req:{}", subject);
+        let reply_key = new_reply_key(subject);
 
         // Encode message: [u16 reply_len][reply_key bytes][payload]
-        let msg = encode_req(&reply_key, _payload.as_ref());
+        let msg = encode_req(&reply_key, payload.as_ref());
 
         // Push request using a multiplexed connection
         {
@@ -201,7 +217,11 @@ impl Transport for RedisTransport {
         Ok(())
     }
     async fn health_check(&self) -> Result<(), TransportError> {
-        Ok(())
+        self.ping_connection().await.map(drop)
+    }
+    async fn connect_probe(&self) -> Result<Option<Box<dyn ProbeConnection>>, TransportError> {
+        let conn = self.ping_connection().await?;
+        Ok(Some(Box::new(RedisProbe { _conn: conn })))
     }
     async fn force_disconnect(&self) -> Result<(), TransportError> {
         // Redis: Transport stores client; actual connections are created per operation.
@@ -248,9 +268,7 @@ impl Publisher for RedisPublisherSingle {
             .send(PubCmd::Msg(payload, tx_ack))
             .await
             .map_err(|_| TransportError::Disconnected)?;
-        rx_ack
-            .await
-            .unwrap_or_else(|_| Err(TransportError::Disconnected))
+        rx_ack.await.unwrap_or(Err(TransportError::Disconnected))
     }
     async fn shutdown(&self) -> Result<(), TransportError> {
         // Best-effort shutdown signal; task will also stop when all senders are dropped.
@@ -330,6 +348,12 @@ impl Subscription for RedisSubscription {
     }
 }
 
+struct RedisProbe {
+    _conn: redis::aio::MultiplexedConnection,
+}
+
+impl ProbeConnection for RedisProbe {}
+
 struct RedisQueryRegistration {
     handle: tokio::task::JoinHandle<()>,
 }
@@ -3,12 +3,14 @@
 use std::sync::Arc;
 
 use bytes::Bytes;
+use futures::StreamExt;
 use tokio::sync::Mutex;
 use zenoh::qos::Reliability;
+use zenoh::query::ConsolidationMode;
 
 use super::{
-    ConnectOptions, IncomingQuery, Payload, QueryResponder, QueryResponderInner, Transport,
-    TransportError, TransportMessage,
+    ConnectOptions, IncomingQuery, Payload, QueryResponder, QueryResponderInner, ReplyStream,
+    Transport, TransportError, TransportMessage,
 };
 #[allow(unused_imports)]
 use zenoh::bytes::ZBytes;
@@ -42,7 +44,6 @@ pub async fn connect(opts: ConnectOptions) -> Result<Box<dyn Transport>, Transpo
         cfg.insert_json5("connect/endpoints", &list)
             .map_err(|e| TransportError::Connect(e.to_string()))?;
     }
-    
 
     let session = zenoh::open(cfg)
         .await
@@ -58,7 +59,10 @@ pub async fn connect(opts: ConnectOptions) -> Result<Box<dyn Transport>, Transpo
     } else {
         Reliability::Reliable
     };
-    Ok(Box::new(ZenohTransport { session, pub_reliability }))
+    Ok(Box::new(ZenohTransport {
+        session,
+        pub_reliability,
+    }))
 }
 
 #[async_trait::async_trait]
@@ -101,11 +105,12 @@ impl Transport for ZenohTransport {
         Ok(Box::new(ZenohPublisher { inner: pub_decl }))
     }
 
-    async fn request(&self, subject: &str, _payload: Bytes) -> Result<Payload, TransportError> {
-        // Minimal implementation: wait for first reply, ignore payload content for now.
+    async fn request(&self, subject: &str, payload: Bytes) -> Result<Payload, TransportError> {
+        // Wait for the first reply; the request body goes as the query payload
         let replies = self
             .session
             .get(subject)
+            .payload(payload)
             .with(flume::bounded(1))
             .await
             .map_err(|e| TransportError::Request(e.to_string()))?;
@@ -119,6 +124,29 @@ impl Transport for ZenohTransport {
         }
     }
 
+    async fn request_stream(
+        &self,
+        subject: &str,
+        payload: Bytes,
+    ) -> Result<ReplyStream, TransportError> {
+        // No consolidation: every reply on the same key is delivered, in order. The
+        // channel closes when the queryable finalizes the query
+        let replies = self
+            .session
+            .get(subject)
+            .payload(payload)
+            .consolidation(ConsolidationMode::None)
+            .with(flume::unbounded())
+            .await
+            .map_err(|e| TransportError::Request(e.to_string()))?;
+        Ok(Box::pin(replies.into_stream().map(
+            |reply| match reply.result() {
+                Ok(sample) => Ok(Payload::from_zenoh(sample.payload().clone())),
+                Err(e) => Err(TransportError::Request(e.to_string())),
+            },
+        )))
+    }
+
     async fn register_queryable(
         &self,
         subject: &str,
@@ -131,7 +159,7 @@ impl Transport for ZenohTransport {
             .declare_queryable(subject)
             .callback(move |query| {
                 let subject = query.key_expr().to_string();
-                let payload = query.payload().cloned().unwrap_or_else(|| ZBytes::new());
+                let payload = query.payload().cloned().unwrap_or_else(ZBytes::new);
                 let responder = ZenohResponder::new(query);
                 let incoming = IncomingQuery {
                     subject,
@@ -173,24 +201,32 @@ impl Transport for ZenohTransport {
 }
 
 struct ZenohResponder {
-    query: zenoh::query::Query,
+    /// Dropping the query sends the final response; `end` does so early
+    query: Mutex<Option<zenoh::query::Query>>,
 }
 
 impl ZenohResponder {
     fn new(query: zenoh::query::Query) -> Self {
-        Self { query }
+        Self {
+            query: Mutex::new(Some(query)),
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl QueryResponderInner for ZenohResponder {
     async fn send(&self, payload: Bytes) -> Result<(), TransportError> {
-        self.query
-            .reply(self.query.key_expr(), payload)
+        let guard = self.query.lock().await;
+        let Some(query) = guard.as_ref() else {
+            return Ok(());
+        };
+        query
+            .reply(query.key_expr(), payload)
             .await
             .map_err(|e| TransportError::Other(e.to_string()))
     }
     async fn end(&self) -> Result<(), TransportError> {
+        let _ = self.query.lock().await.take();
         Ok(())
     }
 }
@@ -0,0 +1,428 @@
+//! `mq-bench trials`: repeat a role configuration and report spread across trials.
+//!
+//! Each trial re-runs this binary with its own run id under
+//! `<out_dir>/<base_run_id>/`, optionally starting companion roles first (e.g. the
+//! subscriber for a publisher trial) and stopping them with SIGINT once the main
+//! role exits. `--matrix` expands the command into variants; with `--shuffle` the
+//! (variant, trial) order is randomized so slow drift on a shared host spreads over
+//! all variants instead of biasing the last ones.
+//!
+//! Per-trial final stats come from the role summaries (via `report::collect`) and
+//! are written to `trials.csv`; `aggregate.csv` holds mean, median, stddev and a
+//! Student-t 95% confidence interval of the mean per variant and metric.
+
+use crate::report::SummaryRow;
+use crate::report::collect::collect_rows;
+use crate::rng::XorShift64;
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tracing::{info, warn};
+
+/// How long companions get to write their summaries after SIGINT
+const COMPANION_STOP_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone)]
+pub struct TrialsConfig {
+    /// Binary to run for each trial (normally the current executable)
+    pub exe: PathBuf,
+    /// Global flags passed to every trial process (before the role)
+    pub forward: Vec<String>,
+    /// `<out_dir>/<base_run_id>`; trials go to `<run_dir>/<base_run_id>_<variant>_t<k>/`
+    pub run_dir: PathBuf,
+    pub base_run_id: String,
+    /// Main role command line (role name and its flags)
+    pub command: Vec<String>,
+    /// Roles started before the main role and stopped after it
+    pub companions: Vec<Vec<String>>,
+    /// `(flag, values)` pairs; every combination is one variant
+    pub matrix: Vec<(String, Vec<String>)>,
+    pub repeat: u32,
+    /// Pause between trials
+    pub cooldown: Duration,
+    /// Wait after starting companions before the main role starts
+    pub settle: Duration,
+    pub shuffle: bool,
+    pub seed: u64,
+}
+
+/// One combination of matrix values
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    /// `payload1024_rate1000` (`base` without a matrix)
+    pub label: String,
+    /// Extra role flags: `--payload 1024 --rate 1000`
+    pub args: Vec<String>,
+}
+
+/// Parse `--matrix KEY=V1,V2,...`
+pub fn parse_matrix_arg(s: &str) -> Result<(String, Vec<String>)> {
+    let (key, values) = s
+        .split_once('=')
+        .with_context(|| format!("matrix entry '{}' is not KEY=V1,V2", s))?;
+    let key = key.trim().trim_start_matches("--").to_string();
+    let values: Vec<String> = values
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if key.is_empty() || values.is_empty() {
+        bail!("matrix entry '{}' is not KEY=V1,V2", s);
+    }
+    Ok((key, values))
+}
+
+/// Cartesian product of the matrix, first key varying slowest
+pub fn expand_matrix(matrix: &[(String, Vec<String>)]) -> Vec<Variant> {
+    let mut variants = vec![Variant {
+        label: String::new(),
+        args: Vec::new(),
+    }];
+    for (key, values) in matrix {
+        variants = variants
+            .into_iter()
+            .flat_map(|v| {
+                values.iter().map(move |value| {
+                    let mut args = v.args.clone();
+                    args.push(format!("--{}", key));
+                    args.push(value.clone());
+                    let part = format!("{}{}", key, value).replace(['/', ' ', '.'], "-");
+                    Variant {
+                        label: match v.label.is_empty() {
+                            true => part,
+                            false => format!("{}_{}", v.label, part),
+                        },
+                        args,
+                    }
+                })
+            })
+            .collect();
+    }
+    for v in variants.iter_mut().filter(|v| v.label.is_empty()) {
+        v.label = "base".into();
+    }
+    variants
+}
+
+/// Trial order as `(variant index, trial number from 1)`; shuffled with a seeded Fisher-Yates
+pub fn schedule(variants: usize, repeat: u32, shuffle: bool, seed: u64) -> Vec<(usize, u32)> {
+    let mut order: Vec<(usize, u32)> = (1..=repeat)
+        .flat_map(|t| (0..variants).map(move |v| (v, t)))
+        .collect();
+    if shuffle {
+        let mut rng = XorShift64::new(seed);
+        for i in (1..order.len()).rev() {
+            order.swap(i, rng.below(i + 1));
+        }
+    }
+    order
+}
+
+/// Per-trial final stats (one row of `trials.csv`)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TrialRow {
+    pub variant: String,
+    pub trial: u32,
+    /// Position in the executed sequence (from 1)
+    pub order: usize,
+    pub run_id: String,
+    /// Main role exited successfully and a summary was found
+    pub ok: bool,
+    pub sub_tps: Option<f64>,
+    pub pub_tps: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub sent: u64,
+    pub recv: u64,
+    pub errors: u64,
+}
+
+impl TrialRow {
+    fn fill(&mut self, row: &SummaryRow) {
+        self.sub_tps = (row.recv > 0).then_some(row.sub_tps);
+        self.pub_tps = row.pub_tps;
+        self.p50_ms = row.p50_ms;
+        self.p95_ms = row.p95_ms;
+        self.p99_ms = row.p99_ms;
+        self.sent = row.sent;
+        self.recv = row.recv;
+        self.errors = row.errors;
+    }
+}
+
+/// Aggregated metric over the successful trials of a variant (one row of `aggregate.csv`)
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregateRow {
+    pub variant: String,
+    pub metric: String,
+    pub n: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64,
+    pub ci95_low: f64,
+    pub ci95_high: f64,
+}
+
+type MetricFn = fn(&TrialRow) -> Option<f64>;
+
+const METRICS: &[(&str, MetricFn)] = &[
+    ("sub_tps", |r| r.sub_tps),
+    ("pub_tps", |r| r.pub_tps),
+    ("p50_ms", |r| r.p50_ms),
+    ("p95_ms", |r| r.p95_ms),
+    ("p99_ms", |r| r.p99_ms),
+];
+
+/// Two-sided 97.5% quantile of Student's t with `df` degrees of freedom
+fn t_975(df: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match df {
+        0 => f64::NAN,
+        1..=30 => TABLE[df - 1],
+        // Cornish-Fisher correction of the normal quantile, within 0.003 beyond 30
+        _ => 1.959964 + 2.372 / df as f64,
+    }
+}
+
+/// Mean, median, sample stddev and 95% CI of the mean (CI collapses to the value for n = 1)
+pub fn describe(values: &[f64]) -> (f64, f64, f64, f64, f64) {
+    let n = values.len();
+    if n == 0 {
+        return (f64::NAN, f64::NAN, f64::NAN, f64::NAN, f64::NAN);
+    }
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = match n % 2 {
+        1 => sorted[n / 2],
+        _ => (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0,
+    };
+    if n == 1 {
+        return (mean, median, 0.0, mean, mean);
+    }
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+    let stddev = var.sqrt();
+    let half = t_975(n - 1) * stddev / (n as f64).sqrt();
+    (mean, median, stddev, mean - half, mean + half)
+}
+
+pub fn aggregate(variants: &[Variant], trials: &[TrialRow]) -> Vec<AggregateRow> {
+    let mut rows = Vec::new();
+    for v in variants {
+        let ok: Vec<&TrialRow> = trials
+            .iter()
+            .filter(|t| t.ok && t.variant == v.label)
+            .collect();
+        for (name, read) in METRICS {
+            let values: Vec<f64> = ok.iter().filter_map(|t| read(t)).collect();
+            if values.is_empty() {
+                continue;
+            }
+            let (mean, median, stddev, ci95_low, ci95_high) = describe(&values);
+            rows.push(AggregateRow {
+                variant: v.label.clone(),
+                metric: name.to_string(),
+                n: values.len(),
+                mean,
+                median,
+                stddev,
+                ci95_low,
+                ci95_high,
+            });
+        }
+    }
+    rows
+}
+
+pub fn render_markdown(rows: &[AggregateRow]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "| variant | metric | n | mean | median | stddev | 95% CI |\n|---|---|---|---|---|---|---|"
+    );
+    for r in rows {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {:.3} | {:.3} | {:.3} | [{:.3}, {:.3}] |",
+            r.variant, r.metric, r.n, r.mean, r.median, r.stddev, r.ci95_low, r.ci95_high
+        );
+    }
+    out
+}
+
+fn write_csv<T: Serialize>(rows: &[T], path: &Path) -> Result<()> {
+    let mut w = csv::Writer::from_path(path)?;
+    for r in rows {
+        w.serialize(r)?;
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn spawn(cfg: &TrialsConfig, run_id: &str, out_dir: &Path, role: &[String]) -> Result<Child> {
+    Command::new(&cfg.exe)
+        .args(&cfg.forward)
+        .arg("--run-id")
+        .arg(run_id)
+        .arg("--out-dir")
+        .arg(out_dir)
+        .args(role)
+        // Snapshots are in the role directories; keep the console for logs
+        .stdout(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to start {}", role.join(" ")))
+}
+
+/// Interrupt a companion like Ctrl-C would, so it writes its final snapshot and summary
+async fn stop(mut child: Child) -> Result<ExitStatus> {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: plain kill(2) on a child we spawned and have not reaped yet
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGINT);
+        }
+    }
+    #[cfg(not(unix))]
+    child.start_kill()?;
+    match tokio::time::timeout(COMPANION_STOP_TIMEOUT, child.wait()).await {
+        Ok(status) => Ok(status?),
+        Err(_) => {
+            child.kill().await?;
+            Ok(child.wait().await?)
+        }
+    }
+}
+
+async fn run_trial(cfg: &TrialsConfig, run_id: &str, variant: &Variant) -> Result<ExitStatus> {
+    let out_dir = &cfg.run_dir;
+    let mut companions = Vec::with_capacity(cfg.companions.len());
+    for c in &cfg.companions {
+        companions.push(spawn(cfg, run_id, out_dir, c)?);
+    }
+    if !companions.is_empty() {
+        tokio::time::sleep(cfg.settle).await;
+    }
+    let mut role = cfg.command.clone();
+    role.extend(variant.args.iter().cloned());
+    let status = spawn(cfg, run_id, out_dir, &role)?.wait().await?;
+    for c in companions {
+        let s = stop(c).await?;
+        if !s.success() {
+            warn!(run_id, status = %s, "Companion role exited with an error");
+        }
+    }
+    Ok(status)
+}
+
+/// Run every trial, then write `trials.csv` and `aggregate.csv` under the base run directory.
+pub async fn run_trials(cfg: &TrialsConfig) -> Result<(Vec<TrialRow>, Vec<AggregateRow>)> {
+    if cfg.command.is_empty() {
+        bail!("no role command given");
+    }
+    let variants = expand_matrix(&cfg.matrix);
+    let order = schedule(variants.len(), cfg.repeat.max(1), cfg.shuffle, cfg.seed);
+    let base_dir = &cfg.run_dir;
+    std::fs::create_dir_all(base_dir)?;
+
+    let mut trials = Vec::with_capacity(order.len());
+    for (i, &(v, t)) in order.iter().enumerate() {
+        let variant = &variants[v];
+        let run_id = format!("{}_{}_t{}", cfg.base_run_id, variant.label, t);
+        info!(run_id = %run_id, trial = i + 1, of = order.len(), "Starting trial");
+        let mut row = TrialRow {
+            variant: variant.label.clone(),
+            trial: t,
+            order: i + 1,
+            run_id: run_id.clone(),
+            ..Default::default()
+        };
+        match run_trial(cfg, &run_id, variant).await {
+            Ok(status) if status.success() => match collect_rows(&base_dir.join(&run_id)) {
+                Ok(rows) => match rows.first() {
+                    Some(summary) => {
+                        row.ok = true;
+                        row.fill(summary);
+                    }
+                    None => warn!(run_id = %run_id, "Trial left no role summary"),
+                },
+                Err(e) => warn!(run_id = %run_id, error = %e, "Trial results unreadable"),
+            },
+            Ok(status) => warn!(run_id = %run_id, status = %status, "Trial failed"),
+            Err(e) => warn!(run_id = %run_id, error = %e, "Trial failed"),
+        }
+        trials.push(row);
+        if i + 1 < order.len() && !cfg.cooldown.is_zero() {
+            tokio::time::sleep(cfg.cooldown).await;
+        }
+    }
+
+    let agg = aggregate(&variants, &trials);
+    write_csv(&trials, &base_dir.join("trials.csv"))?;
+    write_csv(&agg, &base_dir.join("aggregate.csv"))?;
+    Ok((trials, agg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrix_expands_and_shuffled_schedule_covers_every_trial() {
+        let matrix = vec![
+            parse_matrix_arg("payload=256,1024").unwrap(),
+            parse_matrix_arg("--rate=1000, 5000").unwrap(),
+        ];
+        let variants = expand_matrix(&matrix);
+        assert_eq!(variants.len(), 4);
+        assert_eq!(variants[1].label, "payload256_rate5000");
+        assert_eq!(variants[1].args, ["--payload", "256", "--rate", "5000"]);
+        assert_eq!(expand_matrix(&[])[0].label, "base");
+        assert!(parse_matrix_arg("payload").is_err());
+
+        let plain = schedule(4, 3, false, 7);
+        let mut shuffled = schedule(4, 3, true, 7);
+        assert_eq!(shuffled, schedule(4, 3, true, 7));
+        assert_ne!(plain, shuffled);
+        shuffled.sort();
+        let mut sorted = plain.clone();
+        sorted.sort();
+        assert_eq!(shuffled, sorted);
+    }
+
+    #[test]
+    fn aggregate_reports_mean_median_stddev_and_t_interval() {
+        let (mean, median, sd, lo, hi) = describe(&[10.0, 12.0, 14.0, 20.0]);
+        assert_eq!(mean, 14.0);
+        assert_eq!(median, 13.0);
+        assert!((sd - 4.3205).abs() < 1e-3);
+        // t(3) = 3.182: half width 3.182 * 4.3205 / 2
+        assert!((hi - mean - 6.874).abs() < 1e-2);
+        assert!((mean - lo - 6.874).abs() < 1e-2);
+
+        let variants = expand_matrix(&[]);
+        let trial = |tps: f64, ok: bool| TrialRow {
+            variant: "base".into(),
+            ok,
+            sub_tps: Some(tps),
+            ..Default::default()
+        };
+        let rows = aggregate(
+            &variants,
+            &[trial(100.0, true), trial(300.0, true), trial(5.0, false)],
+        );
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].metric, "sub_tps");
+        assert_eq!(rows[0].n, 2);
+        assert_eq!(rows[0].mean, 200.0);
+    }
+}
@@ -0,0 +1,330 @@
+//! Live terminal dashboard (feature `tui`).
+//!
+//! Fed by the session's periodic `Stats::snapshot` loop, so it refreshes at the
+//! snapshot interval and adds no reads of its own. Keys: `d` dumps the current
+//! snapshot as JSON, `q`/Esc/Ctrl-C restore the terminal and interrupt the role
+//! (raw mode swallows the terminal's SIGINT, so it is raised explicitly).
+
+use crate::metrics::stats::StatsSnapshot;
+use crate::output::SnapshotRecord;
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Throughput points kept for the sparkline
+const HISTORY: usize = 512;
+const KEY_POLL: Duration = Duration::from_millis(200);
+
+/// Static labels shown in the header, and where `d` writes dumps
+#[derive(Debug, Clone, Default)]
+pub struct DashboardInfo {
+    pub run_id: String,
+    pub role: String,
+    pub engine: String,
+    pub topic: String,
+    pub dump_dir: PathBuf,
+}
+
+pub struct Dashboard {
+    tx: Sender<StatsSnapshot>,
+    thread: JoinHandle<()>,
+}
+
+impl Dashboard {
+    /// Switch the terminal to the dashboard and start the render thread.
+    pub fn start(info: DashboardInfo) -> Result<Self> {
+        let terminal = ratatui::try_init()?;
+        let (tx, rx) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("mq-bench-tui".into())
+            .spawn(move || {
+                if let Err(e) = run(terminal, &info, rx) {
+                    ratatui::restore();
+                    tracing::warn!(error = %e, "Dashboard stopped");
+                }
+            })?;
+        Ok(Self { tx, thread })
+    }
+
+    /// Sender for the snapshot loop; the dashboard exits once every sender is dropped
+    pub fn feed(&self) -> Sender<StatsSnapshot> {
+        self.tx.clone()
+    }
+
+    /// Drop our sender, wait for the render thread and restore the terminal.
+    pub fn stop(self) {
+        drop(self.tx);
+        let _ = self.thread.join();
+    }
+}
+
+#[derive(Default)]
+struct State {
+    throughput: VecDeque<u64>,
+    last: Option<StatsSnapshot>,
+    dumps: u32,
+    status: String,
+}
+
+fn run(
+    mut terminal: DefaultTerminal,
+    info: &DashboardInfo,
+    rx: Receiver<StatsSnapshot>,
+) -> Result<()> {
+    let mut state = State::default();
+    loop {
+        loop {
+            match rx.try_recv() {
+                Ok(snap) => {
+                    if state.throughput.len() == HISTORY {
+                        state.throughput.pop_front();
+                    }
+                    state
+                        .throughput
+                        .push_back(snap.interval_throughput().round() as u64);
+                    state.last = Some(snap);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    ratatui::restore();
+                    return Ok(());
+                }
+            }
+        }
+        terminal.draw(|f| render(f, info, &state))?;
+
+        if !event::poll(KEY_POLL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return quit(),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return quit(),
+            KeyCode::Char('d') => state.status = dump(info, &mut state),
+            _ => {}
+        }
+    }
+}
+
+/// Restore the terminal, then stop the role the same way Ctrl-C would outside raw mode
+fn quit() -> Result<()> {
+    ratatui::restore();
+    // SAFETY: raise only delivers SIGINT to this process; tokio's ctrl_c handlers pick it up
+    unsafe {
+        libc::raise(libc::SIGINT);
+    }
+    Ok(())
+}
+
+fn dump(info: &DashboardInfo, state: &mut State) -> String {
+    let Some(snap) = &state.last else {
+        return "no snapshot yet".into();
+    };
+    state.dumps += 1;
+    let path = info
+        .dump_dir
+        .join(format!("snapshot-{}-{}.json", snap.timestamp, state.dumps));
+    let written = std::fs::create_dir_all(&info.dump_dir)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| Ok(serde_json::to_vec_pretty(&SnapshotRecord::from(snap))?))
+        .and_then(|json| Ok(std::fs::write(&path, json)?));
+    match written {
+        Ok(()) => format!("dumped {}", path.display()),
+        Err(e) => format!("dump failed: {}", e),
+    }
+}
+
+fn ms(ns: u64) -> String {
+    format!("{:.3}", ns as f64 / 1e6)
+}
+
+fn render(f: &mut Frame, info: &DashboardInfo, state: &State) {
+    let [header, spark, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(8),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(f.area());
+
+    let uptime = state
+        .last
+        .as_ref()
+        .map(|s| s.total_duration.as_secs())
+        .unwrap_or(0);
+    f.render_widget(
+        Paragraph::new(Line::from(format!(
+            " mq-bench {} | run {} | {} | {} | up {}s",
+            info.role, info.run_id, info.engine, info.topic, uptime
+        )))
+        .bold(),
+        header,
+    );
+    f.render_widget(
+        Paragraph::new(Line::from(format!(
+            " q/Ctrl-C stop  d dump snapshot  {}",
+            state.status
+        )))
+        .dim(),
+        footer,
+    );
+
+    let Some(s) = &state.last else {
+        f.render_widget(
+            Paragraph::new("waiting for the first snapshot...").block(Block::bordered()),
+            body,
+        );
+        return;
+    };
+
+    // Newest points on the right
+    let width = spark.width.saturating_sub(2) as usize;
+    let points: Vec<u64> = state
+        .throughput
+        .iter()
+        .skip(state.throughput.len().saturating_sub(width))
+        .copied()
+        .collect();
+    f.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(format!(
+                " throughput {:.0} msg/s (total avg {:.0}) ",
+                s.interval_throughput(),
+                s.total_throughput()
+            )))
+            .style(Style::default().fg(Color::Green))
+            .data(&points),
+        spark,
+    );
+
+    let [left, right] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(body);
+
+    let latency = Table::new(
+        vec![
+            Row::new(vec![
+                "p50".into(),
+                ms(s.interval_latency_ns_p50),
+                ms(s.latency_ns_p50),
+            ]),
+            Row::new(vec![
+                "p95".into(),
+                ms(s.interval_latency_ns_p95),
+                ms(s.latency_ns_p95),
+            ]),
+            Row::new(vec![
+                "p99".into(),
+                ms(s.interval_latency_ns_p99),
+                ms(s.latency_ns_p99),
+            ]),
+            Row::new(vec!["max".into(), String::new(), ms(s.latency_ns_max)]),
+            Row::new(vec![
+                "mean".into(),
+                String::new(),
+                format!("{:.3}", s.latency_ns_mean / 1e6),
+            ]),
+            Row::new(vec![
+                "samples".into(),
+                s.interval_latency_sample_count.to_string(),
+                s.latency_sample_count.to_string(),
+            ]),
+        ],
+        [
+            Constraint::Length(9),
+            Constraint::Length(12),
+            Constraint::Length(12),
+        ],
+    )
+    .header(Row::new(vec!["ms", "interval", "cumulative"]).bold())
+    .block(Block::bordered().title(" latency "));
+    f.render_widget(latency, left);
+
+    let counters: Vec<(&str, String)> = vec![
+        ("sent", s.sent_count.to_string()),
+        ("received", s.received_count.to_string()),
+        ("errors", s.error_count.to_string()),
+        ("  timeouts", s.timeout_count.to_string()),
+        ("  late replies", s.late_reply_count.to_string()),
+        (
+            "connections",
+            format!("{} ({} active)", s.connections, s.active_connections),
+        ),
+        (
+            "conn attempts/fail",
+            format!("{}/{}", s.connection_attempts, s.connection_failures),
+        ),
+        (
+            "reconnects/fail",
+            format!("{}/{}", s.reconnects, s.reconnect_failures),
+        ),
+        ("crashes injected", s.crashes_injected.to_string()),
+        ("duplicates", s.duplicate_count.to_string()),
+        ("gaps", s.gap_count.to_string()),
+        ("head loss", s.head_loss.to_string()),
+        (
+            "excluded warm/cool",
+            format!("{}/{}", s.warmup_excluded_count, s.cooldown_excluded_count),
+        ),
+        ("target rate", format!("{:.0} msg/s", s.target_rate)),
+    ];
+    let counters = Table::new(
+        counters
+            .into_iter()
+            .map(|(k, v)| Row::new(vec![k.to_string(), v])),
+        [Constraint::Length(20), Constraint::Min(10)],
+    )
+    .block(Block::bordered().title(" counters "));
+    f.render_widget(counters, right);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::stats::Stats;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    #[tokio::test]
+    async fn renders_latency_and_counters() {
+        let stats = Stats::new();
+        stats.record_sent().await;
+        stats.record_received_batch(&[2_000_000, 4_000_000]).await;
+        let snap = stats.snapshot().await;
+
+        let mut state = State::default();
+        state.throughput.extend([10, 20, 30]);
+        state.last = Some(snap);
+        let info = DashboardInfo {
+            role: "mt-sub".into(),
+            run_id: "r1".into(),
+            ..Default::default()
+        };
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| render(f, &info, &state)).unwrap();
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(text.contains("mq-bench mt-sub | run r1"));
+        assert!(text.contains("interval"));
+        assert!(text.contains("received"));
+        assert!(text.contains(&ms(state.last.as_ref().unwrap().latency_ns_p99)));
+    }
+}
@@ -0,0 +1,118 @@
+//! Integration tests for mt-sub subscription churn over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::crash::CrashConfig;
+use mq_bench::metrics::stats::Stats;
+use mq_bench::rate::ArrivalConfig;
+use mq_bench::roles::multi_topic::{
+    ChurnConfig, KeyMappingMode, MultiTopicConfig, MultiTopicSubConfig, run_multi_topic,
+    run_multi_topic_sub,
+};
+use mq_bench::transport::{ConnectOptions, Engine};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn sub_config(prefix: &str, churn: Option<ChurnConfig>, share: bool) -> MultiTopicSubConfig {
+    MultiTopicSubConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        topic_prefix: prefix.into(),
+        tenants: 1,
+        regions: 1,
+        services: 1,
+        shards: 4,
+        subscribers: -1,
+        mapping: KeyMappingMode::MDim,
+        key_latency_file: None,
+        churn,
+        duration_secs: 2,
+        snapshot_interval_secs: 1,
+        share_transport: share,
+        ramp_up_secs: 0.0,
+        shared_stats: None,
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        crash_per_topic: false,
+        crash_stagger_secs: 0.0,
+    }
+}
+
+#[tokio::test]
+async fn churned_subscriptions_resume_and_log_first_message_latency() {
+    let dir = std::env::temp_dir().join(format!("mqb-churn-{}", uuid::Uuid::new_v4()));
+    let events_path = dir.join("churn.csv");
+
+    let stats = Arc::new(Stats::new());
+    let mut cfg = sub_config(
+        "churn-test",
+        Some(ChurnConfig {
+            pct_per_sec: 100.0,
+            events_file: Some(events_path.clone()),
+        }),
+        true,
+    );
+    cfg.shared_stats = Some(stats.clone());
+    let sub = tokio::spawn(run_multi_topic_sub(cfg));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // 4 keys at 200 msg/s each, for the whole churn window
+    run_multi_topic(MultiTopicConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        topic_prefix: "churn-test".into(),
+        tenants: 1,
+        regions: 1,
+        services: 1,
+        shards: 4,
+        publishers: -1,
+        mapping: KeyMappingMode::MDim,
+        payload_size: 64,
+        rate_per_pub: Some(200.0),
+        arrival: ArrivalConfig::default(),
+        popularity: Default::default(),
+        key_rates_file: None,
+        duration_secs: 2,
+        snapshot_interval_secs: 1,
+        share_transport: true,
+        ramp_up_secs: 0.0,
+        shared_stats: None,
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        crash_per_topic: false,
+        crash_stagger_secs: 0.0,
+    })
+    .await
+    .expect("mt-pub");
+    sub.await.unwrap().expect("mt-sub");
+
+    // All 4 keys re-created every second for 2 s
+    let mut rdr = csv::Reader::from_path(&events_path).expect("churn.csv");
+    let events: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+    assert!((6..=9).contains(&events.len()), "{} events", events.len());
+    assert_eq!(&events[0][1], "churn-test/t0/r0/svc0/k0");
+    assert_eq!(&events[1][1], "churn-test/t0/r0/svc0/k1");
+    let first_msg: Vec<f64> = events.iter().filter_map(|e| e[3].parse().ok()).collect();
+    assert!(first_msg.len() >= events.len() - 4, "{:?}", events);
+    assert!(first_msg.iter().all(|ms| *ms < 100.0), "{:?}", first_msg);
+
+    // Delivery continues across re-subscriptions; nothing is left subscribed
+    let c = stats.counters();
+    assert!(c.received_count > 1000, "{:?}", c);
+    assert_eq!(c.connections, 0);
+    assert_eq!(c.active_connections, 0);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn churn_requires_shared_transport() {
+    let churn = ChurnConfig {
+        pct_per_sec: 10.0,
+        events_file: None,
+    };
+    let err = run_multi_topic_sub(sub_config("churn-test-per-key", Some(churn), false))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("shared transport"), "{}", err);
+}
@@ -0,0 +1,97 @@
+//! Integration tests for the closed-loop publisher window over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::crash::CrashConfig;
+use mq_bench::metrics::stats::Stats;
+use mq_bench::roles::publisher::{ClosedLoopConfig, PublisherConfig, run_publisher};
+use mq_bench::transport::{ConnectOptions, Engine, TransportBuilder};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn publisher_config(
+    key: &str,
+    stats: Arc<Stats>,
+    closed_loop: ClosedLoopConfig,
+) -> PublisherConfig {
+    PublisherConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        key_expr: key.to_string(),
+        payload_size: 64,
+        rate: None,
+        arrival: Default::default(),
+        duration_secs: Some(1),
+        output_file: None,
+        snapshot_interval_secs: 1,
+        shared_stats: Some(stats),
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        closed_loop: Some(closed_loop),
+    }
+}
+
+#[tokio::test]
+async fn echo_round_trip_bounds_throughput() {
+    // Echo: republish everything on the data key to the ack key after 50 ms
+    let echo = TransportBuilder::connect(Engine::Mock, ConnectOptions::default())
+        .await
+        .expect("connect");
+    let ack_pub: Arc<dyn mq_bench::transport::Publisher> = Arc::from(
+        echo.create_publisher("closed-loop/ack")
+            .await
+            .expect("ack publisher"),
+    );
+    let _echo_sub = echo
+        .subscribe(
+            "closed-loop/data",
+            Box::new(move |m| {
+                let ack_pub = ack_pub.clone();
+                let body = bytes::Bytes::from(m.payload.as_cow().into_owned());
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    let _ = ack_pub.publish(body).await;
+                });
+            }),
+        )
+        .await
+        .expect("echo subscription");
+
+    let stats = Arc::new(Stats::new());
+    let cfg = publisher_config(
+        "closed-loop/data",
+        stats.clone(),
+        ClosedLoopConfig {
+            window: 2,
+            ack_key: Some("closed-loop/ack".into()),
+            ack_timeout: Duration::from_secs(5),
+        },
+    );
+    run_publisher(cfg).await.expect("publisher");
+
+    // Two in flight, each acked after ~50 ms: about 40 sends in one second
+    let c = stats.counters();
+    assert!((20..=44).contains(&c.sent_count), "sent {}", c.sent_count);
+    assert_eq!(c.timeout_count, 0);
+}
+
+#[tokio::test]
+async fn unacked_messages_expire_and_free_their_slots() {
+    let stats = Arc::new(Stats::new());
+    let cfg = publisher_config(
+        "closed-loop/silent",
+        stats.clone(),
+        ClosedLoopConfig {
+            window: 4,
+            ack_key: Some("closed-loop/nobody".into()),
+            ack_timeout: Duration::from_millis(200),
+        },
+    );
+    run_publisher(cfg).await.expect("publisher");
+
+    // Bursts of 4 every 200 ms: ~5-6 bursts in one second, all but the last expired
+    let c = stats.counters();
+    assert!((16..=28).contains(&c.sent_count), "sent {}", c.sent_count);
+    assert!(c.timeout_count >= c.sent_count - 4, "{:?}", c);
+    assert_eq!(c.error_count, c.timeout_count);
+}
@@ -0,0 +1,53 @@
+//! Fixtures shared by the query integration tests. Tests override the fields they
+//! exercise with struct update syntax.
+
+#![allow(dead_code)]
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::roles::queryable::QueryableConfig;
+use mq_bench::roles::requester::RequesterConfig;
+use mq_bench::transport::{ConnectOptions, Engine};
+use std::sync::Arc;
+
+/// One-second mock requester sending unpaced with empty requests
+pub fn requester_config(key: &str, stats: Arc<Stats>) -> RequesterConfig {
+    RequesterConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        key_expr: key.to_string(),
+        request_size: 0,
+        stream: false,
+        stream_dir: None,
+        qps: None,
+        arrival: Default::default(),
+        concurrency: 16,
+        timeout_ms: 1000,
+        duration_secs: 1,
+        output_file: None,
+        snapshot_interval_secs: 1,
+        shared_stats: Some(stats),
+        disable_internal_snapshot: true,
+    }
+}
+
+/// Mock queryable serving `key` with immediate 64-byte replies, stopping after 3s
+pub fn queryable_config(key: &str, stats: Arc<Stats>) -> QueryableConfig {
+    QueryableConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        serve_prefix: vec![key.to_string()],
+        reply_size: 64,
+        reply_mode: Default::default(),
+        service_time: Default::default(),
+        chunks: None,
+        service_seed: None,
+        cpu_bound: false,
+        workers: 0,
+        queue_dir: None,
+        output_file: None,
+        snapshot_interval_secs: 1,
+        shared_stats: Some(stats),
+        disable_internal_snapshot: true,
+        test_stop_after_secs: Some(3),
+    }
+}
@@ -0,0 +1,132 @@
+//! Integration tests for the connection storm role over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::roles::conn_storm::{ConnStormConfig, run_conn_storm};
+use mq_bench::transport::{ConnectOptions, Engine};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn storm_config(connections: u64, rate: Option<f64>, stats: Arc<Stats>) -> ConnStormConfig {
+    ConnStormConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        connections,
+        rate,
+        concurrency: 8,
+        subscribe_prefix: Some("conn-storm-test".into()),
+        hold_secs: 0,
+        connect_timeout: Duration::from_secs(1),
+        degrade_factor: 2.0,
+        output_dir: None,
+        output_file: None,
+        snapshot_interval_secs: 1,
+        shared_stats: Some(stats),
+        disable_internal_snapshot: true,
+    }
+}
+
+#[tokio::test]
+async fn storm_opens_holds_and_closes_every_connection() {
+    let dir = std::env::temp_dir().join(format!("mqb-conn-storm-{}", uuid::Uuid::new_v4()));
+    let stats = Arc::new(Stats::new());
+    let mut cfg = storm_config(200, None, stats.clone());
+    cfg.output_dir = Some(dir.clone());
+    let summary = run_conn_storm(cfg).await.expect("conn-storm");
+
+    assert_eq!(
+        (summary.attempts, summary.connected, summary.failed),
+        (200, 200, 0)
+    );
+    assert!(summary.failures.is_empty());
+    let c = stats.counters();
+    assert_eq!(c.connection_attempts, 200);
+    assert_eq!(c.connection_failures, 0);
+    assert_eq!(c.connections, 0);
+
+    let mut rdr = csv::Reader::from_path(dir.join("connects.csv")).expect("connects.csv");
+    let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+    assert_eq!(rows.len(), 200);
+    assert_eq!(&rows[199][0], "199");
+    assert!(
+        rows.iter()
+            .all(|r| r[3].parse::<f64>().is_ok() && r[5].is_empty())
+    );
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("conn_storm.json")).unwrap())
+            .unwrap();
+    assert_eq!(json["connected"], 200);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn storm_keeps_to_the_connect_rate() {
+    let stats = Arc::new(Stats::new());
+    let summary = run_conn_storm(storm_config(50, Some(100.0), stats))
+        .await
+        .expect("conn-storm");
+    assert_eq!(summary.connected, 50);
+    // 50 attempts at 100/s, the first one immediate
+    assert!(
+        (0.4..0.7).contains(&summary.storm_secs),
+        "{}",
+        summary.storm_secs
+    );
+}
+
+/// A local port with nothing listening on it
+fn closed_port() -> u16 {
+    let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    l.local_addr().unwrap().port()
+}
+
+/// Every attempt must fail when no broker is listening, including for adapters that
+/// only parse options in `connect`
+async fn assert_storm_fails_without_broker(engine: Engine, params: &[(&str, String)]) {
+    let stats = Arc::new(Stats::new());
+    let mut cfg = storm_config(3, None, stats);
+    cfg.engine = engine.clone();
+    cfg.subscribe_prefix = None;
+    cfg.connect.params = params
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect();
+    let summary = run_conn_storm(cfg).await.expect("conn-storm");
+    assert_eq!(
+        (summary.connected, summary.failed),
+        (0, 3),
+        "{:?}: {:?}",
+        engine,
+        summary.failures
+    );
+}
+
+#[cfg(feature = "transport-mqtt")]
+#[tokio::test]
+async fn mqtt_storm_fails_without_broker() {
+    let port = closed_port().to_string();
+    assert_storm_fails_without_broker(Engine::Mqtt, &[("port", port)]).await;
+}
+
+#[cfg(feature = "transport-nats")]
+#[tokio::test]
+async fn nats_storm_fails_without_broker() {
+    let port = closed_port().to_string();
+    assert_storm_fails_without_broker(Engine::Nats, &[("port", port)]).await;
+}
+
+#[cfg(feature = "transport-redis")]
+#[tokio::test]
+async fn redis_storm_fails_without_broker() {
+    let url = format!("redis://127.0.0.1:{}", closed_port());
+    assert_storm_fails_without_broker(Engine::Redis, &[("url", url)]).await;
+}
+
+#[cfg(feature = "transport-amqp-0-9")]
+#[tokio::test]
+async fn amqp_storm_fails_without_broker() {
+    let port = closed_port().to_string();
+    assert_storm_fails_without_broker(Engine::Amqp, &[("port", port)]).await;
+}
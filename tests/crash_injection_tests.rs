@@ -208,12 +208,14 @@ async fn publisher_with_crash_disabled_runs_normally() {
         key_expr: "test/no_crash".to_string(),
         payload_size: 64,
         rate: Some(100.0), // 100 msg/s
+        arrival: Default::default(),
         duration_secs: Some(1),
         output_file: None,
         snapshot_interval_secs: 1,
         shared_stats: Some(stats.clone()),
         disable_internal_snapshot: true,
         crash_config: CrashConfig::default(), // Disabled
+        closed_loop: None,
     };
 
     let result = run_publisher(config).await;
@@ -246,12 +248,14 @@ async fn publisher_with_single_crash_reconnects() {
         key_expr: "test/single_crash".to_string(),
         payload_size: 64,
         rate: Some(50.0),
+        arrival: Default::default(),
         duration_secs: Some(2), // Run for 2 seconds
         output_file: None,
         snapshot_interval_secs: 1,
         shared_stats: Some(stats.clone()),
         disable_internal_snapshot: true,
         crash_config,
+        closed_loop: None,
     };
 
     let result = run_publisher(config).await;
@@ -283,12 +287,14 @@ async fn publisher_without_retry_stops_on_crash() {
         key_expr: "test/no_retry".to_string(),
         payload_size: 64,
         rate: Some(100.0),
+        arrival: Default::default(),
         duration_secs: Some(5), // Long duration - but should stop on crash
         output_file: None,
         snapshot_interval_secs: 1,
         shared_stats: Some(stats.clone()),
         disable_internal_snapshot: true,
         crash_config,
+        closed_loop: None,
     };
 
     let start = std::time::Instant::now();
@@ -331,12 +337,14 @@ async fn publisher_sequence_preserved_across_reconnect() {
         key_expr: "test/sequence_unique_key_12345".to_string(), // Unique key to avoid collision
         payload_size: 64,
         rate: Some(100.0),
+        arrival: Default::default(),
         duration_secs: Some(3), // Run for 3 seconds to ensure crashes happen
         output_file: None,
         snapshot_interval_secs: 1,
         shared_stats: Some(stats.clone()),
         disable_internal_snapshot: true,
         crash_config,
+        closed_loop: None,
     };
 
     let result = run_publisher(config).await;
@@ -384,6 +392,7 @@ async fn subscriber_with_crash_disabled_runs_normally() {
         disable_internal_snapshot: true,
         test_stop_after_secs: Some(1),
         crash_config: CrashConfig::default(),
+        per_source_dir: None,
     };
 
     let result = run_subscriber(config).await;
@@ -418,6 +427,7 @@ async fn subscriber_with_single_crash_reconnects() {
         disable_internal_snapshot: true,
         test_stop_after_secs: Some(2),
         crash_config,
+        per_source_dir: None,
     };
 
     let result = run_subscriber(config).await;
@@ -452,6 +462,7 @@ async fn subscriber_without_retry_stops_on_crash() {
         disable_internal_snapshot: true,
         test_stop_after_secs: Some(5),
         crash_config,
+        per_source_dir: None,
     };
 
     let start = std::time::Instant::now();
@@ -504,12 +515,14 @@ async fn pubsub_both_crashing_independently() {
         key_expr: "test/both_crash_unique_99999".to_string(),
         payload_size: 64,
         rate: Some(50.0),
+        arrival: Default::default(),
         duration_secs: Some(3),
         output_file: None,
         snapshot_interval_secs: 1,
         shared_stats: Some(pub_stats.clone()),
         disable_internal_snapshot: true,
         crash_config: pub_crash,
+        closed_loop: None,
     };
 
     let sub_config = SubscriberConfig {
@@ -522,6 +535,7 @@ async fn pubsub_both_crashing_independently() {
         disable_internal_snapshot: true,
         test_stop_after_secs: Some(3),
         crash_config: sub_crash,
+        per_source_dir: None,
     };
 
     // Run both concurrently
@@ -0,0 +1,39 @@
+//! Integration test for the find-max saturation search over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::find_max::{Driver, FindMaxConfig, Slo, run_find_max};
+use mq_bench::transport::{ConnectOptions, Engine};
+
+#[tokio::test]
+async fn find_max_reaches_ceiling_when_slo_holds() {
+    let cfg = FindMaxConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        topic_prefix: "findmax-test".into(),
+        driver: Driver::Pub,
+        publishers: 2,
+        topics: 1,
+        payload_size: 64,
+        settle_secs: 0,
+        window_secs: 1,
+        slo: Slo {
+            min_delivery: 0.9,
+            max_p99_ns: 1_000_000_000,
+        },
+        start_rate: 200.0,
+        max_rate: 400.0,
+        precision: 0.05,
+    };
+    let result = run_find_max(&cfg).await.expect("find-max");
+    let rates: Vec<f64> = result.curve.iter().map(|p| p.rate).collect();
+    assert_eq!(rates, vec![200.0, 400.0]);
+    for p in &result.curve {
+        assert!(p.passed, "{:?}", p);
+        assert!(p.delivery >= 0.9 && p.delivery <= 1.1, "{:?}", p);
+        assert!(p.sent > 0);
+    }
+    let knee = result.knee.expect("knee");
+    assert_eq!(knee.rate, 400.0);
+    assert!(knee.throughput > 300.0, "{:?}", knee);
+}
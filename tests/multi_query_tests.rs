@@ -0,0 +1,143 @@
+//! Integration tests for the multi-query driver over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::roles::multi_query::{MultiQueryConfig, run_multi_query};
+use mq_bench::roles::queryable::{ChunkCount, ReplyMode, ServiceTime};
+use mq_bench::transport::{ConnectOptions, Engine};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn mq_config(prefix: &str, stats: Arc<Stats>) -> MultiQueryConfig {
+    MultiQueryConfig {
+        engine: Engine::Mock,
+        req_connect: ConnectOptions::default(),
+        qry_connect: ConnectOptions::default(),
+        serve_prefix_template: format!("{}/t{{t}}/r{{r}}", prefix),
+        key_template: format!("{}/t{{t}}/r{{r}}/ns{{n}}/item/{{id}}", prefix),
+        tenants: 2,
+        regions: 2,
+        namespaces: 1,
+        ids: 3,
+        requesters: -1,
+        qps_per_req: Some(20),
+        arrival: Default::default(),
+        concurrency: 2,
+        timeout_ms: 1000,
+        request_size: 32,
+        serve: true,
+        reply_size: 64,
+        reply_mode: Default::default(),
+        service_time: Default::default(),
+        chunks: None,
+        service_seed: None,
+        cpu_bound: false,
+        workers: 0,
+        duration_secs: 1,
+        snapshot_interval_secs: 1,
+        share_transport: true,
+        ramp_up_secs: 0.0,
+        shared_stats: Some(stats),
+        disable_internal_snapshot: true,
+    }
+}
+
+#[tokio::test]
+async fn requesters_query_in_process_queryables_over_templated_keys() {
+    for share_transport in [true, false] {
+        let stats = Arc::new(Stats::new());
+        let mut cfg = mq_config(&format!("mq-inproc-{}", share_transport), stats.clone());
+        cfg.share_transport = share_transport;
+        run_multi_query(cfg).await.expect("multi-query");
+
+        let c = stats.counters();
+        // 12 requesters at 20 q/s for 1 s; in-process replies are not counted as sent
+        assert!((200..=260).contains(&c.sent_count), "{:?}", c);
+        assert_eq!(c.received_count, c.sent_count, "{:?}", c);
+        assert_eq!(c.error_count, 0);
+        let transports = if share_transport { 1 } else { 12 };
+        assert_eq!(c.connection_attempts, transports);
+        assert_eq!(c.connections, 0);
+    }
+}
+
+#[tokio::test]
+async fn serve_only_driver_answers_a_separate_requester_driver() {
+    let qry_stats = Arc::new(Stats::new());
+    let mut qry_cfg = mq_config("mq-split", qry_stats.clone());
+    qry_cfg.requesters = 0;
+    qry_cfg.duration_secs = 3;
+    let qry = tokio::spawn(run_multi_query(qry_cfg));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let req_stats = Arc::new(Stats::new());
+    let mut req_cfg = mq_config("mq-split", req_stats.clone());
+    req_cfg.serve = false;
+    req_cfg.requesters = 4;
+    req_cfg.ramp_up_secs = 0.3;
+    run_multi_query(req_cfg).await.expect("requesters");
+    qry.await.unwrap().expect("queryables");
+
+    let req = req_stats.counters();
+    assert!(req.sent_count >= 50, "{:?}", req);
+    assert_eq!(req.received_count, req.sent_count, "{:?}", req);
+    // Serve-only: replies are the driver's own sent count
+    assert_eq!(qry_stats.counters().sent_count, req.sent_count);
+}
+
+#[tokio::test]
+async fn queries_without_a_queryable_fail() {
+    let stats = Arc::new(Stats::new());
+    let mut cfg = mq_config("mq-unserved", stats.clone());
+    cfg.serve = false;
+    cfg.requesters = 2;
+    run_multi_query(cfg).await.expect("multi-query");
+    let c = stats.counters();
+    assert!(c.sent_count > 0);
+    assert_eq!(c.received_count, 0);
+    assert_eq!(c.error_count, c.sent_count);
+}
+
+#[tokio::test]
+async fn serve_driver_serves_like_qry() {
+    let qry_stats = Arc::new(Stats::new());
+    let mut qry_cfg = mq_config("mq-served", qry_stats.clone());
+    qry_cfg.requesters = 0;
+    qry_cfg.duration_secs = 3;
+    qry_cfg.reply_mode = ReplyMode::Transform;
+    qry_cfg.chunks = Some(ChunkCount { min: 2, max: 2 });
+    qry_cfg.workers = 2;
+    let qry = tokio::spawn(run_multi_query(qry_cfg));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let req_stats = Arc::new(Stats::new());
+    let mut req_cfg = mq_config("mq-served", req_stats.clone());
+    req_cfg.serve = false;
+    req_cfg.requesters = 2;
+    run_multi_query(req_cfg).await.expect("requesters");
+    qry.await.unwrap().expect("queryables");
+
+    let req = req_stats.counters();
+    assert!(req.sent_count > 0);
+    assert_eq!(req.received_count, req.sent_count, "{:?}", req);
+    let qry = qry_stats.counters();
+    // Request-leg latency from each body's header, and two chunks per query
+    assert_eq!(qry.received_count, req.sent_count, "{:?}", qry);
+    assert_eq!(qry.sent_count, 2 * req.sent_count, "{:?}", qry);
+}
+
+#[tokio::test]
+async fn slow_replies_are_classified_late() {
+    let stats = Arc::new(Stats::new());
+    let mut cfg = mq_config("mq-late", stats.clone());
+    cfg.requesters = 1;
+    cfg.timeout_ms = 50;
+    cfg.service_time = ServiceTime::Fixed { ms: 80.0 };
+    run_multi_query(cfg).await.expect("multi-query");
+    let c = stats.counters();
+    assert!(c.sent_count > 0);
+    assert_eq!(c.received_count, 0);
+    assert_eq!(c.late_reply_count, c.sent_count, "{:?}", c);
+    assert_eq!(c.timeout_count, 0, "{:?}", c);
+}
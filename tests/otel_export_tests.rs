@@ -0,0 +1,165 @@
+//! OTLP export against a local collector stand-in.
+//!
+//! The stand-in speaks just enough HTTP/1.1 to accept OTLP/HTTP protobuf posts and
+//! records each request path and body. Run with `cargo test --features otel`.
+
+#![cfg(all(feature = "otel", feature = "transport-mock"))]
+
+use mq_bench::crash::CrashConfig;
+use mq_bench::metrics::prometheus::MetricLabels;
+use mq_bench::metrics::stats::Stats;
+use mq_bench::otel::{OtelConfig, OtlpProtocol};
+use mq_bench::roles::publisher::{PublisherConfig, run_publisher};
+use mq_bench::roles::subscriber::{SubscriberConfig, run_subscriber};
+use mq_bench::transport::{ConnectOptions, Engine};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+type Received = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+async fn start_collector() -> (SocketAddr, Received) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let received: Received = Arc::default();
+    let store = received.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let store = store.clone();
+            tokio::spawn(async move {
+                let _ = serve_conn(stream, store).await;
+            });
+        }
+    });
+    (addr, received)
+}
+
+/// Handle keep-alive OTLP posts: read head + Content-Length body, reply 200 empty.
+async fn serve_conn(mut stream: TcpStream, store: Received) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let head_end = loop {
+            if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break i + 4;
+            }
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        };
+        let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+        let path = head.split_whitespace().nth(1).unwrap_or("").to_string();
+        let len = head
+            .lines()
+            .find_map(|l| {
+                let (k, v) = l.split_once(':')?;
+                k.eq_ignore_ascii_case("content-length")
+                    .then(|| v.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or(0);
+        while buf.len() < head_end + len {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        let body = buf[head_end..head_end + len].to_vec();
+        buf.drain(..head_end + len);
+        store.lock().unwrap().push((path, body));
+        stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: application/x-protobuf\r\nContent-Length: 0\r\n\r\n",
+            )
+            .await?;
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn exports_stats_metrics_and_message_spans_over_http() {
+    let (addr, received) = start_collector().await;
+    let guard = mq_bench::logging::init_with_otel(
+        "warn",
+        &OtelConfig {
+            endpoint: format!("http://{}", addr),
+            protocol: OtlpProtocol::Http,
+            sample_every: 10,
+            metrics_interval: Duration::from_secs(1),
+            resource: vec![("mq_bench.run_id".into(), "otel-test".into())],
+        },
+    )
+    .expect("otel init");
+
+    let stats = Arc::new(Stats::new());
+    mq_bench::otel::register_stats(
+        stats.clone(),
+        &MetricLabels {
+            run_id: "otel-test".into(),
+            role: "pubsub".into(),
+            engine: "mock".into(),
+            topic: "test/otel".into(),
+        },
+    );
+
+    let sub = tokio::spawn(run_subscriber(SubscriberConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        key_expr: "test/otel".to_string(),
+        output_file: None,
+        snapshot_interval_secs: 1,
+        shared_stats: Some(stats.clone()),
+        disable_internal_snapshot: true,
+        test_stop_after_secs: Some(2),
+        crash_config: CrashConfig::default(),
+        per_source_dir: None,
+    }));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    run_publisher(PublisherConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        key_expr: "test/otel".to_string(),
+        payload_size: 128,
+        rate: Some(200.0),
+        arrival: Default::default(),
+        duration_secs: Some(1),
+        output_file: None,
+        snapshot_interval_secs: 1,
+        shared_stats: Some(stats.clone()),
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        closed_loop: None,
+    })
+    .await
+    .expect("publisher");
+    sub.await.unwrap().expect("subscriber");
+    assert!(stats.counters().received_count > 0, "expected deliveries");
+
+    guard.shutdown().await.expect("otel shutdown");
+
+    let received = received.lock().unwrap();
+    let traces: Vec<&Vec<u8>> = received
+        .iter()
+        .filter(|(p, _)| p == "/v1/traces")
+        .map(|(_, b)| b)
+        .collect();
+    let metrics: Vec<&Vec<u8>> = received
+        .iter()
+        .filter(|(p, _)| p == "/v1/metrics")
+        .map(|(_, b)| b)
+        .collect();
+    assert!(traces.iter().any(|b| contains(b, b"mq.publish")));
+    assert!(traces.iter().any(|b| contains(b, b"mq.deliver")));
+    assert!(metrics.iter().any(|b| contains(b, b"mq_bench.sent")));
+    assert!(metrics.iter().any(|b| contains(b, b"otel-test")));
+}
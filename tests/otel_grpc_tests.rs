@@ -0,0 +1,177 @@
+//! OTLP/gRPC export from mt-pub/mt-sub against a local collector stand-in.
+//!
+//! The stand-in is a bare HTTP/2 server that records each gRPC method path and
+//! request body and answers with an empty `Export*ServiceResponse`. It lives in its
+//! own test binary because the OTel providers are process-global. Run with
+//! `cargo test --features otel`.
+
+#![cfg(all(feature = "otel", feature = "transport-mock"))]
+
+use bytes::Bytes;
+use mq_bench::crash::CrashConfig;
+use mq_bench::metrics::prometheus::MetricLabels;
+use mq_bench::metrics::stats::Stats;
+use mq_bench::otel::{OtelConfig, OtlpProtocol};
+use mq_bench::rate::ArrivalConfig;
+use mq_bench::roles::multi_topic::{
+    KeyMappingMode, MultiTopicConfig, MultiTopicSubConfig, run_multi_topic, run_multi_topic_sub,
+};
+use mq_bench::transport::{ConnectOptions, Engine};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+
+type Received = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+async fn start_collector() -> (SocketAddr, Received) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let received: Received = Arc::default();
+    let store = received.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let store = store.clone();
+            tokio::spawn(async move {
+                let _ = serve_conn(stream, store).await;
+            });
+        }
+    });
+    (addr, received)
+}
+
+/// Accept gRPC unary calls on one HTTP/2 connection until the client goes away.
+async fn serve_conn(stream: TcpStream, store: Received) -> Result<(), h2::Error> {
+    let mut conn = h2::server::handshake(stream).await?;
+    while let Some(call) = conn.accept().await {
+        let (req, mut respond) = call?;
+        let store = store.clone();
+        tokio::spawn(async move {
+            let path = req.uri().path().to_string();
+            let mut body = req.into_body();
+            let mut data = Vec::new();
+            while let Some(chunk) = body.data().await {
+                let chunk = chunk?;
+                let _ = body.flow_control().release_capacity(chunk.len());
+                data.extend_from_slice(&chunk);
+            }
+            store.lock().unwrap().push((path, data));
+            let head = http::Response::builder()
+                .status(200)
+                .header("content-type", "application/grpc")
+                .body(())
+                .unwrap();
+            let mut send = respond.send_response(head, false)?;
+            // Uncompressed, zero-length message: an empty Export*ServiceResponse
+            send.send_data(Bytes::from_static(&[0, 0, 0, 0, 0]), false)?;
+            let mut trailers = http::HeaderMap::new();
+            trailers.insert("grpc-status", http::HeaderValue::from_static("0"));
+            send.send_trailers(trailers)
+        });
+    }
+    Ok(())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn multi_topic_roles_export_spans_and_metrics_over_grpc() {
+    let (addr, received) = start_collector().await;
+    let guard = mq_bench::logging::init_with_otel(
+        "warn",
+        &OtelConfig {
+            endpoint: format!("http://{}", addr),
+            protocol: OtlpProtocol::Grpc,
+            sample_every: 10,
+            metrics_interval: Duration::from_secs(1),
+            resource: vec![("mq_bench.run_id".into(), "otel-grpc-test".into())],
+        },
+    )
+    .expect("otel init");
+
+    let stats = Arc::new(Stats::new());
+    mq_bench::otel::register_stats(
+        stats.clone(),
+        &MetricLabels {
+            run_id: "otel-grpc-test".into(),
+            role: "mt-sub".into(),
+            engine: "mock".into(),
+            topic: "otel-grpc".into(),
+        },
+    );
+
+    let sub = tokio::spawn(run_multi_topic_sub(MultiTopicSubConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        topic_prefix: "otel-grpc".into(),
+        tenants: 1,
+        regions: 1,
+        services: 1,
+        shards: 2,
+        subscribers: -1,
+        mapping: KeyMappingMode::MDim,
+        key_latency_file: None,
+        churn: None,
+        duration_secs: 2,
+        snapshot_interval_secs: 1,
+        share_transport: true,
+        ramp_up_secs: 0.0,
+        shared_stats: Some(stats.clone()),
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        crash_per_topic: false,
+        crash_stagger_secs: 0.0,
+    }));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    run_multi_topic(MultiTopicConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        topic_prefix: "otel-grpc".into(),
+        tenants: 1,
+        regions: 1,
+        services: 1,
+        shards: 2,
+        publishers: -1,
+        mapping: KeyMappingMode::MDim,
+        payload_size: 128,
+        rate_per_pub: Some(100.0),
+        arrival: ArrivalConfig::default(),
+        popularity: Default::default(),
+        key_rates_file: None,
+        duration_secs: 1,
+        snapshot_interval_secs: 1,
+        share_transport: true,
+        ramp_up_secs: 0.0,
+        shared_stats: None,
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        crash_per_topic: false,
+        crash_stagger_secs: 0.0,
+    })
+    .await
+    .expect("mt-pub");
+    sub.await.unwrap().expect("mt-sub");
+    assert!(stats.counters().received_count > 0, "expected deliveries");
+
+    guard.shutdown().await.expect("otel shutdown");
+
+    let received = received.lock().unwrap();
+    let bodies = |path: &str| -> Vec<&Vec<u8>> {
+        received
+            .iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, b)| b)
+            .collect()
+    };
+    let traces = bodies("/opentelemetry.proto.collector.trace.v1.TraceService/Export");
+    let metrics = bodies("/opentelemetry.proto.collector.metrics.v1.MetricsService/Export");
+    assert!(traces.iter().any(|b| contains(b, b"mq.publish")));
+    assert!(traces.iter().any(|b| contains(b, b"mq.deliver")));
+    assert!(metrics.iter().any(|b| contains(b, b"mq_bench.received")));
+    assert!(metrics.iter().any(|b| contains(b, b"otel-grpc-test")));
+}
@@ -0,0 +1,115 @@
+//! Integration test for skewed key popularity in mt-pub over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::crash::CrashConfig;
+use mq_bench::metrics::stats::Stats;
+use mq_bench::rate::ArrivalConfig;
+use mq_bench::roles::multi_topic::{
+    KeyMappingMode, MultiTopicConfig, MultiTopicSubConfig, Popularity, run_multi_topic,
+    run_multi_topic_sub,
+};
+use mq_bench::transport::{ConnectOptions, Engine};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn hot_set_keys_get_their_share_and_per_key_files_join() {
+    let dir = std::env::temp_dir().join(format!("mqb-popularity-{}", uuid::Uuid::new_v4()));
+    let rates_path = dir.join("key_rates.csv");
+    let lat_path = dir.join("key_latency.csv");
+
+    let sub_stats = Arc::new(Stats::new());
+    let sub = tokio::spawn(run_multi_topic_sub(MultiTopicSubConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        topic_prefix: "popularity-test".into(),
+        tenants: 1,
+        regions: 1,
+        services: 1,
+        shards: 10,
+        subscribers: -1,
+        mapping: KeyMappingMode::MDim,
+        key_latency_file: Some(lat_path.clone()),
+        churn: None,
+        duration_secs: 2,
+        snapshot_interval_secs: 1,
+        share_transport: true,
+        ramp_up_secs: 0.0,
+        shared_stats: Some(sub_stats.clone()),
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        crash_per_topic: false,
+        crash_stagger_secs: 0.0,
+    }));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // 1 of 10 keys carries half of 10 x 50 msg/s
+    run_multi_topic(MultiTopicConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        topic_prefix: "popularity-test".into(),
+        tenants: 1,
+        regions: 1,
+        services: 1,
+        shards: 10,
+        publishers: -1,
+        mapping: KeyMappingMode::MDim,
+        payload_size: 64,
+        rate_per_pub: Some(50.0),
+        arrival: ArrivalConfig::default(),
+        popularity: Popularity::HotSet {
+            keys_pct: 10.0,
+            traffic_pct: 50.0,
+        },
+        key_rates_file: Some(rates_path.clone()),
+        duration_secs: 1,
+        snapshot_interval_secs: 1,
+        share_transport: true,
+        ramp_up_secs: 0.0,
+        shared_stats: None,
+        disable_internal_snapshot: true,
+        crash_config: CrashConfig::default(),
+        crash_per_topic: false,
+        crash_stagger_secs: 0.0,
+    })
+    .await
+    .expect("mt-pub");
+    sub.await.unwrap().expect("mt-sub");
+
+    let mut rates = csv::Reader::from_path(&rates_path).expect("key_rates.csv");
+    let rates: Vec<(String, f64)> = rates
+        .records()
+        .map(|r| {
+            let r = r.unwrap();
+            (r[0].to_string(), r[3].parse().unwrap())
+        })
+        .collect();
+    assert_eq!(rates.len(), 10);
+    assert_eq!(rates[0].0, "popularity-test/t0/r0/svc0/k0");
+    assert!((rates[0].1 - 250.0).abs() < 1e-6, "{:?}", rates[0]);
+    assert!((rates[9].1 - 250.0 / 9.0).abs() < 1e-6, "{:?}", rates[9]);
+
+    let mut lats = csv::Reader::from_path(&lat_path).expect("key_latency.csv");
+    let received: Vec<(String, u64)> = lats
+        .records()
+        .map(|r| {
+            let r = r.unwrap();
+            (r[0].to_string(), r[1].parse().unwrap())
+        })
+        .collect();
+    let keys: Vec<&String> = received.iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, rates.iter().map(|(k, _)| k).collect::<Vec<_>>());
+    let hot = received[0].1;
+    let cold: u64 = received[1..].iter().map(|(_, n)| n).sum();
+    assert!(hot > 150, "hot key received {}", hot);
+    assert!(
+        hot as f64 / (hot + cold) as f64 > 0.4,
+        "hot {} cold {}",
+        hot,
+        cold
+    );
+    assert_eq!(hot + cold, sub_stats.counters().received_count);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
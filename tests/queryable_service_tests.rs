@@ -0,0 +1,89 @@
+//! Integration tests for queryable service-time models and the worker pool over the
+//! mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+mod common;
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::roles::queryable::{QueryableConfig, ServiceTime, run_queryable};
+use mq_bench::roles::requester::{RequesterConfig, run_requester};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn overloaded_worker_pool_reports_queue_depth_and_delay() {
+    let dir = std::env::temp_dir().join(format!("mqb-qry-queue-{}", uuid::Uuid::new_v4()));
+    let qry_stats = Arc::new(Stats::new());
+    // One worker at 20 ms per query serves 50/s; queries arrive at 100/s
+    let qry = tokio::spawn(run_queryable(QueryableConfig {
+        service_time: ServiceTime::Fixed { ms: 20.0 },
+        service_seed: Some(1),
+        workers: 1,
+        queue_dir: Some(dir.clone()),
+        ..common::queryable_config("qsvc/pool", qry_stats.clone())
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let req_stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        request_size: 64,
+        qps: Some(100),
+        timeout_ms: 5000,
+        ..common::requester_config("qsvc/pool", req_stats.clone())
+    })
+    .await
+    .expect("requester");
+    qry.await.unwrap().expect("queryable");
+
+    let req = req_stats.counters();
+    assert_eq!(req.received_count, req.sent_count, "{:?}", req);
+    assert_eq!(qry_stats.counters().sent_count, req.sent_count);
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("queue.json")).unwrap()).unwrap();
+    assert_eq!(summary["workers"], 1);
+    assert_eq!(summary["dequeued"].as_u64(), Some(req.sent_count));
+    // The requester keeps up to 16 queries in flight, so the queue fills up
+    assert!(summary["max_depth"].as_u64().unwrap() >= 10, "{}", summary);
+    assert!(
+        summary["delay_p99_ms"].as_f64().unwrap() > 150.0,
+        "{}",
+        summary
+    );
+
+    let mut rdr = csv::Reader::from_path(dir.join("queue.csv")).expect("queue.csv");
+    let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.unwrap()).collect();
+    assert!(rows.len() >= 2, "{:?}", rows);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn cpu_bound_service_answers_every_query() {
+    let qry_stats = Arc::new(Stats::new());
+    let qry = tokio::spawn(run_queryable(QueryableConfig {
+        service_time: ServiceTime::Exp { mean_ms: 2.0 },
+        service_seed: Some(1),
+        cpu_bound: true,
+        workers: 2,
+        ..common::queryable_config("qsvc/cpu", qry_stats.clone())
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let req_stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        request_size: 64,
+        qps: Some(100),
+        timeout_ms: 5000,
+        ..common::requester_config("qsvc/cpu", req_stats.clone())
+    })
+    .await
+    .expect("requester");
+    qry.await.unwrap().expect("queryable");
+
+    let req = req_stats.counters();
+    assert!(req.sent_count >= 90, "{:?}", req);
+    assert_eq!(req.received_count, req.sent_count);
+    assert_eq!(qry_stats.counters().sent_count, req.sent_count);
+}
@@ -0,0 +1,86 @@
+//! Integration test for trace replay over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::payload::parse_header;
+use mq_bench::roles::replay::{ReplayConfig, TraceRecord, run_replay};
+use mq_bench::transport::{ConnectOptions, Engine, TransportBuilder};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn replay_keeps_timing_sizes_and_mapped_topics() {
+    let rec = |ms, topic: &str, size| TraceRecord {
+        offset: Duration::from_millis(ms),
+        topic: topic.to_string(),
+        size,
+    };
+    let trace = vec![
+        rec(0, "gw/1/temp", 100),
+        rec(100, "gw/2/temp", 10),
+        rec(200, "gw/1/temp", 300),
+    ];
+
+    let t = TransportBuilder::connect(Engine::Mock, ConnectOptions::default())
+        .await
+        .expect("connect");
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut subs = Vec::new();
+    for key in ["replay-test/site/1/temp", "replay-test/site/2/temp"] {
+        let seen = seen.clone();
+        let key_owned = key.to_string();
+        subs.push(
+            t.subscribe(
+                key,
+                Box::new(move |m| {
+                    let body = m.payload.as_cow();
+                    let h = parse_header(&body).expect("header");
+                    seen.lock()
+                        .unwrap()
+                        .push((key_owned.clone(), h.seq, body.len()));
+                }),
+            )
+            .await
+            .expect("subscribe"),
+        );
+    }
+
+    let stats = Arc::new(Stats::new());
+    let started = Instant::now();
+    run_replay(ReplayConfig {
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        trace,
+        topic_prefix: "replay-test".into(),
+        topic_map: vec![("gw/".into(), "site/".into())],
+        speed: 2.0,
+        loops: 2,
+        duration_secs: None,
+        output_file: None,
+        snapshot_interval_secs: 1,
+        shared_stats: Some(stats.clone()),
+        disable_internal_snapshot: true,
+    })
+    .await
+    .expect("replay");
+    // Second pass starts one mean gap after the span (300 ms), its last record 200 ms
+    // later, all at double speed
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(250), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let mut seen = seen.lock().unwrap().clone();
+    seen.sort_by_key(|s| s.1);
+    let seqs: Vec<u64> = seen.iter().map(|s| s.1).collect();
+    assert_eq!(seqs, vec![0, 1, 2, 3, 4, 5]);
+    assert_eq!(seen[0], ("replay-test/site/1/temp".into(), 0, 100));
+    assert_eq!(
+        seen[1],
+        ("replay-test/site/2/temp".into(), 1, 24),
+        "clamped to header size"
+    );
+    assert_eq!(seen[5].2, 300);
+    assert_eq!(stats.counters().sent_count, 6);
+}
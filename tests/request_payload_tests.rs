@@ -0,0 +1,96 @@
+//! Integration tests for request bodies and queryable reply modes over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+mod common;
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::payload::{generate_payload, parse_header};
+use mq_bench::roles::queryable::{QueryableConfig, ReplyMode, run_queryable};
+use mq_bench::roles::requester::{RequesterConfig, run_requester};
+use mq_bench::transport::{ConnectOptions, Engine, TransportBuilder};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn queryable_sees_request_bodies_and_measures_the_request_leg() {
+    let qry_stats = Arc::new(Stats::new());
+    let qry = tokio::spawn(run_queryable(QueryableConfig {
+        reply_mode: ReplyMode::Echo,
+        test_stop_after_secs: Some(2),
+        ..common::queryable_config("reqpay/echo", qry_stats.clone())
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let req_stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        request_size: 256,
+        qps: Some(100),
+        concurrency: 4,
+        timeout_ms: 500,
+        ..common::requester_config("reqpay/echo", req_stats.clone())
+    })
+    .await
+    .expect("requester");
+    qry.await.unwrap().expect("queryable");
+
+    let (req, served) = (req_stats.counters(), qry_stats.counters());
+    assert!(req.sent_count >= 90, "{:?}", req);
+    assert_eq!(req.received_count, req.sent_count);
+    // Every request carried a header, so the queryable timed each one
+    assert_eq!(served.received_count, req.sent_count);
+    assert_eq!(served.sent_count, req.sent_count);
+}
+
+#[tokio::test]
+async fn replies_echo_or_transform_the_request() {
+    let stats = Arc::new(Stats::new());
+    let echo = tokio::spawn(run_queryable(QueryableConfig {
+        reply_mode: ReplyMode::Echo,
+        test_stop_after_secs: Some(2),
+        ..common::queryable_config("reqpay/modes/echo", stats.clone())
+    }));
+    let transform = tokio::spawn(run_queryable(QueryableConfig {
+        reply_mode: ReplyMode::Transform,
+        test_stop_after_secs: Some(2),
+        ..common::queryable_config("reqpay/modes/transform", stats.clone())
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let t = TransportBuilder::connect(Engine::Mock, ConnectOptions::default())
+        .await
+        .expect("connect");
+    let request = bytes::Bytes::from(generate_payload(42, 512));
+    let sent = parse_header(&request).unwrap();
+
+    let reply = t
+        .request("reqpay/modes/echo", request.clone())
+        .await
+        .expect("echo")
+        .into_bytes();
+    assert_eq!(reply, request);
+
+    let reply = t
+        .request("reqpay/modes/transform", request.clone())
+        .await
+        .expect("transform")
+        .into_bytes();
+    assert_eq!(reply.len(), 64);
+    let h = parse_header(&reply).unwrap();
+    assert_eq!((h.seq, h.timestamp_ns), (42, sent.timestamp_ns));
+
+    echo.await.unwrap().expect("queryable");
+    transform.await.unwrap().expect("queryable");
+}
+
+#[tokio::test]
+async fn request_size_below_the_header_is_rejected() {
+    let stats = Arc::new(Stats::new());
+    let err = run_requester(RequesterConfig {
+        request_size: 10,
+        ..common::requester_config("reqpay/small", stats)
+    })
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("24-byte"), "{}", err);
+}
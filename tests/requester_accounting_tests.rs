@@ -0,0 +1,140 @@
+//! Integration tests for requester outcome accounting.
+//!
+//! These tests drive `run_requester` against mock queryables and check that every
+//! dispatched query is counted once as sent and once as exactly one outcome.
+
+#![cfg(feature = "transport-mock")]
+
+mod common;
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::roles::requester::{RequesterConfig, run_requester};
+use mq_bench::transport::{ConnectOptions, Engine, IncomingQuery, TransportBuilder};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Register a mock queryable on `key` that replies after `delay` and counts queries.
+/// With `fail`, it drops the responder at `delay` instead, failing the query.
+async fn serve(
+    key: &str,
+    delay: Duration,
+    fail: bool,
+) -> (
+    Box<dyn mq_bench::transport::Transport>,
+    Box<dyn mq_bench::transport::QueryRegistration>,
+    Arc<AtomicU64>,
+) {
+    let t = TransportBuilder::connect(Engine::Mock, ConnectOptions::default())
+        .await
+        .expect("connect");
+    let served = Arc::new(AtomicU64::new(0));
+    let served_cb = served.clone();
+    let reg = t
+        .register_queryable(
+            key,
+            Box::new(move |IncomingQuery { responder, .. }| {
+                served_cb.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    if !delay.is_zero() {
+                        tokio::time::sleep(delay).await;
+                    }
+                    if !fail {
+                        let _ = responder.send(bytes::Bytes::from_static(b"ok")).await;
+                    }
+                });
+            }),
+        )
+        .await
+        .expect("queryable");
+    (t, reg, served)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn requester_counts_every_query_exactly() {
+    let key = "test/req/exact_counts";
+    let (_t, reg, served) = serve(key, Duration::ZERO, false).await;
+
+    let stats = Arc::new(Stats::new());
+    run_requester(common::requester_config(key, stats.clone()))
+        .await
+        .expect("requester");
+    let _ = reg.shutdown().await;
+
+    let snap = stats.snapshot().await;
+    assert!(snap.sent_count > 0, "expected queries to be sent");
+    assert_eq!(snap.sent_count, served.load(Ordering::SeqCst));
+    assert_eq!(snap.received_count, snap.sent_count);
+    assert_eq!(snap.latency_sample_count, snap.sent_count);
+    assert_eq!(snap.error_count, 0);
+}
+
+#[tokio::test]
+async fn requester_counts_failed_queries_as_sent() {
+    // No queryable registered: every query fails immediately
+    let stats = Arc::new(Stats::new());
+    run_requester(common::requester_config(
+        "test/req/no_queryable",
+        stats.clone(),
+    ))
+    .await
+    .expect("requester");
+
+    let snap = stats.snapshot().await;
+    assert!(snap.sent_count > 0, "expected queries to be sent");
+    assert_eq!(snap.received_count, 0);
+    assert_eq!(snap.error_count, snap.sent_count);
+    assert_eq!(snap.timeout_count, 0);
+    assert_eq!(snap.late_reply_count, 0);
+}
+
+#[tokio::test]
+async fn requester_classifies_late_replies_and_timeouts_once() {
+    // Replies arrive after the 50ms deadline: each query ends as late or timed out
+    let key = "test/req/late_replies";
+    let (_t, reg, served) = serve(key, Duration::from_millis(75), false).await;
+
+    let stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        timeout_ms: 50,
+        ..common::requester_config(key, stats.clone())
+    })
+    .await
+    .expect("requester");
+    let _ = reg.shutdown().await;
+
+    let snap = stats.snapshot().await;
+    assert!(snap.sent_count > 0, "expected queries to be sent");
+    assert_eq!(snap.sent_count, served.load(Ordering::SeqCst));
+    assert_eq!(snap.received_count, 0);
+    assert_eq!(
+        snap.timeout_count + snap.late_reply_count,
+        snap.sent_count,
+        "each query should be classified exactly once"
+    );
+    assert_eq!(snap.error_count, snap.sent_count);
+    assert!(snap.late_reply_count > 0, "expected late replies");
+}
+
+#[tokio::test]
+async fn requester_counts_failures_after_the_deadline_as_errors() {
+    // Queries fail inside the grace window: errors, neither timeouts nor late replies
+    let key = "test/req/late_failures";
+    let (_t, reg, served) = serve(key, Duration::from_millis(150), true).await;
+
+    let stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        timeout_ms: 100,
+        ..common::requester_config(key, stats.clone())
+    })
+    .await
+    .expect("requester");
+    let _ = reg.shutdown().await;
+
+    let snap = stats.snapshot().await;
+    assert!(snap.sent_count > 0, "expected queries to be sent");
+    assert_eq!(snap.sent_count, served.load(Ordering::SeqCst));
+    assert_eq!(snap.error_count, snap.sent_count);
+    assert_eq!(snap.timeout_count, 0);
+    assert_eq!(snap.late_reply_count, 0);
+}
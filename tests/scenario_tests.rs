@@ -0,0 +1,121 @@
+//! Integration tests for mixed-workload scenarios over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+use mq_bench::output::OutputFormat;
+use mq_bench::scenario::{ScenarioConfig, Workload, WorkloadRole, run_scenario, write_results};
+use mq_bench::transport::{ConnectOptions, Engine};
+
+fn workload(class: &str, role: WorkloadRole, key: &str, count: u32, rate: Option<f64>) -> Workload {
+    Workload {
+        class: class.into(),
+        role,
+        engine: Engine::Mock,
+        connect: ConnectOptions::default(),
+        key: key.into(),
+        count,
+        rate,
+        payload_size: 128,
+        reply_mode: Default::default(),
+        concurrency: 4,
+        timeout_ms: 500,
+        stream: false,
+        service_time: Default::default(),
+        chunks: None,
+        workers: 0,
+        cpu_bound: false,
+    }
+}
+
+#[tokio::test]
+async fn telemetry_and_commands_run_together_with_per_class_stats() {
+    let dir = std::env::temp_dir().join(format!("mqb-scenario-{}", uuid::Uuid::new_v4()));
+    let cfg = ScenarioConfig {
+        duration_secs: 1,
+        isolate: true,
+        workloads: vec![
+            workload("telemetry", WorkloadRole::Sub, "scn/telemetry", 2, None),
+            workload(
+                "telemetry",
+                WorkloadRole::Pub,
+                "scn/telemetry",
+                2,
+                Some(200.0),
+            ),
+            workload("commands", WorkloadRole::Qry, "scn/cmd", 1, None),
+            workload("commands", WorkloadRole::Req, "scn/cmd", 1, Some(50.0)),
+        ],
+        output_dir: Some(dir.clone()),
+        format: OutputFormat::Csv,
+        snapshot_interval_secs: 1,
+    };
+    let result = run_scenario(&cfg).await.expect("scenario");
+
+    // One group per (class, role); the two isolated phases cover the same groups
+    let groups: Vec<(&str, WorkloadRole)> = result
+        .mixed
+        .iter()
+        .map(|g| (g.class.as_str(), g.role))
+        .collect();
+    assert_eq!(
+        groups,
+        vec![
+            ("commands", WorkloadRole::Req),
+            ("commands", WorkloadRole::Qry),
+            ("telemetry", WorkloadRole::Pub),
+            ("telemetry", WorkloadRole::Sub),
+        ]
+    );
+    assert_eq!(result.isolated.len(), 4);
+
+    let get = |class: &str, role| {
+        result
+            .mixed
+            .iter()
+            .find(|g| g.class == class && g.role == role)
+            .unwrap()
+    };
+    let (publ, sub) = (
+        get("telemetry", WorkloadRole::Pub),
+        get("telemetry", WorkloadRole::Sub),
+    );
+    assert_eq!((publ.instances, sub.instances), (2, 2));
+    assert!(publ.sent >= 300, "{:?}", publ);
+    assert_eq!(sub.received, publ.sent);
+    let (req, qry) = (
+        get("commands", WorkloadRole::Req),
+        get("commands", WorkloadRole::Qry),
+    );
+    assert!(req.sent >= 40, "{:?}", req);
+    assert_eq!(req.received, req.sent);
+    assert_eq!(qry.sent, req.sent);
+    // Requests carry the payload header, so the queryable times the request leg
+    assert_eq!(qry.received, req.sent);
+    assert_eq!(req.errors + req.timeouts, 0);
+
+    // Latency-bearing groups carry an interference ratio against their isolated run
+    let rows = result.rows();
+    let req_row = rows.iter().find(|r| r.role == WorkloadRole::Req).unwrap();
+    assert!(req_row.isolated_p99_ms.is_some());
+    assert!(req_row.p99_ratio.is_some_and(|r| r > 0.0));
+    let pub_row = rows.iter().find(|r| r.role == WorkloadRole::Pub).unwrap();
+    assert!(pub_row.p99_ratio.is_none());
+
+    write_results(&result, &dir).unwrap();
+    let mut rdr = csv::Reader::from_path(dir.join("scenario.csv")).expect("scenario.csv");
+    assert_eq!(rdr.records().count(), 4);
+    for phase in ["mixed", "isolated"] {
+        for name in [
+            "telemetry-pub",
+            "telemetry-sub",
+            "commands-req",
+            "commands-qry",
+        ] {
+            let path = dir.join(phase).join(format!("{}.csv", name));
+            let snapshots = std::fs::read_to_string(&path).expect("snapshot series");
+            assert!(snapshots.lines().count() >= 2, "{}", path.display());
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,168 @@
+//! Integration tests for streamed (multi-reply) queries over the mock transport.
+
+#![cfg(feature = "transport-mock")]
+
+mod common;
+
+use mq_bench::metrics::stats::Stats;
+use mq_bench::roles::queryable::{
+    ChunkCount, QueryableConfig, ReplyMode, ServiceTime, run_queryable,
+};
+use mq_bench::roles::requester::{RequesterConfig, run_requester};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn streamed_replies_report_first_and_last_chunk() {
+    let dir = std::env::temp_dir().join(format!("mqb-stream-{}", uuid::Uuid::new_v4()));
+    let qry_stats = Arc::new(Stats::new());
+    let qry = tokio::spawn(run_queryable(QueryableConfig {
+        reply_mode: ReplyMode::Transform,
+        service_time: ServiceTime::Fixed { ms: 5.0 },
+        chunks: Some(ChunkCount { min: 4, max: 4 }),
+        service_seed: Some(3),
+        ..common::queryable_config("qstream/fixed", qry_stats.clone())
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let req_stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        request_size: 32,
+        stream: true,
+        stream_dir: Some(dir.clone()),
+        qps: Some(50),
+        concurrency: 8,
+        timeout_ms: 2000,
+        ..common::requester_config("qstream/fixed", req_stats.clone())
+    })
+    .await
+    .expect("requester");
+    qry.await.unwrap().expect("queryable");
+
+    let req = req_stats.counters();
+    assert!(req.sent_count >= 40, "{:?}", req);
+    assert_eq!(req.received_count, req.sent_count, "{:?}", req);
+    // One reply per chunk on the queryable side
+    assert_eq!(qry_stats.counters().sent_count, 4 * req.sent_count);
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("stream.json")).unwrap()).unwrap();
+    assert_eq!(summary["streams"].as_u64(), Some(req.sent_count));
+    assert_eq!(
+        summary["chunks_received"].as_u64(),
+        Some(4 * req.sent_count)
+    );
+    assert_eq!(summary["chunk_loss_ratio"].as_f64(), Some(0.0));
+    assert_eq!(summary["incomplete_streams"], 0);
+    // Four chunks at 5 ms each: the last arrives about 15 ms after the first
+    let first = summary["first_chunk_p50_ms"].as_f64().unwrap();
+    let last = summary["last_chunk_p50_ms"].as_f64().unwrap();
+    assert!(first >= 4.0, "{}", summary);
+    assert!(last - first >= 12.0, "{}", summary);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn plain_requests_take_the_first_chunk() {
+    let qry_stats = Arc::new(Stats::new());
+    let qry = tokio::spawn(run_queryable(QueryableConfig {
+        reply_mode: ReplyMode::Transform,
+        service_time: ServiceTime::Fixed { ms: 5.0 },
+        chunks: Some(ChunkCount { min: 2, max: 3 }),
+        service_seed: Some(3),
+        ..common::queryable_config("qstream/plain", qry_stats.clone())
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let req_stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        request_size: 32,
+        stream: false,
+        qps: Some(50),
+        concurrency: 8,
+        timeout_ms: 2000,
+        ..common::requester_config("qstream/plain", req_stats.clone())
+    })
+    .await
+    .expect("requester");
+    qry.await.unwrap().expect("queryable");
+
+    let req = req_stats.counters();
+    assert_eq!(req.received_count, req.sent_count, "{:?}", req);
+    let served = qry_stats.counters().sent_count;
+    assert!(
+        (2 * req.sent_count..=3 * req.sent_count).contains(&served),
+        "{} chunks for {} queries",
+        served,
+        req.sent_count
+    );
+    // Well below the ~10 ms to the second chunk
+    assert!(req_stats.snapshot().await.latency_ns_p50 < 9_000_000);
+}
+
+#[tokio::test]
+async fn echo_replies_cannot_be_chunked() {
+    let cfg = QueryableConfig {
+        reply_mode: ReplyMode::Echo,
+        chunks: Some(ChunkCount { min: 2, max: 2 }),
+        ..common::queryable_config("qstream/echo", Arc::new(Stats::new()))
+    };
+    let err = run_queryable(cfg).await.unwrap_err();
+    assert!(err.to_string().contains("echo"), "{}", err);
+}
+
+#[tokio::test]
+async fn streams_past_the_deadline_keep_their_chunks() {
+    let dir = std::env::temp_dir().join(format!("mqb-stream-{}", uuid::Uuid::new_v4()));
+    let qry_stats = Arc::new(Stats::new());
+    let qry = tokio::spawn(run_queryable(QueryableConfig {
+        reply_mode: ReplyMode::Transform,
+        service_time: ServiceTime::Fixed { ms: 5.0 },
+        chunks: Some(ChunkCount { min: 8, max: 8 }),
+        service_seed: Some(3),
+        ..common::queryable_config("qstream/slow", qry_stats.clone())
+    }));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Eight chunks at 5 ms each cannot end within the deadline plus its grace period
+    let req_stats = Arc::new(Stats::new());
+    run_requester(RequesterConfig {
+        request_size: 32,
+        stream: true,
+        stream_dir: Some(dir.clone()),
+        qps: Some(50),
+        concurrency: 8,
+        timeout_ms: 12,
+        ..common::requester_config("qstream/slow", req_stats.clone())
+    })
+    .await
+    .expect("requester");
+    qry.await.unwrap().expect("queryable");
+
+    let req = req_stats.counters();
+    assert_eq!(req.received_count, 0, "{:?}", req);
+    assert_eq!(req.timeout_count, req.sent_count, "{:?}", req);
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join("stream.json")).unwrap()).unwrap();
+    assert_eq!(summary["streams"].as_u64(), Some(req.sent_count));
+    assert_eq!(
+        summary["missed_deadline_streams"].as_u64(),
+        Some(req.sent_count)
+    );
+    assert_eq!(summary["incomplete_streams"].as_u64(), Some(req.sent_count));
+    assert_eq!(
+        summary["chunks_expected"].as_u64(),
+        Some(8 * req.sent_count)
+    );
+    let loss = summary["chunk_loss_ratio"].as_f64().unwrap();
+    assert!((0.4..1.0).contains(&loss), "{}", summary);
+    assert!(
+        summary["first_chunk_p50_ms"].as_f64().unwrap() >= 4.0,
+        "{}",
+        summary
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}